@@ -0,0 +1,124 @@
+#!/usr/bin/env python3
+#
+# Generates `motoko-rts/src/char/tables.rs` from the Unicode database bundled
+# with Python's `unicodedata` module:
+#
+#     python3 gen-char-tables.py > motoko-rts/src/char/tables.rs
+#
+# Tables are sorted lists of ranges. Each entry is a `u32` that packs the first
+# code point of the range (upper 24 bits) and the property value (lower 8 bits).
+# A range extends until the start of the next entry.
+
+import sys
+import unicodedata
+
+MAX_CHAR = 0x10FFFF
+
+# Order must match the `GC_*` constants in `char.rs`
+CATEGORIES = [
+    "Lu", "Ll", "Lt", "Lm", "Lo",
+    "Mn", "Mc", "Me",
+    "Nd", "Nl", "No",
+    "Pc", "Pd", "Ps", "Pe", "Pi", "Pf", "Po",
+    "Sm", "Sc", "Sk", "So",
+    "Zs", "Zl", "Zp",
+    "Cc", "Cf", "Cs", "Co", "Cn",
+]
+
+# Pseudo-categories for runs of alternating upper and lower case letters (e.g. in Latin
+# Extended-A). Folding these saves about a thousand entries.
+ALT_UPPER_FIRST = len(CATEGORIES)
+ALT_LOWER_FIRST = ALT_UPPER_FIRST + 1
+MIN_ALT_RUN = 4
+
+# Order must match the `EAW_*` constants in `char.rs`
+WIDTHS = ["A", "F", "H", "N", "Na", "W"]
+
+# `unicodedata` does not know about the defaults for unassigned code points (it reports "F").
+# Per UAX #11 they are "W" in the blocks reserved for CJK ideographs and "N" elsewhere.
+DEFAULT_WIDE = [
+    (0x3400, 0x4DBF),
+    (0x4E00, 0x9FFF),
+    (0xF900, 0xFAFF),
+    (0x20000, 0x2FFFD),
+    (0x30000, 0x3FFFD),
+]
+
+
+def category_ranges():
+    cats = [unicodedata.category(chr(c)) for c in range(MAX_CHAR + 1)]
+    ranges = []
+    i = 0
+    while i <= MAX_CHAR:
+        cat = cats[i]
+        if cat in ("Lu", "Ll"):
+            other = "Ll" if cat == "Lu" else "Lu"
+            j = i + 1
+            while j <= MAX_CHAR and cats[j] == (cat if (j - i) % 2 == 0 else other):
+                j += 1
+            if j - i >= MIN_ALT_RUN:
+                ranges.append((i, ALT_UPPER_FIRST if cat == "Lu" else ALT_LOWER_FIRST))
+                i = j
+                continue
+        j = i + 1
+        while j <= MAX_CHAR and cats[j] == cat:
+            j += 1
+        ranges.append((i, CATEGORIES.index(cat)))
+        i = j
+    return ranges
+
+
+def east_asian_width(c):
+    if unicodedata.category(chr(c)) == "Cn":
+        return "W" if any(lo <= c <= hi for (lo, hi) in DEFAULT_WIDE) else "N"
+    return unicodedata.east_asian_width(chr(c))
+
+
+def width_ranges():
+    ranges = []
+    last = None
+    for c in range(MAX_CHAR + 1):
+        w = WIDTHS.index(east_asian_width(c))
+        if w != last:
+            ranges.append((c, w))
+            last = w
+    return ranges
+
+
+def check_decimal_digits():
+    # `char_decimal_value` relies on every run of `Nd` code points starting at digit zero
+    start = None
+    for c in range(MAX_CHAR + 1):
+        if unicodedata.category(chr(c)) == "Nd":
+            if start is None:
+                start = c
+            assert unicodedata.decimal(chr(c)) == (c - start) % 10, hex(c)
+        else:
+            start = None
+
+
+def emit(name, doc, ranges):
+    print("/// %s" % doc)
+    print("pub(super) static %s: [u32; %d] = [" % (name, len(ranges)))
+    for i in range(0, len(ranges), 8):
+        chunk = ranges[i : i + 8]
+        print("    " + " ".join("0x%08x," % (start << 8 | v) for (start, v) in chunk))
+    print("];")
+
+
+def main():
+    check_decimal_digits()
+    print("// Generated by `rts/gen-char-tables.py`, do not edit.")
+    print("//")
+    print("// Unicode version: %s" % unicodedata.unidata_version)
+    print()
+    print("pub(super) const ALT_UPPER_FIRST: u8 = %d;" % ALT_UPPER_FIRST)
+    print("pub(super) const ALT_LOWER_FIRST: u8 = %d;" % ALT_LOWER_FIRST)
+    print()
+    emit("GENERAL_CATEGORY", "General category ranges", category_ranges())
+    print()
+    emit("EAST_ASIAN_WIDTH", "East Asian width ranges", width_ranges())
+
+
+if __name__ == "__main__":
+    sys.exit(main())
@@ -2,7 +2,13 @@ use crate::memory::TestMemory;
 
 use motoko_rts::bigint::{self, *};
 use motoko_rts::buf::Buf;
-use motoko_rts::types::{Bytes, Value, Words};
+use motoko_rts::memory::Memory;
+use motoko_rts::text::{text_concat, text_of_str};
+use motoko_rts::types::{size_of, Bits64, Bytes, Value, Words, TAG_BITS64};
+
+use oorandom::Rand64;
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestCaseResult, TestRunner};
 
 // mp functions below are implemented separately for tests as we can't modify mp_int source code to
 // pass a generic heap argument (then monomorphise it for IC).
@@ -24,6 +30,16 @@ unsafe extern "C" fn mp_realloc(
     bigint::mp_realloc(&mut *HEAP, ptr, old_size, new_size)
 }
 
+#[no_mangle]
+unsafe extern "C" fn alloc_words(n: Words<u32>) -> Value {
+    (*HEAP).alloc_words(n)
+}
+
+/// Sets the heap used by the mp functions, for tests of other modules that use bigints internally
+pub unsafe fn set_bigint_heap(heap: &mut TestMemory) {
+    HEAP = heap;
+}
+
 pub unsafe fn test() {
     println!("Testing BigInt ...");
 
@@ -62,8 +78,536 @@ pub unsafe fn test() {
         test_bigint_sleb128(bigint_neg(plus_one));
     }
 
+    //
+    // Text conversion
+    //
+
+    let two_pow_100 = bigint_pow(two, bigint_of_word32(100));
+    assert_eq!(
+        to_text(&mut heap, two_pow_100, 10),
+        "1267650600228229401496703205376"
+    );
+    assert_eq!(
+        to_text(&mut heap, bigint_neg(two_pow_100), 16),
+        "-10000000000000000000000000"
+    );
+    assert_eq!(to_text(&mut heap, bigint_of_word32(0), 2), "0");
+
+    for radix in 2..=36 {
+        for i in 0..100 {
+            let two_pow_i = bigint_pow(two, bigint_of_word32(i));
+            let minus_one = bigint_sub(two_pow_i, one);
+            let n = bigint_mul(minus_one, bigint_of_word32(radix + i));
+            test_bigint_text(&mut heap, n, radix);
+            test_bigint_text(&mut heap, bigint_neg(n), radix);
+        }
+    }
+
+    // Letters in either case, optional sign, text split into a rope
+    let valid = [
+        ("0", 10, "0"),
+        ("-0", 10, "0"),
+        ("+42", 10, "2a"),
+        ("007", 8, "7"),
+        ("FfFf", 16, "ffff"),
+        ("-zz", 36, "-50f"),
+        ("1111111111111111111111111111111111111111", 2, "ffffffffff"),
+    ];
+    for (s, radix, hex) in valid.iter() {
+        let mut n = Value::from_scalar(0);
+        let t = rope_of_str(&mut heap, s);
+        assert_eq!(bigint_of_text(&mut heap, t, *radix, &mut n), 1, "{:?}", s);
+        assert_eq!(to_text(&mut heap, n, 16), *hex);
+        assert!(is_compact(n), "{:?}", s);
+    }
+    assert!(!is_compact(big(&mut heap, 1 << 64)));
+
+    let invalid = [
+        ("", 10),
+        ("-", 10),
+        ("+", 10),
+        ("+-1", 10),
+        ("--1", 10),
+        ("12a", 10),
+        ("2", 2),
+        ("1 ", 10),
+        (" 1", 10),
+        ("1_000", 10),
+        ("1\n", 10),
+        ("0x10", 16),
+        ("1.0", 10),
+    ];
+    for (s, radix) in invalid.iter() {
+        let mut n = Value::from_scalar(0);
+        let t = rope_of_str(&mut heap, s);
+        assert_eq!(bigint_of_text(&mut heap, t, *radix, &mut n), 0, "{:?}", s);
+    }
+
+    //
+    // Number theory, checked against `i128` implementations
+    //
+
+    let mut rng = Rand64::new(0x6e75_6d62_6572);
+    let mut values: Vec<i128> = (-20..=20).collect();
+    for bits in [16, 32, 50, 62].iter() {
+        for _ in 0..10 {
+            let n = (rng.rand_u64() >> (64 - bits)) as i128;
+            values.push(n);
+            values.push(-n);
+        }
+    }
+
+    for &a in values.iter() {
+        for &b in values.iter() {
+            let big_a = big(&mut heap, a);
+            let big_b = big(&mut heap, b);
+            let gcd = ref_gcd(a, b);
+            assert_eq!(int(&mut heap, bigint_gcd(big_a, big_b)), gcd, "{} {}", a, b);
+            let lcm = if gcd == 0 { 0 } else { (a / gcd * b).abs() };
+            assert_eq!(int(&mut heap, bigint_lcm(big_a, big_b)), lcm, "{} {}", a, b);
+
+            if b > 0 {
+                let mut inv = Value::from_scalar(0);
+                let ok = bigint_invmod(big_a, big_b, &mut inv);
+                if gcd == 1 {
+                    assert_eq!(ok, 1, "{} {}", a, b);
+                    let inv = int(&mut heap, inv);
+                    assert!(inv >= 0 && inv < b.max(2), "{} {}", a, b);
+                    assert_eq!((a * inv).rem_euclid(b), 1 % b, "{} {}", a, b);
+                } else {
+                    assert_eq!(ok, 0, "{} {}", a, b);
+                }
+            }
+        }
+    }
+
+    for _ in 0..1000 {
+        let b = rng.rand_i64() as i128;
+        let e = rng.rand_range(0..200) as i128;
+        let m = (rng.rand_u64() >> rng.rand_range(1..64)) as i128 + 1;
+        let expected = ref_powmod(b, e, m);
+        let actual = bigint_powmod(big(&mut heap, b), big(&mut heap, e), big(&mut heap, m));
+        assert_eq!(int(&mut heap, actual), expected, "{} {} {}", b, e, m);
+
+        // Negative exponents invert first
+        if ref_gcd(b, m) == 1 {
+            let actual = bigint_powmod(big(&mut heap, b), big(&mut heap, -e), big(&mut heap, m));
+            let actual = int(&mut heap, actual);
+            assert_eq!((actual * expected) % m, 1 % m, "{} {} {}", b, -e, m);
+        }
+    }
+
+    // Fermat's little theorem with the Mersenne prime 2^127 - 1
+    let p = bigint_sub(bigint_pow(two, bigint_of_word32(127)), one);
+    let p_minus_one = bigint_sub(p, one);
+    for base in 2..20 {
+        let base = bigint_of_word32(base);
+        assert!(bigint_eq(bigint_powmod(base, p_minus_one, p), one));
+    }
+
+    for _ in 0..1000 {
+        let n = (rng.rand_u64() as i128) << rng.rand_range(0..60);
+        let r = bigint_isqrt(big(&mut heap, n));
+        let r = int(&mut heap, r);
+        assert!(r * r <= n && (r + 1) * (r + 1) > n, "{}", n);
+    }
+    let ten_pow_100 = bigint_pow(bigint_of_word32(10), bigint_of_word32(100));
+    let ten_pow_200 = bigint_mul(ten_pow_100, ten_pow_100);
+    assert!(bigint_eq(bigint_isqrt(ten_pow_200), ten_pow_100));
+    assert!(bigint_eq(
+        bigint_isqrt(bigint_sub(ten_pow_200, one)),
+        bigint_sub(ten_pow_100, one)
+    ));
+
+    //
+    // Primality, against a sieve for small numbers
+    //
+
+    const SIEVE_SIZE: usize = 20_000;
+    let mut sieve = vec![true; SIEVE_SIZE];
+    sieve[0] = false;
+    sieve[1] = false;
+    for i in 2..SIEVE_SIZE {
+        if sieve[i] {
+            for j in (i * i..SIEVE_SIZE).step_by(i) {
+                sieve[j] = false;
+            }
+        }
+    }
+    for n in 0..SIEVE_SIZE {
+        let big_n = bigint_of_word32(n as u32);
+        assert_eq!(bigint_is_probable_prime(big_n, 1), sieve[n], "{}", n);
+        assert!(!bigint_is_probable_prime(bigint_neg(big_n), 1), "{}", n);
+    }
+
+    let composites = [
+        "561",                                     // Carmichael number
+        "3215031751",                              // strong pseudoprime to bases 2, 3, 5 and 7
+        "318665857834031151167461",                // strong pseudoprime to the first 12 primes
+        "340282366920938463463374607431768211457", // 2^128 + 1
+        "170141183460469231731687303715884105727170141183460469231731687303715884105727",
+    ];
+    for s in composites.iter() {
+        let n = big_of_str(&mut heap, s);
+        assert!(!bigint_is_probable_prime(n, 0), "{}", s);
+        assert!(!bigint_is_probable_prime(n, 10), "{}", s);
+    }
+
+    let primes = [
+        "2147483647",                              // 2^31 - 1
+        "2305843009213693951",                     // 2^61 - 1
+        "618970019642690137449562111",             // 2^89 - 1
+        "170141183460469231731687303715884105727", // 2^127 - 1
+    ];
+    for s in primes.iter() {
+        let n = big_of_str(&mut heap, s);
+        assert!(bigint_is_probable_prime(n, 0), "{}", s);
+        assert!(bigint_is_probable_prime(n, 10), "{}", s);
+    }
+    let m521 = bigint_sub(bigint_pow(two, bigint_of_word32(521)), one);
+    assert!(bigint_is_probable_prime(m521, 5));
+    assert!(!bigint_is_probable_prime(bigint_mul(m521, p), 5));
+
     HEAP = std::ptr::null_mut();
     drop(heap);
+
+    test_bitwise();
+    test_compact();
+}
+
+/// Checks bitwise operations against `i128`, which has the same semantics within its range
+unsafe fn test_bitwise() {
+    let mut proptest_runner = TestRunner::new(Config {
+        cases: 10_000,
+        failure_persistence: None,
+        ..Default::default()
+    });
+
+    let values = proptest::num::i128::ANY.prop_map(|n| n >> (n as u32 % 128));
+    proptest_runner
+        .run(&(values.clone(), values, 0u32..127), |(a, b, bit)| {
+            let mut heap = TestMemory::new(Words(64 * 1024));
+            HEAP = &mut heap;
+            let result = check_bitwise(&mut heap, a, b, bit);
+            HEAP = std::ptr::null_mut();
+            result
+        })
+        .unwrap();
+
+    // Bits far beyond the magnitude
+    let mut heap = TestMemory::new(Words(64 * 1024));
+    HEAP = &mut heap;
+
+    let minus_one = big(&mut heap, -1);
+    let two_pow_1000 = bigint_pow(bigint_of_word32(2), bigint_of_word32(1000));
+    assert!(bigint_test_bit(minus_one, 100_000));
+    assert!(!bigint_test_bit(two_pow_1000, 100_000));
+    assert!(bigint_test_bit(two_pow_1000, 1000));
+    assert!(!bigint_test_bit(two_pow_1000, 999));
+    assert!(bigint_eq(
+        bigint_set_bit(bigint_of_word32(0), 1000),
+        two_pow_1000
+    ));
+    assert!(bigint_eq(
+        bigint_clear_bit(two_pow_1000, 1000),
+        bigint_of_word32(0)
+    ));
+    assert!(bigint_eq(
+        bigint_clear_bit(minus_one, 1000),
+        bigint_not(two_pow_1000)
+    ));
+    assert_eq!(bigint_popcount(bigint_neg(two_pow_1000)), 1);
+    assert_eq!(bigint_bit_length(bigint_neg(two_pow_1000)), 1001);
+    assert_eq!(
+        bigint_popcount(bigint_sub(two_pow_1000, bigint_of_word32(1))),
+        1000
+    );
+
+    HEAP = std::ptr::null_mut();
+}
+
+unsafe fn check_bitwise(heap: &mut TestMemory, a: i128, b: i128, bit: u32) -> TestCaseResult {
+    let big_a = big(heap, a);
+    let big_b = big(heap, b);
+
+    let results = [
+        ("and", bigint_and(big_a, big_b), a & b),
+        ("or", bigint_or(big_a, big_b), a | b),
+        ("xor", bigint_xor(big_a, big_b), a ^ b),
+        ("not", bigint_not(big_a), !a),
+        ("set_bit", bigint_set_bit(big_a, bit), a | 1 << bit),
+        ("clear_bit", bigint_clear_bit(big_a, bit), a & !(1 << bit)),
+    ];
+    for (op, actual, expected) in results.iter() {
+        // Results that fit in 64 bits are compact, as the compiler expects. `i64::MIN` may be
+        // either.
+        let fits = expected.unsigned_abs() < 1 << 63;
+        let too_big = expected.unsigned_abs() > 1 << 63;
+        if (fits && !is_compact(*actual)) || (too_big && is_compact(*actual)) {
+            return Err(TestCaseError::Fail(
+                format!("{} {} {} {}: not normalized", op, a, b, bit).into(),
+            ));
+        }
+        let actual = int(heap, *actual);
+        if actual != *expected {
+            return Err(TestCaseError::Fail(
+                format!(
+                    "{} {} {} {}: {}, expected {}",
+                    op, a, b, bit, actual, expected
+                )
+                .into(),
+            ));
+        }
+    }
+
+    let magnitude = a.unsigned_abs();
+    if bigint_test_bit(big_a, bit) != (a >> bit & 1 == 1)
+        || bigint_popcount(big_a) != magnitude.count_ones()
+        || bigint_bit_length(big_a) != 128 - magnitude.leading_zeros()
+    {
+        return Err(TestCaseError::Fail(format!("{} {}", a, bit).into()));
+    }
+
+    Ok(())
+}
+
+/// Checks the fast path for numbers that fit in 64 bits
+unsafe fn test_compact() {
+    let mut proptest_runner = TestRunner::new(Config {
+        cases: 10_000,
+        failure_persistence: None,
+        ..Default::default()
+    });
+
+    // Mostly small numbers, but also numbers near the ends of the `i64` range
+    let values = proptest::num::i64::ANY.prop_map(|n| n >> (n as u32 % 64));
+    proptest_runner
+        .run(&(values.clone(), values), |(a, b)| {
+            let mut heap = TestMemory::new(Words(64 * 1024));
+            HEAP = &mut heap;
+            let result = check_compact(&mut heap, a, b);
+            HEAP = std::ptr::null_mut();
+            result
+        })
+        .unwrap();
+
+    let mut heap = TestMemory::new(Words(64 * 1024));
+    HEAP = &mut heap;
+
+    // Overflow promotes to libtommath, and small results of the slow path are compact again
+    let max = bigint_of_int64(i64::MAX);
+    let min = bigint_of_int64(i64::MIN);
+    let one = bigint_of_word32(1);
+    let minus_one = bigint_of_int64(-1);
+    let max_plus_one = bigint_add(max, one);
+    assert!(!is_compact(max_plus_one));
+    assert_eq!(int(&mut heap, max_plus_one), i64::MAX as i128 + 1);
+    assert!(is_compact(bigint_sub(max_plus_one, one)));
+    assert!(bigint_eq(bigint_sub(max_plus_one, one), max));
+    assert_eq!(int(&mut heap, bigint_neg(min)), -(i64::MIN as i128));
+    assert_eq!(
+        int(&mut heap, bigint_div(min, minus_one)),
+        -(i64::MIN as i128)
+    );
+    assert_eq!(int(&mut heap, bigint_rem(min, minus_one)), 0);
+    assert_eq!(
+        int(&mut heap, bigint_mul(max, max)),
+        i64::MAX as i128 * i64::MAX as i128
+    );
+    assert_eq!(int(&mut heap, bigint_sub(min, one)), i64::MIN as i128 - 1);
+
+    // Counting only allocates the `Bits64` objects of the results
+    let mut counter = bigint_of_word32(1 << 30);
+    let before = heap.allocated();
+    for _ in 0..1000 {
+        counter = bigint_add(counter, one);
+    }
+    assert_eq!(
+        heap.allocated() - before,
+        1000 * size_of::<Bits64>().to_bytes().as_usize()
+    );
+    assert_eq!(int(&mut heap, counter), (1 << 30) + 1000);
+
+    // Small operands can be tagged scalars, as compiled code passes them
+    let scalar_one = Value::from_signed_scalar(1);
+    let scalar_minus_seven = Value::from_signed_scalar(-7);
+    let before = heap.allocated();
+    for _ in 0..1000 {
+        counter = bigint_add(counter, scalar_one);
+    }
+    assert_eq!(
+        heap.allocated() - before,
+        1000 * size_of::<Bits64>().to_bytes().as_usize()
+    );
+    assert_eq!(int(&mut heap, counter), (1 << 30) + 2000);
+    assert_eq!(
+        int(&mut heap, bigint_sub(scalar_minus_seven, counter)),
+        -7 - (1 << 30) - 2000
+    );
+    assert_eq!(
+        int(&mut heap, bigint_mul(counter, scalar_minus_seven)),
+        ((1 << 30) + 2000) * -7
+    );
+    assert_eq!(
+        int(&mut heap, bigint_add(scalar_one, max)),
+        i64::MAX as i128 + 1
+    );
+    assert_eq!(
+        int(&mut heap, bigint_mul(max_plus_one, scalar_minus_seven)),
+        (i64::MAX as i128 + 1) * -7
+    );
+
+    // Beyond 64 bits every result is an `mp_int` with its digit array
+    let before = heap.allocated();
+    let mut counter = max_plus_one;
+    for _ in 0..1000 {
+        counter = bigint_add(counter, one);
+    }
+    assert!(heap.allocated() - before > 10 * 1000 * size_of::<Bits64>().to_bytes().as_usize());
+
+    HEAP = std::ptr::null_mut();
+}
+
+unsafe fn check_compact(heap: &mut TestMemory, a: i64, b: i64) -> TestCaseResult {
+    let big_a = bigint_of_int64(a);
+    let big_b = bigint_of_int64(b);
+    let (a, b) = (i128::from(a), i128::from(b));
+
+    let mut results = vec![
+        ("add", bigint_add(big_a, big_b), a + b),
+        ("sub", bigint_sub(big_a, big_b), a - b),
+        ("mul", bigint_mul(big_a, big_b), a * b),
+        ("neg", bigint_neg(big_a), -a),
+    ];
+    if b != 0 {
+        // libtommath and Rust both round towards zero
+        results.push(("div", bigint_div(big_a, big_b), a / b));
+        results.push(("rem", bigint_rem(big_a, big_b), a % b));
+    }
+
+    for (op, actual, expected) in results.iter() {
+        let fits = *expected >= i128::from(i64::MIN) && *expected <= i128::from(i64::MAX);
+        if is_compact(*actual) != fits || int(heap, *actual) != *expected {
+            return Err(TestCaseError::Fail(
+                format!("{} {} {}: {}", op, a, b, int(heap, *actual)).into(),
+            ));
+        }
+    }
+
+    // Operands in the other representation give the same results
+    let mp_a = mp(heap, a);
+    let mp_b = mp(heap, b);
+    if !bigint_eq(bigint_add(mp_a, big_b), bigint_add(big_a, mp_b))
+        || bigint_eq(big_a, mp_b) != (a == b)
+        || bigint_leb128_size(big_a) != bigint_leb128_size(mp_a)
+        || bigint_sleb128_size(big_a) != bigint_sleb128_size(mp_a)
+    {
+        return Err(TestCaseError::Fail(format!("{} {}", a, b).into()));
+    }
+
+    Ok(())
+}
+
+unsafe fn is_compact(n: Value) -> bool {
+    n.tag() == TAG_BITS64
+}
+
+unsafe fn big_of_str(heap: &mut TestMemory, s: &str) -> Value {
+    let t = text_of_str(heap, s);
+    let mut n = Value::from_scalar(0);
+    assert_eq!(bigint_of_text(heap, t, 10, &mut n), 1);
+    n
+}
+
+unsafe fn big(heap: &mut TestMemory, n: i128) -> Value {
+    big_of_str(heap, &n.to_string())
+}
+
+/// `n` as an `mp_int`, even if it is compact. Results of libtommath are not made compact.
+unsafe fn mp(heap: &mut TestMemory, n: i128) -> Value {
+    let offset = big(heap, 1 << 100);
+    let r = bigint_sub(big(heap, n + (1 << 100)), offset);
+    assert!(!is_compact(r));
+    r
+}
+
+unsafe fn int(heap: &mut TestMemory, n: Value) -> i128 {
+    to_text(heap, n, 10).parse().unwrap()
+}
+
+fn ref_gcd(a: i128, b: i128) -> i128 {
+    let (mut a, mut b) = (a.abs(), b.abs());
+    while b != 0 {
+        let r = a % b;
+        a = b;
+        b = r;
+    }
+    a
+}
+
+fn ref_powmod(b: i128, mut e: i128, m: i128) -> i128 {
+    let mut base = b.rem_euclid(m);
+    let mut result = 1 % m;
+    while e > 0 {
+        if e & 1 == 1 {
+            result = result * base % m;
+        }
+        base = base * base % m;
+        e >>= 1;
+    }
+    result
+}
+
+// Check text conversion roundtrip, and that the digits have the expected value
+unsafe fn test_bigint_text(heap: &mut TestMemory, n: Value, radix: u32) {
+    let t = bigint_to_text(heap, n, radix);
+    let s = text(heap, t);
+
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.as_str()),
+    };
+    assert!(!digits.is_empty());
+    assert!(digits == "0" || !digits.starts_with('0'), "{:?}", s);
+
+    let mut value = bigint_of_word32(0);
+    for c in digits.chars() {
+        assert!(!c.is_ascii_uppercase(), "{:?}", s);
+        let digit = bigint_of_word32(c.to_digit(radix).unwrap());
+        value = bigint_add(bigint_mul(value, bigint_of_word32(radix)), digit);
+    }
+    if negative {
+        value = bigint_neg(value);
+    }
+    assert!(bigint_eq(value, n), "{:?}", s);
+
+    let mut n2 = Value::from_scalar(0);
+    assert_eq!(bigint_of_text(heap, t, radix, &mut n2), 1);
+    assert!(bigint_eq(n, n2), "{:?}", s);
+}
+
+unsafe fn to_text(heap: &mut TestMemory, n: Value, radix: u32) -> String {
+    let t = bigint_to_text(heap, n, radix);
+    text(heap, t)
+}
+
+unsafe fn text(heap: &mut TestMemory, t: Value) -> String {
+    let t = motoko_rts::text::blob_of_text(heap, t);
+    let blob = t.as_blob();
+    let bytes = std::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize());
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+/// Builds a rope out of short pieces
+unsafe fn rope_of_str<M: Memory>(mem: &mut M, s: &str) -> Value {
+    if s.len() <= 2 {
+        text_of_str(mem, s)
+    } else {
+        let (s1, s2) = s.split_at(s.len() / 2);
+        let t1 = rope_of_str(mem, s1);
+        let t2 = rope_of_str(mem, s2);
+        text_concat(mem, t1, t2)
+    }
 }
 
 // Check leb128 encode/decode roundtrip
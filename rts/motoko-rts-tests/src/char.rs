@@ -0,0 +1,117 @@
+use motoko_rts::char::*;
+
+pub unsafe fn test() {
+    println!("Testing character classification ...");
+
+    //
+    // General category, one character per category
+    //
+
+    let categories: [(u32, u32); 33] = [
+        (0x0041, GC_Lu),   // A
+        (0x0061, GC_Ll),   // a
+        (0x01C5, GC_Lt),   // ǅ
+        (0x02B0, GC_Lm),   // ʰ
+        (0x4E2D, GC_Lo),   // 中
+        (0x0301, GC_Mn),   // combining acute accent
+        (0x0903, GC_Mc),   // devanagari sign visarga
+        (0x20DD, GC_Me),   // combining enclosing circle
+        (0x0037, GC_Nd),   // 7
+        (0x216B, GC_Nl),   // Ⅻ
+        (0x00BD, GC_No),   // ½
+        (0x005F, GC_Pc),   // _
+        (0x002D, GC_Pd),   // -
+        (0x0028, GC_Ps),   // (
+        (0x0029, GC_Pe),   // )
+        (0x00AB, GC_Pi),   // «
+        (0x00BB, GC_Pf),   // »
+        (0x0021, GC_Po),   // !
+        (0x002B, GC_Sm),   // +
+        (0x0024, GC_Sc),   // $
+        (0x005E, GC_Sk),   // ^
+        (0x00A9, GC_So),   // ©
+        (0x0020, GC_Zs),   // space
+        (0x2028, GC_Zl),   // line separator
+        (0x2029, GC_Zp),   // paragraph separator
+        (0x0000, GC_Cc),   // NUL
+        (0x200B, GC_Cf),   // zero width space
+        (0xD800, GC_Cs),   // high surrogate
+        (0xE000, GC_Co),   // private use
+        (0x0378, GC_Cn),   // unassigned
+        (0x0100, GC_Lu),   // Ā (run of alternating upper and lower case letters)
+        (0x0101, GC_Ll),   // ā
+        (0x110000, GC_Cn), // not a code point
+    ];
+
+    for (c, cat) in categories.iter() {
+        assert_eq!(char_general_category(*c), *cat, "category of {:#x}", c);
+    }
+
+    //
+    // Predicates, checked against `char` methods in the ASCII and Latin-1 range, where the
+    // Unicode versions of the RTS tables and the Rust standard library cannot disagree
+    //
+
+    for c in 0..=0xFFu32 {
+        let ch = char::from_u32(c).unwrap();
+        assert_eq!(char_is_numeric(c) != 0, ch.is_numeric(), "{:#x}", c);
+        assert_eq!(
+            char_is_alphanumeric(c) != 0,
+            ch.is_alphanumeric(),
+            "{:#x}",
+            c
+        );
+        assert_eq!(char_is_control(c) != 0, ch.is_control(), "{:#x}", c);
+        assert_eq!(
+            char_decimal_value(c),
+            ch.to_digit(10).map(|d| d as i32).unwrap_or(-1),
+            "{:#x}",
+            c
+        );
+    }
+
+    assert_eq!(char_is_punctuation('!' as u32), 1);
+    assert_eq!(char_is_punctuation('«' as u32), 1);
+    assert_eq!(char_is_punctuation('+' as u32), 0);
+    assert_eq!(char_is_punctuation('$' as u32), 0);
+    assert_eq!(char_is_numeric('Ⅻ' as u32), 1);
+    assert_eq!(char_is_alphanumeric('中' as u32), 1);
+    assert_eq!(char_is_alphanumeric(0x0903), 0); // a mark, though `Other_Alphabetic`
+
+    //
+    // Decimal digit values outside of ASCII
+    //
+
+    assert_eq!(char_decimal_value(0x0663), 3); // arabic-indic digit three
+    assert_eq!(char_decimal_value(0x1D7CE), 0); // mathematical bold digit zero
+    assert_eq!(char_decimal_value(0x1D7D8), 0); // mathematical double-struck digit zero
+    assert_eq!(char_decimal_value(0x1D7FF), 9); // mathematical monospace digit nine
+    assert_eq!(char_decimal_value(0x216B), -1); // Ⅻ is numeric, but not a decimal digit
+
+    // Decimal digits are exactly the characters of category `Nd`
+    for c in 0..=0x10FFFFu32 {
+        let digit = char_decimal_value(c);
+        assert!(digit < 10);
+        assert_eq!(digit >= 0, char_general_category(c) == GC_Nd, "{:#x}", c);
+    }
+
+    //
+    // East Asian width
+    //
+
+    let widths: [(u32, u32); 9] = [
+        (0x0061, EAW_Na), // a
+        (0x4E2D, EAW_W),  // 中
+        (0xFF21, EAW_F),  // fullwidth A
+        (0xFF61, EAW_H),  // halfwidth ideographic full stop
+        (0x00A1, EAW_A),  // ¡
+        (0x00A9, EAW_N),  // ©
+        (0x1F600, EAW_W), // grinning face
+        (0x2A6E0, EAW_W), // unassigned, but in a block reserved for CJK ideographs
+        (0x0378, EAW_N),  // unassigned
+    ];
+
+    for (c, width) in widths.iter() {
+        assert_eq!(char_east_asian_width(*c), *width, "width of {:#x}", c);
+    }
+}
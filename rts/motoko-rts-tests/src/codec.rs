@@ -0,0 +1,175 @@
+//! Binary-to-text codec tests
+
+use crate::memory::TestMemory;
+
+use motoko_rts::codec::{
+    codec_decode, codec_encode, CODEC_BASE32, CODEC_BASE58, CODEC_BASE64, CODEC_BASE64_URL,
+    CODEC_HEX,
+};
+use motoko_rts::text::{text_concat, text_of_ptr_size, text_of_str};
+use motoko_rts::types::{Bytes, Value, Words};
+
+use oorandom::Rand64;
+
+const CODECS: [u32; 5] = [
+    CODEC_HEX,
+    CODEC_BASE32,
+    CODEC_BASE64,
+    CODEC_BASE64_URL,
+    CODEC_BASE58,
+];
+
+pub unsafe fn test() {
+    println!("Testing binary-to-text codecs ...");
+
+    let mut heap = TestMemory::new(Words(1024 * 1024));
+
+    println!("  Testing RFC 4648 vectors");
+    let vectors: [(&[u8], &str, &str, &str); 7] = [
+        (b"", "", "", ""),
+        (b"f", "66", "MY======", "Zg=="),
+        (b"fo", "666f", "MZXQ====", "Zm8="),
+        (b"foo", "666f6f", "MZXW6===", "Zm9v"),
+        (b"foob", "666f6f62", "MZXW6YQ=", "Zm9vYg=="),
+        (b"fooba", "666f6f6261", "MZXW6YTB", "Zm9vYmE="),
+        (b"foobar", "666f6f626172", "MZXW6YTBOI======", "Zm9vYmFy"),
+    ];
+    for (bytes, hex, base32, base64) in vectors.iter() {
+        check_codec(&mut heap, CODEC_HEX, bytes, hex);
+        check_codec(&mut heap, CODEC_BASE32, bytes, base32);
+        check_codec(&mut heap, CODEC_BASE64, bytes, base64);
+        check_codec(
+            &mut heap,
+            CODEC_BASE64_URL,
+            bytes,
+            base64.trim_end_matches('='),
+        );
+    }
+    check_codec(&mut heap, CODEC_BASE64, b"\xfb\xff\xbf", "+/+/");
+    check_codec(&mut heap, CODEC_BASE64_URL, b"\xfb\xff\xbf", "-_-_");
+
+    println!("  Testing base58 vectors");
+    check_codec(&mut heap, CODEC_BASE58, b"", "");
+    check_codec(
+        &mut heap,
+        CODEC_BASE58,
+        b"Hello World!",
+        "2NEpo7TZRRrLZSi2U",
+    );
+    check_codec(
+        &mut heap,
+        CODEC_BASE58,
+        b"\x00\x00\x00\x00\x28\x7f\xb4\xcd",
+        "1111233QC4",
+    );
+
+    println!("  Testing strict decoding");
+    let malformed = [
+        (CODEC_HEX, "DEADBEEF"),
+        (CODEC_HEX, "abc"),
+        (CODEC_HEX, "de ad"),
+        (CODEC_BASE32, "mzxw6==="),
+        (CODEC_BASE32, "MZXW6"),
+        (CODEC_BASE32, "MZXW6=="),
+        (CODEC_BASE32, "MZ=XW6=="),
+        (CODEC_BASE32, "MZXW7==="), // unused bits set
+        (CODEC_BASE32, "M======="),
+        (CODEC_BASE64, "Zm8"),
+        (CODEC_BASE64, "Zm9="),
+        (CODEC_BASE64, "-_-_"),
+        (CODEC_BASE64_URL, "Zm8="),
+        (CODEC_BASE64_URL, "+/+/"),
+        (CODEC_BASE58, "1I"),
+        (CODEC_BASE58, "2g="),
+    ];
+    for (codec, text) in malformed.iter() {
+        assert_eq!(decode(&mut heap, *codec, text, false), None);
+    }
+
+    println!("  Testing tolerant decoding");
+    let tolerated = [
+        (CODEC_HEX, "DE AD-be:ef", None),
+        (CODEC_HEX, "DEAD-beef", Some(&b"\xde\xad\xbe\xef"[..])),
+        (CODEC_HEX, "abc", None),
+        (CODEC_BASE32, "mzxw6", Some(&b"foo"[..])),
+        (CODEC_BASE32, "MZXW7===", Some(&b"foo"[..])),
+        (
+            CODEC_BASE32,
+            "em77e-bvlzu-aq",
+            Some(&b"\x23\x3f\xf2\x06\xab\xcd\x01"[..]),
+        ),
+        (CODEC_BASE32, "M", None),
+        (CODEC_BASE64, "Zm8", Some(&b"fo"[..])),
+        (CODEC_BASE64, "Z m\n8 =", Some(&b"fo"[..])),
+        (CODEC_BASE64, "-_-_", Some(&b"\xfb\xff\xbf"[..])),
+        (CODEC_BASE64_URL, "+/+/==", Some(&b"\xfb\xff\xbf"[..])),
+        (CODEC_BASE64, "Z", None),
+        (CODEC_BASE58, " 2g\n", Some(&b"a"[..])),
+        (CODEC_BASE58, "0", None),
+    ];
+    for (codec, text, expected) in tolerated.iter() {
+        assert_eq!(
+            decode(&mut heap, *codec, text, true).as_deref(),
+            *expected,
+            "{}",
+            text
+        );
+    }
+
+    println!("  Testing text ropes");
+    let front = text_of_str(&mut heap, "Zm9vYmFyZm9v");
+    let back = text_of_str(&mut heap, "YmFyZm9vYmFy");
+    let rope = text_concat(&mut heap, front, back);
+    let mut out = Value::from_scalar(0);
+    assert_eq!(
+        codec_decode(&mut heap, CODEC_BASE64, rope, false, &mut out),
+        1
+    );
+    assert_eq!(blob_bytes(out), b"foobarfoobarfoobar");
+
+    println!("  Testing random round trips");
+    let mut rng = Rand64::new(0xC0DEC);
+    for _ in 0..200 {
+        let len = rng.rand_range(0..70) as usize;
+        let bytes: Vec<u8> = (0..len)
+            .map(|_| match rng.rand_range(0..4) {
+                0 => 0,
+                _ => rng.rand_u64() as u8,
+            })
+            .collect();
+        for codec in CODECS.iter() {
+            let blob = text_of_ptr_size(&mut heap, bytes.as_ptr(), Bytes(len as u32));
+            let text = codec_encode(&mut heap, *codec, blob);
+            let text = String::from_utf8(blob_bytes(text).to_vec()).unwrap();
+            for tolerant in [false, true].iter() {
+                assert_eq!(
+                    decode(&mut heap, *codec, &text, *tolerant).as_deref(),
+                    Some(&bytes[..])
+                );
+            }
+        }
+    }
+}
+
+/// Checks the canonical text of a blob, and that both modes decode it
+unsafe fn check_codec(heap: &mut TestMemory, codec: u32, bytes: &[u8], text: &str) {
+    let blob = text_of_ptr_size(heap, bytes.as_ptr(), Bytes(bytes.len() as u32));
+    let encoded = codec_encode(heap, codec, blob);
+    assert_eq!(blob_bytes(encoded), text.as_bytes());
+    assert_eq!(decode(heap, codec, text, false).as_deref(), Some(bytes));
+    assert_eq!(decode(heap, codec, text, true).as_deref(), Some(bytes));
+}
+
+unsafe fn decode(heap: &mut TestMemory, codec: u32, text: &str, tolerant: bool) -> Option<Vec<u8>> {
+    let text = text_of_str(heap, text);
+    let mut out = Value::from_scalar(0);
+    match codec_decode(heap, codec, text, tolerant, &mut out) {
+        0 => None,
+        _ => Some(blob_bytes(out).to_vec()),
+    }
+}
+
+unsafe fn blob_bytes<'a>(blob: Value) -> &'a [u8] {
+    let blob = blob.as_blob();
+    std::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize())
+}
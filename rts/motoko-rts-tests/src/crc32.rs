@@ -1,6 +1,6 @@
 use crate::memory::TestMemory;
 
-use motoko_rts::principal_id::{base32_of_checksummed_blob, base32_to_blob};
+use motoko_rts::principal_id::{base32_of_checksummed_blob, base32_to_blob, update_crc32};
 use motoko_rts::text::{text_compare, text_of_ptr_size};
 use motoko_rts::types::{Bytes, Words};
 
@@ -78,4 +78,17 @@ pub unsafe fn test() {
         ),
         0
     );
+
+    //
+    // Running checksums
+    //
+
+    assert_eq!(update_crc32(0, b""), 0);
+    assert_eq!(update_crc32(0, b"123456789"), 0xcbf43926);
+    let data: Vec<u8> = (0..1000u32).map(|i| (i * 31) as u8).collect();
+    let crc = update_crc32(0, &data);
+    for split in [0, 1, 500, 999, 1000].iter() {
+        let (front, back) = data.split_at(*split);
+        assert_eq!(update_crc32(update_crc32(0, front), back), crc);
+    }
 }
@@ -0,0 +1,307 @@
+use crate::bigint::set_bigint_heap;
+use crate::memory::TestMemory;
+
+use motoko_rts::float::format::{format_float, FMT_SHORTEST, MAX_FORMATTED_LEN};
+use motoko_rts::float::parse::{float_of_text, parse_float};
+use motoko_rts::memory::Memory;
+use motoko_rts::text::{text_concat, text_of_str};
+use motoko_rts::types::{Value, Words};
+
+use proptest::strategy::Strategy;
+use proptest::test_runner::{Config, TestCaseError, TestCaseResult, TestRunner};
+
+pub unsafe fn test() {
+    println!("Testing float parsing and formatting ...");
+
+    test_parse();
+    test_format();
+}
+
+unsafe fn test_parse() {
+    let mut mem = TestMemory::new(Words(1024 * 1024));
+    set_bigint_heap(&mut mem);
+
+    println!("  Testing parsing fixed cases");
+    let cases = [
+        "0",
+        "-0",
+        "007",
+        "1.",
+        ".5",
+        "1.e5",
+        "1e-7",
+        "+12.5E+3",
+        "inf",
+        "-Infinity",
+        "NaN",
+        "9007199254740993",
+        "9007199254740995",
+        "2.2250738585072011e-308",
+        "2.2250738585072012e-308",
+        "4.9406564584124654e-324",
+        "2.4703282292062327e-324",
+        "2.4703282292062328e-324",
+        "1.7976931348623157e308",
+        "1.7976931348623158e308",
+        "1.7976931348623159e308",
+        "1e-400",
+        "1e400",
+        "0e999999999999",
+        "0.000000000000000000000000000000000000000000000001e48",
+        "123456789012345678901234567890e-20",
+        "8.98846567431158e307",
+        "",
+        "+",
+        "-",
+        ".",
+        "e5",
+        "1e",
+        "1e+",
+        "--1",
+        "1.2.3",
+        "infinit",
+        "nan1",
+        " 1",
+        "1 ",
+        "1_000",
+    ];
+    for s in cases.iter() {
+        check_decimal(&mut mem, s).unwrap();
+    }
+
+    println!("  Testing parsing long inputs");
+    // 2^53 + 1 is halfway between two floats, the far away digit decides the rounding
+    let zeros = "0".repeat(1000);
+    check_decimal(&mut mem, &format!("9007199254740993.{}", zeros)).unwrap();
+    check_decimal(&mut mem, &format!("9007199254740993.{}1", zeros)).unwrap();
+    check_decimal(&mut mem, &format!("9007199254740993{}e-1000", zeros)).unwrap();
+    check_decimal(&mut mem, &format!("0.{}9007199254740993e1016", zeros)).unwrap();
+
+    println!("  Testing parsing hexadecimal floats");
+    let hex_cases = [
+        ("0x1p0", Some(1.0)),
+        ("0x1.8p1", Some(3.0)),
+        ("-0X.8P-1", Some(-0.25)),
+        ("0xff", Some(255.0)),
+        ("0x1p-1074", Some(f64::from_bits(1))),
+        ("0x1p-1075", Some(0.0)),
+        ("0x1.0000000000001p-1075", Some(f64::from_bits(1))),
+        ("0x1.fffffffffffffp1023", Some(f64::MAX)),
+        ("0x1.fffffffffffff8p1023", Some(f64::INFINITY)),
+        ("0x20000000000001", Some(9007199254740992.0)),
+        ("0x20000000000003", Some(9007199254740996.0)),
+        (
+            "0x200000000000010000000000000001",
+            Some(f64::from_bits(0x4740000000000001)),
+        ),
+        ("0x", None),
+        ("0x.", None),
+        ("0xp1", None),
+        ("0x1p", None),
+        ("0x1e", Some(30.0)),
+        ("0x1g", None),
+        ("00x1", None),
+    ];
+    for (s, expected) in hex_cases.iter() {
+        let text = rope_of_str(&mut mem, s);
+        assert!(
+            same(parse_float(text), *expected),
+            "{:?}: {:?}",
+            s,
+            parse_float(text)
+        );
+    }
+
+    let text = text_of_str(&mut mem, "0x1.8p1");
+    let mut out = 0.0;
+    assert_eq!(float_of_text(text, &mut out), 1);
+    assert_eq!(out, 3.0);
+    let text = text_of_str(&mut mem, "three");
+    assert_eq!(float_of_text(text, &mut out), 0);
+
+    drop(mem);
+
+    println!("  Testing parsing formatted floats");
+    run(
+        proptest::num::f64::ANY.prop_map(|f| {
+            [
+                format!("{}", f),
+                format!("{:e}", f),
+                format!("{:.30e}", f),
+                format!("{:.16e}", f),
+            ]
+        }),
+        |strs| {
+            let mut mem = TestMemory::new(Words(64 * 1024));
+            set_bigint_heap(&mut mem);
+            strs.iter().try_for_each(|s| check_decimal(&mut mem, s))
+        },
+    );
+
+    println!("  Testing parsing generated decimals");
+    run(
+        proptest::string::string_regex("[+-]?[0-9]{0,40}(\\.[0-9]{0,40})?([eE][+-]?[0-9]{1,3})?")
+            .unwrap(),
+        |s| {
+            let mut mem = TestMemory::new(Words(64 * 1024));
+            set_bigint_heap(&mut mem);
+            check_decimal(&mut mem, &s)
+        },
+    );
+
+    println!("  Testing parsing halfway cases");
+    run((1u64 << 52..1 << 53, 1u32..11), |(m, shift)| {
+        // Exactly halfway between two consecutive floats above 2^53
+        let n = (u128::from(m) << shift) + (1 << (shift - 1));
+        let mut mem = TestMemory::new(Words(64 * 1024));
+        set_bigint_heap(&mut mem);
+        check_decimal(&mut mem, &format!("{}", n))?;
+        check_decimal(&mut mem, &format!("{}.00000000000000000000001", n))?;
+        check_decimal(&mut mem, &format!("{}{}e-20", n, "0".repeat(20)))
+    });
+
+    println!("  Testing parsing malformed inputs");
+    run(
+        proptest::string::string_regex("[-+.0-9eEinfatyINF]{0,12}").unwrap(),
+        |s| {
+            let mut mem = TestMemory::new(Words(64 * 1024));
+            set_bigint_heap(&mut mem);
+            check_decimal(&mut mem, &s)
+        },
+    );
+}
+
+fn test_format() {
+    println!("  Testing formatting against snprintf");
+    // Formatting used to be done with `snprintf`, this checks that the output did not change
+    let fmts = ["%.*f\0", "%.*e\0", "%.*g\0", "%.*a\0"];
+    run(
+        (proptest::num::f64::ANY, 0usize..=100, 0..fmts.len()),
+        |(f, prec, mode)| {
+            let mut expected = [0u8; 512];
+            let expected_len = unsafe {
+                libc::snprintf(
+                    expected.as_mut_ptr() as *mut _,
+                    expected.len(),
+                    fmts[mode].as_ptr() as *const _,
+                    prec as libc::c_int,
+                    f,
+                )
+            };
+            let expected = &expected[..expected_len as usize];
+            let actual = format(f, prec, mode as u32);
+            if actual.as_bytes() == expected {
+                Ok(())
+            } else {
+                Err(TestCaseError::Fail(
+                    format!(
+                        "{:?} with precision {} in mode {}: {:?}, snprintf gives {:?}",
+                        f,
+                        prec,
+                        mode,
+                        actual,
+                        String::from_utf8_lossy(expected)
+                    )
+                    .into(),
+                ))
+            }
+        },
+    );
+
+    println!("  Testing shortest formatting");
+    let cases = [
+        (0.0, "0"),
+        (-0.0, "-0"),
+        (0.1, "0.1"),
+        (-2.5, "-2.5"),
+        (123.456, "123.456"),
+        (1e20, "100000000000000000000"),
+        (1e21, "1e+21"),
+        (0.000001, "0.000001"),
+        (1.5e-7, "1.5e-7"),
+        (5e-324, "5e-324"),
+        (f64::MAX, "1.7976931348623157e+308"),
+        (f64::INFINITY, "inf"),
+        (-f64::INFINITY, "-inf"),
+        (f64::NAN, "nan"),
+    ];
+    for (f, expected) in cases.iter() {
+        assert_eq!(format(*f, 0, FMT_SHORTEST), *expected);
+    }
+
+    run(
+        proptest::num::f64::NORMAL | proptest::num::f64::SUBNORMAL,
+        |f| {
+            let s = format(f, 0, FMT_SHORTEST);
+            if s.parse::<f64>() != Ok(f) {
+                return Err(TestCaseError::Fail(
+                    format!("{:?} formatted as {}", f, s).into(),
+                ));
+            }
+            // Same number of significant digits as the shortest representation found by Rust
+            let digits = |s: &str| {
+                let mantissa = s.split('e').next().unwrap();
+                let digits = mantissa
+                    .trim_start_matches(&['-', '0', '.'][..])
+                    .replace('.', "");
+                digits.trim_end_matches('0').len()
+            };
+            if digits(&s) != digits(&format!("{:e}", f)) {
+                return Err(TestCaseError::Fail(
+                    format!("{:?} formatted as {}", f, s).into(),
+                ));
+            }
+            Ok(())
+        },
+    );
+}
+
+fn format(f: f64, prec: usize, mode: u32) -> String {
+    let mut buf = [0u8; MAX_FORMATTED_LEN];
+    let len = format_float(&mut buf, f, prec, mode);
+    String::from_utf8(buf[..len].to_vec()).unwrap()
+}
+
+fn run<S: Strategy>(strategy: S, test: impl Fn(S::Value) -> TestCaseResult) {
+    let mut proptest_runner = TestRunner::new(Config {
+        cases: 10_000,
+        failure_persistence: None,
+        ..Default::default()
+    });
+
+    proptest_runner.run(&strategy, test).unwrap();
+}
+
+/// Builds a balanced rope out of short pieces, so that parsing has to work across rope nodes
+unsafe fn rope_of_str<M: Memory>(mem: &mut M, s: &str) -> Value {
+    if s.len() <= 4 {
+        text_of_str(mem, s)
+    } else {
+        // Inputs are ASCII, so any split is at a character boundary
+        let (s1, s2) = s.split_at(s.len() / 2);
+        let t1 = rope_of_str(mem, s1);
+        let t2 = rope_of_str(mem, s2);
+        text_concat(mem, t1, t2)
+    }
+}
+
+fn same(a: Option<f64>, b: Option<f64>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a.to_bits() == b.to_bits() || (a.is_nan() && b.is_nan()),
+        (None, None) => true,
+        _ => false,
+    }
+}
+
+/// Compares with the parser of the Rust standard library, which is correctly rounded
+fn check_decimal<M: Memory>(mem: &mut M, s: &str) -> TestCaseResult {
+    let expected = s.parse::<f64>().ok();
+    let actual = unsafe { parse_float(rope_of_str(mem, s)) };
+    if same(actual, expected) {
+        Ok(())
+    } else {
+        Err(TestCaseError::Fail(
+            format!("{:?}: parsed as {:?}, expected {:?}", s, actual, expected).into(),
+        ))
+    }
+}
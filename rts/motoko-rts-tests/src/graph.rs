@@ -0,0 +1,255 @@
+//! Heap-graph serialization tests
+
+use crate::memory::TestMemory;
+
+use motoko_rts::graph::{
+    deserialize, graph_has_signature, graph_image_size, graph_serialize, HashPool,
+};
+use motoko_rts::memory::{alloc_array, alloc_blob, Memory};
+use motoko_rts::stream::{alloc_stream, alloc_stream_reader};
+use motoko_rts::types::*;
+
+static mut IMAGE: *mut Vec<u8> = std::ptr::null_mut();
+
+extern "C" fn collect(_stream: *mut Stream, ptr: *const u8, n: u32) {
+    unsafe { (*IMAGE).extend_from_slice(std::slice::from_raw_parts(ptr, n as usize)) }
+}
+
+static mut SOURCE: &[u8] = &[];
+
+fn from_source(reader: *mut StreamReader, ptr: *mut u8, n: Bytes<u32>) {
+    unsafe {
+        let start = (*reader).ptr64 as usize;
+        let bytes = &SOURCE[start..start + n.as_usize()];
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+        (*reader).ptr64 += n.as_u32() as u64;
+    }
+}
+
+pub unsafe fn test() {
+    println!("Testing heap-graph serialization ...");
+
+    let mut mem = TestMemory::new(Words(1024 * 1024));
+    let mut pool = Box::new(HashPool::new());
+    let no_statics = blob_of(&mut mem, &[]);
+
+    println!("  Testing scalar roots");
+    let image = serialize(&mut mem, Value::from_scalar(42));
+    let null = alloc_object(&mut mem, TAG_NULL, &[]);
+    let root = roundtrip(&mut mem, &image, null, no_statics, &mut pool);
+    assert_eq!(root.get_scalar(), 42);
+
+    println!("  Testing signatures");
+    SOURCE = Box::leak(image.clone().into_boxed_slice());
+    for other in [&b"(Nat, Text, Int)"[..], &b"(Nat, Tex)"[..]] {
+        let other = blob_of(&mut mem, other);
+        let reader = alloc_stream_reader(&mut mem, Bytes(100));
+        reader.setup_source(0, image.len() as u64, from_source);
+        assert!(!graph_has_signature(reader, other));
+    }
+
+    println!("  Testing sharing and cycles");
+    let old_null = alloc_object(&mut mem, TAG_NULL, &[]);
+    let blob = blob_of(&mut mem, b"shared");
+    let array = alloc_array(&mut mem, 7);
+    let mutbox = alloc_object(&mut mem, TAG_MUTBOX, &[array]);
+    let some = alloc_object(&mut mem, TAG_SOME, &[old_null]);
+    let bits = mem.alloc_words(size_of::<Bits64>());
+    (*bits.as_obj()).tag = TAG_BITS64;
+    (*(bits.as_obj() as *mut Bits64)).set_bits(0x0123_4567_89AB_CDEF);
+    set_fields(
+        array,
+        &[
+            blob,
+            blob,
+            mutbox,
+            Value::from_scalar(42),
+            old_null,
+            some,
+            bits,
+        ],
+    );
+
+    let hashes: &'static [u32] = Box::leak(vec![1, 7].into_boxed_slice());
+    let obj_ind = alloc_object(&mut mem, TAG_OBJ_IND, &[Value::from_scalar(3)]);
+    let object = mem.alloc_words(size_of::<Object>() + Words(2));
+    (*object.as_obj()).tag = TAG_OBJECT;
+    (*(object.as_obj() as *mut Object)).size = 2;
+    (*(object.as_obj() as *mut Object)).hash_ptr = skew(hashes.as_ptr() as usize) as u32;
+    set_fields(object, &[array, obj_ind]);
+    let variant = mem.alloc_words(size_of::<Variant>());
+    (*variant.as_obj()).tag = TAG_VARIANT;
+    (*(variant.as_obj() as *mut Variant)).tag = 5;
+    (*(variant.as_obj() as *mut Variant)).field = object;
+
+    let root = alloc_array(&mut mem, 3);
+    set_fields(root, &[variant, object, array]);
+
+    let image = serialize(&mut mem, root);
+    // Every object once, in few more words than the objects themselves
+    assert!(image.len() < 4 * 64);
+    assert_eq!(serialize(&mut mem, root), image);
+
+    let new_root = roundtrip(&mut mem, &image, null, no_statics, &mut pool);
+    assert_ne!(new_root.get_raw(), root.get_raw());
+    let variant = field(new_root, 0);
+    let object = field(new_root, 1);
+    let array = field(new_root, 2);
+    assert_eq!(variant.tag(), TAG_VARIANT);
+    assert_eq!((*(variant.as_obj() as *mut Variant)).tag, 5);
+    assert!((*(variant.as_obj() as *mut Variant)).field == object);
+
+    assert_eq!(object.tag(), TAG_OBJECT);
+    let new_hashes = unskew((*(object.as_obj() as *mut Object)).hash_ptr as usize);
+    assert_ne!(new_hashes, hashes.as_ptr() as usize); // in the pool
+    assert_eq!(
+        std::slice::from_raw_parts(new_hashes as *const u32, 2),
+        hashes
+    );
+    assert!(field(object, 0) == array);
+    let obj_ind = field(object, 1);
+    assert_eq!(obj_ind.tag(), TAG_OBJ_IND);
+    assert_eq!((*(obj_ind.as_obj() as *mut ObjInd)).field.get_scalar(), 3);
+
+    assert_eq!(array.tag(), TAG_ARRAY);
+    assert!(field(array, 0) == field(array, 1));
+    let blob = field(array, 0).as_blob();
+    assert_eq!(
+        std::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize()),
+        b"shared"
+    );
+    let mutbox = field(array, 2);
+    assert_eq!(mutbox.tag(), TAG_MUTBOX);
+    assert!((*(mutbox.as_obj() as *mut MutBox)).field == array);
+    assert_eq!(field(array, 3).get_scalar(), 42);
+    assert!(field(array, 4) == null);
+    let some = field(array, 5);
+    assert!((*(some.as_obj() as *mut Some)).field == null);
+    let bits = field(array, 6);
+    assert_eq!(
+        (*(bits.as_obj() as *mut Bits64)).bits(),
+        0x0123_4567_89AB_CDEF
+    );
+
+    // The copy has the same image
+    assert_eq!(serialize(&mut mem, new_root), image);
+
+    // Static hash arrays of the program are reused
+    let hash_ptr = skew(hashes.as_ptr() as usize) as u32;
+    let mut table = 2u32.to_le_bytes().to_vec();
+    table.extend_from_slice(&hash_ptr.to_le_bytes());
+    let statics = blob_of(&mut mem, &table);
+    let new_root = roundtrip(&mut mem, &image, null, statics, &mut pool);
+    let object = field(new_root, 1);
+    assert_eq!((*(object.as_obj() as *mut Object)).hash_ptr, hash_ptr);
+
+    println!("  Testing big integers");
+    let bigint = mem.alloc_words(size_of::<BigInt>() + Words(3));
+    (*bigint.as_obj()).tag = TAG_BIGINT;
+    let mp_int = &mut (*(bigint.as_obj() as *mut BigInt)).mp_int;
+    mp_int.alloc = 3;
+    mp_int.used = 2;
+    mp_int.sign = 1;
+    mp_int.dp = (bigint.as_obj() as *mut BigInt).payload_addr();
+    *mp_int.dp = 5;
+    *mp_int.dp.add(1) = 3;
+    let image = serialize(&mut mem, bigint);
+    let new_bigint = roundtrip(&mut mem, &image, null, no_statics, &mut pool);
+    assert_eq!(new_bigint.tag(), TAG_BIGINT);
+    let new_int = new_bigint.as_obj() as *mut BigInt;
+    assert_eq!((*new_int).mp_int.alloc, 3);
+    assert_eq!((*new_int).mp_int.used, 2);
+    assert_eq!((*new_int).mp_int.sign, 1);
+    assert_eq!(*new_int.payload_addr(), 5);
+    assert_eq!(*new_int.payload_addr().add(1), 3);
+
+    println!("  Testing many objects");
+    let n = 5000;
+    let array = alloc_array(&mut mem, 2 * n);
+    let mut fields = vec![];
+    for i in 0..n {
+        let blob = blob_of(&mut mem, &i.to_le_bytes());
+        fields.push(blob);
+        fields.push(blob);
+    }
+    set_fields(array, &fields);
+    let image = serialize(&mut mem, array);
+    let new_array = roundtrip(&mut mem, &image, null, no_statics, &mut pool);
+    for i in 0..n {
+        let blob = field(new_array, 2 * i);
+        assert!(blob == field(new_array, 2 * i + 1));
+        assert_eq!(
+            std::slice::from_raw_parts(blob.as_blob().payload_const(), 4),
+            &i.to_le_bytes()
+        );
+    }
+    assert_eq!(serialize(&mut mem, new_array), image);
+}
+
+const SIGNATURE: &[u8] = b"(Nat, Text)";
+
+unsafe fn serialize(mem: &mut TestMemory, root: Value) -> Vec<u8> {
+    IMAGE = Box::into_raw(Box::new(vec![]));
+    let signature = blob_of(mem, SIGNATURE);
+    let stream = alloc_stream(mem, Bytes(64));
+    stream.setup_callback_dest(collect);
+    graph_serialize(mem, stream, root, signature);
+    stream.shutdown();
+    let image = *Box::from_raw(IMAGE);
+    assert_eq!(graph_image_size(mem, root, signature), image.len() as u64);
+    image
+}
+
+unsafe fn roundtrip(
+    mem: &mut TestMemory,
+    image: &[u8],
+    null: Value,
+    statics: Value,
+    pool: &mut HashPool,
+) -> Value {
+    SOURCE = Box::leak(image.to_vec().into_boxed_slice());
+    let signature = blob_of(mem, SIGNATURE);
+    let reader = alloc_stream_reader(mem, Bytes(100));
+    reader.setup_source(0, image.len() as u64, from_source);
+    assert!(graph_has_signature(reader, signature));
+    let root = deserialize(mem, reader, null, statics, pool);
+    assert_eq!(reader.remaining(), 0);
+    root
+}
+
+unsafe fn alloc_object(mem: &mut TestMemory, tag: Tag, fields: &[Value]) -> Value {
+    let value = mem.alloc_words(size_of::<Obj>() + Words(fields.len() as u32));
+    (*value.as_obj()).tag = tag;
+    let payload = value.as_obj().add(1) as *mut Value;
+    for (i, field) in fields.iter().enumerate() {
+        *payload.add(i) = *field;
+    }
+    value
+}
+
+unsafe fn blob_of(mem: &mut TestMemory, bytes: &[u8]) -> Value {
+    let blob = alloc_blob(mem, Bytes(bytes.len() as u32));
+    let payload = blob.as_blob_mut().payload_addr();
+    std::ptr::copy_nonoverlapping(bytes.as_ptr(), payload, bytes.len());
+    blob
+}
+
+/// Sets the fields of an array or object
+unsafe fn set_fields(value: Value, fields: &[Value]) {
+    let payload = match value.tag() {
+        TAG_ARRAY => value.as_array().payload_addr(),
+        _ => (value.as_obj() as *mut Object).payload_addr(),
+    };
+    for (i, field) in fields.iter().enumerate() {
+        *payload.add(i) = *field;
+    }
+}
+
+unsafe fn field(value: Value, idx: u32) -> Value {
+    match value.tag() {
+        TAG_ARRAY => value.as_array().get(idx),
+        _ => *(value.as_obj() as *mut Object)
+            .payload_addr()
+            .add(idx as usize),
+    }
+}
@@ -0,0 +1,1103 @@
+use crate::bigint::set_bigint_heap;
+use crate::memory::TestMemory;
+
+use motoko_rts::bigint::bigint_to_text;
+use motoko_rts::buf::Buf;
+use motoko_rts::idl::canonical::{idl_canonical_header, idl_structural_hash, structural_hash};
+use motoko_rts::idl::error::{
+    idl_end_message, idl_error_pop, idl_error_push_elem, idl_error_push_field, idl_error_push_opt,
+    idl_error_push_var, idl_error_push_variant, idl_error_set_elem, path_len, set_segment,
+    set_type, start_message, write_context, Segment,
+};
+use motoko_rts::idl::print::{idl_print_args, MAX_DEPTH};
+use motoko_rts::idl::quota::{
+    decoding_quota, decoding_work, idl_set_decoding_quota, DEFAULT_BASE_QUOTA,
+    DEFAULT_QUOTA_PER_BYTE,
+};
+use motoko_rts::idl::subtype::{idl_sub, idl_sub_cache};
+use motoko_rts::idl::value::*;
+use motoko_rts::idl::{find_non_productive_type, parse_idl_header, skip_any};
+use motoko_rts::leb128::{leb128_decode, sleb128_decode};
+use motoko_rts::text::blob_of_text;
+use motoko_rts::types::{Bits64, Value, Variant, Words, TAG_VARIANT};
+
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+use std::iter::once;
+
+// Sample messages, used for both decoding and printing
+
+/// (42 : nat)
+const NAT: &[u8] = b"DIDL\x00\x01\x7d\x2a";
+
+/// One argument of each primitive type (except `nat`, `int`, `empty` and the other sizes)
+const PRIMITIVES: &[u8] = b"DIDL\x00\x0a\x7e\x74\x72\x71\x68\x70\x7f\x73\x78\x77\
+    \x01\
+    \xff\xff\xff\xff\xff\xff\xff\xff\
+    \x00\x00\x00\x00\x00\x00\xf8\x3f\
+    \x02hi\
+    \x01\x03\x01\x02\x03\
+    \x00\x00\x20\xc0\
+    \xff\xff\xff\xff\xff\xff\xff\xff\
+    \x80";
+
+/// record { 0 : nat8; 1 : vec text; 2 : opt variant { 5 : null; 7 : int } }
+const NESTED: &[u8] = b"DIDL\x04\
+    \x6c\x03\x00\x7b\x01\x01\x02\x02\
+    \x6d\x71\
+    \x6e\x03\
+    \x6b\x02\x05\x7f\x07\x7c\
+    \x01\x00\
+    \xff\
+    \x02\x01a\x02bc\
+    \x01\x01\x7d";
+
+/// Empty compound values, and `int` and `nat` beyond 64 bits
+const EMPTY_AND_BIG: &[u8] = b"DIDL\x03\x6d\x7d\x6e\x7d\x6c\x00\x05\x00\x01\x02\x7d\x7c\
+    \x00\x00\
+    \x80\x80\x80\x80\x80\x80\x80\x80\x80\x80\x01\
+    \x80\x80\x80\x80\x80\x80\x80\x80\x80\x80\x7f";
+
+/// func () -> () and service {}
+const REFERENCES: &[u8] = b"DIDL\x02\x6a\x00\x00\x00\x69\x00\x02\x00\x01\
+    \x01\x01\x03\xaa\xbb\xcc\x03foo\
+    \x01\x02\xaa\xbb";
+
+/// A future type, which is skipped, and null
+const FUTURE: &[u8] = b"DIDL\x01\x67\x00\x02\x00\x7f\x02\x00\xaa\xbb";
+
+/// Future types with type codes -25, -26 and -100, whose table entries and values carry data,
+/// and a `composite_query` function
+const FUTURES: &[u8] = b"DIDL\x04\x67\x00\x66\x03abc\x9c\x7f\x01\xff\x6a\x00\x00\x01\x03\
+    \x04\x00\x01\x02\x03\
+    \x02\x00\xaa\xbb\
+    \x00\x00\
+    \x01\x00\xcc\
+    \x01\x01\x03\xaa\xbb\xcc\x03foo";
+
+/// vec nat8, record { 1 : text; 5 : int } and variant { 3 : null }
+const BLOB_AND_FIELDS: &[u8] = b"DIDL\x03\x6d\x7b\x6c\x02\x01\x71\x05\x7c\x6b\x01\x03\x7f\
+    \x03\x00\x01\x02\
+    \x04a\"\x00\xff\
+    \x05x\n\"\xc3\xa9\x07\
+    \x00";
+
+/// type list = opt record { 0 : nat; 1 : list }, with `len` elements
+fn list(len: u32) -> Vec<u8> {
+    let mut message = b"DIDL\x02\x6e\x01\x6c\x02\x00\x7d\x01\x00\x01\x00".to_vec();
+    for i in 0..len {
+        message.push(1);
+        leb128(&mut message, i);
+    }
+    message.push(0);
+    message
+}
+
+pub unsafe fn test() {
+    println!("Testing IDL ...");
+
+    let mut heap = TestMemory::new(Words(1024 * 1024));
+    set_bigint_heap(&mut heap);
+
+    test_decode(&mut heap);
+    test_print(&mut heap);
+    test_skip(&mut heap);
+    test_quota(&mut heap);
+    test_error_context(&mut heap);
+    test_canonical(&mut heap);
+    test_subtype();
+}
+
+unsafe fn test_decode(heap: &mut TestMemory) {
+    println!("  Testing value decoding");
+
+    check_decode(heap, NAT, "(nat 42)");
+    check_decode(
+        heap,
+        PRIMITIVES,
+        "(true, int64 -1, float64 1.5, \"hi\", principal 010203, reserved, null, \
+         float32 -2.5, nat64 18446744073709551615, int8 -128)",
+    );
+    check_decode(
+        heap,
+        NESTED,
+        "(record {0 = nat8 255; 1 = vec {\"a\"; \"bc\"}; 2 = opt variant {7 = int -3}})",
+    );
+    check_decode(
+        heap,
+        EMPTY_AND_BIG,
+        "(vec {}, null, record {}, nat 1180591620717411303424, \
+         int -1180591620717411303424)",
+    );
+    check_decode(heap, REFERENCES, "(func aabbcc.\"foo\", service aabb)");
+    check_decode(heap, FUTURE, "(reserved, null)");
+    check_decode(
+        heap,
+        FUTURES,
+        "(reserved, reserved, reserved, func aabbcc.\"foo\")",
+    );
+
+    // A long list, which the decoder must not handle by recursion
+    const LIST_LEN: u32 = 4000;
+    let args = decode_args(heap, &mut list(LIST_LEN));
+    let mut list = args.as_array().get(0);
+    for i in 0..LIST_LEN {
+        let (tag, opt) = variant(list);
+        assert_eq!(tag, VALUE_opt);
+        assert_eq!(opt.as_array().len(), 1);
+        let (tag, record) = variant(opt.as_array().get(0));
+        assert_eq!(tag, VALUE_record);
+        let fields = record.as_array();
+        assert_eq!(fields.len(), 2);
+        let head = fields.get(0).as_array().get(1);
+        assert_eq!(show(heap, head), format!("nat {}", i));
+        list = fields.get(1).as_array().get(1);
+    }
+    assert_eq!(show(heap, list), "null");
+}
+
+unsafe fn test_print(heap: &mut TestMemory) {
+    println!("  Testing printing");
+
+    let cases: [(&[u8], &str); 8] = [
+        (NAT, "(42)"),
+        (
+            PRIMITIVES,
+            r#"(true, -1 : int64, 1.5 : float64, "hi", principal "kw6ia-hibai-bq", reserved, null, -2.5 : float32, 18446744073709551615 : nat64, -128 : int8)"#,
+        ),
+        (
+            NESTED,
+            r#"(record { 255 : nat8; vec { "a"; "bc" }; opt variant { 7 = -3 } })"#,
+        ),
+        (
+            EMPTY_AND_BIG,
+            "(vec {}, null, record {}, 1180591620717411303424, -1180591620717411303424)",
+        ),
+        (
+            REFERENCES,
+            r#"(func "xzg7q-tfkxp-ga"."foo", service "jgbcz-gfkxm")"#,
+        ),
+        (FUTURE, "(reserved, null)"),
+        (
+            FUTURES,
+            r#"(reserved, reserved, reserved, func "xzg7q-tfkxp-ga"."foo")"#,
+        ),
+        (
+            BLOB_AND_FIELDS,
+            r#"(blob "a\22\00\ff", record { 1 = "x\n\"é"; 5 = +7 }, variant { 3 })"#,
+        ),
+    ];
+    for (message, expected) in cases.iter() {
+        assert_eq!(print_args(heap, message, 1000, 100), *expected);
+    }
+
+    // Deeper values are elided
+    assert_eq!(
+        print_args(heap, &list(100), 1000, 3),
+        "(opt record { 0; opt ... })"
+    );
+    assert_eq!(print_args(heap, NESTED, 1000, 0), "(...)");
+    // However deep the caller asks for, as printing recurses
+    let printed = print_args(heap, &list(4000), 100_000, u32::MAX);
+    assert_eq!(printed.matches("opt ").count() as u32, MAX_DEPTH / 2);
+    assert!(printed.contains("; opt ... }"));
+    assert_eq!(
+        print_args(heap, NESTED, 1000, 1),
+        r#"(record { 255 : nat8; ...; ... })"#
+    );
+
+    // Truncated output is a prefix of the full output, at a character boundary
+    for (message, expected) in cases.iter() {
+        for max_len in 0..expected.len() + 2 {
+            let printed = print_args(heap, message, max_len as u32, 100);
+            if max_len >= expected.len() {
+                assert_eq!(printed, *expected);
+            } else {
+                assert!(printed.len() <= max_len.max(3), "{:?}", printed);
+                let prefix = printed.strip_suffix("...").unwrap();
+                assert!(expected.starts_with(prefix), "{:?}", printed);
+            }
+        }
+    }
+}
+
+unsafe fn test_skip(heap: &mut TestMemory) {
+    println!("  Testing skipping");
+
+    let mut messages: Vec<Vec<u8>> = [
+        NAT,
+        PRIMITIVES,
+        NESTED,
+        EMPTY_AND_BIG,
+        REFERENCES,
+        FUTURE,
+        FUTURES,
+        BLOB_AND_FIELDS,
+    ]
+    .iter()
+    .map(|message| message.to_vec())
+    .collect();
+
+    // A long list, which must not be skipped by recursion
+    messages.push(list(4000));
+
+    // `vec null` and `vec record {}` with 2^32-1 elements, which take no input
+    messages.push(b"DIDL\x01\x6d\x7f\x01\x00\xff\xff\xff\xff\x0f".to_vec());
+    messages.push(b"DIDL\x02\x6d\x01\x6c\x00\x01\x00\xff\xff\xff\xff\x0f".to_vec());
+
+    // `vec nat64`, skipped at once
+    let mut message = b"DIDL\x01\x6d\x78\x01\x00\x80\x80\x01".to_vec();
+    message.resize(message.len() + 16384 * 8, 0xab);
+    messages.push(message);
+
+    for message in messages.iter_mut() {
+        skip_args(heap, message);
+    }
+
+    // Non-productive recursive types
+    let mut types = Types::default();
+    let looping = types.reserve();
+    types.set(looping, record(&[(0, T_NAT), (1, looping)]));
+    assert_eq!(non_productive_type(heap, &types), Some(looping as u32));
+
+    let mut types = Types::default();
+    let a = types.reserve();
+    let b = types.add(record(&[(0, T_TEXT), (2, a)]));
+    let c = types.add(record(&[(0, b)]));
+    types.set(a, record(&[(0, T_NAT), (1, c)]));
+    assert!(non_productive_type(heap, &types).is_some());
+
+    // Recursion through other constructors takes input, and records may be shared
+    let mut types = Types::default();
+    let list_nat = types.reserve();
+    let list_node = types.add(record(&[(0, T_NAT), (1, list_nat)]));
+    types.set(list_nat, opt(list_node));
+    tree(&mut types, T_NAT);
+    let stream = types.reserve();
+    types.set(stream, variant_type(&[(0, stream)]));
+    let leaf = types.add(record(&[(0, T_NAT)]));
+    let pair = types.add(record(&[(0, leaf), (1, leaf)]));
+    types.add(record(&[(0, pair), (1, leaf), (2, pair)]));
+    assert_eq!(non_productive_type(heap, &types), None);
+}
+
+/// Skips all arguments of a message, which must end right after them
+unsafe fn skip_args(heap: &mut TestMemory, message: &mut [u8]) {
+    skip_args_extended(heap, false, message)
+}
+
+unsafe fn skip_args_extended(heap: &mut TestMemory, extended: bool, message: &mut [u8]) {
+    let mut buf = Buf {
+        ptr: message.as_mut_ptr(),
+        end: message.as_mut_ptr().add(message.len()),
+    };
+    let mut typtbl = std::ptr::null_mut();
+    let mut n_types = 0;
+    let mut main_types = std::ptr::null_mut();
+    parse_idl_header(
+        heap,
+        extended,
+        &mut buf,
+        &mut typtbl,
+        &mut n_types,
+        &mut main_types,
+    );
+
+    let mut main_types = Buf {
+        ptr: main_types,
+        end: buf.end,
+    };
+    for _ in 0..leb128_decode(&mut main_types) {
+        let t = sleb128_decode(&mut main_types);
+        skip_any(heap, &mut buf, typtbl, t, 0);
+    }
+    let (ptr, end) = (buf.ptr, buf.end);
+    assert_eq!(ptr, end);
+}
+
+/// Looks for non-productive recursive types, without parsing (which rejects them)
+unsafe fn non_productive_type(heap: &mut TestMemory, types: &Types) -> Option<u32> {
+    let mut bytes = types.entries.concat();
+    let start = bytes.as_mut_ptr();
+    let mut typtbl = vec![];
+    let mut offset = 0;
+    for entry in types.entries.iter() {
+        typtbl.push(start.add(offset));
+        offset += entry.len();
+    }
+    find_non_productive_type(
+        heap,
+        typtbl.as_mut_ptr(),
+        typtbl.len() as u32,
+        start.add(bytes.len()),
+    )
+}
+
+unsafe fn test_quota(heap: &mut TestMemory) {
+    println!("  Testing decoding quota");
+
+    // No budget, to measure the work
+    idl_set_decoding_quota(u32::MAX, u32::MAX);
+
+    // Decoding charges every value, and vectors for their elements up front
+    decode_args(heap, &mut NESTED.to_vec());
+    assert_eq!(decoding_work(), 10);
+
+    // Skipping lists is linear in their size
+    for len in [0, 1, 10, 100, 1000, 4000].iter() {
+        let mut message = list(*len);
+        skip_args(heap, &mut message);
+        assert_eq!(decoding_work(), 3 * u64::from(*len) + 1);
+        assert!(decoding_work() <= 2 * message.len() as u64);
+    }
+
+    // Vectors of values that take no input take constant work, however long they are
+    for message in [
+        b"DIDL\x01\x6d\x7f\x01\x00\xff\xff\xff\xff\x0f".to_vec(),
+        b"DIDL\x02\x6d\x01\x6c\x00\x01\x00\xff\xff\xff\xff\x0f".to_vec(),
+    ]
+    .iter_mut()
+    {
+        skip_args(heap, message);
+        assert!(decoding_work() <= 2);
+    }
+
+    // Records of nulls in a vector take work linear in the size of the message for a given
+    // type, but quadratic in the size of the message overall, which the default budget stops
+    let mut proptest_runner = TestRunner::new(Config {
+        cases: 1_000,
+        failure_persistence: None,
+        ..Default::default()
+    });
+    proptest_runner
+        .run(&(0u32..500, 0u32..64), |(count, k)| {
+            let mut heap = TestMemory::new(Words(64 * 1024));
+            let mut message = null_bomb(count, k);
+            skip_args(&mut heap, &mut message);
+            let work = decoding_work();
+            if work != 1 + u64::from(count) * u64::from(k + 3) {
+                return Err(TestCaseError::Fail(format!("work: {}", work).into()));
+            }
+            if work > u64::from(k + 3) * message.len() as u64 {
+                return Err(TestCaseError::Fail("work not linear".into()));
+            }
+            Ok(())
+        })
+        .unwrap();
+
+    let mut message = null_bomb(1000, 1000);
+    skip_args(heap, &mut message);
+    let quota =
+        u64::from(DEFAULT_QUOTA_PER_BYTE) * message.len() as u64 + u64::from(DEFAULT_BASE_QUOTA);
+    assert!(decoding_work() > quota);
+
+    // The default budget would stop it, as messages get the budget of their size, except in
+    // stable variables
+    idl_set_decoding_quota(DEFAULT_QUOTA_PER_BYTE, DEFAULT_BASE_QUOTA);
+    decode_args(heap, &mut NESTED.to_vec());
+    assert_eq!(
+        decoding_quota(),
+        u64::from(DEFAULT_QUOTA_PER_BYTE) * NESTED.len() as u64 + u64::from(DEFAULT_BASE_QUOTA)
+    );
+    skip_args_extended(heap, true, &mut message);
+    assert_eq!(decoding_quota(), u64::MAX);
+    assert!(decoding_work() > quota);
+}
+
+/// `vec record { bool; record { null; ... } }` with `count` elements and `k` nulls in the inner
+/// record, which take no input
+fn null_bomb(count: u32, k: u32) -> Vec<u8> {
+    let mut types = Types::default();
+    let nulls: Vec<(u32, i32)> = (0..k).map(|id| (id, T_NULL)).collect();
+    let inner = types.add(record(&nulls));
+    let element = types.add(record(&[(0, T_BOOL), (1, inner)]));
+    let bomb = types.add(vec(element));
+    let mut message = types.message(&[bomb]);
+    leb128(&mut message, count);
+    message.resize(message.len() + count as usize, 1);
+    message
+}
+
+unsafe fn test_error_context(heap: &mut TestMemory) {
+    println!("  Testing error context");
+
+    let mut message = NESTED.to_vec();
+    let mut buf = Buf {
+        ptr: message.as_mut_ptr(),
+        end: message.as_mut_ptr().add(message.len()),
+    };
+    // The context refers to the buffer, which moves on
+    let buf: *mut Buf = &mut buf;
+    start_message(buf);
+    assert_eq!(error_context(), " (at byte 0)");
+
+    (*buf).ptr = (*buf).ptr.add(12);
+    set_type(2);
+    set_segment(0, Segment::Arg(0));
+    set_segment(1, Segment::Field(3));
+    set_segment(2, Segment::Elem(12));
+    assert_eq!(
+        error_context(),
+        " (at byte 12, type 2, in arg 0 → field 3 → vec[12])"
+    );
+
+    // Setting a segment drops the deeper ones, primitive types have no index
+    set_type(-15);
+    set_segment(1, Segment::Variant(7));
+    assert_eq!(error_context(), " (at byte 12, in arg 0 → variant 7)");
+
+    // Only the outer segments of long paths are shown
+    for depth in 1..40 {
+        set_segment(depth, Segment::Opt);
+    }
+    assert!(error_context().ends_with("opt → opt → ... (8 more))"));
+
+    // A buffer that moved on to other data
+    (*buf).ptr = (*buf).end.add(1);
+    assert!(error_context().starts_with(" (at unknown byte, in arg 0"));
+
+    // Generated code pushes and pops segments as it descends
+    (*buf).ptr = (*buf).ptr.sub(1);
+    set_segment(0, Segment::Arg(1));
+    idl_error_push_field(5);
+    idl_error_push_elem();
+    idl_error_set_elem(2);
+    idl_error_push_opt();
+    assert!(error_context().ends_with("in arg 1 → field 5 → vec[2] → opt)"));
+    idl_error_pop();
+    idl_error_set_elem(3);
+    idl_error_push_variant(9);
+    idl_error_push_var();
+    assert!(error_context().ends_with("in arg 1 → field 5 → vec[3] → variant 9 → var)"));
+    for _ in 0..4 {
+        idl_error_pop();
+    }
+    assert!(error_context().ends_with("in arg 1)"));
+
+    idl_end_message();
+    assert_eq!(error_context(), "");
+
+    // Skipping and decoding extend the path temporarily
+    skip_args(heap, &mut NESTED.to_vec());
+    assert_eq!(path_len(), 0);
+    decode_args(heap, &mut NESTED.to_vec());
+    assert_eq!(error_context(), "");
+}
+
+unsafe fn error_context() -> String {
+    let mut context = String::new();
+    write_context(&mut context).unwrap();
+    context
+}
+
+// Primitive types, for the subtype tests
+const T_NULL: i32 = -1;
+const T_BOOL: i32 = -2;
+const T_NAT: i32 = -3;
+const T_INT: i32 = -4;
+const T_NAT8: i32 = -5;
+const T_TEXT: i32 = -15;
+const T_RESERVED: i32 = -16;
+const T_EMPTY: i32 = -17;
+const T_PRINCIPAL: i32 = -24;
+
+unsafe fn test_canonical(heap: &mut TestMemory) {
+    println!("  Testing canonical type tables");
+
+    // `type list = opt record { 0 : nat; 1 : list }`
+    let mut types = Types::default();
+    let list = types.reserve();
+    let node = types.add(record(&[(0, T_NAT), (1, list)]));
+    types.set(list, opt(node));
+    let expected = b"DIDL\x02\x6e\x01\x6c\x02\x00\x7d\x01\x00\x01\x00";
+    assert_eq!(canonical(heap, false, &types.message(&[list])), expected);
+
+    // In the opposite order, with an unused type
+    let mut types = Types::default();
+    types.add(vec(T_TEXT));
+    let node = types.reserve();
+    let list = types.add(opt(node));
+    types.set(node, record(&[(0, T_NAT), (1, list)]));
+    assert_eq!(canonical(heap, false, &types.message(&[list])), expected);
+
+    // Unrolled twice
+    let mut types = Types::default();
+    let list = types.reserve();
+    let node1 = types.reserve();
+    let list2 = types.add(opt(node1));
+    let node2 = types.add(record(&[(0, T_NAT), (1, list2)]));
+    types.set(list, opt(node2));
+    types.set(node1, record(&[(0, T_NAT), (1, list)]));
+    let unrolled = types.message(&[list, list2]);
+    assert_eq!(
+        canonical(heap, false, &unrolled),
+        b"DIDL\x02\x6e\x01\x6c\x02\x00\x7d\x01\x00\x02\x00\x00"
+    );
+
+    // The canonical form is a fixpoint
+    let mut canonical_unrolled = canonical(heap, false, &unrolled);
+    assert_eq!(
+        canonical(heap, false, &canonical_unrolled),
+        canonical_unrolled
+    );
+    assert_eq!(
+        idl_structural_hash(heap, false, &mut buf(&mut canonical_unrolled)),
+        structural_hash(&canonical_unrolled)
+    );
+
+    // Only primitive types
+    assert_eq!(canonical(heap, false, NAT), b"DIDL\x00\x01\x7d");
+
+    // Different element types, field ids and annotations make different types
+    let hash = |heap: &mut TestMemory, entries: Vec<Vec<u8>>| {
+        let types = Types { entries };
+        structural_hash(&canonical(heap, true, &types.message(&[0])))
+    };
+    let hashes = [
+        hash(heap, vec![record(&[(0, T_NAT), (1, 1)]), opt(0)]),
+        hash(heap, vec![record(&[(0, T_INT), (1, 1)]), opt(0)]),
+        hash(heap, vec![record(&[(0, T_NAT), (2, 1)]), opt(0)]),
+        hash(heap, vec![record(&[(0, T_NAT), (1, 1)]), vec(0)]),
+        hash(heap, vec![func(&[T_NAT], &[], &[])]),
+        hash(heap, vec![func(&[T_NAT], &[], &[1])]),
+        hash(heap, vec![func(&[], &[T_NAT], &[])]),
+        hash(
+            heap,
+            vec![service(&[("get", 1)]), func(&[], &[T_NAT], &[1])],
+        ),
+        hash(
+            heap,
+            vec![service(&[("put", 1)]), func(&[], &[T_NAT], &[1])],
+        ),
+        // Mutable stable variables
+        hash(heap, vec![alias(1), record(&[])]),
+        hash(heap, vec![record(&[])]),
+        hash(heap, vec![alias(T_NAT)]),
+    ];
+    for (i, hash1) in hashes.iter().enumerate() {
+        for hash2 in hashes[..i].iter() {
+            assert_ne!(hash1, hash2);
+        }
+    }
+
+    // Equal methods and mutable variables are merged
+    let mut types = Types::default();
+    let get = types.add(func(&[], &[T_NAT], &[1]));
+    let var = types.add(alias(T_TEXT));
+    let put = types.add(func(&[var], &[], &[]));
+    let var2 = types.add(alias(T_TEXT));
+    let put2 = types.add(func(&[var2], &[], &[]));
+    let get2 = types.add(func(&[], &[T_NAT], &[1]));
+    let actor = types.add(service(&[
+        ("get", get),
+        ("get2", get2),
+        ("put", put),
+        ("put2", put2),
+    ]));
+    let mut expected = Types::default();
+    expected.add(service(&[("get", 2), ("get2", 2), ("put", 3), ("put2", 3)]));
+    expected.add(alias(T_TEXT));
+    expected.add(func(&[], &[T_NAT], &[1]));
+    expected.add(func(&[1], &[], &[]));
+    assert_eq!(
+        canonical(heap, true, &types.message(&[actor, var2])),
+        expected.message(&[0, 1])
+    );
+}
+
+/// The canonical form of the header of a message
+unsafe fn canonical(heap: &mut TestMemory, extended: bool, message: &[u8]) -> Vec<u8> {
+    let mut message = message.to_vec();
+    bytes(idl_canonical_header(heap, extended, &mut buf(&mut message)))
+}
+
+fn buf(message: &mut [u8]) -> Buf {
+    unsafe {
+        Buf {
+            ptr: message.as_mut_ptr(),
+            end: message.as_mut_ptr().add(message.len()),
+        }
+    }
+}
+
+/// A type table under construction
+#[derive(Default)]
+struct Types {
+    entries: Vec<Vec<u8>>,
+}
+
+impl Types {
+    fn add(&mut self, entry: Vec<u8>) -> i32 {
+        self.entries.push(entry);
+        self.entries.len() as i32 - 1
+    }
+
+    /// Adds a placeholder, for recursive types
+    fn reserve(&mut self) -> i32 {
+        self.add(vec![])
+    }
+
+    fn set(&mut self, t: i32, entry: Vec<u8>) {
+        self.entries[t as usize] = entry;
+    }
+
+    /// The header of a message with this type table and arguments of types `args`
+    fn message(&self, args: &[i32]) -> Vec<u8> {
+        let mut message = b"DIDL".to_vec();
+        leb128(&mut message, self.entries.len() as u32);
+        for entry in self.entries.iter() {
+            message.extend_from_slice(entry);
+        }
+        leb128(&mut message, args.len() as u32);
+        for t in args.iter() {
+            sleb128(&mut message, *t);
+        }
+        message
+    }
+}
+
+fn opt(t: i32) -> Vec<u8> {
+    let mut entry = vec![0x6e];
+    sleb128(&mut entry, t);
+    entry
+}
+
+fn vec(t: i32) -> Vec<u8> {
+    let mut entry = vec![0x6d];
+    sleb128(&mut entry, t);
+    entry
+}
+
+fn fields(code: u8, fields: &[(u32, i32)]) -> Vec<u8> {
+    let mut entry = vec![code];
+    leb128(&mut entry, fields.len() as u32);
+    for (id, t) in fields {
+        leb128(&mut entry, *id);
+        sleb128(&mut entry, *t);
+    }
+    entry
+}
+
+fn record(record_fields: &[(u32, i32)]) -> Vec<u8> {
+    fields(0x6c, record_fields)
+}
+
+fn variant_type(variant_fields: &[(u32, i32)]) -> Vec<u8> {
+    fields(0x6b, variant_fields)
+}
+
+fn func(args: &[i32], results: &[i32], annotations: &[u8]) -> Vec<u8> {
+    let mut entry = vec![0x6a];
+    for ts in [args, results].iter() {
+        leb128(&mut entry, ts.len() as u32);
+        for t in ts.iter() {
+            sleb128(&mut entry, *t);
+        }
+    }
+    leb128(&mut entry, annotations.len() as u32);
+    entry.extend_from_slice(annotations);
+    entry
+}
+
+fn service(methods: &[(&str, i32)]) -> Vec<u8> {
+    let mut entry = vec![0x69];
+    leb128(&mut entry, methods.len() as u32);
+    for (name, t) in methods {
+        leb128(&mut entry, name.len() as u32);
+        entry.extend_from_slice(name.as_bytes());
+        sleb128(&mut entry, *t);
+    }
+    entry
+}
+
+/// A mutable stable variable, in the extended type table of stable variables
+fn alias(t: i32) -> Vec<u8> {
+    let mut entry = vec![0x01];
+    sleb128(&mut entry, t);
+    entry
+}
+
+/// `type t = vec record { 0 : elem; 1 : t }`
+fn tree(types: &mut Types, elem: i32) -> i32 {
+    let t = types.reserve();
+    let node = types.add(record(&[(0, elem), (1, t)]));
+    types.set(t, vec(node));
+    t
+}
+
+unsafe fn test_subtype() {
+    println!("  Testing subtyping");
+
+    let mut heap = TestMemory::new(Words(4 * 1024 * 1024));
+
+    let mut types = Types::default();
+
+    let opt_nat = types.add(opt(T_NAT));
+    let opt_int = types.add(opt(T_INT));
+    let opt_text = types.add(opt(T_TEXT));
+
+    let vec_nat = types.add(vec(T_NAT));
+    let vec_int = types.add(vec(T_INT));
+    let vec_text = types.add(vec(T_TEXT));
+
+    let record_empty = types.add(record(&[]));
+    let record_nat = types.add(record(&[(1, T_NAT)]));
+    let record_int = types.add(record(&[(1, T_INT)]));
+    let record_text = types.add(record(&[(1, T_TEXT)]));
+    let record_nat_text = types.add(record(&[(1, T_NAT), (2, T_TEXT)]));
+    let record_opt = types.add(record(&[(1, opt_nat)]));
+    let record_null = types.add(record(&[(1, T_NULL)]));
+    let record_reserved = types.add(record(&[(1, T_RESERVED)]));
+
+    let variant_nat = types.add(variant_type(&[(1, T_NAT)]));
+    let variant_text = types.add(variant_type(&[(1, T_TEXT)]));
+    let variant_int_text = types.add(variant_type(&[(1, T_INT), (2, T_TEXT)]));
+
+    let func_unit = types.add(func(&[], &[], &[]));
+    let func_query = types.add(func(&[], &[], &[1]));
+    let func_oneway = types.add(func(&[], &[], &[2]));
+    let func_composite_query = types.add(func(&[], &[], &[3]));
+    let func_int_nat = types.add(func(&[T_INT], &[T_NAT], &[]));
+    let func_nat_int = types.add(func(&[T_NAT], &[T_INT], &[]));
+    let func_int_unit = types.add(func(&[T_INT], &[], &[]));
+    let func_int_nat_unit = types.add(func(&[T_INT, T_NAT], &[], &[]));
+    let func_int_nat_text = types.add(func(&[T_INT], &[T_NAT, T_TEXT], &[]));
+    let func_nat_opt_int = types.add(func(&[T_NAT, opt_text], &[T_INT], &[]));
+    let func_unit_nat = types.add(func(&[], &[T_NAT], &[]));
+    let func_unit_int = types.add(func(&[], &[T_INT], &[]));
+    let func_unit_opt = types.add(func(&[], &[opt_nat], &[]));
+
+    let service_a = types.add(service(&[("a", func_nat_int)]));
+    let service_a_int_nat = types.add(service(&[("a", func_int_nat)]));
+    let service_b = types.add(service(&[("b", func_unit)]));
+    let service_ab = types.add(service(&[("a", func_int_nat), ("b", func_unit)]));
+
+    // Recursive types: `type list = opt record { 0 : nat; 1 : list }`, trees of `nat`, `int`
+    // and `text`, a tree of `int` unrolled once, and `type loop = variant { 0 : loop }`
+    let list_nat = types.reserve();
+    let list_node = types.add(record(&[(0, T_NAT), (1, list_nat)]));
+    types.set(list_nat, opt(list_node));
+    let tree_nat = tree(&mut types, T_NAT);
+    let tree_int = tree(&mut types, T_INT);
+    let tree_text = tree(&mut types, T_TEXT);
+    let tree_int_unrolled = types.reserve();
+    let inner = types.add(record(&[(0, T_INT), (1, tree_int_unrolled)]));
+    let inner = types.add(vec(inner));
+    let outer = types.add(record(&[(0, T_INT), (1, inner)]));
+    types.set(tree_int_unrolled, vec(outer));
+    let loop_ = types.reserve();
+    types.set(loop_, variant_type(&[(0, loop_)]));
+
+    let positive = [
+        (T_NAT, T_NAT),
+        (T_NAT, T_INT),
+        (T_NAT8, T_NAT8),
+        (T_PRINCIPAL, T_PRINCIPAL),
+        (T_TEXT, T_RESERVED),
+        (record_nat, T_RESERVED),
+        (T_EMPTY, T_NAT),
+        (T_EMPTY, record_nat),
+        (T_NULL, opt_nat),
+        (T_NAT, opt_int),
+        (opt_nat, opt_int),
+        (opt_text, opt_nat),
+        (record_nat, opt_text),
+        (vec_nat, vec_int),
+        (record_nat_text, record_int),
+        (record_nat, record_nat),
+        (record_empty, record_opt),
+        (record_empty, record_null),
+        (record_empty, record_reserved),
+        (variant_nat, variant_int_text),
+        (variant_nat, variant_nat),
+        (func_unit, func_unit),
+        (func_query, func_query),
+        (func_composite_query, func_composite_query),
+        (func_int_nat, func_nat_int),
+        (func_int_nat, func_int_unit),
+        (func_int_nat_text, func_nat_opt_int),
+        (func_unit, func_unit_opt),
+        (service_ab, service_a),
+        (service_ab, service_b),
+        (service_a, service_a),
+        (list_nat, list_nat),
+        (tree_nat, tree_int),
+        (tree_nat, tree_int_unrolled),
+        (tree_int, tree_int_unrolled),
+        (tree_int_unrolled, tree_int),
+        (loop_, loop_),
+        (loop_, T_RESERVED),
+    ];
+
+    let negative = [
+        (T_INT, T_NAT),
+        (T_NAT, T_NAT8),
+        (T_NAT8, T_NAT),
+        (T_TEXT, T_NAT),
+        (T_PRINCIPAL, T_TEXT),
+        (T_RESERVED, T_NAT),
+        (T_NAT, T_EMPTY),
+        (T_NULL, T_NAT),
+        (opt_nat, T_NAT),
+        (T_NAT, vec_nat),
+        (vec_nat, T_NAT),
+        (vec_nat, vec_text),
+        (record_nat, record_text),
+        (record_empty, record_nat),
+        (record_nat, variant_nat),
+        (variant_int_text, variant_nat),
+        (variant_nat, variant_text),
+        (func_nat_int, func_int_nat),
+        (func_int_nat_unit, func_int_unit),
+        (func_unit_int, func_unit_nat),
+        (func_unit, func_unit_nat),
+        (func_query, func_unit),
+        (func_unit, func_query),
+        (func_query, func_oneway),
+        (func_composite_query, func_query),
+        (func_query, func_composite_query),
+        (func_unit, service_b),
+        (service_a, service_b),
+        (service_a, service_ab),
+        (service_a, service_a_int_nat),
+        (tree_int, tree_nat),
+        (tree_text, tree_int),
+        (tree_int_unrolled, tree_nat),
+        (record_empty, loop_),
+        (loop_, variant_nat),
+    ];
+
+    let mut message = types.message(&[]);
+    let table = parse_types(&mut heap, &mut message);
+
+    for (t1, t2) in positive.iter() {
+        assert!(
+            check_sub(&mut heap, table, None, *t1, *t2),
+            "{} <: {}",
+            t1,
+            t2
+        );
+    }
+    for (t1, t2) in negative.iter() {
+        assert!(
+            !check_sub(&mut heap, table, None, *t1, *t2),
+            "{} </: {}",
+            t1,
+            t2
+        );
+    }
+
+    // A cache shared by many checks gives the same results as fresh caches, in any order
+    let all_types: Vec<i32> = (T_EMPTY..=T_NULL)
+        .chain(once(T_PRINCIPAL))
+        .chain(0..table.1 as i32)
+        .collect();
+    let mut pairs = vec![];
+    for t1 in all_types.iter() {
+        for t2 in all_types.iter() {
+            pairs.push((*t1, *t2));
+        }
+    }
+    let expected: Vec<bool> = pairs
+        .iter()
+        .map(|(t1, t2)| check_sub(&mut heap, table, None, *t1, *t2))
+        .collect();
+    for reverse in [false, true].iter() {
+        let cache = idl_sub_cache(&mut heap, table.1, table.1);
+        let mut order: Vec<usize> = (0..pairs.len()).collect();
+        if *reverse {
+            order.reverse();
+        }
+        for i in order {
+            let (t1, t2) = pairs[i];
+            assert_eq!(
+                check_sub(&mut heap, table, Some(cache), t1, t2),
+                expected[i],
+                "{} <: {}",
+                t1,
+                t2
+            );
+        }
+    }
+}
+
+/// The type table of a message: the table, its size, and the end of the message
+unsafe fn parse_types(heap: &mut TestMemory, message: &mut [u8]) -> (*mut *mut u8, u32, *mut u8) {
+    let mut buf = Buf {
+        ptr: message.as_mut_ptr(),
+        end: message.as_mut_ptr().add(message.len()),
+    };
+    let mut typtbl = std::ptr::null_mut();
+    let mut n_types = 0;
+    let mut main_types = std::ptr::null_mut();
+    parse_idl_header(
+        heap,
+        false,
+        &mut buf,
+        &mut typtbl,
+        &mut n_types,
+        &mut main_types,
+    );
+    (typtbl, n_types, buf.end)
+}
+
+/// Checks `t1 <: t2` within one type table, with a fresh cache unless one is given
+unsafe fn check_sub(
+    heap: &mut TestMemory,
+    (typtbl, n_types, end): (*mut *mut u8, u32, *mut u8),
+    cache: Option<Value>,
+    t1: i32,
+    t2: i32,
+) -> bool {
+    let cache = cache.unwrap_or_else(|| idl_sub_cache(heap, n_types, n_types));
+    idl_sub(cache, typtbl, end, typtbl, end, t1, t2)
+}
+
+unsafe fn print_args(
+    heap: &mut TestMemory,
+    message: &[u8],
+    max_len: u32,
+    max_depth: u32,
+) -> String {
+    let mut message = message.to_vec();
+    let mut buf = Buf {
+        ptr: message.as_mut_ptr(),
+        end: message.as_mut_ptr().add(message.len()),
+    };
+    let text = idl_print_args(heap, &mut buf, max_len, max_depth);
+    string(heap, text)
+}
+
+unsafe fn decode_args(heap: &mut TestMemory, message: &mut [u8]) -> Value {
+    let mut buf = Buf {
+        ptr: message.as_mut_ptr(),
+        end: message.as_mut_ptr().add(message.len()),
+    };
+    idl_decode_args(heap, &mut buf)
+}
+
+unsafe fn check_decode(heap: &mut TestMemory, message: &[u8], expected: &str) {
+    let args = decode_args(heap, &mut message.to_vec());
+    let args = args.as_array();
+    let shown: Vec<String> = (0..args.len()).map(|i| show(heap, args.get(i))).collect();
+    assert_eq!(format!("({})", shown.join(", ")), expected);
+}
+
+fn leb128(bytes: &mut Vec<u8>, mut n: u32) {
+    while n >= 0x80 {
+        bytes.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push(n as u8);
+}
+
+fn sleb128(bytes: &mut Vec<u8>, mut n: i32) {
+    while !(-0x40..0x40).contains(&n) {
+        bytes.push((n & 0x7f) as u8 | 0x80);
+        n >>= 7;
+    }
+    bytes.push((n & 0x7f) as u8);
+}
+
+unsafe fn variant(value: Value) -> (u32, Value) {
+    assert_eq!(value.tag(), TAG_VARIANT);
+    let variant = value.get_ptr() as *const Variant;
+    ((*variant).tag, (*variant).field)
+}
+
+unsafe fn elements(array: Value) -> Vec<Value> {
+    let array = array.as_array();
+    (0..array.len()).map(|i| array.get(i)).collect()
+}
+
+unsafe fn bytes(value: Value) -> Vec<u8> {
+    let blob = value.as_blob();
+    std::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize()).to_vec()
+}
+
+unsafe fn hex(value: Value) -> String {
+    bytes(value).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+unsafe fn string(heap: &mut TestMemory, text: Value) -> String {
+    String::from_utf8(bytes(blob_of_text(heap, text))).unwrap()
+}
+
+/// Textual Candid of a decoded value, with numbers annotated by their types
+#[allow(non_upper_case_globals)]
+unsafe fn show(heap: &mut TestMemory, value: Value) -> String {
+    let (tag, payload) = variant(value);
+    let numbers = [
+        (VALUE_nat, "nat"),
+        (VALUE_nat8, "nat8"),
+        (VALUE_nat16, "nat16"),
+        (VALUE_nat32, "nat32"),
+        (VALUE_nat64, "nat64"),
+        (VALUE_int, "int"),
+        (VALUE_int8, "int8"),
+        (VALUE_int16, "int16"),
+        (VALUE_int32, "int32"),
+        (VALUE_int64, "int64"),
+    ];
+    if let Some((_, name)) = numbers.iter().find(|(number_tag, _)| *number_tag == tag) {
+        let text = bigint_to_text(heap, payload, 10);
+        return format!("{} {}", name, string(heap, text));
+    }
+
+    match tag {
+        VALUE_null => "null".to_string(),
+        VALUE_reserved => "reserved".to_string(),
+        VALUE_bool => (payload.get_raw() == 1).to_string(),
+        VALUE_float32 | VALUE_float64 => {
+            let name = if tag == VALUE_float32 {
+                "float32"
+            } else {
+                "float64"
+            };
+            let bits = (*(payload.get_ptr() as *const Bits64)).bits();
+            format!("{} {}", name, f64::from_bits(bits))
+        }
+        VALUE_text => format!("{:?}", string(heap, payload)),
+        VALUE_principal => format!("principal {}", hex(payload)),
+        VALUE_opt => match elements(payload).as_slice() {
+            [] => "null".to_string(),
+            [value] => format!("opt {}", show(heap, *value)),
+            _ => panic!("opt with more than one value"),
+        },
+        VALUE_vec => {
+            let shown: Vec<String> = elements(payload)
+                .into_iter()
+                .map(|value| show(heap, value))
+                .collect();
+            format!("vec {{{}}}", shown.join("; "))
+        }
+        VALUE_record | VALUE_variant => {
+            let fields = if tag == VALUE_record {
+                elements(payload)
+            } else {
+                vec![payload]
+            };
+            let shown: Vec<String> = fields
+                .into_iter()
+                .map(|field| {
+                    let field = field.as_array();
+                    let id = bigint_to_text(heap, field.get(0), 10);
+                    let id = string(heap, id);
+                    format!("{} = {}", id, show(heap, field.get(1)))
+                })
+                .collect();
+            let name = if tag == VALUE_record {
+                "record"
+            } else {
+                "variant"
+            };
+            format!("{} {{{}}}", name, shown.join("; "))
+        }
+        VALUE_func => {
+            let pair = payload.as_array();
+            format!("func {}.{:?}", hex(pair.get(0)), string(heap, pair.get(1)))
+        }
+        VALUE_service => format!("service {}", hex(payload)),
+        _ => panic!("unknown value tag {:#x}", tag),
+    }
+}
@@ -0,0 +1,173 @@
+//! LZ compression tests
+
+use motoko_rts::lz::{
+    max_compressed_size, Compressor, Decompressor, Sink, Source, BLOCK_SIZE, WINDOW_SIZE,
+};
+
+use oorandom::Rand64;
+use proptest::collection::vec;
+use proptest::test_runner::{Config, TestCaseError, TestRunner};
+
+pub unsafe fn test() {
+    println!("Testing LZ compression ...");
+
+    println!("  Testing samples");
+    let mut rng = Rand64::new(0x6c7a);
+    let random: Vec<u8> = (0..5000).map(|_| rng.rand_u64() as u8).collect();
+
+    assert!(compress(&[b""]).is_empty());
+    check_roundtrip(&[b"a"]);
+    check_roundtrip(&[b"abcd", b"abcd", b"abcdabcdabcd"]);
+
+    let text = b"Stable variables survive upgrades. ".repeat(1000);
+    let compressed = check_roundtrip(&[&text]);
+    assert!(compressed < text.len() / 20);
+    // Matches refer back across chunks
+    let chunks: Vec<&[u8]> = text.chunks(100).collect();
+    let compressed = check_roundtrip(&chunks);
+    assert!(compressed < text.len() / 10);
+
+    // Runs are matches that overlap with their output, longer than any length byte
+    let compressed = check_roundtrip(&[&[7; 100_000]]);
+    assert!(compressed < 500);
+
+    // Random data does not compress, but grows only a little
+    let compressed = check_roundtrip(&[&random]);
+    assert!(compressed <= random.len() + random.len() / 255 + 16);
+
+    // Repetitions right within the window, and just beyond it
+    let window = WINDOW_SIZE as usize;
+    let mut data = random[..window - 1].to_vec();
+    data.extend_from_slice(&random[..100]);
+    let compressed = check_roundtrip(&[&data]);
+    assert!(compressed < window + 50);
+    let mut data = random[..window].to_vec();
+    data.extend_from_slice(&random[..100]);
+    let compressed = check_roundtrip(&[&data]);
+    assert!(compressed > window + 100);
+
+    println!("  Testing blocks");
+    let block = BLOCK_SIZE as usize;
+    // Tiny chunks of incompressible data still stay within the bound
+    let chunks: Vec<&[u8]> = random.chunks(1).collect();
+    let compressed = check_blocks_roundtrip(&chunks);
+    assert!(compressed as u64 <= max_compressed_size(random.len() as u64));
+    let chunks: Vec<&[u8]> = random.chunks(block + 1).collect();
+    check_blocks_roundtrip(&chunks);
+    let compressed = check_blocks_roundtrip(&[&text, &random]);
+    assert!(compressed < random.len() + text.len() / 20);
+    assert!(compress_blocks(&[b""]).is_empty());
+
+    println!("  Testing random chunks");
+    let mut proptest_runner = TestRunner::new(Config {
+        cases: 1_000,
+        failure_persistence: None,
+        ..Default::default()
+    });
+    let chunks = vec(vec(0u8..4, 0..3000), 1..5);
+    proptest_runner
+        .run(
+            &(chunks, 1usize..300, 1usize..500),
+            |(chunks, piece, request)| {
+                let chunks: Vec<&[u8]> = chunks.iter().map(|chunk| &chunk[..]).collect();
+                let expected = chunks.concat();
+                let compressed = compress(&chunks);
+                let decompressed = decompress(&compressed, expected.len(), piece, request);
+                if decompressed != expected {
+                    return Err(TestCaseError::Fail(
+                        format!("roundtrip failed for {:?}", chunks).into(),
+                    ));
+                }
+                let compressed = compress_blocks(&chunks);
+                let decompressed = decompress(&compressed, expected.len(), piece, request);
+                if decompressed != expected
+                    || compressed.len() as u64 > max_compressed_size(expected.len() as u64)
+                {
+                    return Err(TestCaseError::Fail(
+                        format!("block roundtrip failed for {:?}", chunks).into(),
+                    ));
+                }
+                Ok(())
+            },
+        )
+        .unwrap();
+}
+
+/// Checks that the chunks decompress to their contents, and returns the compressed size
+unsafe fn check_roundtrip(chunks: &[&[u8]]) -> usize {
+    let expected = chunks.concat();
+    let compressed = compress(chunks);
+    for (piece, request) in [(1, 1), (7, 13), (256, 1000), (10_000, 100_000)].iter() {
+        let decompressed = decompress(&compressed, expected.len(), *piece, *request);
+        assert_eq!(decompressed, expected);
+    }
+    compressed.len()
+}
+
+/// Like `check_roundtrip`, with blocks of `BLOCK_SIZE` bytes
+unsafe fn check_blocks_roundtrip(chunks: &[&[u8]]) -> usize {
+    let expected = chunks.concat();
+    let compressed = compress_blocks(chunks);
+    for (piece, request) in [(1, 1), (256, 1000), (10_000, 100_000)].iter() {
+        let decompressed = decompress(&compressed, expected.len(), *piece, *request);
+        assert_eq!(decompressed, expected);
+    }
+    compressed.len()
+}
+
+pub struct VecSink(pub Vec<u8>);
+
+impl Sink for VecSink {
+    unsafe fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+}
+
+/// Hands out the compressed data in pieces of at most `piece` bytes
+pub struct SliceSource<'a> {
+    pub data: &'a [u8],
+    pub piece: usize,
+}
+
+impl<'a> Source for SliceSource<'a> {
+    unsafe fn read(&mut self, dest: *mut u8, n: u32) -> u32 {
+        let n = (n as usize).min(self.piece).min(self.data.len());
+        std::ptr::copy_nonoverlapping(self.data.as_ptr(), dest, n);
+        self.data = &self.data[n..];
+        n as u32
+    }
+}
+
+fn compress(chunks: &[&[u8]]) -> Vec<u8> {
+    let mut compressor = Box::new(Compressor::new());
+    let mut sink = VecSink(vec![]);
+    for chunk in chunks {
+        unsafe { compressor.compress(&mut sink, chunk.as_ptr(), chunk.len() as u32) };
+    }
+    sink.0
+}
+
+fn compress_blocks(chunks: &[&[u8]]) -> Vec<u8> {
+    let mut compressor = Box::new(Compressor::new());
+    let mut sink = VecSink(vec![]);
+    for chunk in chunks {
+        unsafe { compressor.write(&mut sink, chunk.as_ptr(), chunk.len() as u32) };
+    }
+    unsafe { compressor.finish(&mut sink) };
+    sink.0
+}
+
+/// Decompresses `len` bytes, `request` bytes at a time
+fn decompress(compressed: &[u8], len: usize, piece: usize, request: usize) -> Vec<u8> {
+    let mut decompressor = Box::new(Decompressor::new());
+    let mut source = SliceSource {
+        data: compressed,
+        piece,
+    };
+    let mut decompressed = vec![0u8; len];
+    for chunk in decompressed.chunks_mut(request) {
+        unsafe { decompressor.decompress(&mut source, chunk.as_mut_ptr(), chunk.len() as u32) };
+    }
+    assert!(source.data.is_empty());
+    decompressed
+}
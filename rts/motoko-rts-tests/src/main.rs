@@ -2,13 +2,21 @@
 
 mod bigint;
 mod bitmap;
+mod char;
+mod codec;
 mod continuation_table;
 mod crc32;
+mod float;
 mod gc;
+mod graph;
+mod idl;
 mod leb128;
+mod lz;
 mod memory;
 mod principal_id;
+mod region;
 mod remembered_set;
+mod sha2;
 mod stream;
 mod text;
 mod utf8;
@@ -24,15 +32,23 @@ fn main() {
     unsafe {
         bigint::test();
         bitmap::test();
+        char::test();
+        codec::test();
         continuation_table::test();
         crc32::test();
+        float::test();
         gc::test();
+        graph::test();
+        idl::test();
         leb128::test();
+        lz::test();
         principal_id::test();
+        region::test();
         stream::test();
         text::test();
         utf8::test();
         remembered_set::test();
+        sha2::test();
     }
 }
 
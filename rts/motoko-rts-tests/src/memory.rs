@@ -14,6 +14,11 @@ impl TestMemory {
         TestMemory { heap, hp }
     }
 
+    /// Bytes allocated so far
+    pub fn allocated(&self) -> usize {
+        self.hp - self.heap.as_ptr() as usize
+    }
+
     unsafe fn grow_memory(&mut self, ptr: usize) {
         let heap_end = self.heap.as_ptr() as usize + self.heap.len();
         if ptr > heap_end {
@@ -1,8 +1,14 @@
 use crate::memory::TestMemory;
 
-use motoko_rts::principal_id::{blob_of_principal, principal_of_blob};
+use motoko_rts::principal_id::{
+    account_id_of_principal, account_id_of_text, account_id_to_text, account_id_valid,
+    blob_of_principal, check_account_id_text, principal_class, principal_derived,
+    principal_of_blob, principal_self_authenticating, PRINCIPAL_ANONYMOUS, PRINCIPAL_DERIVED,
+    PRINCIPAL_OPAQUE, PRINCIPAL_RESERVED, PRINCIPAL_SELF_AUTHENTICATING,
+};
+use motoko_rts::sha2::sha224;
 use motoko_rts::text::{text_compare, text_of_ptr_size, text_of_str};
-use motoko_rts::types::{Bytes, Words};
+use motoko_rts::types::{Bytes, Value, Words};
 
 pub unsafe fn test() {
     println!("Testing principal id encoding ...");
@@ -22,6 +28,16 @@ pub unsafe fn test() {
         0,
     );
 
+    // The example of the interface specification
+    let text = text_of_ptr_size(&mut heap, b"\xAB\xCD\x01".as_ptr(), Bytes(3));
+    assert_eq!(
+        text_compare(
+            principal_of_blob(&mut heap, text),
+            text_of_str(&mut heap, "em77e-bvlzu-aq"),
+        ),
+        0,
+    );
+
     let text = text_of_ptr_size(&mut heap, b"\xC0\xFE\xFE\xD0\x0D".as_ptr(), Bytes(5));
     let principal = principal_of_blob(&mut heap, text);
 
@@ -46,4 +62,152 @@ pub unsafe fn test() {
         ),
         0
     );
+
+    //
+    // Construction
+    //
+
+    // The example DER-encoded Ed25519 public key of the interface specification. The specification
+    // has no principals of keys, this one is computed with Python's hashlib, zlib and base64.
+    let der = b"\x30\x2a\x30\x05\x06\x03\x2b\x65\x70\x03\x21\x00\
+        \xb3\x99\x76\x56\xba\x51\xff\x6d\xa3\x7b\x61\xd8\xd5\x49\xec\x80\
+        \x71\x72\x66\xec\xf4\x8f\xb5\xda\x52\xb6\x54\x41\x26\x34\x84\x4c";
+    let key = text_of_ptr_size(&mut heap, der.as_ptr(), Bytes(der.len() as u32));
+    let principal = principal_self_authenticating(&mut heap, key);
+    assert_eq!(principal.as_blob().len(), Bytes(29));
+    assert_eq!(principal_class(principal), PRINCIPAL_SELF_AUTHENTICATING);
+    // As defined by the specification: H(public_key) · 0x02
+    let mut expected = sha224(der).to_vec();
+    expected.push(0x02);
+    assert_eq!(blob_bytes(principal), &expected[..]);
+    assert_eq!(
+        text_compare(
+            principal_of_blob(&mut heap, principal),
+            text_of_str(
+                &mut heap,
+                "3wm44-7ho22-qzoy7-l3lx5-6lfnz-kvrrz-xdwpa-a5466-rdpxi-74ken-nae"
+            ),
+        ),
+        0
+    );
+
+    // The specification has no derived principals either, this one is computed with Python
+    let canister = principal_of_text(&mut heap, "rrkah-fqaaa-aaaaa-aaaaq-cai");
+    assert_eq!(principal_class(canister), PRINCIPAL_OPAQUE);
+    let nonce = text_of_str(&mut heap, "nonce");
+    let principal = principal_derived(&mut heap, canister, nonce);
+    assert_eq!(principal_class(principal), PRINCIPAL_DERIVED);
+    // As defined by the specification: H(|registering_principal| · registering_principal ·
+    // derivation_nonce) · 0x03
+    let mut preimage = vec![10];
+    preimage.extend_from_slice(blob_bytes(canister));
+    preimage.extend_from_slice(b"nonce");
+    let mut expected = sha224(&preimage).to_vec();
+    expected.push(0x03);
+    assert_eq!(blob_bytes(principal), &expected[..]);
+    assert_eq!(
+        text_compare(
+            principal_of_blob(&mut heap, principal),
+            text_of_str(
+                &mut heap,
+                "cfnoe-i4qnt-t3mok-sao3q-pstr7-jbee5-3gzbj-xkdol-3oe7t-qjq6q-fqg"
+            ),
+        ),
+        0
+    );
+
+    //
+    // Classification
+    //
+
+    assert_eq!(
+        principal_class(principal_of_text(&mut heap, "aaaaa-aa")),
+        PRINCIPAL_OPAQUE
+    );
+    assert_eq!(
+        principal_class(principal_of_text(&mut heap, "2vxsx-fae")),
+        PRINCIPAL_ANONYMOUS
+    );
+    assert_eq!(class_of_bytes(&mut heap, b"\x7f"), PRINCIPAL_RESERVED);
+    assert_eq!(
+        class_of_bytes(&mut heap, b"\x01\x02\x7f"),
+        PRINCIPAL_RESERVED
+    );
+    // Classes that need a certain length
+    assert_eq!(class_of_bytes(&mut heap, b"\x01\x02"), PRINCIPAL_OPAQUE);
+    assert_eq!(class_of_bytes(&mut heap, b"\x01\x03"), PRINCIPAL_OPAQUE);
+    assert_eq!(class_of_bytes(&mut heap, b"\x01\x04"), PRINCIPAL_OPAQUE);
+
+    //
+    // Account identifiers
+    //
+
+    let anonymous = principal_of_text(&mut heap, "2vxsx-fae");
+    let subaccount = text_of_ptr_size(&mut heap, [0u8; 32].as_ptr(), Bytes(32));
+    let account_id = account_id_of_principal(&mut heap, anonymous, subaccount);
+    assert!(account_id_valid(account_id));
+    assert_eq!(
+        text_compare(
+            account_id_to_text(&mut heap, account_id),
+            text_of_str(
+                &mut heap,
+                "1c7a48ba6a562aa9eaa2481a9049cdf0433b9738c992d698c31d8abf89cadc79"
+            ),
+        ),
+        0
+    );
+
+    let mut bytes = [0u8; 32];
+    bytes[31] = 1;
+    let subaccount = text_of_ptr_size(&mut heap, bytes.as_ptr(), Bytes(32));
+    let account_id = account_id_of_principal(&mut heap, canister, subaccount);
+    let text = text_of_str(
+        &mut heap,
+        "3593F52E2DF55B057920693FEAAB7471B3F5A23D991FE2CC4CD1DF85338A3047",
+    );
+    assert_eq!(
+        text_compare(account_id_of_text(&mut heap, text), account_id),
+        0
+    );
+
+    // Nothing but 64 hex digits with the right checksum is an account identifier
+    for text in [
+        "3593f52e-2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a3047",
+        "3593f52e 2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a3047",
+        "3593f52e2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a304",
+        "3593f52e2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a30470",
+        "3593f52e2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a304g",
+        "3593f52e2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a3048",
+    ]
+    .iter()
+    {
+        assert!(check_account_id_text(text.as_bytes()).is_err(), "{}", text);
+    }
+    assert_eq!(
+        check_account_id_text(b"3593f52e2df55b057920693feaab7471b3f5a23d991fe2cc4cd1df85338a3047"),
+        Ok(())
+    );
+
+    // Any change breaks the checksum
+    *account_id.as_blob_mut().payload_addr().add(10) ^= 1;
+    assert!(!account_id_valid(account_id));
+    assert!(!account_id_valid(subaccount));
+}
+
+unsafe fn principal_of_text(heap: &mut TestMemory, text: &str) -> Value {
+    let text = text_of_str(heap, text);
+    blob_of_principal(heap, text)
+}
+
+unsafe fn class_of_bytes(heap: &mut TestMemory, bytes: &[u8]) -> u32 {
+    principal_class(text_of_ptr_size(
+        heap,
+        bytes.as_ptr(),
+        Bytes(bytes.len() as u32),
+    ))
+}
+
+unsafe fn blob_bytes<'a>(blob: Value) -> &'a [u8] {
+    let blob = blob.as_blob();
+    std::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize())
 }
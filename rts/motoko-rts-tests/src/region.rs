@@ -0,0 +1,173 @@
+//! Stable-memory region tests
+
+use motoko_rts::region::{Regions, StableMemory, BLOCK_PAGES, METADATA_PAGES};
+
+use oorandom::Rand64;
+
+const PAGE_SIZE: u64 = 64 * 1024;
+
+/// Stand-in for stable memory, that fails to grow beyond `max_pages`
+struct TestStableMemory {
+    data: Vec<u8>,
+    max_pages: u64,
+}
+
+impl TestStableMemory {
+    fn new(max_pages: u64) -> TestStableMemory {
+        TestStableMemory {
+            data: vec![],
+            max_pages,
+        }
+    }
+}
+
+impl StableMemory for TestStableMemory {
+    unsafe fn size(&mut self) -> u64 {
+        self.data.len() as u64 / PAGE_SIZE
+    }
+
+    unsafe fn grow(&mut self, pages: u64) -> u64 {
+        let size = self.size();
+        if size + pages > self.max_pages {
+            return u64::MAX;
+        }
+        self.data.resize(((size + pages) * PAGE_SIZE) as usize, 0);
+        size
+    }
+
+    unsafe fn read(&mut self, offset: u64, dest: *mut u8, n: u64) {
+        let bytes = &self.data[offset as usize..(offset + n) as usize];
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), dest, bytes.len());
+    }
+
+    unsafe fn write(&mut self, offset: u64, src: *const u8, n: u64) {
+        let bytes = &mut self.data[offset as usize..(offset + n) as usize];
+        std::ptr::copy_nonoverlapping(src, bytes.as_mut_ptr(), bytes.len());
+    }
+}
+
+pub unsafe fn test() {
+    println!("Testing stable-memory regions ...");
+
+    let base = 3;
+    let mut stable = TestStableMemory::new(base + METADATA_PAGES + 5 * BLOCK_PAGES);
+    // Pretend that something else uses the pages before the region space
+    stable.grow(base);
+    stable.data.iter_mut().for_each(|byte| *byte = 0xAA);
+
+    println!("  Testing region creation and growth");
+    let mut regions = Box::new(Regions::new());
+    assert_eq!((regions.base_page(), regions.end_page()), (None, 0));
+    regions.init(&mut stable, base);
+    assert_eq!(stable.size(), base + METADATA_PAGES);
+    assert_eq!(regions.count(), 0);
+    assert_eq!(regions.base_page(), Some(base));
+    assert_eq!(regions.end_page(), base + METADATA_PAGES);
+
+    let r0 = regions.new_region(&mut stable);
+    let r1 = regions.new_region(&mut stable);
+    assert_eq!((r0, r1), (0, 1));
+    assert_eq!(regions.size(r0), 0);
+
+    // Growing within a block takes no new block
+    assert_eq!(regions.grow(&mut stable, r0, 1), 0);
+    assert_eq!(regions.grow(&mut stable, r0, BLOCK_PAGES - 1), 1);
+    assert_eq!(regions.blocks(), 1);
+    assert_eq!(regions.grow(&mut stable, r1, 10), 0);
+    assert_eq!(regions.grow(&mut stable, r0, 1), BLOCK_PAGES);
+    assert_eq!(regions.blocks(), 3);
+    assert_eq!(regions.size(r0), BLOCK_PAGES + 1);
+    assert_eq!(regions.size(r1), 10);
+    assert_eq!(stable.size(), base + METADATA_PAGES + 3 * BLOCK_PAGES);
+    assert_eq!(regions.end_page(), stable.size());
+    assert!(stable.data[..(base * PAGE_SIZE) as usize]
+        .iter()
+        .all(|byte| *byte == 0xAA));
+
+    println!("  Testing region reads and writes");
+    let mut rng = Rand64::new(0x5265);
+    let block_size = BLOCK_PAGES * PAGE_SIZE;
+    // Across the boundary between the blocks of region 0, which are not adjacent
+    let data0: Vec<u8> = (0..100_000).map(|_| rng.rand_u64() as u8).collect();
+    let offset0 = block_size - 30_000;
+    regions.write(&mut stable, r0, offset0, data0.as_ptr(), data0.len() as u64);
+    let data1: Vec<u8> = (0..5000).map(|_| rng.rand_u64() as u8).collect();
+    regions.write(&mut stable, r1, 0, data1.as_ptr(), data1.len() as u64);
+    check_contents(&regions, &mut stable, r0, offset0, &data0);
+    check_contents(&regions, &mut stable, r1, 0, &data1);
+
+    // Region 1 owns the block in between
+    let block1 = (base + METADATA_PAGES + BLOCK_PAGES) * PAGE_SIZE;
+    assert_eq!(stable.data[block1 as usize..][..data1.len()], data1[..]);
+
+    println!("  Testing region survival");
+    let mut upgraded = Box::new(Regions::new());
+    upgraded.init(&mut stable, base);
+    assert_eq!(upgraded.count(), 2);
+    assert_eq!(upgraded.blocks(), 3);
+    assert_eq!(upgraded.size(r0), BLOCK_PAGES + 1);
+    assert_eq!(upgraded.size(r1), 10);
+    check_contents(&upgraded, &mut stable, r0, offset0, &data0);
+    check_contents(&upgraded, &mut stable, r1, 0, &data1);
+
+    // Growing after the upgrade keeps the old contents
+    let r2 = upgraded.new_region(&mut stable);
+    assert_eq!(upgraded.grow(&mut stable, r2, 1), 0);
+    assert_eq!(upgraded.grow(&mut stable, r1, BLOCK_PAGES), 10);
+    let end1 = 10 * PAGE_SIZE;
+    let data2: Vec<u8> = (0..block_size as usize).map(|i| i as u8).collect();
+    upgraded.write(&mut stable, r1, end1, data2.as_ptr(), data2.len() as u64);
+    check_contents(&upgraded, &mut stable, r1, 0, &data1);
+    check_contents(&upgraded, &mut stable, r1, end1, &data2);
+    check_contents(&upgraded, &mut stable, r0, offset0, &data0);
+
+    println!("  Testing region growth failure");
+    assert_eq!(upgraded.blocks(), 5);
+    assert_eq!(upgraded.grow(&mut stable, r2, BLOCK_PAGES), u64::MAX);
+    assert_eq!(upgraded.size(r2), 1);
+    assert_eq!(upgraded.blocks(), 5);
+    assert_eq!(upgraded.grow(&mut stable, r2, BLOCK_PAGES - 1), 1);
+    assert_eq!(upgraded.grow(&mut stable, r0, u64::MAX), u64::MAX);
+
+    let mut reloaded = Box::new(Regions::new());
+    reloaded.init(&mut stable, base);
+    assert_eq!(reloaded.count(), 3);
+    assert_eq!(reloaded.size(r2), BLOCK_PAGES);
+    check_contents(&reloaded, &mut stable, r1, end1, &data2);
+
+    println!("  Testing region space over cleared pages");
+    // Pages left over from an image of the stable variables, with a cleared header
+    let mut stable = TestStableMemory::new(base + METADATA_PAGES + BLOCK_PAGES);
+    stable.grow(base + 1);
+    let start = (base * PAGE_SIZE) as usize;
+    stable.data[start + 16..]
+        .iter_mut()
+        .for_each(|byte| *byte = 0xAA);
+    let mut cleared = Box::new(Regions::new());
+    cleared.init(&mut stable, base);
+    assert_eq!(stable.size(), base + METADATA_PAGES);
+    assert_eq!(cleared.count(), 0);
+    let r0 = cleared.new_region(&mut stable);
+    assert_eq!(cleared.size(r0), 0);
+    assert_eq!(cleared.grow(&mut stable, r0, BLOCK_PAGES), 0);
+    assert_eq!(cleared.blocks(), 1);
+    assert_eq!(cleared.end_page(), stable.size());
+}
+
+unsafe fn check_contents(
+    regions: &Regions,
+    stable: &mut TestStableMemory,
+    region: u32,
+    offset: u64,
+    expected: &[u8],
+) {
+    let mut contents = vec![0u8; expected.len()];
+    regions.read(
+        stable,
+        region,
+        offset,
+        contents.as_mut_ptr(),
+        contents.len() as u64,
+    );
+    assert_eq!(contents, expected);
+}
@@ -0,0 +1,181 @@
+//! SHA-2 tests
+
+use crate::memory::TestMemory;
+
+use motoko_rts::sha2::{
+    sha224, sha256, sha2_digest, sha2_finish, sha2_new, sha2_update, sha512, Hasher, Sha256,
+    Sha512, SHA224_SIZE, SHA2_224, SHA2_256, SHA2_512, SHA512_SIZE,
+};
+use motoko_rts::text::{text_concat, text_of_str};
+use motoko_rts::types::{Value, Words};
+
+const MESSAGE_448: &[u8] = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+const MESSAGE_896: &[u8] = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmno\
+                             ijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+pub unsafe fn test() {
+    println!("Testing SHA-2 ...");
+
+    println!("  Testing SHA-224");
+    // From the NIST examples of FIPS 180-4
+    assert_eq!(
+        hex(&sha224(b"")),
+        "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f"
+    );
+    assert_eq!(
+        hex(&sha224(b"abc")),
+        "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+    );
+    assert_eq!(
+        hex(&sha224(MESSAGE_448)),
+        "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525"
+    );
+
+    // A million times `a`, in pieces that straddle the blocks
+    let mut hasher = Sha256::sha224();
+    let piece = [b'a'; 1000];
+    for _ in 0..1000 {
+        hasher.update(&piece[..999]);
+        hasher.update(&piece[..1]);
+    }
+    let mut digest = [0; SHA224_SIZE];
+    hasher.finish(&mut digest);
+    assert_eq!(
+        hex(&digest),
+        "20794655980c91d8bbb4c1ea97618a4bf03f42581948b2ee4ee7ad67"
+    );
+
+    println!("  Testing SHA-256");
+    assert_eq!(
+        hex(&sha256(b"")),
+        "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+    );
+    assert_eq!(
+        hex(&sha256(b"abc")),
+        "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+    );
+    assert_eq!(
+        hex(&sha256(MESSAGE_448)),
+        "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+    );
+    assert_eq!(
+        hex(&sha256(MESSAGE_896)),
+        "cf5b16a778af8380036ce59e7b0492370b249b11e8f07a51afac45037afee9d1"
+    );
+    assert_eq!(
+        hex(&sha256(&vec![b'a'; 1_000_000])),
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+    );
+
+    println!("  Testing SHA-512");
+    assert_eq!(
+        hex(&sha512(b"")),
+        "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce\
+         47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+    );
+    assert_eq!(
+        hex(&sha512(b"abc")),
+        "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a\
+         2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+    );
+    assert_eq!(
+        hex(&sha512(MESSAGE_448)),
+        "204a8fc6dda82f0a0ced7beb8e08a41657c16ef468b228a8279be331a703c335\
+         96fd15c13b1b07f9aa1d3bea57789ca031ad85c7a71dd70354ec631238ca3445"
+    );
+    assert_eq!(
+        hex(&sha512(MESSAGE_896)),
+        "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018\
+         501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+    );
+    let mut hasher = Sha512::new();
+    for _ in 0..1000 {
+        hasher.update(&piece[..999]);
+        hasher.update(&piece[..1]);
+    }
+    let mut digest = [0; SHA512_SIZE];
+    hasher.finish(&mut digest);
+    assert_eq!(
+        hex(&digest),
+        "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973eb\
+         de0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
+    );
+
+    test_hasher_objects();
+}
+
+unsafe fn test_hasher_objects() {
+    println!("  Testing hasher objects");
+    let mut mem = TestMemory::new(Words(1024 * 1024));
+
+    // A rope of several blobs
+    let mut text = text_of_str(&mut mem, "Motoko text ");
+    for part in ["ropes hash ", "without ", "flattening"].iter() {
+        let part = text_of_str(&mut mem, part);
+        text = text_concat(&mut mem, text, part);
+    }
+    let flat = text_of_str(&mut mem, "Motoko text ropes hash without flattening");
+
+    let expected = "386063ea72bcde17164e6b2ffb427036dca1130a10f8cdd80bd96edd";
+    assert_eq!(hex_blob(sha2_digest(&mut mem, SHA2_224, text)), expected);
+    assert_eq!(hex_blob(sha2_digest(&mut mem, SHA2_224, flat)), expected);
+    let expected = "270c863c0f71b483a3026c979f408d905972d1c89f79e55eb33093ff74d8bf6a\
+                    c12bcb1f47d4ea8000d788c01912aa9f46709d9c0a4e9781499427333683a611";
+    assert_eq!(hex_blob(sha2_digest(&mut mem, SHA2_512, text)), expected);
+
+    // Finishing does not stop a hasher
+    let hasher = sha2_new(&mut mem, SHA2_256);
+    assert_eq!(hex_blob(sha2_finish(&mut mem, hasher)), hex(&sha256(b"")));
+    let blob = text_of_str(&mut mem, "ab");
+    sha2_update(hasher, blob);
+    let blob = text_of_str(&mut mem, "c");
+    sha2_update(hasher, blob);
+    assert_eq!(
+        hex_blob(sha2_finish(&mut mem, hasher)),
+        hex(&sha256(b"abc"))
+    );
+    sha2_update(hasher, text);
+    let mut expected = b"abc".to_vec();
+    expected.extend_from_slice(b"Motoko text ropes hash without flattening");
+    assert_eq!(
+        hex_blob(sha2_finish(&mut mem, hasher)),
+        hex(&sha256(&expected))
+    );
+
+    let hasher = sha2_new(&mut mem, SHA2_512);
+    sha2_update(hasher, text);
+    assert_eq!(
+        hex_blob(sha2_finish(&mut mem, hasher)),
+        hex(&sha512(&expected[3..]))
+    );
+
+    // Hasher objects are checked, as they are blobs to Motoko
+    let blob = hasher.as_blob_mut();
+    let bytes = std::slice::from_raw_parts_mut(blob.payload_addr(), blob.len().as_usize());
+    assert!(Hasher::of_object(bytes).is_some());
+    let not_hashers: [(usize, u8); 3] = [
+        (0, 7),               // unknown algorithm
+        (0, SHA2_256 as u8),  // wrong size for the algorithm
+        (4 + 64 + 128, 0xff), // block length out of range
+    ];
+    for (offset, byte) in not_hashers.iter() {
+        let original = bytes[*offset];
+        bytes[*offset] = *byte;
+        assert!(Hasher::of_object(bytes).is_none());
+        bytes[*offset] = original;
+    }
+    assert!(Hasher::of_object(b"not a hasher").is_none());
+}
+
+unsafe fn hex_blob(blob: Value) -> String {
+    let blob = blob.as_blob();
+    hex(std::slice::from_raw_parts(
+        blob.payload_const(),
+        blob.len().as_usize(),
+    ))
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
@@ -2,8 +2,17 @@
 
 use crate::memory::TestMemory;
 
-use motoko_rts::stream::alloc_stream;
-use motoko_rts::types::{Bytes, Stream, Value, Words};
+use crate::lz::{SliceSource, VecSink};
+
+use motoko_rts::leb128::leb128_decode;
+use motoko_rts::lz::{Compressor, Decompressor};
+use motoko_rts::memory::alloc_blob;
+use motoko_rts::principal_id::update_crc32;
+use motoko_rts::stream::{
+    alloc_stream, alloc_stream_reader, check_image, image_trailer, stream_reader_read_blob,
+    verify_image_blob, IMAGE_TRAILER_SIZE,
+};
+use motoko_rts::types::{Bytes, Stream, StreamReader, Value, Words};
 
 pub unsafe fn test() {
     println!("Testing streaming ...");
@@ -17,13 +26,13 @@ pub unsafe fn test() {
     for b in 32..92u8 {
         stream.as_stream().cache_byte(b);
     }
-    assert_eq!(stream.as_blob().get(32), 32);
-    assert_eq!(stream.as_blob().get(91), 91);
+    assert_eq!(stream.as_blob().get(48), 32);
+    assert_eq!(stream.as_blob().get(107), 91);
 
     println!("  Testing stream decay");
     let blob = stream.as_stream().split();
     assert_eq!(blob.as_blob().len(), Bytes(60));
-    assert_eq!(stream.as_blob().len(), Bytes(24));
+    assert_eq!(stream.as_blob().len(), Bytes(40));
 
     println!("  Testing stream filling (blocks)");
     let stream = Value::from_ptr(alloc_stream(&mut mem, Bytes(6000)) as usize);
@@ -33,11 +42,11 @@ pub unsafe fn test() {
             .as_stream()
             .cache_bytes(&chunk[0], Bytes(chunk.len() as u32));
     }
-    assert_eq!(stream.as_blob().get(32), 10);
-    assert_eq!(stream.as_blob().get(33), 1);
-    assert_eq!(stream.as_blob().get(41), 9);
-    assert_eq!(stream.as_blob().get(42), 10);
-    assert_eq!(stream.as_blob().get(6031), 9);
+    assert_eq!(stream.as_blob().get(48), 10);
+    assert_eq!(stream.as_blob().get(49), 1);
+    assert_eq!(stream.as_blob().get(57), 9);
+    assert_eq!(stream.as_blob().get(58), 10);
+    assert_eq!(stream.as_blob().get(6047), 9);
     let blob = stream.as_stream().split();
     assert_eq!(blob.as_blob().len(), Bytes(6000));
 
@@ -68,4 +77,247 @@ pub unsafe fn test() {
     assert_eq!(WRITTEN, Bytes(6020)); // all at once
     stream.shutdown();
     assert_eq!(WRITTEN, Bytes(6021)); // u8 too
+
+    test_sinks(&mut mem);
+    test_reader(&mut mem);
+    test_image_trailer(&mut mem);
+}
+
+static mut HEAP: *mut TestMemory = std::ptr::null_mut();
+
+fn to_heap(stream: *mut Stream, ptr: *const u8, n: Bytes<u32>) {
+    unsafe { stream.append_to_heap(&mut *HEAP, ptr, n) }
+}
+
+static mut CHUNKS: *mut Vec<Vec<u8>> = std::ptr::null_mut();
+
+extern "C" fn collect(_stream: *mut Stream, ptr: *const u8, n: u32) {
+    unsafe { (*CHUNKS).push(std::slice::from_raw_parts(ptr, n as usize).to_vec()) }
+}
+
+/// Writes bytes in pieces of growing size, some of them bigger than the cache
+unsafe fn write_pieces(stream: *mut Stream, data: &[u8]) {
+    let mut rest = data;
+    let mut size = 1;
+    while !rest.is_empty() {
+        let n = size.min(rest.len());
+        if n == 1 {
+            stream.cache_byte(rest[0]);
+        } else {
+            stream.cache_bytes(rest.as_ptr(), Bytes(n as u32));
+        }
+        rest = &rest[n..];
+        size = size * 3 % 1000 + 1;
+    }
+}
+
+unsafe fn test_sinks(mem: &mut TestMemory) {
+    println!("  Testing heap sink");
+    HEAP = Box::into_raw(Box::new(TestMemory::new(Words(1024 * 1024))));
+    let data: Vec<u8> = (0..20_000u32).map(|i| (i * 13 % 251) as u8).collect();
+
+    let stream = alloc_stream(mem, Bytes(60));
+    stream.setup_heap_outputter(to_heap);
+    assert_eq!(stream.concat_heap(&mut *HEAP).as_blob().len(), Bytes(0));
+    write_pieces(stream, &data);
+    assert!((*stream).ptr64 > 0); // chunks went to the heap already
+    assert!((*stream).chain.is_ptr());
+    assert_eq!((*stream).limit64, 0);
+    let blob = stream.concat_heap(&mut *HEAP).as_blob();
+    assert_eq!(blob.len(), Bytes(data.len() as u32));
+    assert_eq!(
+        std::slice::from_raw_parts(blob.payload_const(), data.len()),
+        &data[..]
+    );
+
+    assert!(!(*stream).chain.is_ptr());
+
+    // The stream starts over
+    write_pieces(stream, b"again");
+    let blob = stream.concat_heap(&mut *HEAP).as_blob();
+    assert_eq!(
+        std::slice::from_raw_parts(blob.payload_const(), 5),
+        b"again"
+    );
+
+    // Bigger chunks bypass the cache, also the first one
+    let stream = alloc_stream(mem, Bytes(1000));
+    stream.setup_heap_outputter(to_heap);
+    stream.cache_bytes(data.as_ptr(), Bytes(200));
+    assert_eq!((*stream).ptr64, 200);
+    stream.cache_bytes(data[200..].as_ptr(), Bytes(200));
+    assert_eq!((*stream).ptr64, 400);
+
+    println!("  Testing callback sink");
+    CHUNKS = Box::into_raw(Box::new(vec![]));
+    let stream = alloc_stream(mem, Bytes(100));
+    stream.setup_callback_dest(collect);
+    write_pieces(stream, &data);
+    stream.shutdown();
+    assert!((*CHUNKS).len() > 1);
+    assert_eq!((*CHUNKS).concat(), data);
+    assert_eq!((*stream).ptr64, data.len() as u64);
+}
+
+static mut SOURCE: &[u8] = &[];
+
+fn from_source(reader: *mut StreamReader, ptr: *mut u8, n: Bytes<u32>) {
+    unsafe {
+        let start = (*reader).ptr64 as usize;
+        let bytes = &SOURCE[start..start + n.as_usize()];
+        std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len());
+        (*reader).ptr64 += n.as_u32() as u64;
+    }
+}
+
+unsafe fn test_reader(mem: &mut TestMemory) {
+    println!("  Testing stream reading");
+
+    // LEB128 numbers, a long one, and a blob
+    let numbers: Vec<u32> = (0..500).map(|i| i * 10007).collect();
+    let mut source = vec![];
+    for n in numbers.iter() {
+        let mut n = *n;
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                source.push(byte);
+                break;
+            }
+            source.push(byte | 0x80);
+        }
+    }
+    let long_start = source.len();
+    source.extend(std::iter::repeat(0x80).take(40));
+    source.push(0);
+    let blob: Vec<u8> = (0..300).map(|i| (i * 7) as u8).collect();
+    source.extend_from_slice(&blob);
+    source.extend_from_slice(b"skippedtail");
+    SOURCE = Box::leak(source.into_boxed_slice());
+
+    let reader = alloc_stream_reader(mem, Bytes(64));
+    assert_eq!(reader.capacity(), Bytes(64));
+    reader.setup_source(0, SOURCE.len() as u64, from_source);
+    assert_eq!(reader.remaining(), SOURCE.len() as u64);
+    assert_eq!(reader.available(), Bytes(0)); // nothing pulled yet
+
+    for n in numbers.iter() {
+        assert_eq!(leb128_decode(reader.fill_leb128()), *n);
+    }
+    assert_eq!(reader.remaining(), (SOURCE.len() - long_start) as u64);
+
+    // A number that spans many chunks is made available in one piece
+    let cursor = reader.fill_leb128();
+    let (ptr, end) = ((*cursor).ptr, (*cursor).end);
+    assert!(end as usize - ptr as usize >= 41);
+    assert_eq!(*ptr.add(40), 0);
+    (*cursor).ptr = ptr.add(41);
+
+    // Bigger reads bypass the cache
+    let mut read = vec![0u8; 300];
+    reader.read_bytes(read.as_mut_ptr(), Bytes(300));
+    assert_eq!(read, blob);
+
+    reader.fill(Bytes(4));
+    assert_eq!(reader.available(), Bytes(11)); // everything that is left
+    reader.skip_bytes(7);
+    let mut read = vec![0u8; 4];
+    reader.read_bytes(read.as_mut_ptr(), Bytes(4));
+    assert_eq!(read, b"tail");
+    assert_eq!(reader.remaining(), 0);
+
+    // Skipping beyond the cache does not read
+    let reader = alloc_stream_reader(mem, Bytes(16));
+    reader.setup_source(0, SOURCE.len() as u64, from_source);
+    reader.fill(Bytes(1));
+    assert_eq!(reader.available(), Bytes(16));
+    reader.skip_bytes(long_start as u64 + 41);
+    assert_eq!((*reader).ptr64, long_start as u64 + 41);
+    let mut read = vec![0u8; 10];
+    reader.read_bytes(read.as_mut_ptr(), Bytes(10));
+    assert_eq!(read, &blob[..10]);
+
+    // Blobs can be longer than the cache
+    let value = stream_reader_read_blob(mem, reader, Bytes(290));
+    assert_eq!(value.as_blob().len(), Bytes(290));
+    for (i, byte) in blob[10..].iter().enumerate() {
+        assert_eq!(value.as_blob().get(i as u32), *byte);
+    }
+    assert_eq!(reader.remaining(), 11);
+
+    // Compressed images are not seekable, skipping decompresses
+    COMPRESSOR = Box::into_raw(Box::new(Compressor::new()));
+    SINK = Box::into_raw(Box::new(VecSink(vec![])));
+    let stream = alloc_stream(mem, Bytes(100));
+    (*stream).outputter = to_compressor;
+    stream.cache_bytes(SOURCE.as_ptr(), Bytes(SOURCE.len() as u32));
+    stream.shutdown();
+    assert!((*SINK).0.len() < SOURCE.len());
+
+    DECOMPRESSOR = Box::into_raw(Box::new(Decompressor::new()));
+    COMPRESSED = Box::into_raw(Box::new(SliceSource {
+        data: Box::leak((*SINK).0.clone().into_boxed_slice()),
+        piece: 64,
+    }));
+    let reader = alloc_stream_reader(mem, Bytes(16));
+    reader.setup_source(0, SOURCE.len() as u64, from_decompressor);
+    (*reader).seekable = false;
+    reader.fill(Bytes(1));
+    reader.skip_bytes(long_start as u64 + 41);
+    assert_eq!((*reader).ptr64, long_start as u64 + 41);
+    let mut read = vec![0u8; 300];
+    reader.read_bytes(read.as_mut_ptr(), Bytes(300));
+    assert_eq!(read, blob);
+    assert_eq!(reader.remaining(), 11);
+}
+
+static mut COMPRESSOR: *mut Compressor = std::ptr::null_mut();
+static mut SINK: *mut VecSink = std::ptr::null_mut();
+static mut DECOMPRESSOR: *mut Decompressor = std::ptr::null_mut();
+static mut COMPRESSED: *mut SliceSource<'static> = std::ptr::null_mut();
+
+fn to_compressor(_stream: *mut Stream, ptr: *const u8, n: Bytes<u32>) {
+    unsafe { (*COMPRESSOR).compress(&mut *SINK, ptr, n.as_u32()) }
+}
+
+fn from_decompressor(reader: *mut StreamReader, ptr: *mut u8, n: Bytes<u32>) {
+    unsafe {
+        (*DECOMPRESSOR).decompress(&mut *COMPRESSED, ptr, n.as_u32());
+        (*reader).ptr64 += n.as_u32() as u64;
+    }
+}
+
+unsafe fn test_image_trailer(mem: &mut TestMemory) {
+    println!("  Testing image trailers");
+
+    let data = b"DIDL\x00\x01\x71\x05hello".to_vec();
+    let mut image = data.clone();
+    image.extend_from_slice(&image_trailer(data.len() as u64, update_crc32(0, &data)));
+    assert_eq!(image.len(), data.len() + IMAGE_TRAILER_SIZE as usize);
+    assert_eq!(check_image(&image), Ok(data.len() as u64));
+    assert_eq!(check_image(&image_trailer(0, 0)), Ok(0));
+
+    let mut corrupted = image.clone();
+    corrupted[7] ^= 1;
+    assert_eq!(check_image(&corrupted), Err("image checksum mismatch"));
+    let mut corrupted = image.clone();
+    corrupted[data.len() + 8] ^= 1;
+    assert_eq!(check_image(&corrupted), Err("image checksum mismatch"));
+    let mut newer = image.clone();
+    newer[data.len() + 12] += 1;
+    assert_eq!(check_image(&newer), Err("unsupported image version"));
+    assert_eq!(check_image(&image[1..]), Err("image length mismatch"));
+    assert_eq!(check_image(&image[..10]), Err("image too short"));
+    let mut extended = image.clone();
+    extended.insert(0, 0);
+    assert_eq!(check_image(&extended), Err("image length mismatch"));
+
+    // Verifying a blob drops the trailer
+    let blob = alloc_blob(mem, Bytes(image.len() as u32));
+    let payload = blob.as_blob_mut().payload_addr();
+    std::ptr::copy_nonoverlapping(image.as_ptr(), payload, image.len());
+    verify_image_blob(blob);
+    assert_eq!(blob.as_blob().len(), Bytes(data.len() as u32));
+    assert_eq!(std::slice::from_raw_parts(payload, data.len()), &data[..]);
 }
@@ -2,7 +2,11 @@
 //!
 //! - libtommath memory management
 //! - libtommath wrappers
+//! - compact representation of numbers that fit in 64 bits
 //! - (s)leb128 encoding/decoding for bigints
+//! - conversion to and from text in radixes 2 to 36
+//! - number-theoretic functions (gcd, modular arithmetic, primality)
+//! - bitwise operations, with the semantics of an infinite two's complement representation
 
 /*
 A libtommath arbitrary precision integer is a struct (`mp_int`) that contains a pointer to a data
@@ -29,13 +33,29 @@ This scheme makes the following assumptions:
  - libtommath never modifies the data on the heap.
    (or put differently, we only pass those to libtommath when they are immutable)
  - libtommath uses mp_calloc() and mp_realloc() _only_ to allocate the `mp_digit *` array.
+
+Compact numbers
+---------------
+
+An `mp_int` allocated by libtommath takes at least MP_PREC digits, so even a small number that
+does not fit in a tagged scalar takes more than a hundred bytes. Numbers that fit in an `i64` are
+therefore stored as `Bits64` objects instead. Functions below accept both representations, and
+arithmetic on two compact numbers is done natively, falling back to libtommath only on overflow.
+
+Small `BigInt`s (e.g. literals, or results of the slow path) count as compact inputs too, so the
+result of the fast path is always a `Bits64`. Other functions convert `Bits64` inputs to `BigInt`s
+with `as_mp_int`, which allocates; this is fine as they allocate their result anyway.
 */
 
 use crate::buf::{read_byte, Buf};
 use crate::mem_utils::memcpy_bytes;
-use crate::memory::Memory;
+use crate::memory::{alloc_blob, Memory};
+use crate::rts_trap_with;
+use crate::text::{text_of_ptr_size, text_size, text_to_buf};
 use crate::tommath_bindings::*;
-use crate::types::{size_of, BigInt, Bytes, Stream, Value, TAG_BIGINT};
+use crate::types::{
+    size_of, BigInt, Bits64, Bytes, Stream, StreamReader, Value, Words, TAG_BIGINT, TAG_BITS64,
+};
 
 use motoko_rts_macros::ic_mem_fn;
 
@@ -125,6 +145,17 @@ extern "C" {
     pub(crate) fn bigint_trap() -> !;
 }
 
+// Allocation for compact numbers. Like the functions above, this is implemented separately in
+// the tests, as the functions in this module do not take a heap argument.
+extern "C" {
+    fn alloc_words(n: Words<u32>) -> Value;
+}
+
+// Not in the generated bindings, as bindgen's `size_t` would not match `usize` on all targets
+extern "C" {
+    fn mp_rand_source(source: unsafe extern "C" fn(out: *mut libc::c_void, size: usize) -> mp_err);
+}
+
 #[inline]
 pub(crate) unsafe fn check(err: mp_err) {
     if err != 0 {
@@ -137,8 +168,8 @@ pub(crate) unsafe fn mp_get_u32(p: *const mp_int) -> u32 {
     mp_get_i32(p) as u32
 }
 
-#[cfg(feature = "ic")]
-unsafe fn mp_get_u64(p: *const mp_int) -> u64 {
+#[inline]
+pub(crate) unsafe fn mp_get_u64(p: *const mp_int) -> u64 {
     mp_get_i64(p) as u64
 }
 
@@ -152,7 +183,7 @@ pub(crate) unsafe fn mp_iszero(p: *const mp_int) -> bool {
 }
 
 // Allocates an mp_int on the stack
-unsafe fn tmp_bigint() -> mp_int {
+pub(crate) unsafe fn tmp_bigint() -> mp_int {
     let mut i: mp_int = core::mem::zeroed();
     check(mp_init(&mut i));
     i
@@ -171,60 +202,109 @@ unsafe fn persist_bigint(i: mp_int) -> Value {
     Value::from_ptr(r as usize)
 }
 
+/// Persists an mp_int like `persist_bigint`, as a compact number if it fits
+unsafe fn persist_compact(i: mp_int) -> Value {
+    if mp_count_bits(&i) < 64 {
+        bigint_of_compact(mp_get_i64(&i))
+    } else {
+        persist_bigint(i)
+    }
+}
+
+unsafe fn bigint_of_compact(n: i64) -> Value {
+    let r = alloc_words(size_of::<Bits64>());
+    let bits64 = r.get_ptr() as *mut Bits64;
+    (*bits64).header.tag = TAG_BITS64;
+    (*bits64).set_bits(n as u64);
+    r
+}
+
+/// Value of a number that fits in an `i64`, in either representation. Compiled code also passes
+/// small operands of `bigint_add`, `bigint_sub` and `bigint_mul` as tagged scalars.
+unsafe fn compact(a: Value) -> Option<i64> {
+    if a.is_scalar() {
+        Some(i64::from(a.get_signed_scalar()))
+    } else if a.tag() == TAG_BITS64 {
+        Some((*(a.get_ptr() as *const Bits64)).bits() as i64)
+    } else {
+        let mp_int = a.as_bigint().mp_int_ptr();
+        if mp_count_bits(mp_int) < 64 {
+            Some(mp_get_i64(mp_int))
+        } else {
+            None
+        }
+    }
+}
+
+/// libtommath view of a number. Compact numbers and tagged scalars are copied to a new `BigInt`.
+unsafe fn as_mp_int(a: Value) -> *const mp_int {
+    if a.is_scalar() || a.tag() == TAG_BITS64 {
+        let mut i = tmp_bigint();
+        mp_set_i64(&mut i, compact(a).unwrap());
+        persist_bigint(i).as_bigint().mp_int_ptr()
+    } else {
+        a.as_bigint().mp_int_ptr()
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn bigint_of_word32(w: u32) -> Value {
-    let mut i = tmp_bigint();
-    mp_set_u32(&mut i, w);
-    persist_bigint(i)
+    bigint_of_compact(i64::from(w))
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_of_int32(j: i32) -> Value {
-    let mut i = tmp_bigint();
-    mp_set_i32(&mut i, j);
-    persist_bigint(i)
+    bigint_of_compact(i64::from(j))
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_to_word32_wrap(p: Value) -> u32 {
-    mp_get_u32(p.as_bigint().mp_int_ptr())
+    match compact(p) {
+        Some(n) => n as u32,
+        None => mp_get_u32(p.as_bigint().mp_int_ptr()),
+    }
 }
 
 #[no_mangle]
 unsafe extern "C" fn bigint_to_word32_trap(p: Value) -> u32 {
-    let mp_int = p.as_bigint().mp_int_ptr();
-
-    if mp_isneg(mp_int) || mp_count_bits(mp_int) > 32 {
-        bigint_trap();
+    match compact(p) {
+        Some(n) if n >= 0 && n <= i64::from(u32::MAX) => n as u32,
+        _ => bigint_trap(),
     }
-
-    mp_get_u32(mp_int)
 }
 
 // a : BigInt, msg : Blob
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_to_word32_trap_with(p: Value, msg: Value) -> u32 {
-    let mp_int = p.as_bigint().mp_int_ptr();
-
-    if mp_isneg(mp_int) || mp_count_bits(mp_int) > 32 {
-        crate::rts_trap(msg.as_blob().payload_const(), msg.as_blob().len());
+    match compact(p) {
+        Some(n) if n >= 0 && n <= i64::from(u32::MAX) => n as u32,
+        _ => crate::rts_trap(msg.as_blob().payload_const(), msg.as_blob().len()),
     }
-
-    mp_get_u32(mp_int)
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_to_word64_wrap(p: Value) -> u64 {
-    mp_get_u64(p.as_bigint().mp_int_ptr())
+    match compact(p) {
+        Some(n) => n as u64,
+        None => mp_get_u64(p.as_bigint().mp_int_ptr()),
+    }
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_to_word64_trap(p: Value) -> u64 {
+    if let Some(n) = compact(p) {
+        if n < 0 {
+            bigint_trap();
+        }
+        return n as u64;
+    }
+
+    // Only `BigInt`s are not compact
     let mp_int = p.as_bigint().mp_int_ptr();
 
     if mp_isneg(mp_int) || mp_count_bits(mp_int) > 64 {
@@ -234,20 +314,19 @@ unsafe extern "C" fn bigint_to_word64_trap(p: Value) -> u64 {
     mp_get_u64(mp_int)
 }
 
-#[cfg(feature = "ic")]
 #[no_mangle]
-unsafe extern "C" fn bigint_of_word64(w: u64) -> Value {
+pub unsafe extern "C" fn bigint_of_word64(w: u64) -> Value {
+    if w <= i64::MAX as u64 {
+        return bigint_of_compact(w as i64);
+    }
     let mut i = tmp_bigint();
     mp_set_u64(&mut i, w);
     persist_bigint(i)
 }
 
-#[cfg(feature = "ic")]
 #[no_mangle]
-unsafe extern "C" fn bigint_of_int64(j: i64) -> Value {
-    let mut i = tmp_bigint();
-    mp_set_i64(&mut i, j);
-    persist_bigint(i)
+pub unsafe extern "C" fn bigint_of_int64(j: i64) -> Value {
+    bigint_of_compact(j)
 }
 
 #[cfg(feature = "ic")]
@@ -270,70 +349,83 @@ unsafe extern "C" fn bigint_to_float64(p: Value) -> f64 {
     if p.is_scalar() {
         p.get_signed_scalar() as f64
     } else {
-        let mp_int = p.as_bigint().mp_int_ptr();
+        let mp_int = as_mp_int(p);
         mp_get_double(mp_int)
     }
 }
 
+unsafe fn bigint_cmp(a: Value, b: Value) -> core::cmp::Ordering {
+    match (compact(a), compact(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => mp_cmp(as_mp_int(a), as_mp_int(b)).cmp(&0),
+    }
+}
+
 #[no_mangle]
 pub unsafe extern "C" fn bigint_eq(a: Value, b: Value) -> bool {
-    mp_cmp(a.as_bigint().mp_int_ptr(), b.as_bigint().mp_int_ptr()) == 0
+    bigint_cmp(a, b).is_eq()
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_lt(a: Value, b: Value) -> bool {
-    mp_cmp(a.as_bigint().mp_int_ptr(), b.as_bigint().mp_int_ptr()) < 0
+    bigint_cmp(a, b).is_lt()
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_gt(a: Value, b: Value) -> bool {
-    mp_cmp(a.as_bigint().mp_int_ptr(), b.as_bigint().mp_int_ptr()) > 0
+    bigint_cmp(a, b).is_gt()
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_le(a: Value, b: Value) -> bool {
-    mp_cmp(a.as_bigint().mp_int_ptr(), b.as_bigint().mp_int_ptr()) <= 0
+    bigint_cmp(a, b).is_le()
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_ge(a: Value, b: Value) -> bool {
-    mp_cmp(a.as_bigint().mp_int_ptr(), b.as_bigint().mp_int_ptr()) >= 0
+    bigint_cmp(a, b).is_ge()
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_add(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        if let Some(r) = a.checked_add(b) {
+            return bigint_of_compact(r);
+        }
+    }
+
     let mut i = tmp_bigint();
-    check(mp_add(
-        a.as_bigint().mp_int_ptr(),
-        b.as_bigint().mp_int_ptr(),
-        &mut i,
-    ));
+    check(mp_add(as_mp_int(a), as_mp_int(b), &mut i));
     persist_bigint(i)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_sub(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        if let Some(r) = a.checked_sub(b) {
+            return bigint_of_compact(r);
+        }
+    }
+
     let mut i = tmp_bigint();
-    check(mp_sub(
-        a.as_bigint().mp_int_ptr(),
-        b.as_bigint().mp_int_ptr(),
-        &mut i,
-    ));
+    check(mp_sub(as_mp_int(a), as_mp_int(b), &mut i));
     persist_bigint(i)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_mul(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        if let Some(r) = a.checked_mul(b) {
+            return bigint_of_compact(r);
+        }
+    }
+
     let mut i = tmp_bigint();
-    check(mp_mul(
-        a.as_bigint().mp_int_ptr(),
-        b.as_bigint().mp_int_ptr(),
-        &mut i,
-    ));
+    check(mp_mul(as_mp_int(a), as_mp_int(b), &mut i));
     persist_bigint(i)
 }
 
@@ -341,30 +433,41 @@ pub unsafe extern "C" fn bigint_mul(a: Value, b: Value) -> Value {
 pub unsafe extern "C" fn bigint_pow(a: Value, b: Value) -> Value {
     let exp = bigint_to_word32_trap(b);
     let mut i = tmp_bigint();
-    check(mp_expt_u32(a.as_bigint().mp_int_ptr(), exp, &mut i));
+    check(mp_expt_u32(as_mp_int(a), exp, &mut i));
     persist_bigint(i)
 }
 
-#[cfg(feature = "ic")]
 #[no_mangle]
-unsafe extern "C" fn bigint_div(a: Value, b: Value) -> Value {
+pub unsafe extern "C" fn bigint_div(a: Value, b: Value) -> Value {
+    // Division by zero is left to libtommath, which fails
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        if let Some(r) = a.checked_div(b) {
+            return bigint_of_compact(r);
+        }
+    }
+
     let mut i = tmp_bigint();
     check(mp_div(
-        a.as_bigint().mp_int_ptr(),
-        b.as_bigint().mp_int_ptr(),
+        as_mp_int(a),
+        as_mp_int(b),
         &mut i,
         core::ptr::null_mut(),
     ));
     persist_bigint(i)
 }
 
-#[cfg(feature = "ic")]
 #[no_mangle]
-unsafe extern "C" fn bigint_rem(a: Value, b: Value) -> Value {
+pub unsafe extern "C" fn bigint_rem(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        if let Some(r) = a.checked_rem(b) {
+            return bigint_of_compact(r);
+        }
+    }
+
     let mut i = tmp_bigint();
     check(mp_div(
-        a.as_bigint().mp_int_ptr(),
-        b.as_bigint().mp_int_ptr(),
+        as_mp_int(a),
+        as_mp_int(b),
         core::ptr::null_mut(),
         &mut i,
     ));
@@ -373,30 +476,41 @@ unsafe extern "C" fn bigint_rem(a: Value, b: Value) -> Value {
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_neg(a: Value) -> Value {
+    if let Some(r) = compact(a).and_then(i64::checked_neg) {
+        return bigint_of_compact(r);
+    }
+
     let mut i = tmp_bigint();
-    check(mp_neg(a.as_bigint().mp_int_ptr(), &mut i));
+    check(mp_neg(as_mp_int(a), &mut i));
     persist_bigint(i)
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_abs(a: Value) -> Value {
+    if let Some(r) = compact(a).and_then(i64::checked_abs) {
+        return bigint_of_compact(r);
+    }
+
     let mut i = tmp_bigint();
-    check(mp_abs(a.as_bigint().mp_int_ptr(), &mut i));
+    check(mp_abs(as_mp_int(a), &mut i));
     persist_bigint(i)
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_isneg(a: Value) -> bool {
-    mp_isneg(a.as_bigint().mp_int_ptr())
+    match compact(a) {
+        Some(n) => n < 0,
+        None => mp_isneg(a.as_bigint().mp_int_ptr()),
+    }
 }
 
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn bigint_lsh(a: Value, b: i32) -> Value {
     let mut i = tmp_bigint();
-    check(mp_mul_2d(a.as_bigint().mp_int_ptr(), b, &mut i));
+    check(mp_mul_2d(as_mp_int(a), b, &mut i));
     persist_bigint(i)
 }
 
@@ -404,26 +518,335 @@ unsafe extern "C" fn bigint_lsh(a: Value, b: i32) -> Value {
 #[no_mangle]
 unsafe extern "C" fn bigint_rsh(a: Value, b: i32) -> Value {
     let mut i = tmp_bigint();
-    check(mp_div_2d(
-        a.as_bigint().mp_int_ptr(),
-        b,
-        &mut i,
-        core::ptr::null_mut(),
-    ));
+    check(mp_div_2d(as_mp_int(a), b, &mut i, core::ptr::null_mut()));
     persist_bigint(i)
 }
 
+// Bitwise operations treat negative numbers as infinite two's complement bit strings, like
+// Python's `int`. libtommath's `mp_and`, `mp_or` and `mp_xor` already do, and so do the `i64`
+// operators on compact numbers.
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_and(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        return bigint_of_compact(a & b);
+    }
+
+    let mut i = tmp_bigint();
+    check(mp_and(as_mp_int(a), as_mp_int(b), &mut i));
+    persist_compact(i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_or(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        return bigint_of_compact(a | b);
+    }
+
+    let mut i = tmp_bigint();
+    check(mp_or(as_mp_int(a), as_mp_int(b), &mut i));
+    persist_compact(i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_xor(a: Value, b: Value) -> Value {
+    if let (Some(a), Some(b)) = (compact(a), compact(b)) {
+        return bigint_of_compact(a ^ b);
+    }
+
+    let mut i = tmp_bigint();
+    check(mp_xor(as_mp_int(a), as_mp_int(b), &mut i));
+    persist_compact(i)
+}
+
+/// `-a - 1`
+#[no_mangle]
+pub unsafe extern "C" fn bigint_not(a: Value) -> Value {
+    if let Some(a) = compact(a) {
+        return bigint_of_compact(!a);
+    }
+
+    let mut i = tmp_bigint();
+    check(mp_complement(as_mp_int(a), &mut i));
+    persist_compact(i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_test_bit(a: Value, bit: u32) -> bool {
+    if let Some(a) = compact(a) {
+        return a >> bit.min(63) & 1 != 0;
+    }
+
+    let a = as_mp_int(a);
+    let negative = mp_isneg(a);
+
+    // Above the magnitude, the bits are the sign
+    if bit >= mp_count_bits(a) as u32 {
+        return negative;
+    }
+
+    // The bits of a negative number are the complement of the bits of `|a| - 1`
+    let mut i = tmp_bigint();
+    if negative {
+        check(mp_add_d(a, 1, &mut i));
+        check(mp_abs(&i, &mut i));
+        check(mp_div_2d(&i, bit as i32, &mut i, core::ptr::null_mut()));
+    } else {
+        check(mp_div_2d(a, bit as i32, &mut i, core::ptr::null_mut()));
+    }
+    (mp_get_u32(&i) & 1 != 0) != negative
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_set_bit(a: Value, bit: u32) -> Value {
+    match compact(a) {
+        Some(a) if bit < 63 => return bigint_of_compact(a | 1 << bit),
+        _ => {}
+    }
+
+    let mut mask = tmp_bigint();
+    check(mp_2expt(&mut mask, bit as i32));
+    let mut i = tmp_bigint();
+    check(mp_or(as_mp_int(a), &mask, &mut i));
+    persist_compact(i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_clear_bit(a: Value, bit: u32) -> Value {
+    match compact(a) {
+        Some(a) if bit < 63 => return bigint_of_compact(a & !(1 << bit)),
+        _ => {}
+    }
+
+    let mut mask = tmp_bigint();
+    check(mp_2expt(&mut mask, bit as i32));
+    check(mp_complement(&mask, &mut mask));
+    let mut i = tmp_bigint();
+    check(mp_and(as_mp_int(a), &mask, &mut i));
+    persist_compact(i)
+}
+
+/// Number of one bits in the magnitude, like Python's `int.bit_count`
+#[no_mangle]
+pub unsafe extern "C" fn bigint_popcount(a: Value) -> u32 {
+    if let Some(a) = compact(a) {
+        return a.unsigned_abs().count_ones();
+    }
+
+    let a = as_mp_int(a);
+    // Unused bits of the digits are zero
+    let digits = core::slice::from_raw_parts((*a).dp, (*a).used as usize);
+    digits.iter().map(|digit| digit.count_ones()).sum()
+}
+
+/// Number of bits in the magnitude, like Python's `int.bit_length`
+#[no_mangle]
+pub unsafe extern "C" fn bigint_bit_length(a: Value) -> u32 {
+    if let Some(a) = compact(a) {
+        return 64 - a.unsigned_abs().leading_zeros();
+    }
+
+    mp_count_bits(as_mp_int(a)) as u32
+}
+
 #[no_mangle]
 unsafe extern "C" fn bigint_count_bits(a: Value) -> i32 {
-    mp_count_bits(a.as_bigint().mp_int_ptr())
+    match compact(a) {
+        Some(n) => 64 - n.unsigned_abs().leading_zeros() as i32,
+        None => mp_count_bits(a.as_bigint().mp_int_ptr()),
+    }
+}
+
+unsafe fn check_radix(radix: u32) {
+    if !(2..=36).contains(&radix) {
+        rts_trap_with("bigint: radix must be between 2 and 36");
+    }
+}
+
+/// Digits of the number in the given radix, with a leading `-` for negative numbers. Digits above
+/// 9 are lower case letters.
+#[ic_mem_fn]
+pub unsafe fn bigint_to_text<M: Memory>(mem: &mut M, a: Value, radix: u32) -> Value {
+    check_radix(radix);
+    let a = as_mp_int(a);
+
+    // Includes the sign and the NUL terminator
+    let mut size = 0;
+    check(mp_radix_size(a, radix as i32, &mut size));
+
+    let scratch = alloc_blob(mem, Bytes(size as u32));
+    let buf = scratch.as_blob_mut().payload_addr();
+    let mut written = 0;
+    check(mp_to_radix(
+        a,
+        buf as *mut _,
+        size as _,
+        &mut written,
+        radix as i32,
+    ));
+    let len = written as u32 - 1; // without the NUL terminator
+
+    // libtommath uses upper case letters
+    for i in 0..len as usize {
+        let c = buf.add(i);
+        *c = (*c).to_ascii_lowercase();
+    }
+
+    text_of_ptr_size(mem, buf, Bytes(len))
+}
+
+/// Parses an optional sign followed by at least one digit in the given radix. Letters can be in
+/// upper or lower case. Returns 1 and stores the number in `out` on success, as a compact number
+/// if it fits, returns 0 on malformed input.
+#[ic_mem_fn]
+pub unsafe fn bigint_of_text<M: Memory>(
+    mem: &mut M,
+    text: Value,
+    radix: u32,
+    out: *mut Value,
+) -> u32 {
+    check_radix(radix);
+
+    // libtommath needs the digits in contiguous memory, with a NUL terminator
+    let len = text_size(text).as_usize();
+    let scratch = alloc_blob(mem, Bytes(len as u32 + 1));
+    let buf = scratch.as_blob_mut().payload_addr();
+    text_to_buf(text, buf);
+    *buf.add(len) = 0;
+
+    let str = core::slice::from_raw_parts(buf, len);
+    let digits = match str.first() {
+        Some(b'+') | Some(b'-') => &str[1..],
+        _ => str,
+    };
+
+    // `mp_read_radix` stops at the first invalid character, and accepts empty input
+    if digits.is_empty() || !digits.iter().all(|c| (*c as char).is_digit(radix)) {
+        return 0;
+    }
+
+    // `mp_read_radix` only knows about `-`
+    let start = if str[0] == b'+' { buf.add(1) } else { buf };
+
+    let mut i = tmp_bigint();
+    check(mp_read_radix(&mut i, start as *const _, radix as i32));
+    *out = persist_compact(i);
+    1
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_gcd(a: Value, b: Value) -> Value {
+    let mut i = tmp_bigint();
+    check(mp_gcd(as_mp_int(a), as_mp_int(b), &mut i));
+    persist_bigint(i)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn bigint_lcm(a: Value, b: Value) -> Value {
+    let a = as_mp_int(a);
+    let b = as_mp_int(b);
+    let mut i = tmp_bigint();
+    // libtommath divides by the gcd, which is 0 here
+    if !(mp_iszero(a) && mp_iszero(b)) {
+        check(mp_lcm(a, b, &mut i));
+    }
+    persist_bigint(i)
+}
+
+/// Checks that `m` is a positive modulus. Returns whether it is 1, in which case every result is
+/// 0. libtommath's modular functions reject or mishandle that modulus, so callers handle it.
+unsafe fn check_modulus(m: *const mp_int) -> bool {
+    if mp_isneg(m) || mp_iszero(m) {
+        rts_trap_with("bigint: modulus must be positive");
+    }
+    (*m).used == 1 && *(*m).dp == 1
+}
+
+/// `b ^ e mod m`, in `[0, m)`. Negative exponents are allowed when `b` is invertible modulo `m`.
+#[no_mangle]
+pub unsafe extern "C" fn bigint_powmod(b: Value, e: Value, m: Value) -> Value {
+    let m = as_mp_int(m);
+    let mut i = tmp_bigint();
+    if !check_modulus(m) {
+        check(mp_exptmod(as_mp_int(b), as_mp_int(e), m, &mut i));
+    }
+    persist_bigint(i)
+}
+
+/// Inverse of `a` modulo `m`, in `[0, m)`. Returns 1 and stores the inverse in `out` if `a` and
+/// `m` are coprime, returns 0 otherwise.
+#[no_mangle]
+pub unsafe extern "C" fn bigint_invmod(a: Value, m: Value, out: *mut Value) -> u32 {
+    let m = as_mp_int(m);
+    let mut i = tmp_bigint();
+    // With a modulus larger than 1, the only invalid input is a non-invertible `a`
+    if !check_modulus(m) {
+        if mp_invmod(as_mp_int(a), m, &mut i) != 0 {
+            return 0;
+        }
+        // The inverse of a negative number comes out negative
+        if mp_isneg(&i) {
+            check(mp_add(&i, m, &mut i));
+        }
+    }
+    *out = persist_bigint(i);
+    1
+}
+
+/// Integer square root, rounded down
+#[no_mangle]
+pub unsafe extern "C" fn bigint_isqrt(a: Value) -> Value {
+    let a = as_mp_int(a);
+    if mp_isneg(a) {
+        rts_trap_with("bigint_isqrt: negative argument");
+    }
+    let mut i = tmp_bigint();
+    check(mp_sqrt(a, &mut i));
+    persist_bigint(i)
+}
+
+static mut RAND_STATE: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Source of the random Miller-Rabin bases of `mp_prime_is_prime`. Canisters must be
+/// deterministic, so this is a fixed-seed xorshift generator, not an entropy source.
+unsafe extern "C" fn bigint_rand_source(out: *mut libc::c_void, size: usize) -> mp_err {
+    let out = out as *mut u8;
+    for i in 0..size {
+        RAND_STATE ^= RAND_STATE << 13;
+        RAND_STATE ^= RAND_STATE >> 7;
+        RAND_STATE ^= RAND_STATE << 17;
+        *out.add(i) = RAND_STATE as u8;
+    }
+    0
+}
+
+/// Baillie-PSW test followed by `rounds` Miller-Rabin tests with pseudo-random bases. Primes are
+/// never rejected. No composite numbers passing the Baillie-PSW test are known, the additional
+/// rounds bound the probability of such a number passing by `4^-rounds`.
+#[no_mangle]
+pub unsafe extern "C" fn bigint_is_probable_prime(a: Value, rounds: u32) -> bool {
+    let a = as_mp_int(a);
+    // libtommath ignores the sign
+    if mp_isneg(a) {
+        return false;
+    }
+    mp_rand_source(bigint_rand_source);
+    let mut result = 0;
+    check(mp_prime_is_prime(
+        a,
+        core::cmp::min(rounds, i32::MAX as u32) as i32,
+        &mut result,
+    ));
+    result != 0
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_leb128_size(a: Value) -> u32 {
-    if mp_iszero(a.as_bigint().mp_int_ptr()) {
+    let bits = bigint_count_bits(a) as u32;
+    if bits == 0 {
         1
     } else {
-        (bigint_count_bits(a) as u32 + 6) / 7 // divide by 7, round up
+        (bits + 6) / 7 // divide by 7, round up
     }
 }
 
@@ -449,19 +872,25 @@ unsafe fn bigint_leb128_encode_go(tmp: *mut mp_int, mut buf: *mut u8, add_bit: b
 #[no_mangle]
 pub unsafe extern "C" fn bigint_leb128_encode(n: Value, buf: *mut u8) {
     let mut tmp: mp_int = core::mem::zeroed(); // or core::mem::uninitialized?
-    check(mp_init_copy(&mut tmp, n.as_bigint().mp_int_ptr()));
+    check(mp_init_copy(&mut tmp, as_mp_int(n)));
     bigint_leb128_encode_go(&mut tmp, buf, false)
 }
 
 #[no_mangle]
 pub unsafe extern "C" fn bigint_leb128_stream_encode(stream: *mut Stream, n: Value) {
     let mut tmp: mp_int = core::mem::zeroed(); // or core::mem::uninitialized?
-    check(mp_init_copy(&mut tmp, n.as_bigint().mp_int_ptr()));
+    check(mp_init_copy(&mut tmp, as_mp_int(n)));
     stream.write_leb128(&mut tmp, false)
 }
 
 #[no_mangle]
 unsafe extern "C" fn bigint_2complement_bits(n: Value) -> u32 {
+    if let Some(n) = compact(n) {
+        // The bits of `!n` are the bits of `-n - 1` for negative numbers
+        let magnitude = if n < 0 { !n } else { n };
+        return 1 + 64 - magnitude.leading_zeros();
+    }
+
     let mp_int = n.as_bigint().mp_int_ptr();
     if mp_isneg(mp_int) {
         let mut tmp: mp_int = core::mem::zeroed(); // or core::mem::uninitialized?
@@ -481,7 +910,7 @@ pub unsafe extern "C" fn bigint_sleb128_size(n: Value) -> u32 {
 #[no_mangle]
 pub unsafe extern "C" fn bigint_sleb128_encode(n: Value, buf: *mut u8) {
     let mut tmp: mp_int = core::mem::zeroed(); // or core::mem::uninitialized?
-    check(mp_init_copy(&mut tmp, n.as_bigint().mp_int_ptr()));
+    check(mp_init_copy(&mut tmp, as_mp_int(n)));
 
     if mp_isneg(&tmp) {
         // Turn negative numbers into the two's complement of the right size
@@ -499,7 +928,7 @@ pub unsafe extern "C" fn bigint_sleb128_encode(n: Value, buf: *mut u8) {
 #[no_mangle]
 pub unsafe extern "C" fn bigint_sleb128_stream_encode(stream: *mut Stream, n: Value) {
     let mut tmp: mp_int = core::mem::zeroed(); // or core::mem::uninitialized?
-    check(mp_init_copy(&mut tmp, n.as_bigint().mp_int_ptr()));
+    check(mp_init_copy(&mut tmp, as_mp_int(n)));
 
     if mp_isneg(&tmp) {
         // Turn negative numbers into the two's complement of the right size
@@ -535,6 +964,12 @@ pub unsafe extern "C" fn bigint_leb128_decode(buf: *mut Buf) -> Value {
     persist_bigint(i)
 }
 
+/// like `bigint_leb128_decode`, but from a stream reader
+#[no_mangle]
+pub unsafe extern "C" fn bigint_leb128_stream_decode(reader: *mut StreamReader) -> Value {
+    bigint_leb128_decode(reader.fill_leb128())
+}
+
 /// Decode at most 5 bytes of LEB128 data to a compact bignum `Value`.
 /// The number of 7-bit chunks are located in the lower portion of `leb`
 /// as indicated by `bits`.
@@ -603,6 +1038,12 @@ pub unsafe extern "C" fn bigint_sleb128_decode(buf: *mut Buf) -> Value {
     persist_bigint(i)
 }
 
+/// like `bigint_sleb128_decode`, but from a stream reader
+#[no_mangle]
+pub unsafe extern "C" fn bigint_sleb128_stream_decode(reader: *mut StreamReader) -> Value {
+    bigint_sleb128_decode(reader.fill_leb128())
+}
+
 /// Decode at most 5 bytes of SLEB128 data to a compact bignum `Value`.
 /// The number of 7-bit chunks are located in the lower portion of `sleb`
 /// as indicated by `bits`.
@@ -1,3 +1,9 @@
+//! Unicode character properties and case mappings
+
+#![allow(non_upper_case_globals)]
+
+mod tables;
+
 #[cfg(feature = "ic")]
 #[no_mangle]
 unsafe extern "C" fn char_to_upper(c: u32) -> u32 {
@@ -43,3 +49,119 @@ unsafe extern "C" fn char_is_lowercase(c: u32) -> u32 {
 unsafe extern "C" fn char_is_alphabetic(c: u32) -> u32 {
     core::char::from_u32_unchecked(c).is_alphabetic().into()
 }
+
+//
+// Character classification beyond what `core::char` offers, based on the range tables in
+// `char/tables.rs`. Code points that are not valid `char`s are classified as unassigned.
+//
+
+// General categories, in the order of the Unicode Standard (section 4.5). The numbering is
+// shared with `gen-char-tables.py`.
+pub const GC_Lu: u32 = 0; // Uppercase_Letter
+pub const GC_Ll: u32 = 1; // Lowercase_Letter
+pub const GC_Lt: u32 = 2; // Titlecase_Letter
+pub const GC_Lm: u32 = 3; // Modifier_Letter
+pub const GC_Lo: u32 = 4; // Other_Letter
+pub const GC_Mn: u32 = 5; // Nonspacing_Mark
+pub const GC_Mc: u32 = 6; // Spacing_Mark
+pub const GC_Me: u32 = 7; // Enclosing_Mark
+pub const GC_Nd: u32 = 8; // Decimal_Number
+pub const GC_Nl: u32 = 9; // Letter_Number
+pub const GC_No: u32 = 10; // Other_Number
+pub const GC_Pc: u32 = 11; // Connector_Punctuation
+pub const GC_Pd: u32 = 12; // Dash_Punctuation
+pub const GC_Ps: u32 = 13; // Open_Punctuation
+pub const GC_Pe: u32 = 14; // Close_Punctuation
+pub const GC_Pi: u32 = 15; // Initial_Punctuation
+pub const GC_Pf: u32 = 16; // Final_Punctuation
+pub const GC_Po: u32 = 17; // Other_Punctuation
+pub const GC_Sm: u32 = 18; // Math_Symbol
+pub const GC_Sc: u32 = 19; // Currency_Symbol
+pub const GC_Sk: u32 = 20; // Modifier_Symbol
+pub const GC_So: u32 = 21; // Other_Symbol
+pub const GC_Zs: u32 = 22; // Space_Separator
+pub const GC_Zl: u32 = 23; // Line_Separator
+pub const GC_Zp: u32 = 24; // Paragraph_Separator
+pub const GC_Cc: u32 = 25; // Control
+pub const GC_Cf: u32 = 26; // Format
+pub const GC_Cs: u32 = 27; // Surrogate
+pub const GC_Co: u32 = 28; // Private_Use
+pub const GC_Cn: u32 = 29; // Unassigned
+
+// East Asian width property values (UAX #11), in alphabetical order. The numbering is shared
+// with `gen-char-tables.py`.
+pub const EAW_A: u32 = 0; // Ambiguous
+pub const EAW_F: u32 = 1; // Fullwidth
+pub const EAW_H: u32 = 2; // Halfwidth
+pub const EAW_N: u32 = 3; // Neutral
+pub const EAW_Na: u32 = 4; // Narrow
+pub const EAW_W: u32 = 5; // Wide
+
+/// Returns the start of the table range that contains `c`, and the value for that range
+fn lookup(table: &[u32], c: u32) -> (u32, u8) {
+    let idx = match table.binary_search_by(|entry| (entry >> 8).cmp(&c)) {
+        Ok(idx) => idx,
+        Err(idx) => idx - 1, // all tables start at code point 0
+    };
+    let entry = table[idx];
+    (entry >> 8, entry as u8)
+}
+
+#[no_mangle]
+pub extern "C" fn char_general_category(c: u32) -> u32 {
+    let (start, cat) = lookup(&tables::GENERAL_CATEGORY, c);
+    match cat {
+        tables::ALT_UPPER_FIRST if (c - start) % 2 == 0 => GC_Lu,
+        tables::ALT_UPPER_FIRST => GC_Ll,
+        tables::ALT_LOWER_FIRST if (c - start) % 2 == 0 => GC_Ll,
+        tables::ALT_LOWER_FIRST => GC_Lu,
+        _ => cat as u32,
+    }
+}
+
+/// Numeric characters have one of the categories `Nd`, `Nl` or `No`
+#[no_mangle]
+pub extern "C" fn char_is_numeric(c: u32) -> u32 {
+    let cat = char_general_category(c);
+    (GC_Nd..=GC_No).contains(&cat).into()
+}
+
+/// Alphanumeric characters are letters (categories `Lu` to `Lo`) or numeric. Both come from
+/// the same table, so unlike `char_is_alphabetic`, marks of the `Other_Alphabetic` property
+/// do not count.
+#[no_mangle]
+pub extern "C" fn char_is_alphanumeric(c: u32) -> u32 {
+    let cat = char_general_category(c);
+    ((GC_Lu..=GC_Lo).contains(&cat) || (GC_Nd..=GC_No).contains(&cat)).into()
+}
+
+#[no_mangle]
+pub extern "C" fn char_is_control(c: u32) -> u32 {
+    (char_general_category(c) == GC_Cc).into()
+}
+
+/// Punctuation characters have one of the categories `Pc`, `Pd`, `Ps`, `Pe`, `Pi`, `Pf` or
+/// `Po`. NB. This is narrower than `char::is_ascii_punctuation`, which also includes symbols
+/// like `+` and `$`.
+#[no_mangle]
+pub extern "C" fn char_is_punctuation(c: u32) -> u32 {
+    let cat = char_general_category(c);
+    (GC_Pc..=GC_Po).contains(&cat).into()
+}
+
+/// Returns the value of a decimal digit (category `Nd`), or -1 for other characters
+#[no_mangle]
+pub extern "C" fn char_decimal_value(c: u32) -> i32 {
+    let (start, cat) = lookup(&tables::GENERAL_CATEGORY, c);
+    if cat as u32 == GC_Nd {
+        // Runs of decimal digits always start at digit zero (checked by the table generator)
+        ((c - start) % 10) as i32
+    } else {
+        -1
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn char_east_asian_width(c: u32) -> u32 {
+    lookup(&tables::EAST_ASIAN_WIDTH, c).1 as u32
+}
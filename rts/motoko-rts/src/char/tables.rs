@@ -0,0 +1,461 @@
+// Generated by `rts/gen-char-tables.py`, do not edit.
+//
+// Unicode version: 14.0.0
+
+pub(super) const ALT_UPPER_FIRST: u8 = 30;
+pub(super) const ALT_LOWER_FIRST: u8 = 31;
+
+/// General category ranges
+pub(super) static GENERAL_CATEGORY: [u32; 2972] = [
+    0x00000019, 0x00002016, 0x00002111, 0x00002413, 0x00002511, 0x0000280d, 0x0000290e, 0x00002a11,
+    0x00002b12, 0x00002c11, 0x00002d0c, 0x00002e11, 0x00003008, 0x00003a11, 0x00003c12, 0x00003f11,
+    0x00004100, 0x00005b0d, 0x00005c11, 0x00005d0e, 0x00005e14, 0x00005f0b, 0x00006014, 0x00006101,
+    0x00007b0d, 0x00007c12, 0x00007d0e, 0x00007e12, 0x00007f19, 0x0000a016, 0x0000a111, 0x0000a213,
+    0x0000a615, 0x0000a711, 0x0000a814, 0x0000a915, 0x0000aa04, 0x0000ab0f, 0x0000ac12, 0x0000ad1a,
+    0x0000ae15, 0x0000af14, 0x0000b015, 0x0000b112, 0x0000b20a, 0x0000b414, 0x0000b501, 0x0000b611,
+    0x0000b814, 0x0000b90a, 0x0000ba04, 0x0000bb10, 0x0000bc0a, 0x0000bf11, 0x0000c000, 0x0000d712,
+    0x0000d800, 0x0000df01, 0x0000f712, 0x0000f801, 0x0001001e, 0x0001381f, 0x0001491f, 0x0001791e,
+    0x00017f01, 0x00018100, 0x0001831f, 0x00018700, 0x00018801, 0x00018900, 0x00018c01, 0x00018e00,
+    0x00019201, 0x00019300, 0x00019501, 0x00019600, 0x00019901, 0x00019c00, 0x00019e01, 0x00019f00,
+    0x0001a11f, 0x0001a71e, 0x0001ab1f, 0x0001af00, 0x0001b001, 0x0001b100, 0x0001b41f, 0x0001b800,
+    0x0001b901, 0x0001bb04, 0x0001bc00, 0x0001bd01, 0x0001c004, 0x0001c400, 0x0001c502, 0x0001c601,
+    0x0001c700, 0x0001c802, 0x0001c901, 0x0001ca00, 0x0001cb02, 0x0001cc1f, 0x0001dd1f, 0x0001f001,
+    0x0001f100, 0x0001f202, 0x0001f31f, 0x0001f700, 0x0001f91f, 0x00023401, 0x00023a00, 0x00023c01,
+    0x00023d00, 0x00023f01, 0x00024100, 0x00024201, 0x00024300, 0x0002471f, 0x00025001, 0x00029404,
+    0x00029501, 0x0002b003, 0x0002c214, 0x0002c603, 0x0002d214, 0x0002e003, 0x0002e514, 0x0002ec03,
+    0x0002ed14, 0x0002ee03, 0x0002ef14, 0x00030005, 0x0003701e, 0x00037403, 0x00037514, 0x00037600,
+    0x00037701, 0x0003781d, 0x00037a03, 0x00037b01, 0x00037e11, 0x00037f00, 0x0003801d, 0x00038414,
+    0x00038600, 0x00038711, 0x00038800, 0x00038b1d, 0x00038c00, 0x00038d1d, 0x00038e00, 0x00039001,
+    0x00039100, 0x0003a21d, 0x0003a300, 0x0003ac01, 0x0003cf00, 0x0003d001, 0x0003d200, 0x0003d501,
+    0x0003d81e, 0x0003f001, 0x0003f400, 0x0003f501, 0x0003f612, 0x0003f700, 0x0003f801, 0x0003f900,
+    0x0003fb01, 0x0003fd00, 0x00043001, 0x0004601e, 0x00048215, 0x00048305, 0x00048807, 0x00048a1e,
+    0x0004c11e, 0x0004cf1f, 0x0005301d, 0x00053100, 0x0005571d, 0x00055903, 0x00055a11, 0x00056001,
+    0x00058911, 0x00058a0c, 0x00058b1d, 0x00058d15, 0x00058f13, 0x0005901d, 0x00059105, 0x0005be0c,
+    0x0005bf05, 0x0005c011, 0x0005c105, 0x0005c311, 0x0005c405, 0x0005c611, 0x0005c705, 0x0005c81d,
+    0x0005d004, 0x0005eb1d, 0x0005ef04, 0x0005f311, 0x0005f51d, 0x0006001a, 0x00060612, 0x00060911,
+    0x00060b13, 0x00060c11, 0x00060e15, 0x00061005, 0x00061b11, 0x00061c1a, 0x00061d11, 0x00062004,
+    0x00064003, 0x00064104, 0x00064b05, 0x00066008, 0x00066a11, 0x00066e04, 0x00067005, 0x00067104,
+    0x0006d411, 0x0006d504, 0x0006d605, 0x0006dd1a, 0x0006de15, 0x0006df05, 0x0006e503, 0x0006e705,
+    0x0006e915, 0x0006ea05, 0x0006ee04, 0x0006f008, 0x0006fa04, 0x0006fd15, 0x0006ff04, 0x00070011,
+    0x00070e1d, 0x00070f1a, 0x00071004, 0x00071105, 0x00071204, 0x00073005, 0x00074b1d, 0x00074d04,
+    0x0007a605, 0x0007b104, 0x0007b21d, 0x0007c008, 0x0007ca04, 0x0007eb05, 0x0007f403, 0x0007f615,
+    0x0007f711, 0x0007fa03, 0x0007fb1d, 0x0007fd05, 0x0007fe13, 0x00080004, 0x00081605, 0x00081a03,
+    0x00081b05, 0x00082403, 0x00082505, 0x00082803, 0x00082905, 0x00082e1d, 0x00083011, 0x00083f1d,
+    0x00084004, 0x00085905, 0x00085c1d, 0x00085e11, 0x00085f1d, 0x00086004, 0x00086b1d, 0x00087004,
+    0x00088814, 0x00088904, 0x00088f1d, 0x0008901a, 0x0008921d, 0x00089805, 0x0008a004, 0x0008c903,
+    0x0008ca05, 0x0008e21a, 0x0008e305, 0x00090306, 0x00090404, 0x00093a05, 0x00093b06, 0x00093c05,
+    0x00093d04, 0x00093e06, 0x00094105, 0x00094906, 0x00094d05, 0x00094e06, 0x00095004, 0x00095105,
+    0x00095804, 0x00096205, 0x00096411, 0x00096608, 0x00097011, 0x00097103, 0x00097204, 0x00098105,
+    0x00098206, 0x0009841d, 0x00098504, 0x00098d1d, 0x00098f04, 0x0009911d, 0x00099304, 0x0009a91d,
+    0x0009aa04, 0x0009b11d, 0x0009b204, 0x0009b31d, 0x0009b604, 0x0009ba1d, 0x0009bc05, 0x0009bd04,
+    0x0009be06, 0x0009c105, 0x0009c51d, 0x0009c706, 0x0009c91d, 0x0009cb06, 0x0009cd05, 0x0009ce04,
+    0x0009cf1d, 0x0009d706, 0x0009d81d, 0x0009dc04, 0x0009de1d, 0x0009df04, 0x0009e205, 0x0009e41d,
+    0x0009e608, 0x0009f004, 0x0009f213, 0x0009f40a, 0x0009fa15, 0x0009fb13, 0x0009fc04, 0x0009fd11,
+    0x0009fe05, 0x0009ff1d, 0x000a0105, 0x000a0306, 0x000a041d, 0x000a0504, 0x000a0b1d, 0x000a0f04,
+    0x000a111d, 0x000a1304, 0x000a291d, 0x000a2a04, 0x000a311d, 0x000a3204, 0x000a341d, 0x000a3504,
+    0x000a371d, 0x000a3804, 0x000a3a1d, 0x000a3c05, 0x000a3d1d, 0x000a3e06, 0x000a4105, 0x000a431d,
+    0x000a4705, 0x000a491d, 0x000a4b05, 0x000a4e1d, 0x000a5105, 0x000a521d, 0x000a5904, 0x000a5d1d,
+    0x000a5e04, 0x000a5f1d, 0x000a6608, 0x000a7005, 0x000a7204, 0x000a7505, 0x000a7611, 0x000a771d,
+    0x000a8105, 0x000a8306, 0x000a841d, 0x000a8504, 0x000a8e1d, 0x000a8f04, 0x000a921d, 0x000a9304,
+    0x000aa91d, 0x000aaa04, 0x000ab11d, 0x000ab204, 0x000ab41d, 0x000ab504, 0x000aba1d, 0x000abc05,
+    0x000abd04, 0x000abe06, 0x000ac105, 0x000ac61d, 0x000ac705, 0x000ac906, 0x000aca1d, 0x000acb06,
+    0x000acd05, 0x000ace1d, 0x000ad004, 0x000ad11d, 0x000ae004, 0x000ae205, 0x000ae41d, 0x000ae608,
+    0x000af011, 0x000af113, 0x000af21d, 0x000af904, 0x000afa05, 0x000b001d, 0x000b0105, 0x000b0206,
+    0x000b041d, 0x000b0504, 0x000b0d1d, 0x000b0f04, 0x000b111d, 0x000b1304, 0x000b291d, 0x000b2a04,
+    0x000b311d, 0x000b3204, 0x000b341d, 0x000b3504, 0x000b3a1d, 0x000b3c05, 0x000b3d04, 0x000b3e06,
+    0x000b3f05, 0x000b4006, 0x000b4105, 0x000b451d, 0x000b4706, 0x000b491d, 0x000b4b06, 0x000b4d05,
+    0x000b4e1d, 0x000b5505, 0x000b5706, 0x000b581d, 0x000b5c04, 0x000b5e1d, 0x000b5f04, 0x000b6205,
+    0x000b641d, 0x000b6608, 0x000b7015, 0x000b7104, 0x000b720a, 0x000b781d, 0x000b8205, 0x000b8304,
+    0x000b841d, 0x000b8504, 0x000b8b1d, 0x000b8e04, 0x000b911d, 0x000b9204, 0x000b961d, 0x000b9904,
+    0x000b9b1d, 0x000b9c04, 0x000b9d1d, 0x000b9e04, 0x000ba01d, 0x000ba304, 0x000ba51d, 0x000ba804,
+    0x000bab1d, 0x000bae04, 0x000bba1d, 0x000bbe06, 0x000bc005, 0x000bc106, 0x000bc31d, 0x000bc606,
+    0x000bc91d, 0x000bca06, 0x000bcd05, 0x000bce1d, 0x000bd004, 0x000bd11d, 0x000bd706, 0x000bd81d,
+    0x000be608, 0x000bf00a, 0x000bf315, 0x000bf913, 0x000bfa15, 0x000bfb1d, 0x000c0005, 0x000c0106,
+    0x000c0405, 0x000c0504, 0x000c0d1d, 0x000c0e04, 0x000c111d, 0x000c1204, 0x000c291d, 0x000c2a04,
+    0x000c3a1d, 0x000c3c05, 0x000c3d04, 0x000c3e05, 0x000c4106, 0x000c451d, 0x000c4605, 0x000c491d,
+    0x000c4a05, 0x000c4e1d, 0x000c5505, 0x000c571d, 0x000c5804, 0x000c5b1d, 0x000c5d04, 0x000c5e1d,
+    0x000c6004, 0x000c6205, 0x000c641d, 0x000c6608, 0x000c701d, 0x000c7711, 0x000c780a, 0x000c7f15,
+    0x000c8004, 0x000c8105, 0x000c8206, 0x000c8411, 0x000c8504, 0x000c8d1d, 0x000c8e04, 0x000c911d,
+    0x000c9204, 0x000ca91d, 0x000caa04, 0x000cb41d, 0x000cb504, 0x000cba1d, 0x000cbc05, 0x000cbd04,
+    0x000cbe06, 0x000cbf05, 0x000cc006, 0x000cc51d, 0x000cc605, 0x000cc706, 0x000cc91d, 0x000cca06,
+    0x000ccc05, 0x000cce1d, 0x000cd506, 0x000cd71d, 0x000cdd04, 0x000cdf1d, 0x000ce004, 0x000ce205,
+    0x000ce41d, 0x000ce608, 0x000cf01d, 0x000cf104, 0x000cf31d, 0x000d0005, 0x000d0206, 0x000d0404,
+    0x000d0d1d, 0x000d0e04, 0x000d111d, 0x000d1204, 0x000d3b05, 0x000d3d04, 0x000d3e06, 0x000d4105,
+    0x000d451d, 0x000d4606, 0x000d491d, 0x000d4a06, 0x000d4d05, 0x000d4e04, 0x000d4f15, 0x000d501d,
+    0x000d5404, 0x000d5706, 0x000d580a, 0x000d5f04, 0x000d6205, 0x000d641d, 0x000d6608, 0x000d700a,
+    0x000d7915, 0x000d7a04, 0x000d801d, 0x000d8105, 0x000d8206, 0x000d841d, 0x000d8504, 0x000d971d,
+    0x000d9a04, 0x000db21d, 0x000db304, 0x000dbc1d, 0x000dbd04, 0x000dbe1d, 0x000dc004, 0x000dc71d,
+    0x000dca05, 0x000dcb1d, 0x000dcf06, 0x000dd205, 0x000dd51d, 0x000dd605, 0x000dd71d, 0x000dd806,
+    0x000de01d, 0x000de608, 0x000df01d, 0x000df206, 0x000df411, 0x000df51d, 0x000e0104, 0x000e3105,
+    0x000e3204, 0x000e3405, 0x000e3b1d, 0x000e3f13, 0x000e4004, 0x000e4603, 0x000e4705, 0x000e4f11,
+    0x000e5008, 0x000e5a11, 0x000e5c1d, 0x000e8104, 0x000e831d, 0x000e8404, 0x000e851d, 0x000e8604,
+    0x000e8b1d, 0x000e8c04, 0x000ea41d, 0x000ea504, 0x000ea61d, 0x000ea704, 0x000eb105, 0x000eb204,
+    0x000eb405, 0x000ebd04, 0x000ebe1d, 0x000ec004, 0x000ec51d, 0x000ec603, 0x000ec71d, 0x000ec805,
+    0x000ece1d, 0x000ed008, 0x000eda1d, 0x000edc04, 0x000ee01d, 0x000f0004, 0x000f0115, 0x000f0411,
+    0x000f1315, 0x000f1411, 0x000f1515, 0x000f1805, 0x000f1a15, 0x000f2008, 0x000f2a0a, 0x000f3415,
+    0x000f3505, 0x000f3615, 0x000f3705, 0x000f3815, 0x000f3905, 0x000f3a0d, 0x000f3b0e, 0x000f3c0d,
+    0x000f3d0e, 0x000f3e06, 0x000f4004, 0x000f481d, 0x000f4904, 0x000f6d1d, 0x000f7105, 0x000f7f06,
+    0x000f8005, 0x000f8511, 0x000f8605, 0x000f8804, 0x000f8d05, 0x000f981d, 0x000f9905, 0x000fbd1d,
+    0x000fbe15, 0x000fc605, 0x000fc715, 0x000fcd1d, 0x000fce15, 0x000fd011, 0x000fd515, 0x000fd911,
+    0x000fdb1d, 0x00100004, 0x00102b06, 0x00102d05, 0x00103106, 0x00103205, 0x00103806, 0x00103905,
+    0x00103b06, 0x00103d05, 0x00103f04, 0x00104008, 0x00104a11, 0x00105004, 0x00105606, 0x00105805,
+    0x00105a04, 0x00105e05, 0x00106104, 0x00106206, 0x00106504, 0x00106706, 0x00106e04, 0x00107105,
+    0x00107504, 0x00108205, 0x00108306, 0x00108505, 0x00108706, 0x00108d05, 0x00108e04, 0x00108f06,
+    0x00109008, 0x00109a06, 0x00109d05, 0x00109e15, 0x0010a000, 0x0010c61d, 0x0010c700, 0x0010c81d,
+    0x0010cd00, 0x0010ce1d, 0x0010d001, 0x0010fb11, 0x0010fc03, 0x0010fd01, 0x00110004, 0x0012491d,
+    0x00124a04, 0x00124e1d, 0x00125004, 0x0012571d, 0x00125804, 0x0012591d, 0x00125a04, 0x00125e1d,
+    0x00126004, 0x0012891d, 0x00128a04, 0x00128e1d, 0x00129004, 0x0012b11d, 0x0012b204, 0x0012b61d,
+    0x0012b804, 0x0012bf1d, 0x0012c004, 0x0012c11d, 0x0012c204, 0x0012c61d, 0x0012c804, 0x0012d71d,
+    0x0012d804, 0x0013111d, 0x00131204, 0x0013161d, 0x00131804, 0x00135b1d, 0x00135d05, 0x00136011,
+    0x0013690a, 0x00137d1d, 0x00138004, 0x00139015, 0x00139a1d, 0x0013a000, 0x0013f61d, 0x0013f801,
+    0x0013fe1d, 0x0014000c, 0x00140104, 0x00166d15, 0x00166e11, 0x00166f04, 0x00168016, 0x00168104,
+    0x00169b0d, 0x00169c0e, 0x00169d1d, 0x0016a004, 0x0016eb11, 0x0016ee09, 0x0016f104, 0x0016f91d,
+    0x00170004, 0x00171205, 0x00171506, 0x0017161d, 0x00171f04, 0x00173205, 0x00173406, 0x00173511,
+    0x0017371d, 0x00174004, 0x00175205, 0x0017541d, 0x00176004, 0x00176d1d, 0x00176e04, 0x0017711d,
+    0x00177205, 0x0017741d, 0x00178004, 0x0017b405, 0x0017b606, 0x0017b705, 0x0017be06, 0x0017c605,
+    0x0017c706, 0x0017c905, 0x0017d411, 0x0017d703, 0x0017d811, 0x0017db13, 0x0017dc04, 0x0017dd05,
+    0x0017de1d, 0x0017e008, 0x0017ea1d, 0x0017f00a, 0x0017fa1d, 0x00180011, 0x0018060c, 0x00180711,
+    0x00180b05, 0x00180e1a, 0x00180f05, 0x00181008, 0x00181a1d, 0x00182004, 0x00184303, 0x00184404,
+    0x0018791d, 0x00188004, 0x00188505, 0x00188704, 0x0018a905, 0x0018aa04, 0x0018ab1d, 0x0018b004,
+    0x0018f61d, 0x00190004, 0x00191f1d, 0x00192005, 0x00192306, 0x00192705, 0x00192906, 0x00192c1d,
+    0x00193006, 0x00193205, 0x00193306, 0x00193905, 0x00193c1d, 0x00194015, 0x0019411d, 0x00194411,
+    0x00194608, 0x00195004, 0x00196e1d, 0x00197004, 0x0019751d, 0x00198004, 0x0019ac1d, 0x0019b004,
+    0x0019ca1d, 0x0019d008, 0x0019da0a, 0x0019db1d, 0x0019de15, 0x001a0004, 0x001a1705, 0x001a1906,
+    0x001a1b05, 0x001a1c1d, 0x001a1e11, 0x001a2004, 0x001a5506, 0x001a5605, 0x001a5706, 0x001a5805,
+    0x001a5f1d, 0x001a6005, 0x001a6106, 0x001a6205, 0x001a6306, 0x001a6505, 0x001a6d06, 0x001a7305,
+    0x001a7d1d, 0x001a7f05, 0x001a8008, 0x001a8a1d, 0x001a9008, 0x001a9a1d, 0x001aa011, 0x001aa703,
+    0x001aa811, 0x001aae1d, 0x001ab005, 0x001abe07, 0x001abf05, 0x001acf1d, 0x001b0005, 0x001b0406,
+    0x001b0504, 0x001b3405, 0x001b3506, 0x001b3605, 0x001b3b06, 0x001b3c05, 0x001b3d06, 0x001b4205,
+    0x001b4306, 0x001b4504, 0x001b4d1d, 0x001b5008, 0x001b5a11, 0x001b6115, 0x001b6b05, 0x001b7415,
+    0x001b7d11, 0x001b7f1d, 0x001b8005, 0x001b8206, 0x001b8304, 0x001ba106, 0x001ba205, 0x001ba606,
+    0x001ba805, 0x001baa06, 0x001bab05, 0x001bae04, 0x001bb008, 0x001bba04, 0x001be605, 0x001be706,
+    0x001be805, 0x001bea06, 0x001bed05, 0x001bee06, 0x001bef05, 0x001bf206, 0x001bf41d, 0x001bfc11,
+    0x001c0004, 0x001c2406, 0x001c2c05, 0x001c3406, 0x001c3605, 0x001c381d, 0x001c3b11, 0x001c4008,
+    0x001c4a1d, 0x001c4d04, 0x001c5008, 0x001c5a04, 0x001c7803, 0x001c7e11, 0x001c8001, 0x001c891d,
+    0x001c9000, 0x001cbb1d, 0x001cbd00, 0x001cc011, 0x001cc81d, 0x001cd005, 0x001cd311, 0x001cd405,
+    0x001ce106, 0x001ce205, 0x001ce904, 0x001ced05, 0x001cee04, 0x001cf405, 0x001cf504, 0x001cf706,
+    0x001cf805, 0x001cfa04, 0x001cfb1d, 0x001d0001, 0x001d2c03, 0x001d6b01, 0x001d7803, 0x001d7901,
+    0x001d9b03, 0x001dc005, 0x001e001e, 0x001e9601, 0x001e9e1e, 0x001f0001, 0x001f0800, 0x001f1001,
+    0x001f161d, 0x001f1800, 0x001f1e1d, 0x001f2001, 0x001f2800, 0x001f3001, 0x001f3800, 0x001f4001,
+    0x001f461d, 0x001f4800, 0x001f4e1d, 0x001f5001, 0x001f581d, 0x001f5900, 0x001f5a1d, 0x001f5b00,
+    0x001f5c1d, 0x001f5d00, 0x001f5e1d, 0x001f5f00, 0x001f6001, 0x001f6800, 0x001f7001, 0x001f7e1d,
+    0x001f8001, 0x001f8802, 0x001f9001, 0x001f9802, 0x001fa001, 0x001fa802, 0x001fb001, 0x001fb51d,
+    0x001fb601, 0x001fb800, 0x001fbc02, 0x001fbd14, 0x001fbe01, 0x001fbf14, 0x001fc201, 0x001fc51d,
+    0x001fc601, 0x001fc800, 0x001fcc02, 0x001fcd14, 0x001fd001, 0x001fd41d, 0x001fd601, 0x001fd800,
+    0x001fdc1d, 0x001fdd14, 0x001fe001, 0x001fe800, 0x001fed14, 0x001ff01d, 0x001ff201, 0x001ff51d,
+    0x001ff601, 0x001ff800, 0x001ffc02, 0x001ffd14, 0x001fff1d, 0x00200016, 0x00200b1a, 0x0020100c,
+    0x00201611, 0x0020180f, 0x00201910, 0x00201a0d, 0x00201b0f, 0x00201d10, 0x00201e0d, 0x00201f0f,
+    0x00202011, 0x00202817, 0x00202918, 0x00202a1a, 0x00202f16, 0x00203011, 0x0020390f, 0x00203a10,
+    0x00203b11, 0x00203f0b, 0x00204111, 0x00204412, 0x0020450d, 0x0020460e, 0x00204711, 0x00205212,
+    0x00205311, 0x0020540b, 0x00205511, 0x00205f16, 0x0020601a, 0x0020651d, 0x0020661a, 0x0020700a,
+    0x00207103, 0x0020721d, 0x0020740a, 0x00207a12, 0x00207d0d, 0x00207e0e, 0x00207f03, 0x0020800a,
+    0x00208a12, 0x00208d0d, 0x00208e0e, 0x00208f1d, 0x00209003, 0x00209d1d, 0x0020a013, 0x0020c11d,
+    0x0020d005, 0x0020dd07, 0x0020e105, 0x0020e207, 0x0020e505, 0x0020f11d, 0x00210015, 0x00210200,
+    0x00210315, 0x00210700, 0x00210815, 0x00210a01, 0x00210b00, 0x00210e01, 0x00211000, 0x00211301,
+    0x00211415, 0x00211500, 0x00211615, 0x00211812, 0x00211900, 0x00211e15, 0x00212400, 0x00212515,
+    0x00212600, 0x00212715, 0x00212800, 0x00212915, 0x00212a00, 0x00212e15, 0x00212f01, 0x00213000,
+    0x00213401, 0x00213504, 0x00213901, 0x00213a15, 0x00213c01, 0x00213e00, 0x00214012, 0x00214500,
+    0x00214601, 0x00214a15, 0x00214b12, 0x00214c15, 0x00214e01, 0x00214f15, 0x0021500a, 0x00216009,
+    0x00218300, 0x00218401, 0x00218509, 0x0021890a, 0x00218a15, 0x00218c1d, 0x00219012, 0x00219515,
+    0x00219a12, 0x00219c15, 0x0021a012, 0x0021a115, 0x0021a312, 0x0021a415, 0x0021a612, 0x0021a715,
+    0x0021ae12, 0x0021af15, 0x0021ce12, 0x0021d015, 0x0021d212, 0x0021d315, 0x0021d412, 0x0021d515,
+    0x0021f412, 0x00230015, 0x0023080d, 0x0023090e, 0x00230a0d, 0x00230b0e, 0x00230c15, 0x00232012,
+    0x00232215, 0x0023290d, 0x00232a0e, 0x00232b15, 0x00237c12, 0x00237d15, 0x00239b12, 0x0023b415,
+    0x0023dc12, 0x0023e215, 0x0024271d, 0x00244015, 0x00244b1d, 0x0024600a, 0x00249c15, 0x0024ea0a,
+    0x00250015, 0x0025b712, 0x0025b815, 0x0025c112, 0x0025c215, 0x0025f812, 0x00260015, 0x00266f12,
+    0x00267015, 0x0027680d, 0x0027690e, 0x00276a0d, 0x00276b0e, 0x00276c0d, 0x00276d0e, 0x00276e0d,
+    0x00276f0e, 0x0027700d, 0x0027710e, 0x0027720d, 0x0027730e, 0x0027740d, 0x0027750e, 0x0027760a,
+    0x00279415, 0x0027c012, 0x0027c50d, 0x0027c60e, 0x0027c712, 0x0027e60d, 0x0027e70e, 0x0027e80d,
+    0x0027e90e, 0x0027ea0d, 0x0027eb0e, 0x0027ec0d, 0x0027ed0e, 0x0027ee0d, 0x0027ef0e, 0x0027f012,
+    0x00280015, 0x00290012, 0x0029830d, 0x0029840e, 0x0029850d, 0x0029860e, 0x0029870d, 0x0029880e,
+    0x0029890d, 0x00298a0e, 0x00298b0d, 0x00298c0e, 0x00298d0d, 0x00298e0e, 0x00298f0d, 0x0029900e,
+    0x0029910d, 0x0029920e, 0x0029930d, 0x0029940e, 0x0029950d, 0x0029960e, 0x0029970d, 0x0029980e,
+    0x00299912, 0x0029d80d, 0x0029d90e, 0x0029da0d, 0x0029db0e, 0x0029dc12, 0x0029fc0d, 0x0029fd0e,
+    0x0029fe12, 0x002b0015, 0x002b3012, 0x002b4515, 0x002b4712, 0x002b4d15, 0x002b741d, 0x002b7615,
+    0x002b961d, 0x002b9715, 0x002c0000, 0x002c3001, 0x002c6000, 0x002c6101, 0x002c6200, 0x002c6501,
+    0x002c671e, 0x002c6e00, 0x002c7101, 0x002c7200, 0x002c7301, 0x002c7500, 0x002c7601, 0x002c7c03,
+    0x002c7e00, 0x002c811f, 0x002ce401, 0x002ce515, 0x002ceb1e, 0x002cef05, 0x002cf200, 0x002cf301,
+    0x002cf41d, 0x002cf911, 0x002cfd0a, 0x002cfe11, 0x002d0001, 0x002d261d, 0x002d2701, 0x002d281d,
+    0x002d2d01, 0x002d2e1d, 0x002d3004, 0x002d681d, 0x002d6f03, 0x002d7011, 0x002d711d, 0x002d7f05,
+    0x002d8004, 0x002d971d, 0x002da004, 0x002da71d, 0x002da804, 0x002daf1d, 0x002db004, 0x002db71d,
+    0x002db804, 0x002dbf1d, 0x002dc004, 0x002dc71d, 0x002dc804, 0x002dcf1d, 0x002dd004, 0x002dd71d,
+    0x002dd804, 0x002ddf1d, 0x002de005, 0x002e0011, 0x002e020f, 0x002e0310, 0x002e040f, 0x002e0510,
+    0x002e0611, 0x002e090f, 0x002e0a10, 0x002e0b11, 0x002e0c0f, 0x002e0d10, 0x002e0e11, 0x002e170c,
+    0x002e1811, 0x002e1a0c, 0x002e1b11, 0x002e1c0f, 0x002e1d10, 0x002e1e11, 0x002e200f, 0x002e2110,
+    0x002e220d, 0x002e230e, 0x002e240d, 0x002e250e, 0x002e260d, 0x002e270e, 0x002e280d, 0x002e290e,
+    0x002e2a11, 0x002e2f03, 0x002e3011, 0x002e3a0c, 0x002e3c11, 0x002e400c, 0x002e4111, 0x002e420d,
+    0x002e4311, 0x002e5015, 0x002e5211, 0x002e550d, 0x002e560e, 0x002e570d, 0x002e580e, 0x002e590d,
+    0x002e5a0e, 0x002e5b0d, 0x002e5c0e, 0x002e5d0c, 0x002e5e1d, 0x002e8015, 0x002e9a1d, 0x002e9b15,
+    0x002ef41d, 0x002f0015, 0x002fd61d, 0x002ff015, 0x002ffc1d, 0x00300016, 0x00300111, 0x00300415,
+    0x00300503, 0x00300604, 0x00300709, 0x0030080d, 0x0030090e, 0x00300a0d, 0x00300b0e, 0x00300c0d,
+    0x00300d0e, 0x00300e0d, 0x00300f0e, 0x0030100d, 0x0030110e, 0x00301215, 0x0030140d, 0x0030150e,
+    0x0030160d, 0x0030170e, 0x0030180d, 0x0030190e, 0x00301a0d, 0x00301b0e, 0x00301c0c, 0x00301d0d,
+    0x00301e0e, 0x00302015, 0x00302109, 0x00302a05, 0x00302e06, 0x0030300c, 0x00303103, 0x00303615,
+    0x00303809, 0x00303b03, 0x00303c04, 0x00303d11, 0x00303e15, 0x0030401d, 0x00304104, 0x0030971d,
+    0x00309905, 0x00309b14, 0x00309d03, 0x00309f04, 0x0030a00c, 0x0030a104, 0x0030fb11, 0x0030fc03,
+    0x0030ff04, 0x0031001d, 0x00310504, 0x0031301d, 0x00313104, 0x00318f1d, 0x00319015, 0x0031920a,
+    0x00319615, 0x0031a004, 0x0031c015, 0x0031e41d, 0x0031f004, 0x00320015, 0x00321f1d, 0x0032200a,
+    0x00322a15, 0x0032480a, 0x00325015, 0x0032510a, 0x00326015, 0x0032800a, 0x00328a15, 0x0032b10a,
+    0x0032c015, 0x00340004, 0x004dc015, 0x004e0004, 0x00a01503, 0x00a01604, 0x00a48d1d, 0x00a49015,
+    0x00a4c71d, 0x00a4d004, 0x00a4f803, 0x00a4fe11, 0x00a50004, 0x00a60c03, 0x00a60d11, 0x00a61004,
+    0x00a62008, 0x00a62a04, 0x00a62c1d, 0x00a6401e, 0x00a66e04, 0x00a66f05, 0x00a67007, 0x00a67311,
+    0x00a67405, 0x00a67e11, 0x00a67f03, 0x00a6801e, 0x00a69c03, 0x00a69e05, 0x00a6a004, 0x00a6e609,
+    0x00a6f005, 0x00a6f211, 0x00a6f81d, 0x00a70014, 0x00a71703, 0x00a72014, 0x00a7221e, 0x00a73001,
+    0x00a7321e, 0x00a77003, 0x00a77101, 0x00a7791e, 0x00a77e1e, 0x00a78803, 0x00a78914, 0x00a78b1e,
+    0x00a78f04, 0x00a7901e, 0x00a79401, 0x00a7961e, 0x00a7ab00, 0x00a7af01, 0x00a7b000, 0x00a7b51f,
+    0x00a7c500, 0x00a7c801, 0x00a7c900, 0x00a7ca01, 0x00a7cb1d, 0x00a7d000, 0x00a7d101, 0x00a7d21d,
+    0x00a7d301, 0x00a7d41d, 0x00a7d51f, 0x00a7da1d, 0x00a7f203, 0x00a7f500, 0x00a7f601, 0x00a7f704,
+    0x00a7f803, 0x00a7fa01, 0x00a7fb04, 0x00a80205, 0x00a80304, 0x00a80605, 0x00a80704, 0x00a80b05,
+    0x00a80c04, 0x00a82306, 0x00a82505, 0x00a82706, 0x00a82815, 0x00a82c05, 0x00a82d1d, 0x00a8300a,
+    0x00a83615, 0x00a83813, 0x00a83915, 0x00a83a1d, 0x00a84004, 0x00a87411, 0x00a8781d, 0x00a88006,
+    0x00a88204, 0x00a8b406, 0x00a8c405, 0x00a8c61d, 0x00a8ce11, 0x00a8d008, 0x00a8da1d, 0x00a8e005,
+    0x00a8f204, 0x00a8f811, 0x00a8fb04, 0x00a8fc11, 0x00a8fd04, 0x00a8ff05, 0x00a90008, 0x00a90a04,
+    0x00a92605, 0x00a92e11, 0x00a93004, 0x00a94705, 0x00a95206, 0x00a9541d, 0x00a95f11, 0x00a96004,
+    0x00a97d1d, 0x00a98005, 0x00a98306, 0x00a98404, 0x00a9b305, 0x00a9b406, 0x00a9b605, 0x00a9ba06,
+    0x00a9bc05, 0x00a9be06, 0x00a9c111, 0x00a9ce1d, 0x00a9cf03, 0x00a9d008, 0x00a9da1d, 0x00a9de11,
+    0x00a9e004, 0x00a9e505, 0x00a9e603, 0x00a9e704, 0x00a9f008, 0x00a9fa04, 0x00a9ff1d, 0x00aa0004,
+    0x00aa2905, 0x00aa2f06, 0x00aa3105, 0x00aa3306, 0x00aa3505, 0x00aa371d, 0x00aa4004, 0x00aa4305,
+    0x00aa4404, 0x00aa4c05, 0x00aa4d06, 0x00aa4e1d, 0x00aa5008, 0x00aa5a1d, 0x00aa5c11, 0x00aa6004,
+    0x00aa7003, 0x00aa7104, 0x00aa7715, 0x00aa7a04, 0x00aa7b06, 0x00aa7c05, 0x00aa7d06, 0x00aa7e04,
+    0x00aab005, 0x00aab104, 0x00aab205, 0x00aab504, 0x00aab705, 0x00aab904, 0x00aabe05, 0x00aac004,
+    0x00aac105, 0x00aac204, 0x00aac31d, 0x00aadb04, 0x00aadd03, 0x00aade11, 0x00aae004, 0x00aaeb06,
+    0x00aaec05, 0x00aaee06, 0x00aaf011, 0x00aaf204, 0x00aaf303, 0x00aaf506, 0x00aaf605, 0x00aaf71d,
+    0x00ab0104, 0x00ab071d, 0x00ab0904, 0x00ab0f1d, 0x00ab1104, 0x00ab171d, 0x00ab2004, 0x00ab271d,
+    0x00ab2804, 0x00ab2f1d, 0x00ab3001, 0x00ab5b14, 0x00ab5c03, 0x00ab6001, 0x00ab6903, 0x00ab6a14,
+    0x00ab6c1d, 0x00ab7001, 0x00abc004, 0x00abe306, 0x00abe505, 0x00abe606, 0x00abe805, 0x00abe906,
+    0x00abeb11, 0x00abec06, 0x00abed05, 0x00abee1d, 0x00abf008, 0x00abfa1d, 0x00ac0004, 0x00d7a41d,
+    0x00d7b004, 0x00d7c71d, 0x00d7cb04, 0x00d7fc1d, 0x00d8001b, 0x00e0001c, 0x00f90004, 0x00fa6e1d,
+    0x00fa7004, 0x00fada1d, 0x00fb0001, 0x00fb071d, 0x00fb1301, 0x00fb181d, 0x00fb1d04, 0x00fb1e05,
+    0x00fb1f04, 0x00fb2912, 0x00fb2a04, 0x00fb371d, 0x00fb3804, 0x00fb3d1d, 0x00fb3e04, 0x00fb3f1d,
+    0x00fb4004, 0x00fb421d, 0x00fb4304, 0x00fb451d, 0x00fb4604, 0x00fbb214, 0x00fbc31d, 0x00fbd304,
+    0x00fd3e0e, 0x00fd3f0d, 0x00fd4015, 0x00fd5004, 0x00fd901d, 0x00fd9204, 0x00fdc81d, 0x00fdcf15,
+    0x00fdd01d, 0x00fdf004, 0x00fdfc13, 0x00fdfd15, 0x00fe0005, 0x00fe1011, 0x00fe170d, 0x00fe180e,
+    0x00fe1911, 0x00fe1a1d, 0x00fe2005, 0x00fe3011, 0x00fe310c, 0x00fe330b, 0x00fe350d, 0x00fe360e,
+    0x00fe370d, 0x00fe380e, 0x00fe390d, 0x00fe3a0e, 0x00fe3b0d, 0x00fe3c0e, 0x00fe3d0d, 0x00fe3e0e,
+    0x00fe3f0d, 0x00fe400e, 0x00fe410d, 0x00fe420e, 0x00fe430d, 0x00fe440e, 0x00fe4511, 0x00fe470d,
+    0x00fe480e, 0x00fe4911, 0x00fe4d0b, 0x00fe5011, 0x00fe531d, 0x00fe5411, 0x00fe580c, 0x00fe590d,
+    0x00fe5a0e, 0x00fe5b0d, 0x00fe5c0e, 0x00fe5d0d, 0x00fe5e0e, 0x00fe5f11, 0x00fe6212, 0x00fe630c,
+    0x00fe6412, 0x00fe671d, 0x00fe6811, 0x00fe6913, 0x00fe6a11, 0x00fe6c1d, 0x00fe7004, 0x00fe751d,
+    0x00fe7604, 0x00fefd1d, 0x00feff1a, 0x00ff001d, 0x00ff0111, 0x00ff0413, 0x00ff0511, 0x00ff080d,
+    0x00ff090e, 0x00ff0a11, 0x00ff0b12, 0x00ff0c11, 0x00ff0d0c, 0x00ff0e11, 0x00ff1008, 0x00ff1a11,
+    0x00ff1c12, 0x00ff1f11, 0x00ff2100, 0x00ff3b0d, 0x00ff3c11, 0x00ff3d0e, 0x00ff3e14, 0x00ff3f0b,
+    0x00ff4014, 0x00ff4101, 0x00ff5b0d, 0x00ff5c12, 0x00ff5d0e, 0x00ff5e12, 0x00ff5f0d, 0x00ff600e,
+    0x00ff6111, 0x00ff620d, 0x00ff630e, 0x00ff6411, 0x00ff6604, 0x00ff7003, 0x00ff7104, 0x00ff9e03,
+    0x00ffa004, 0x00ffbf1d, 0x00ffc204, 0x00ffc81d, 0x00ffca04, 0x00ffd01d, 0x00ffd204, 0x00ffd81d,
+    0x00ffda04, 0x00ffdd1d, 0x00ffe013, 0x00ffe212, 0x00ffe314, 0x00ffe415, 0x00ffe513, 0x00ffe71d,
+    0x00ffe815, 0x00ffe912, 0x00ffed15, 0x00ffef1d, 0x00fff91a, 0x00fffc15, 0x00fffe1d, 0x01000004,
+    0x01000c1d, 0x01000d04, 0x0100271d, 0x01002804, 0x01003b1d, 0x01003c04, 0x01003e1d, 0x01003f04,
+    0x01004e1d, 0x01005004, 0x01005e1d, 0x01008004, 0x0100fb1d, 0x01010011, 0x0101031d, 0x0101070a,
+    0x0101341d, 0x01013715, 0x01014009, 0x0101750a, 0x01017915, 0x01018a0a, 0x01018c15, 0x01018f1d,
+    0x01019015, 0x01019d1d, 0x0101a015, 0x0101a11d, 0x0101d015, 0x0101fd05, 0x0101fe1d, 0x01028004,
+    0x01029d1d, 0x0102a004, 0x0102d11d, 0x0102e005, 0x0102e10a, 0x0102fc1d, 0x01030004, 0x0103200a,
+    0x0103241d, 0x01032d04, 0x01034109, 0x01034204, 0x01034a09, 0x01034b1d, 0x01035004, 0x01037605,
+    0x01037b1d, 0x01038004, 0x01039e1d, 0x01039f11, 0x0103a004, 0x0103c41d, 0x0103c804, 0x0103d011,
+    0x0103d109, 0x0103d61d, 0x01040000, 0x01042801, 0x01045004, 0x01049e1d, 0x0104a008, 0x0104aa1d,
+    0x0104b000, 0x0104d41d, 0x0104d801, 0x0104fc1d, 0x01050004, 0x0105281d, 0x01053004, 0x0105641d,
+    0x01056f11, 0x01057000, 0x01057b1d, 0x01057c00, 0x01058b1d, 0x01058c00, 0x0105931d, 0x01059400,
+    0x0105961d, 0x01059701, 0x0105a21d, 0x0105a301, 0x0105b21d, 0x0105b301, 0x0105ba1d, 0x0105bb01,
+    0x0105bd1d, 0x01060004, 0x0107371d, 0x01074004, 0x0107561d, 0x01076004, 0x0107681d, 0x01078003,
+    0x0107861d, 0x01078703, 0x0107b11d, 0x0107b203, 0x0107bb1d, 0x01080004, 0x0108061d, 0x01080804,
+    0x0108091d, 0x01080a04, 0x0108361d, 0x01083704, 0x0108391d, 0x01083c04, 0x01083d1d, 0x01083f04,
+    0x0108561d, 0x01085711, 0x0108580a, 0x01086004, 0x01087715, 0x0108790a, 0x01088004, 0x01089f1d,
+    0x0108a70a, 0x0108b01d, 0x0108e004, 0x0108f31d, 0x0108f404, 0x0108f61d, 0x0108fb0a, 0x01090004,
+    0x0109160a, 0x01091c1d, 0x01091f11, 0x01092004, 0x01093a1d, 0x01093f11, 0x0109401d, 0x01098004,
+    0x0109b81d, 0x0109bc0a, 0x0109be04, 0x0109c00a, 0x0109d01d, 0x0109d20a, 0x010a0004, 0x010a0105,
+    0x010a041d, 0x010a0505, 0x010a071d, 0x010a0c05, 0x010a1004, 0x010a141d, 0x010a1504, 0x010a181d,
+    0x010a1904, 0x010a361d, 0x010a3805, 0x010a3b1d, 0x010a3f05, 0x010a400a, 0x010a491d, 0x010a5011,
+    0x010a591d, 0x010a6004, 0x010a7d0a, 0x010a7f11, 0x010a8004, 0x010a9d0a, 0x010aa01d, 0x010ac004,
+    0x010ac815, 0x010ac904, 0x010ae505, 0x010ae71d, 0x010aeb0a, 0x010af011, 0x010af71d, 0x010b0004,
+    0x010b361d, 0x010b3911, 0x010b4004, 0x010b561d, 0x010b580a, 0x010b6004, 0x010b731d, 0x010b780a,
+    0x010b8004, 0x010b921d, 0x010b9911, 0x010b9d1d, 0x010ba90a, 0x010bb01d, 0x010c0004, 0x010c491d,
+    0x010c8000, 0x010cb31d, 0x010cc001, 0x010cf31d, 0x010cfa0a, 0x010d0004, 0x010d2405, 0x010d281d,
+    0x010d3008, 0x010d3a1d, 0x010e600a, 0x010e7f1d, 0x010e8004, 0x010eaa1d, 0x010eab05, 0x010ead0c,
+    0x010eae1d, 0x010eb004, 0x010eb21d, 0x010f0004, 0x010f1d0a, 0x010f2704, 0x010f281d, 0x010f3004,
+    0x010f4605, 0x010f510a, 0x010f5511, 0x010f5a1d, 0x010f7004, 0x010f8205, 0x010f8611, 0x010f8a1d,
+    0x010fb004, 0x010fc50a, 0x010fcc1d, 0x010fe004, 0x010ff71d, 0x01100006, 0x01100105, 0x01100206,
+    0x01100304, 0x01103805, 0x01104711, 0x01104e1d, 0x0110520a, 0x01106608, 0x01107005, 0x01107104,
+    0x01107305, 0x01107504, 0x0110761d, 0x01107f05, 0x01108206, 0x01108304, 0x0110b006, 0x0110b305,
+    0x0110b706, 0x0110b905, 0x0110bb11, 0x0110bd1a, 0x0110be11, 0x0110c205, 0x0110c31d, 0x0110cd1a,
+    0x0110ce1d, 0x0110d004, 0x0110e91d, 0x0110f008, 0x0110fa1d, 0x01110005, 0x01110304, 0x01112705,
+    0x01112c06, 0x01112d05, 0x0111351d, 0x01113608, 0x01114011, 0x01114404, 0x01114506, 0x01114704,
+    0x0111481d, 0x01115004, 0x01117305, 0x01117411, 0x01117604, 0x0111771d, 0x01118005, 0x01118206,
+    0x01118304, 0x0111b306, 0x0111b605, 0x0111bf06, 0x0111c104, 0x0111c511, 0x0111c905, 0x0111cd11,
+    0x0111ce06, 0x0111cf05, 0x0111d008, 0x0111da04, 0x0111db11, 0x0111dc04, 0x0111dd11, 0x0111e01d,
+    0x0111e10a, 0x0111f51d, 0x01120004, 0x0112121d, 0x01121304, 0x01122c06, 0x01122f05, 0x01123206,
+    0x01123405, 0x01123506, 0x01123605, 0x01123811, 0x01123e05, 0x01123f1d, 0x01128004, 0x0112871d,
+    0x01128804, 0x0112891d, 0x01128a04, 0x01128e1d, 0x01128f04, 0x01129e1d, 0x01129f04, 0x0112a911,
+    0x0112aa1d, 0x0112b004, 0x0112df05, 0x0112e006, 0x0112e305, 0x0112eb1d, 0x0112f008, 0x0112fa1d,
+    0x01130005, 0x01130206, 0x0113041d, 0x01130504, 0x01130d1d, 0x01130f04, 0x0113111d, 0x01131304,
+    0x0113291d, 0x01132a04, 0x0113311d, 0x01133204, 0x0113341d, 0x01133504, 0x01133a1d, 0x01133b05,
+    0x01133d04, 0x01133e06, 0x01134005, 0x01134106, 0x0113451d, 0x01134706, 0x0113491d, 0x01134b06,
+    0x01134e1d, 0x01135004, 0x0113511d, 0x01135706, 0x0113581d, 0x01135d04, 0x01136206, 0x0113641d,
+    0x01136605, 0x01136d1d, 0x01137005, 0x0113751d, 0x01140004, 0x01143506, 0x01143805, 0x01144006,
+    0x01144205, 0x01144506, 0x01144605, 0x01144704, 0x01144b11, 0x01145008, 0x01145a11, 0x01145c1d,
+    0x01145d11, 0x01145e05, 0x01145f04, 0x0114621d, 0x01148004, 0x0114b006, 0x0114b305, 0x0114b906,
+    0x0114ba05, 0x0114bb06, 0x0114bf05, 0x0114c106, 0x0114c205, 0x0114c404, 0x0114c611, 0x0114c704,
+    0x0114c81d, 0x0114d008, 0x0114da1d, 0x01158004, 0x0115af06, 0x0115b205, 0x0115b61d, 0x0115b806,
+    0x0115bc05, 0x0115be06, 0x0115bf05, 0x0115c111, 0x0115d804, 0x0115dc05, 0x0115de1d, 0x01160004,
+    0x01163006, 0x01163305, 0x01163b06, 0x01163d05, 0x01163e06, 0x01163f05, 0x01164111, 0x01164404,
+    0x0116451d, 0x01165008, 0x01165a1d, 0x01166011, 0x01166d1d, 0x01168004, 0x0116ab05, 0x0116ac06,
+    0x0116ad05, 0x0116ae06, 0x0116b005, 0x0116b606, 0x0116b705, 0x0116b804, 0x0116b911, 0x0116ba1d,
+    0x0116c008, 0x0116ca1d, 0x01170004, 0x01171b1d, 0x01171d05, 0x01172006, 0x01172205, 0x01172606,
+    0x01172705, 0x01172c1d, 0x01173008, 0x01173a0a, 0x01173c11, 0x01173f15, 0x01174004, 0x0117471d,
+    0x01180004, 0x01182c06, 0x01182f05, 0x01183806, 0x01183905, 0x01183b11, 0x01183c1d, 0x0118a000,
+    0x0118c001, 0x0118e008, 0x0118ea0a, 0x0118f31d, 0x0118ff04, 0x0119071d, 0x01190904, 0x01190a1d,
+    0x01190c04, 0x0119141d, 0x01191504, 0x0119171d, 0x01191804, 0x01193006, 0x0119361d, 0x01193706,
+    0x0119391d, 0x01193b05, 0x01193d06, 0x01193e05, 0x01193f04, 0x01194006, 0x01194104, 0x01194206,
+    0x01194305, 0x01194411, 0x0119471d, 0x01195008, 0x01195a1d, 0x0119a004, 0x0119a81d, 0x0119aa04,
+    0x0119d106, 0x0119d405, 0x0119d81d, 0x0119da05, 0x0119dc06, 0x0119e005, 0x0119e104, 0x0119e211,
+    0x0119e304, 0x0119e406, 0x0119e51d, 0x011a0004, 0x011a0105, 0x011a0b04, 0x011a3305, 0x011a3906,
+    0x011a3a04, 0x011a3b05, 0x011a3f11, 0x011a4705, 0x011a481d, 0x011a5004, 0x011a5105, 0x011a5706,
+    0x011a5905, 0x011a5c04, 0x011a8a05, 0x011a9706, 0x011a9805, 0x011a9a11, 0x011a9d04, 0x011a9e11,
+    0x011aa31d, 0x011ab004, 0x011af91d, 0x011c0004, 0x011c091d, 0x011c0a04, 0x011c2f06, 0x011c3005,
+    0x011c371d, 0x011c3805, 0x011c3e06, 0x011c3f05, 0x011c4004, 0x011c4111, 0x011c461d, 0x011c5008,
+    0x011c5a0a, 0x011c6d1d, 0x011c7011, 0x011c7204, 0x011c901d, 0x011c9205, 0x011ca81d, 0x011ca906,
+    0x011caa05, 0x011cb106, 0x011cb205, 0x011cb406, 0x011cb505, 0x011cb71d, 0x011d0004, 0x011d071d,
+    0x011d0804, 0x011d0a1d, 0x011d0b04, 0x011d3105, 0x011d371d, 0x011d3a05, 0x011d3b1d, 0x011d3c05,
+    0x011d3e1d, 0x011d3f05, 0x011d4604, 0x011d4705, 0x011d481d, 0x011d5008, 0x011d5a1d, 0x011d6004,
+    0x011d661d, 0x011d6704, 0x011d691d, 0x011d6a04, 0x011d8a06, 0x011d8f1d, 0x011d9005, 0x011d921d,
+    0x011d9306, 0x011d9505, 0x011d9606, 0x011d9705, 0x011d9804, 0x011d991d, 0x011da008, 0x011daa1d,
+    0x011ee004, 0x011ef305, 0x011ef506, 0x011ef711, 0x011ef91d, 0x011fb004, 0x011fb11d, 0x011fc00a,
+    0x011fd515, 0x011fdd13, 0x011fe115, 0x011ff21d, 0x011fff11, 0x01200004, 0x01239a1d, 0x01240009,
+    0x01246f1d, 0x01247011, 0x0124751d, 0x01248004, 0x0125441d, 0x012f9004, 0x012ff111, 0x012ff31d,
+    0x01300004, 0x01342f1d, 0x0134301a, 0x0134391d, 0x01440004, 0x0146471d, 0x01680004, 0x016a391d,
+    0x016a4004, 0x016a5f1d, 0x016a6008, 0x016a6a1d, 0x016a6e11, 0x016a7004, 0x016abf1d, 0x016ac008,
+    0x016aca1d, 0x016ad004, 0x016aee1d, 0x016af005, 0x016af511, 0x016af61d, 0x016b0004, 0x016b3005,
+    0x016b3711, 0x016b3c15, 0x016b4003, 0x016b4411, 0x016b4515, 0x016b461d, 0x016b5008, 0x016b5a1d,
+    0x016b5b0a, 0x016b621d, 0x016b6304, 0x016b781d, 0x016b7d04, 0x016b901d, 0x016e4000, 0x016e6001,
+    0x016e800a, 0x016e9711, 0x016e9b1d, 0x016f0004, 0x016f4b1d, 0x016f4f05, 0x016f5004, 0x016f5106,
+    0x016f881d, 0x016f8f05, 0x016f9303, 0x016fa01d, 0x016fe003, 0x016fe211, 0x016fe303, 0x016fe405,
+    0x016fe51d, 0x016ff006, 0x016ff21d, 0x01700004, 0x0187f81d, 0x01880004, 0x018cd61d, 0x018d0004,
+    0x018d091d, 0x01aff003, 0x01aff41d, 0x01aff503, 0x01affc1d, 0x01affd03, 0x01afff1d, 0x01b00004,
+    0x01b1231d, 0x01b15004, 0x01b1531d, 0x01b16404, 0x01b1681d, 0x01b17004, 0x01b2fc1d, 0x01bc0004,
+    0x01bc6b1d, 0x01bc7004, 0x01bc7d1d, 0x01bc8004, 0x01bc891d, 0x01bc9004, 0x01bc9a1d, 0x01bc9c15,
+    0x01bc9d05, 0x01bc9f11, 0x01bca01a, 0x01bca41d, 0x01cf0005, 0x01cf2e1d, 0x01cf3005, 0x01cf471d,
+    0x01cf5015, 0x01cfc41d, 0x01d00015, 0x01d0f61d, 0x01d10015, 0x01d1271d, 0x01d12915, 0x01d16506,
+    0x01d16705, 0x01d16a15, 0x01d16d06, 0x01d1731a, 0x01d17b05, 0x01d18315, 0x01d18505, 0x01d18c15,
+    0x01d1aa05, 0x01d1ae15, 0x01d1eb1d, 0x01d20015, 0x01d24205, 0x01d24515, 0x01d2461d, 0x01d2e00a,
+    0x01d2f41d, 0x01d30015, 0x01d3571d, 0x01d3600a, 0x01d3791d, 0x01d40000, 0x01d41a01, 0x01d43400,
+    0x01d44e01, 0x01d4551d, 0x01d45601, 0x01d46800, 0x01d48201, 0x01d49c00, 0x01d49d1d, 0x01d49e00,
+    0x01d4a01d, 0x01d4a200, 0x01d4a31d, 0x01d4a500, 0x01d4a71d, 0x01d4a900, 0x01d4ad1d, 0x01d4ae00,
+    0x01d4b601, 0x01d4ba1d, 0x01d4bb01, 0x01d4bc1d, 0x01d4bd01, 0x01d4c41d, 0x01d4c501, 0x01d4d000,
+    0x01d4ea01, 0x01d50400, 0x01d5061d, 0x01d50700, 0x01d50b1d, 0x01d50d00, 0x01d5151d, 0x01d51600,
+    0x01d51d1d, 0x01d51e01, 0x01d53800, 0x01d53a1d, 0x01d53b00, 0x01d53f1d, 0x01d54000, 0x01d5451d,
+    0x01d54600, 0x01d5471d, 0x01d54a00, 0x01d5511d, 0x01d55201, 0x01d56c00, 0x01d58601, 0x01d5a000,
+    0x01d5ba01, 0x01d5d400, 0x01d5ee01, 0x01d60800, 0x01d62201, 0x01d63c00, 0x01d65601, 0x01d67000,
+    0x01d68a01, 0x01d6a61d, 0x01d6a800, 0x01d6c112, 0x01d6c201, 0x01d6db12, 0x01d6dc01, 0x01d6e200,
+    0x01d6fb12, 0x01d6fc01, 0x01d71512, 0x01d71601, 0x01d71c00, 0x01d73512, 0x01d73601, 0x01d74f12,
+    0x01d75001, 0x01d75600, 0x01d76f12, 0x01d77001, 0x01d78912, 0x01d78a01, 0x01d79000, 0x01d7a912,
+    0x01d7aa01, 0x01d7c312, 0x01d7c401, 0x01d7ca00, 0x01d7cb01, 0x01d7cc1d, 0x01d7ce08, 0x01d80015,
+    0x01da0005, 0x01da3715, 0x01da3b05, 0x01da6d15, 0x01da7505, 0x01da7615, 0x01da8405, 0x01da8515,
+    0x01da8711, 0x01da8c1d, 0x01da9b05, 0x01daa01d, 0x01daa105, 0x01dab01d, 0x01df0001, 0x01df0a04,
+    0x01df0b01, 0x01df1f1d, 0x01e00005, 0x01e0071d, 0x01e00805, 0x01e0191d, 0x01e01b05, 0x01e0221d,
+    0x01e02305, 0x01e0251d, 0x01e02605, 0x01e02b1d, 0x01e10004, 0x01e12d1d, 0x01e13005, 0x01e13703,
+    0x01e13e1d, 0x01e14008, 0x01e14a1d, 0x01e14e04, 0x01e14f15, 0x01e1501d, 0x01e29004, 0x01e2ae05,
+    0x01e2af1d, 0x01e2c004, 0x01e2ec05, 0x01e2f008, 0x01e2fa1d, 0x01e2ff13, 0x01e3001d, 0x01e7e004,
+    0x01e7e71d, 0x01e7e804, 0x01e7ec1d, 0x01e7ed04, 0x01e7ef1d, 0x01e7f004, 0x01e7ff1d, 0x01e80004,
+    0x01e8c51d, 0x01e8c70a, 0x01e8d005, 0x01e8d71d, 0x01e90000, 0x01e92201, 0x01e94405, 0x01e94b03,
+    0x01e94c1d, 0x01e95008, 0x01e95a1d, 0x01e95e11, 0x01e9601d, 0x01ec710a, 0x01ecac15, 0x01ecad0a,
+    0x01ecb013, 0x01ecb10a, 0x01ecb51d, 0x01ed010a, 0x01ed2e15, 0x01ed2f0a, 0x01ed3e1d, 0x01ee0004,
+    0x01ee041d, 0x01ee0504, 0x01ee201d, 0x01ee2104, 0x01ee231d, 0x01ee2404, 0x01ee251d, 0x01ee2704,
+    0x01ee281d, 0x01ee2904, 0x01ee331d, 0x01ee3404, 0x01ee381d, 0x01ee3904, 0x01ee3a1d, 0x01ee3b04,
+    0x01ee3c1d, 0x01ee4204, 0x01ee431d, 0x01ee4704, 0x01ee481d, 0x01ee4904, 0x01ee4a1d, 0x01ee4b04,
+    0x01ee4c1d, 0x01ee4d04, 0x01ee501d, 0x01ee5104, 0x01ee531d, 0x01ee5404, 0x01ee551d, 0x01ee5704,
+    0x01ee581d, 0x01ee5904, 0x01ee5a1d, 0x01ee5b04, 0x01ee5c1d, 0x01ee5d04, 0x01ee5e1d, 0x01ee5f04,
+    0x01ee601d, 0x01ee6104, 0x01ee631d, 0x01ee6404, 0x01ee651d, 0x01ee6704, 0x01ee6b1d, 0x01ee6c04,
+    0x01ee731d, 0x01ee7404, 0x01ee781d, 0x01ee7904, 0x01ee7d1d, 0x01ee7e04, 0x01ee7f1d, 0x01ee8004,
+    0x01ee8a1d, 0x01ee8b04, 0x01ee9c1d, 0x01eea104, 0x01eea41d, 0x01eea504, 0x01eeaa1d, 0x01eeab04,
+    0x01eebc1d, 0x01eef012, 0x01eef21d, 0x01f00015, 0x01f02c1d, 0x01f03015, 0x01f0941d, 0x01f0a015,
+    0x01f0af1d, 0x01f0b115, 0x01f0c01d, 0x01f0c115, 0x01f0d01d, 0x01f0d115, 0x01f0f61d, 0x01f1000a,
+    0x01f10d15, 0x01f1ae1d, 0x01f1e615, 0x01f2031d, 0x01f21015, 0x01f23c1d, 0x01f24015, 0x01f2491d,
+    0x01f25015, 0x01f2521d, 0x01f26015, 0x01f2661d, 0x01f30015, 0x01f3fb14, 0x01f40015, 0x01f6d81d,
+    0x01f6dd15, 0x01f6ed1d, 0x01f6f015, 0x01f6fd1d, 0x01f70015, 0x01f7741d, 0x01f78015, 0x01f7d91d,
+    0x01f7e015, 0x01f7ec1d, 0x01f7f015, 0x01f7f11d, 0x01f80015, 0x01f80c1d, 0x01f81015, 0x01f8481d,
+    0x01f85015, 0x01f85a1d, 0x01f86015, 0x01f8881d, 0x01f89015, 0x01f8ae1d, 0x01f8b015, 0x01f8b21d,
+    0x01f90015, 0x01fa541d, 0x01fa6015, 0x01fa6e1d, 0x01fa7015, 0x01fa751d, 0x01fa7815, 0x01fa7d1d,
+    0x01fa8015, 0x01fa871d, 0x01fa9015, 0x01faad1d, 0x01fab015, 0x01fabb1d, 0x01fac015, 0x01fac61d,
+    0x01fad015, 0x01fada1d, 0x01fae015, 0x01fae81d, 0x01faf015, 0x01faf71d, 0x01fb0015, 0x01fb931d,
+    0x01fb9415, 0x01fbcb1d, 0x01fbf008, 0x01fbfa1d, 0x02000004, 0x02a6e01d, 0x02a70004, 0x02b7391d,
+    0x02b74004, 0x02b81e1d, 0x02b82004, 0x02cea21d, 0x02ceb004, 0x02ebe11d, 0x02f80004, 0x02fa1e1d,
+    0x03000004, 0x03134b1d, 0x0e00011a, 0x0e00021d, 0x0e00201a, 0x0e00801d, 0x0e010005, 0x0e01f01d,
+    0x0f00001c, 0x0ffffe1d, 0x1000001c, 0x10fffe1d,
+];
+
+/// East Asian width ranges
+pub(super) static EAST_ASIAN_WIDTH: [u32; 597] = [
+    0x00000003, 0x00002004, 0x00007f03, 0x0000a100, 0x0000a204, 0x0000a400, 0x0000a504, 0x0000a700,
+    0x0000a903, 0x0000aa00, 0x0000ab03, 0x0000ac04, 0x0000ad00, 0x0000af04, 0x0000b000, 0x0000b503,
+    0x0000b600, 0x0000bb03, 0x0000bc00, 0x0000c003, 0x0000c600, 0x0000c703, 0x0000d000, 0x0000d103,
+    0x0000d700, 0x0000d903, 0x0000de00, 0x0000e203, 0x0000e600, 0x0000e703, 0x0000e800, 0x0000eb03,
+    0x0000ec00, 0x0000ee03, 0x0000f000, 0x0000f103, 0x0000f200, 0x0000f403, 0x0000f700, 0x0000fb03,
+    0x0000fc00, 0x0000fd03, 0x0000fe00, 0x0000ff03, 0x00010100, 0x00010203, 0x00011100, 0x00011203,
+    0x00011300, 0x00011403, 0x00011b00, 0x00011c03, 0x00012600, 0x00012803, 0x00012b00, 0x00012c03,
+    0x00013100, 0x00013403, 0x00013800, 0x00013903, 0x00013f00, 0x00014303, 0x00014400, 0x00014503,
+    0x00014800, 0x00014c03, 0x00014d00, 0x00014e03, 0x00015200, 0x00015403, 0x00016600, 0x00016803,
+    0x00016b00, 0x00016c03, 0x0001ce00, 0x0001cf03, 0x0001d000, 0x0001d103, 0x0001d200, 0x0001d303,
+    0x0001d400, 0x0001d503, 0x0001d600, 0x0001d703, 0x0001d800, 0x0001d903, 0x0001da00, 0x0001db03,
+    0x0001dc00, 0x0001dd03, 0x00025100, 0x00025203, 0x00026100, 0x00026203, 0x0002c400, 0x0002c503,
+    0x0002c700, 0x0002c803, 0x0002c900, 0x0002cc03, 0x0002cd00, 0x0002ce03, 0x0002d000, 0x0002d103,
+    0x0002d800, 0x0002dc03, 0x0002dd00, 0x0002de03, 0x0002df00, 0x0002e003, 0x00030000, 0x00037003,
+    0x00039100, 0x0003a203, 0x0003a300, 0x0003aa03, 0x0003b100, 0x0003c203, 0x0003c300, 0x0003ca03,
+    0x00040100, 0x00040203, 0x00041000, 0x00045003, 0x00045100, 0x00045203, 0x00110005, 0x00116003,
+    0x00201000, 0x00201103, 0x00201300, 0x00201703, 0x00201800, 0x00201a03, 0x00201c00, 0x00201e03,
+    0x00202000, 0x00202303, 0x00202400, 0x00202803, 0x00203000, 0x00203103, 0x00203200, 0x00203403,
+    0x00203500, 0x00203603, 0x00203b00, 0x00203c03, 0x00203e00, 0x00203f03, 0x00207400, 0x00207503,
+    0x00207f00, 0x00208003, 0x00208100, 0x00208503, 0x0020a902, 0x0020aa03, 0x0020ac00, 0x0020ad03,
+    0x00210300, 0x00210403, 0x00210500, 0x00210603, 0x00210900, 0x00210a03, 0x00211300, 0x00211403,
+    0x00211600, 0x00211703, 0x00212100, 0x00212303, 0x00212600, 0x00212703, 0x00212b00, 0x00212c03,
+    0x00215300, 0x00215503, 0x00215b00, 0x00215f03, 0x00216000, 0x00216c03, 0x00217000, 0x00217a03,
+    0x00218900, 0x00218a03, 0x00219000, 0x00219a03, 0x0021b800, 0x0021ba03, 0x0021d200, 0x0021d303,
+    0x0021d400, 0x0021d503, 0x0021e700, 0x0021e803, 0x00220000, 0x00220103, 0x00220200, 0x00220403,
+    0x00220700, 0x00220903, 0x00220b00, 0x00220c03, 0x00220f00, 0x00221003, 0x00221100, 0x00221203,
+    0x00221500, 0x00221603, 0x00221a00, 0x00221b03, 0x00221d00, 0x00222103, 0x00222300, 0x00222403,
+    0x00222500, 0x00222603, 0x00222700, 0x00222d03, 0x00222e00, 0x00222f03, 0x00223400, 0x00223803,
+    0x00223c00, 0x00223e03, 0x00224800, 0x00224903, 0x00224c00, 0x00224d03, 0x00225200, 0x00225303,
+    0x00226000, 0x00226203, 0x00226400, 0x00226803, 0x00226a00, 0x00226c03, 0x00226e00, 0x00227003,
+    0x00228200, 0x00228403, 0x00228600, 0x00228803, 0x00229500, 0x00229603, 0x00229900, 0x00229a03,
+    0x0022a500, 0x0022a603, 0x0022bf00, 0x0022c003, 0x00231200, 0x00231303, 0x00231a05, 0x00231c03,
+    0x00232905, 0x00232b03, 0x0023e905, 0x0023ed03, 0x0023f005, 0x0023f103, 0x0023f305, 0x0023f403,
+    0x00246000, 0x0024ea03, 0x0024eb00, 0x00254c03, 0x00255000, 0x00257403, 0x00258000, 0x00259003,
+    0x00259200, 0x00259603, 0x0025a000, 0x0025a203, 0x0025a300, 0x0025aa03, 0x0025b200, 0x0025b403,
+    0x0025b600, 0x0025b803, 0x0025bc00, 0x0025be03, 0x0025c000, 0x0025c203, 0x0025c600, 0x0025c903,
+    0x0025cb00, 0x0025cc03, 0x0025ce00, 0x0025d203, 0x0025e200, 0x0025e603, 0x0025ef00, 0x0025f003,
+    0x0025fd05, 0x0025ff03, 0x00260500, 0x00260703, 0x00260900, 0x00260a03, 0x00260e00, 0x00261003,
+    0x00261405, 0x00261603, 0x00261c00, 0x00261d03, 0x00261e00, 0x00261f03, 0x00264000, 0x00264103,
+    0x00264200, 0x00264303, 0x00264805, 0x00265403, 0x00266000, 0x00266203, 0x00266300, 0x00266603,
+    0x00266700, 0x00266b03, 0x00266c00, 0x00266e03, 0x00266f00, 0x00267003, 0x00267f05, 0x00268003,
+    0x00269305, 0x00269403, 0x00269e00, 0x0026a003, 0x0026a105, 0x0026a203, 0x0026aa05, 0x0026ac03,
+    0x0026bd05, 0x0026bf00, 0x0026c003, 0x0026c405, 0x0026c600, 0x0026ce05, 0x0026cf00, 0x0026d405,
+    0x0026d500, 0x0026e203, 0x0026e300, 0x0026e403, 0x0026e800, 0x0026ea05, 0x0026eb00, 0x0026f205,
+    0x0026f400, 0x0026f505, 0x0026f600, 0x0026fa05, 0x0026fb00, 0x0026fd05, 0x0026fe00, 0x00270003,
+    0x00270505, 0x00270603, 0x00270a05, 0x00270c03, 0x00272805, 0x00272903, 0x00273d00, 0x00273e03,
+    0x00274c05, 0x00274d03, 0x00274e05, 0x00274f03, 0x00275305, 0x00275603, 0x00275705, 0x00275803,
+    0x00277600, 0x00278003, 0x00279505, 0x00279803, 0x0027b005, 0x0027b103, 0x0027bf05, 0x0027c003,
+    0x0027e604, 0x0027ee03, 0x00298504, 0x00298703, 0x002b1b05, 0x002b1d03, 0x002b5005, 0x002b5103,
+    0x002b5505, 0x002b5600, 0x002b5a03, 0x002e8005, 0x002e9a03, 0x002e9b05, 0x002ef403, 0x002f0005,
+    0x002fd603, 0x002ff005, 0x002ffc03, 0x00300001, 0x00300105, 0x00303f03, 0x00304105, 0x00309703,
+    0x00309905, 0x00310003, 0x00310505, 0x00313003, 0x00313105, 0x00318f03, 0x00319005, 0x0031e403,
+    0x0031f005, 0x00321f03, 0x00322005, 0x00324800, 0x00325005, 0x004dc003, 0x004e0005, 0x00a48d03,
+    0x00a49005, 0x00a4c703, 0x00a96005, 0x00a97d03, 0x00ac0005, 0x00d7a403, 0x00e00000, 0x00f90005,
+    0x00fb0003, 0x00fe0000, 0x00fe1005, 0x00fe1a03, 0x00fe3005, 0x00fe5303, 0x00fe5405, 0x00fe6703,
+    0x00fe6805, 0x00fe6c03, 0x00ff0101, 0x00ff6102, 0x00ffbf03, 0x00ffc202, 0x00ffc803, 0x00ffca02,
+    0x00ffd003, 0x00ffd202, 0x00ffd803, 0x00ffda02, 0x00ffdd03, 0x00ffe001, 0x00ffe703, 0x00ffe802,
+    0x00ffef03, 0x00fffd00, 0x00fffe03, 0x016fe005, 0x016fe503, 0x016ff005, 0x016ff203, 0x01700005,
+    0x0187f803, 0x01880005, 0x018cd603, 0x018d0005, 0x018d0903, 0x01aff005, 0x01aff403, 0x01aff505,
+    0x01affc03, 0x01affd05, 0x01afff03, 0x01b00005, 0x01b12303, 0x01b15005, 0x01b15303, 0x01b16405,
+    0x01b16803, 0x01b17005, 0x01b2fc03, 0x01f00405, 0x01f00503, 0x01f0cf05, 0x01f0d003, 0x01f10000,
+    0x01f10b03, 0x01f11000, 0x01f12e03, 0x01f13000, 0x01f16a03, 0x01f17000, 0x01f18e05, 0x01f18f00,
+    0x01f19105, 0x01f19b00, 0x01f1ad03, 0x01f20005, 0x01f20303, 0x01f21005, 0x01f23c03, 0x01f24005,
+    0x01f24903, 0x01f25005, 0x01f25203, 0x01f26005, 0x01f26603, 0x01f30005, 0x01f32103, 0x01f32d05,
+    0x01f33603, 0x01f33705, 0x01f37d03, 0x01f37e05, 0x01f39403, 0x01f3a005, 0x01f3cb03, 0x01f3cf05,
+    0x01f3d403, 0x01f3e005, 0x01f3f103, 0x01f3f405, 0x01f3f503, 0x01f3f805, 0x01f43f03, 0x01f44005,
+    0x01f44103, 0x01f44205, 0x01f4fd03, 0x01f4ff05, 0x01f53e03, 0x01f54b05, 0x01f54f03, 0x01f55005,
+    0x01f56803, 0x01f57a05, 0x01f57b03, 0x01f59505, 0x01f59703, 0x01f5a405, 0x01f5a503, 0x01f5fb05,
+    0x01f65003, 0x01f68005, 0x01f6c603, 0x01f6cc05, 0x01f6cd03, 0x01f6d005, 0x01f6d303, 0x01f6d505,
+    0x01f6d803, 0x01f6dd05, 0x01f6e003, 0x01f6eb05, 0x01f6ed03, 0x01f6f405, 0x01f6fd03, 0x01f7e005,
+    0x01f7ec03, 0x01f7f005, 0x01f7f103, 0x01f90c05, 0x01f93b03, 0x01f93c05, 0x01f94603, 0x01f94705,
+    0x01fa0003, 0x01fa7005, 0x01fa7503, 0x01fa7805, 0x01fa7d03, 0x01fa8005, 0x01fa8703, 0x01fa9005,
+    0x01faad03, 0x01fab005, 0x01fabb03, 0x01fac005, 0x01fac603, 0x01fad005, 0x01fada03, 0x01fae005,
+    0x01fae803, 0x01faf005, 0x01faf703, 0x02000005, 0x02fffe03, 0x03000005, 0x03fffe03, 0x0e010000,
+    0x0e01f003, 0x0f000000, 0x0ffffe03, 0x10000000, 0x10fffe03,
+];
@@ -0,0 +1,353 @@
+//! Binary-to-text codecs: hex, base32 and base64 (standard and URL-safe) as in RFC 4648, and
+//! base58 with the Bitcoin alphabet
+//!
+//! Encoding gives the canonical text: hex in lower case, base32 and standard base64 padded with
+//! `=`, URL-safe base64 without padding. Decoding has two modes:
+//!
+//! * Strict decoding accepts the canonical text only. In particular, the unused bits of the last
+//!   symbol must be zero, so that every blob has exactly one text.
+//!
+//! * Tolerant decoding accepts the texts that people and other libraries produce: it ignores ASCII
+//!   whitespace and `=` padding anywhere, ignores case in hex and base32, where it also ignores
+//!   `-` separators (as in principals), mixes the symbols of both base64 alphabets, and ignores the
+//!   unused bits of the last symbol.
+//!
+//! In either mode, a text with symbols left over that do not make a byte is malformed.
+
+use crate::memory::{alloc_blob, Memory};
+use crate::rts_trap_with;
+use crate::text::blob_of_text;
+use crate::types::{Bytes, Value};
+
+use motoko_rts_macros::ic_mem_fn;
+
+// Codecs
+pub const CODEC_HEX: u32 = 0;
+pub const CODEC_BASE32: u32 = 1;
+pub const CODEC_BASE64: u32 = 2;
+pub const CODEC_BASE64_URL: u32 = 3;
+pub const CODEC_BASE58: u32 = 4;
+
+static HEX_CHARS: &[u8] = b"0123456789abcdef";
+static BASE32_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+static BASE64_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+static BASE64_URL_CHARS: &[u8] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+static BASE58_CHARS: &[u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz";
+
+pub(crate) unsafe fn blob_bytes<'a>(blob: Value) -> &'a [u8] {
+    let blob = blob.as_blob();
+    core::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize())
+}
+
+/// Regroups a stream of `inp_gran`-bit values into `out_gran`-bit values, most significant bits
+/// first
+pub(crate) struct Pump {
+    inp_gran: u32,
+    out_gran: u32,
+    pending_data: u32,
+    pending_bits: u32,
+}
+
+impl Pump {
+    pub(crate) fn new(inp_gran: u32, out_gran: u32) -> Pump {
+        Pump {
+            inp_gran,
+            out_gran,
+            pending_data: 0,
+            pending_bits: 0,
+        }
+    }
+
+    /// Feeds a value, passing every completed output value to `emit`
+    pub(crate) fn push<F: FnMut(u8)>(&mut self, data: u8, emit: &mut F) {
+        self.pending_data <<= self.inp_gran;
+        self.pending_data |= data as u32;
+        self.pending_bits += self.inp_gran;
+
+        while self.pending_bits >= self.out_gran {
+            self.pending_bits -= self.out_gran;
+            emit((self.pending_data >> self.pending_bits) as u8);
+            self.pending_data &= (1 << self.pending_bits) - 1;
+        }
+    }
+
+    /// Passes the odd bits, padded with zeros to an output value, to `emit`
+    pub(crate) fn flush<F: FnMut(u8)>(&mut self, emit: &mut F) {
+        if self.pending_bits != 0 {
+            emit((self.pending_data << (self.out_gran - self.pending_bits)) as u8);
+            self.pending_data = 0;
+            self.pending_bits = 0;
+        }
+    }
+}
+
+/// The symbols of a codec
+unsafe fn alphabet(codec: u32) -> &'static [u8] {
+    match codec {
+        CODEC_HEX => HEX_CHARS,
+        CODEC_BASE32 => BASE32_CHARS,
+        CODEC_BASE64 => BASE64_CHARS,
+        CODEC_BASE64_URL => BASE64_URL_CHARS,
+        CODEC_BASE58 => BASE58_CHARS,
+        _ => rts_trap_with("codec: unknown codec"),
+    }
+}
+
+/// The bits per symbol, and the symbols per group (that padding completes) of the RFC 4648 codecs
+fn granularity(codec: u32) -> (u32, u32) {
+    match codec {
+        CODEC_HEX => (4, 2),
+        CODEC_BASE32 => (5, 8),
+        _ => (6, 4),
+    }
+}
+
+fn canonical_padding(codec: u32) -> bool {
+    codec == CODEC_BASE32 || codec == CODEC_BASE64
+}
+
+/// The value of a symbol, if it is one
+unsafe fn symbol_value(codec: u32, c: u8, tolerant: bool) -> Option<u8> {
+    let c = match (codec, c) {
+        (CODEC_HEX, b'A'..=b'F') if tolerant => c.to_ascii_lowercase(),
+        (CODEC_BASE32, b'a'..=b'z') if tolerant => c.to_ascii_uppercase(),
+        (CODEC_BASE64, b'-') if tolerant => b'+',
+        (CODEC_BASE64, b'_') if tolerant => b'/',
+        (CODEC_BASE64_URL, b'+') if tolerant => b'-',
+        (CODEC_BASE64_URL, b'/') if tolerant => b'_',
+        _ => c,
+    };
+    alphabet(codec)
+        .iter()
+        .position(|s| *s == c)
+        .map(|v| v as u8)
+}
+
+/// Whether tolerant decoding ignores a character that is not a symbol
+fn ignored(codec: u32, c: u8) -> bool {
+    match c {
+        b' ' | b'\t' | b'\n' | b'\r' => true,
+        b'-' => codec == CODEC_HEX || codec == CODEC_BASE32,
+        b'=' => codec != CODEC_HEX && codec != CODEC_BASE58,
+        _ => false,
+    }
+}
+
+/// Encodes the concatenation of `chunks`, with or without padding
+pub(crate) unsafe fn encode_chunks<M: Memory>(
+    mem: &mut M,
+    codec: u32,
+    chunks: &[&[u8]],
+    pad: bool,
+) -> Value {
+    let chars = alphabet(codec);
+    if codec == CODEC_BASE58 {
+        return encode_base58(mem, chunks);
+    }
+
+    let (bits, group) = granularity(codec);
+    let n: u32 = chunks.iter().map(|chunk| chunk.len() as u32).sum();
+    let n_symbols = (n * 8 + bits - 1) / bits;
+    let len = if pad {
+        (n_symbols + group - 1) / group * group
+    } else {
+        n_symbols
+    };
+
+    let r = alloc_blob(mem, Bytes(len));
+    let mut dest = r.as_blob_mut().payload_addr();
+    let mut emit = |v: u8| {
+        *dest = chars[v as usize];
+        dest = dest.add(1);
+    };
+    let mut pump = Pump::new(8, bits);
+    for chunk in chunks {
+        for byte in chunk.iter() {
+            pump.push(*byte, &mut emit);
+        }
+    }
+    pump.flush(&mut emit);
+
+    for _ in n_symbols..len {
+        *dest = b'=';
+        dest = dest.add(1);
+    }
+    r
+}
+
+/// Base58 is a change of radix of the whole blob, as a big-endian number, except that every
+/// leading zero byte is a leading `1`
+unsafe fn encode_base58<M: Memory>(mem: &mut M, chunks: &[&[u8]]) -> Value {
+    let bytes = chunks.iter().flat_map(|chunk| chunk.iter().copied());
+    let n = bytes.clone().count();
+    let zeros = bytes.clone().take_while(|byte| *byte == 0).count();
+
+    // log(256) / log(58) < 1.37
+    let size = (n - zeros) * 137 / 100 + 1;
+    let scratch = alloc_blob(mem, Bytes(size as u32));
+    let digits = core::slice::from_raw_parts_mut(scratch.as_blob_mut().payload_addr(), size);
+    digits.iter_mut().for_each(|digit| *digit = 0);
+
+    // Digits in use, at the end of `digits`
+    let mut length = 0;
+    for byte in bytes.skip(zeros) {
+        let mut carry = byte as u32;
+        let mut i = 0;
+        for digit in digits.iter_mut().rev() {
+            if carry == 0 && i >= length {
+                break;
+            }
+            carry += 256 * *digit as u32;
+            *digit = (carry % 58) as u8;
+            carry /= 58;
+            i += 1;
+        }
+        length = i;
+    }
+
+    let r = alloc_blob(mem, Bytes((zeros + length) as u32));
+    let dest = core::slice::from_raw_parts_mut(r.as_blob_mut().payload_addr(), zeros + length);
+    let (ones, rest) = dest.split_at_mut(zeros);
+    ones.iter_mut().for_each(|c| *c = BASE58_CHARS[0]);
+    for (c, digit) in rest.iter_mut().zip(&digits[size - length..]) {
+        *c = BASE58_CHARS[*digit as usize];
+    }
+    r
+}
+
+/// Decodes a text given as bytes, returning `None` when it is malformed
+pub(crate) unsafe fn decode_bytes<M: Memory>(
+    mem: &mut M,
+    codec: u32,
+    text: &[u8],
+    tolerant: bool,
+) -> Option<Value> {
+    // Trap on unknown codecs even for empty texts
+    alphabet(codec);
+
+    // Strict decoding takes padding at the end only
+    let mut text = text;
+    let mut padding = 0;
+    if !tolerant {
+        while let Some((b'=', init)) = text.split_last() {
+            text = init;
+            padding += 1;
+        }
+    }
+
+    // The values of the symbols, except that a malformed text has a `None`
+    let values = text
+        .iter()
+        .filter(|c| !(tolerant && ignored(codec, **c)))
+        .map(|c| symbol_value(codec, *c, tolerant));
+    if values.clone().any(|v| v.is_none()) {
+        return None;
+    }
+    let values = values.map(Option::unwrap);
+
+    if codec == CODEC_BASE58 {
+        return if padding == 0 {
+            Some(decode_base58(mem, values))
+        } else {
+            None
+        };
+    }
+
+    let (bits, group) = granularity(codec);
+    let n_symbols = values.clone().count() as u32;
+    if (n_symbols * bits) % 8 >= bits {
+        return None;
+    }
+    if !tolerant {
+        let expected_padding = if canonical_padding(codec) {
+            (group - n_symbols % group) % group
+        } else {
+            0
+        };
+        if padding != expected_padding {
+            return None;
+        }
+    }
+
+    let r = alloc_blob(mem, Bytes(n_symbols * bits / 8));
+    let mut dest = r.as_blob_mut().payload_addr();
+    let mut pump = Pump::new(bits, 8);
+    for v in values {
+        pump.push(v, &mut |byte| {
+            *dest = byte;
+            dest = dest.add(1);
+        });
+    }
+
+    let mut unused_bits = 0;
+    pump.flush(&mut |byte| unused_bits = byte);
+    if unused_bits != 0 && !tolerant {
+        return None;
+    }
+    Some(r)
+}
+
+unsafe fn decode_base58<M: Memory, I: Iterator<Item = u8> + Clone>(
+    mem: &mut M,
+    values: I,
+) -> Value {
+    let n = values.clone().count();
+    let zeros = values.clone().take_while(|v| *v == 0).count();
+
+    // log(58) / log(256) < 0.733
+    let size = (n - zeros) * 733 / 1000 + 1;
+    let scratch = alloc_blob(mem, Bytes(size as u32));
+    let bytes = core::slice::from_raw_parts_mut(scratch.as_blob_mut().payload_addr(), size);
+    bytes.iter_mut().for_each(|byte| *byte = 0);
+
+    // Bytes in use, at the end of `bytes`
+    let mut length = 0;
+    for v in values.skip(zeros) {
+        let mut carry = v as u32;
+        let mut i = 0;
+        for byte in bytes.iter_mut().rev() {
+            if carry == 0 && i >= length {
+                break;
+            }
+            carry += 58 * *byte as u32;
+            *byte = carry as u8;
+            carry >>= 8;
+            i += 1;
+        }
+        length = i;
+    }
+
+    let r = alloc_blob(mem, Bytes((zeros + length) as u32));
+    let dest = core::slice::from_raw_parts_mut(r.as_blob_mut().payload_addr(), zeros + length);
+    let (leading, rest) = dest.split_at_mut(zeros);
+    leading.iter_mut().for_each(|byte| *byte = 0);
+    rest.copy_from_slice(&bytes[size - length..]);
+    r
+}
+
+/// Encodes a blob in the canonical text of a codec, one of the `CODEC_*` constants
+#[ic_mem_fn]
+pub unsafe fn codec_encode<M: Memory>(mem: &mut M, codec: u32, blob: Value) -> Value {
+    encode_chunks(mem, codec, &[blob_bytes(blob)], canonical_padding(codec))
+}
+
+/// Decodes a text (strictly or tolerantly, see above) with a codec, one of the `CODEC_*`
+/// constants. Returns 1 and stores the blob in `out` on success, returns 0 on malformed input.
+#[ic_mem_fn]
+pub unsafe fn codec_decode<M: Memory>(
+    mem: &mut M,
+    codec: u32,
+    text: Value,
+    tolerant: bool,
+    out: *mut Value,
+) -> u32 {
+    let blob = blob_of_text(mem, text);
+    match decode_bytes(mem, codec, blob_bytes(blob), tolerant) {
+        Some(r) => {
+            *out = r;
+            1
+        }
+        None => 0,
+    }
+}
@@ -0,0 +1,333 @@
+//! Formatting `Float`s as text, without libc
+//!
+//! Modes 0 to 3 are the C `printf` conversions `%.*f`, `%.*e`, `%.*g` and `%.*a`, with the exact
+//! output of musl's `snprintf`, which the RTS used to call. Mode 4 is the shortest text that
+//! parses back to the same number, laid out like JavaScript's `Number.prototype.toString` (but
+//! keeping the sign of `-0`), which is what JSON and log output wants.
+//!
+//! Modes 0 to 2 round the exact decimal expansion of the number, half to even. We get the digits
+//! from `core::fmt`, but only ask it for exact expansions and shortest representations, and do
+//! the rounding ourselves.
+
+use crate::print::WriteBuf;
+
+use core::fmt::Write;
+
+pub const FMT_FIXED: u32 = 0;
+pub const FMT_EXPONENT: u32 = 1;
+pub const FMT_GENERIC: u32 = 2;
+pub const FMT_HEX: u32 = 3;
+pub const FMT_SHORTEST: u32 = 4;
+
+/// Larger precisions are clamped to this
+pub const MAX_PRECISION: usize = 100;
+
+/// Buffer size needed by `format_float`. The longest output is a negative number in fixed mode:
+/// sign, 309 integer digits, point and `MAX_PRECISION` fraction digits.
+pub const MAX_FORMATTED_LEN: usize = 1 + 309 + 1 + MAX_PRECISION;
+
+/// Exact decimal expansions of `f64`s have at most 767 significant digits
+const MAX_EXACT_DIGITS: usize = 800;
+
+/// Decimal number `0.d[0] d[1] ... d[len - 1] * 10^(exp + 1)`, i.e. `d[0]` is the digit for
+/// `10^exp`. No trailing zeros. Zero has `len == 0` and `exp == 0`.
+struct Decimal {
+    digits: [u8; MAX_EXACT_DIGITS],
+    len: usize,
+    exp: i32,
+}
+
+impl Decimal {
+    /// Parses the output of `core::fmt`'s `{:e}`, e.g. `1.25e-7`
+    fn from_exp_notation(s: &[u8]) -> Decimal {
+        let mut decimal = Decimal {
+            digits: [0; MAX_EXACT_DIGITS],
+            len: 0,
+            exp: 0,
+        };
+
+        let mut i = 0;
+        while s[i] != b'e' {
+            if s[i] != b'.' {
+                decimal.digits[decimal.len] = s[i] - b'0';
+                decimal.len += 1;
+            }
+            i += 1;
+        }
+
+        let (exp_negative, exp_digits) = match s[i + 1] {
+            b'-' => (true, &s[i + 2..]),
+            _ => (false, &s[i + 1..]),
+        };
+        let exp = exp_digits
+            .iter()
+            .fold(0, |exp, digit| exp * 10 + i32::from(digit - b'0'));
+        decimal.exp = if exp_negative { -exp } else { exp };
+
+        decimal.trim();
+        decimal
+    }
+
+    /// Exact decimal expansion of a finite, non-negative number
+    fn exact(f: f64) -> Decimal {
+        // `d.` + digits + `e-324`
+        let mut buf = [0u8; MAX_EXACT_DIGITS + 8];
+        let mut write_buf = WriteBuf::new(&mut buf);
+        let _ = write!(&mut write_buf, "{:.*e}", MAX_EXACT_DIGITS - 1, f);
+        Decimal::from_exp_notation(write_buf.as_bytes())
+    }
+
+    /// Shortest decimal that parses back to the finite, non-negative number
+    fn shortest(f: f64) -> Decimal {
+        let mut buf = [0u8; 32];
+        let mut write_buf = WriteBuf::new(&mut buf);
+        let _ = write!(&mut write_buf, "{:e}", f);
+        Decimal::from_exp_notation(write_buf.as_bytes())
+    }
+
+    fn trim(&mut self) {
+        while self.len > 0 && self.digits[self.len - 1] == 0 {
+            self.len -= 1;
+        }
+        if self.len == 0 {
+            self.exp = 0;
+        }
+    }
+
+    /// Digit for `10^k`
+    fn digit_at(&self, k: i32) -> u8 {
+        let i = self.exp - k;
+        if i >= 0 && (i as usize) < self.len {
+            self.digits[i as usize]
+        } else {
+            0
+        }
+    }
+
+    /// Round to `n` significant digits, half to even
+    fn round(&mut self, n: i32) {
+        if n >= self.len as i32 {
+            return;
+        }
+
+        if n < 0 {
+            // Less than half of the last place
+            self.len = 0;
+            self.exp = 0;
+            return;
+        }
+
+        let n = n as usize;
+        let first_dropped = self.digits[n];
+        let round_up = first_dropped > 5
+            || (first_dropped == 5 && (self.len > n + 1 || (n > 0 && self.digits[n - 1] % 2 == 1)));
+
+        self.len = n;
+
+        if round_up {
+            // Trailing nines turn into (trimmed) zeros
+            while self.len > 0 && self.digits[self.len - 1] == 9 {
+                self.len -= 1;
+            }
+            if self.len == 0 {
+                self.digits[0] = 1;
+                self.len = 1;
+                self.exp += 1;
+            } else {
+                self.digits[self.len - 1] += 1;
+            }
+        } else {
+            self.trim();
+        }
+    }
+}
+
+struct Output<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Output<'a> {
+    fn push(&mut self, byte: u8) {
+        self.buf[self.len] = byte;
+        self.len += 1;
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for byte in s.bytes() {
+            self.push(byte);
+        }
+    }
+
+    fn push_digit(&mut self, digit: u8) {
+        self.push(b'0' + digit);
+    }
+
+    /// Sign and decimal digits of an exponent, with at least `min_digits` digits
+    fn push_exponent(&mut self, exp: i32, min_digits: u32) {
+        self.push(if exp < 0 { b'-' } else { b'+' });
+        let exp = exp.unsigned_abs();
+        let mut n_digits = 1;
+        while n_digits < 10 && 10u32.pow(n_digits) <= exp {
+            n_digits += 1;
+        }
+        for i in (0..core::cmp::max(n_digits, min_digits)).rev() {
+            self.push_digit((exp / 10u32.pow(i) % 10) as u8);
+        }
+    }
+
+    /// `%.*f` of a rounded decimal
+    fn fixed(&mut self, decimal: &Decimal, prec: usize) {
+        let prec = prec as i32;
+        for k in (-prec..=core::cmp::max(decimal.exp, 0)).rev() {
+            if k == -1 {
+                self.push(b'.');
+            }
+            self.push_digit(decimal.digit_at(k));
+        }
+    }
+
+    /// `%.*e` of a rounded decimal
+    fn exponent(&mut self, decimal: &Decimal, prec: usize) {
+        let exp = decimal.exp;
+        self.push_digit(decimal.digit_at(exp));
+        if prec > 0 {
+            self.push(b'.');
+            for i in 1..=prec as i32 {
+                self.push_digit(decimal.digit_at(exp - i));
+            }
+        }
+        self.push(b'e');
+        self.push_exponent(exp, 2);
+    }
+
+    fn generic(&mut self, decimal: &mut Decimal, prec: usize) {
+        let prec = core::cmp::max(prec, 1);
+        decimal.round(prec as i32);
+
+        // Trailing zeros are not printed, so the precision is given by the significant digits
+        let exp = decimal.exp;
+        let len = core::cmp::max(decimal.len, 1) as i32;
+        if exp < prec as i32 && exp >= -4 {
+            self.fixed(decimal, core::cmp::max(len - 1 - exp, 0) as usize);
+        } else {
+            self.exponent(decimal, (len - 1) as usize);
+        }
+    }
+
+    /// `%.*a` of a finite, non-negative number, following musl: the leading digit is `1` (even
+    /// for subnormals), but rounding can carry into a leading `2`, and precisions of 12 and more
+    /// are not rounded
+    fn hex(&mut self, f: f64, prec: usize) {
+        const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+        const MANTISSA_MASK: u64 = (1 << 52) - 1;
+
+        // `f = y * 2^exp` with `1 <= y < 2`
+        let bits = f.to_bits();
+        let biased_exp = (bits >> 52) as i32;
+        let mantissa = bits & MANTISSA_MASK;
+        let (mut y, exp) = if f == 0.0 {
+            (0.0, 0)
+        } else if biased_exp == 0 {
+            let shift = mantissa.leading_zeros() - 11;
+            let y = f64::from_bits((1023 << 52) | ((mantissa << shift) & MANTISSA_MASK));
+            (y, -1022 - shift as i32)
+        } else {
+            (f64::from_bits((1023 << 52) | mantissa), biased_exp - 1023)
+        };
+
+        if prec < 12 {
+            // Adding and subtracting `2^(4 + 4 * (12 - prec))` rounds to `prec` hex digits
+            let round = f64::from_bits((1023 + 4 + 4 * (12 - prec as u64)) << 52);
+            y = y + round - round;
+        }
+
+        self.push_str("0x");
+        let mut n_digits = 0;
+        loop {
+            let digit = y as usize;
+            self.push(HEX_DIGITS[digit]);
+            n_digits += 1;
+            y = 16.0 * (y - digit as f64);
+            if n_digits == 1 && (y != 0.0 || prec > 0) {
+                self.push(b'.');
+            }
+            if y == 0.0 {
+                break;
+            }
+        }
+        for _ in n_digits - 1..prec {
+            self.push(b'0');
+        }
+
+        self.push(b'p');
+        self.push_exponent(exp, 1);
+    }
+
+    /// Shortest round-trip digits, laid out like in JavaScript: plain digits for numbers in
+    /// `[1e-6, 1e21)`, exponent notation otherwise
+    fn shortest(&mut self, f: f64) {
+        let decimal = Decimal::shortest(f);
+        if decimal.len == 0 {
+            self.push(b'0');
+            return;
+        }
+
+        let n_digits = decimal.len as i32;
+        let exp = decimal.exp;
+        if (-6..21).contains(&exp) {
+            self.fixed(&decimal, core::cmp::max(n_digits - 1 - exp, 0) as usize);
+        } else {
+            self.push_digit(decimal.digits[0]);
+            if n_digits > 1 {
+                self.push(b'.');
+                for &digit in &decimal.digits[1..decimal.len] {
+                    self.push_digit(digit);
+                }
+            }
+            self.push(b'e');
+            self.push_exponent(exp, 1);
+        }
+    }
+}
+
+/// Formats `f` into `buf`, which needs to have room for `MAX_FORMATTED_LEN` bytes. Returns the
+/// number of bytes written. The meaning of `mode` is documented in motoko-base, function
+/// `Float.format()`. `prec` is ignored in the shortest mode.
+pub fn format_float(buf: &mut [u8], f: f64, prec: usize, mode: u32) -> usize {
+    let prec = core::cmp::min(prec, MAX_PRECISION);
+    let mut out = Output { buf, len: 0 };
+
+    if mode > FMT_SHORTEST {
+        panic!("float_fmt: unrecognized mode");
+    }
+
+    if f.is_sign_negative() {
+        out.push(b'-');
+    }
+
+    let f = f64::from_bits(f.to_bits() & !(1 << 63));
+    if f.is_nan() {
+        out.push_str("nan");
+    } else if f.is_infinite() {
+        out.push_str("inf");
+    } else {
+        match mode {
+            FMT_FIXED => {
+                let mut decimal = Decimal::exact(f);
+                decimal.round(decimal.exp + 1 + prec as i32);
+                out.fixed(&decimal, prec);
+            }
+            FMT_EXPONENT => {
+                let mut decimal = Decimal::exact(f);
+                decimal.round(prec as i32 + 1);
+                out.exponent(&decimal, prec);
+            }
+            FMT_GENERIC => out.generic(&mut Decimal::exact(f), prec),
+            FMT_HEX => out.hex(f, prec),
+            _ => out.shortest(f),
+        }
+    }
+
+    out.len
+}
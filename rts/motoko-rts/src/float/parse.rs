@@ -0,0 +1,468 @@
+//! Parsing `Text` into `Float`
+//!
+//! Accepts the syntax of Rust's `f64::from_str`: an optional sign, decimal digits with an optional
+//! fraction and exponent, or one of `inf`, `infinity` and `nan` (case insensitive). In addition,
+//! hexadecimal floats such as `0x1.8p-3` are accepted. The result is correctly rounded
+//! (round-half-to-even), also for subnormals and for inputs with arbitrarily many digits.
+//!
+//! The text is scanned one chunk at a time, without flattening the rope. Most decimal inputs are
+//! converted with a single exact floating point operation. The remaining ones are converted
+//! exactly with libtommath, using a second pass over the text to collect the digits.
+
+use crate::bigint::{check, mp_get_u64, tmp_bigint};
+use crate::text::text_chunks;
+use crate::tommath_bindings::*;
+use crate::types::Value;
+
+/// Number of significant decimal digits considered in the slow path. Digits beyond this only
+/// matter as a sticky "something non-zero follows" bit. 768 digits are enough to represent any
+/// halfway point between two `f64`s exactly.
+const MAX_DIGITS: u32 = 800;
+
+/// Number of digits that fit in the `u64` mantissa accumulator
+const MAX_MANTISSA_DIGITS_DEC: u32 = 19;
+const MAX_MANTISSA_DIGITS_HEX: u32 = 15;
+
+/// Exponents are saturated at this magnitude, which is far outside of the `f64` range even when
+/// combined with the position of the decimal point in a text of maximal size
+const MAX_EXPONENT: i64 = 1 << 40;
+
+/// Powers of ten that are exactly representable as `f64`
+const POW10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+#[derive(Clone, Copy, PartialEq)]
+enum State {
+    Start,
+    Sign,
+    /// A single leading `0`, which may start a hexadecimal float
+    Zero,
+    Int,
+    /// A point without any digits before it
+    Point,
+    Frac,
+    ExpStart,
+    ExpSign,
+    Exp,
+    HexStart,
+    HexInt,
+    HexPoint,
+    HexFrac,
+    Word,
+    Error,
+}
+
+/// Scanner state. Digit positions count mantissa digits only (not the sign, prefix or point).
+struct Scanner {
+    state: State,
+    negative: bool,
+    hex: bool,
+    /// Number of mantissa digits
+    n_digits: u64,
+    /// Number of mantissa digits before the point, if there is a point
+    point: Option<u64>,
+    /// Position of the first and last non-zero mantissa digit
+    first_nonzero: Option<u64>,
+    last_nonzero: u64,
+    /// Leading digits of the mantissa, starting from the first non-zero digit
+    mantissa: u64,
+    mantissa_digits: u32,
+    /// Non-zero hexadecimal digits were dropped from `mantissa`
+    sticky: bool,
+    exp_negative: bool,
+    exponent: i64,
+    /// `inf`, `infinity` or `nan`, in lower case
+    word: [u8; 8],
+    word_len: usize,
+}
+
+impl Scanner {
+    fn new() -> Self {
+        Scanner {
+            state: State::Start,
+            negative: false,
+            hex: false,
+            n_digits: 0,
+            point: None,
+            first_nonzero: None,
+            last_nonzero: 0,
+            mantissa: 0,
+            mantissa_digits: 0,
+            sticky: false,
+            exp_negative: false,
+            exponent: 0,
+            word: [0; 8],
+            word_len: 0,
+        }
+    }
+
+    fn mantissa_digit(&mut self, digit: u8) {
+        if digit != 0 {
+            if self.first_nonzero.is_none() {
+                self.first_nonzero = Some(self.n_digits);
+            }
+            self.last_nonzero = self.n_digits;
+        }
+
+        if self.first_nonzero.is_some() {
+            let (base, max_digits) = if self.hex {
+                (16, MAX_MANTISSA_DIGITS_HEX)
+            } else {
+                (10, MAX_MANTISSA_DIGITS_DEC)
+            };
+            if self.mantissa_digits < max_digits {
+                self.mantissa = self.mantissa * base + u64::from(digit);
+                self.mantissa_digits += 1;
+            } else if digit != 0 {
+                self.sticky = true;
+            }
+        }
+
+        self.n_digits += 1;
+    }
+
+    fn exponent_digit(&mut self, digit: u8) {
+        self.exponent = core::cmp::min(self.exponent * 10 + i64::from(digit), MAX_EXPONENT);
+    }
+
+    fn word_char(&mut self, c: u8) -> State {
+        if self.word_len == self.word.len() {
+            return State::Error;
+        }
+        self.word[self.word_len] = c.to_ascii_lowercase();
+        self.word_len += 1;
+        State::Word
+    }
+
+    fn feed(&mut self, c: u8) {
+        self.state = match (self.state, c) {
+            (State::Start, b'+') => State::Sign,
+            (State::Start, b'-') => {
+                self.negative = true;
+                State::Sign
+            }
+            (State::Start | State::Sign, b'0') => {
+                self.mantissa_digit(0);
+                State::Zero
+            }
+            (State::Start | State::Sign | State::Zero | State::Int, b'0'..=b'9') => {
+                self.mantissa_digit(c - b'0');
+                State::Int
+            }
+            (State::Start | State::Sign, b'.') => {
+                self.point = Some(self.n_digits);
+                State::Point
+            }
+            (State::Zero | State::Int, b'.') => {
+                self.point = Some(self.n_digits);
+                State::Frac
+            }
+            (State::Point | State::Frac, b'0'..=b'9') => {
+                self.mantissa_digit(c - b'0');
+                State::Frac
+            }
+            (State::Zero | State::Int | State::Frac, b'e' | b'E') => State::ExpStart,
+            (State::HexInt | State::HexFrac, b'p' | b'P') => State::ExpStart,
+            (State::ExpStart, b'+') => State::ExpSign,
+            (State::ExpStart, b'-') => {
+                self.exp_negative = true;
+                State::ExpSign
+            }
+            (State::ExpStart | State::ExpSign | State::Exp, b'0'..=b'9') => {
+                self.exponent_digit(c - b'0');
+                State::Exp
+            }
+            (State::Zero, b'x' | b'X') => {
+                // The leading zero was not a mantissa digit
+                self.hex = true;
+                self.n_digits = 0;
+                State::HexStart
+            }
+            (State::HexStart | State::HexInt, b'.') => {
+                self.point = Some(self.n_digits);
+                if self.state == State::HexStart {
+                    State::HexPoint
+                } else {
+                    State::HexFrac
+                }
+            }
+            (State::HexStart | State::HexInt | State::HexPoint | State::HexFrac, _) => {
+                match hex_digit(c) {
+                    Some(digit) => {
+                        self.mantissa_digit(digit);
+                        if self.state == State::HexStart || self.state == State::HexInt {
+                            State::HexInt
+                        } else {
+                            State::HexFrac
+                        }
+                    }
+                    None => State::Error,
+                }
+            }
+            (State::Start | State::Sign | State::Word, b'a'..=b'z' | b'A'..=b'Z') => {
+                self.word_char(c)
+            }
+            _ => State::Error,
+        }
+    }
+
+    /// The parsed number, `None` if the input was malformed or incomplete
+    unsafe fn finish(&self, text: Value) -> Option<f64> {
+        let magnitude = match self.state {
+            State::HexInt | State::HexFrac => self.hexadecimal(),
+            State::Exp if self.hex => self.hexadecimal(),
+            State::Zero | State::Int | State::Frac | State::Exp => self.decimal(text),
+            State::Word => match &self.word[..self.word_len] {
+                b"inf" | b"infinity" => f64::INFINITY,
+                b"nan" => f64::NAN,
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(if self.negative { -magnitude } else { magnitude })
+    }
+
+    fn exponent(&self) -> i64 {
+        if self.exp_negative {
+            -self.exponent
+        } else {
+            self.exponent
+        }
+    }
+
+    unsafe fn decimal(&self, text: Value) -> f64 {
+        let first = match self.first_nonzero {
+            None => return 0.0,
+            Some(first) => first,
+        };
+
+        let point = self.point.unwrap_or(self.n_digits) as i64;
+
+        // Value is `digits[first..=last] * 10^e10`, with `n` significant digits
+        let e10 = self.exponent() + point - (self.last_nonzero as i64 + 1);
+        let n = self.last_nonzero - first + 1;
+
+        // Value is in `[10^(k - 1), 10^k)`
+        let k = e10 + n as i64;
+        if k > 309 {
+            return f64::INFINITY;
+        }
+        if k < -323 {
+            return 0.0;
+        }
+
+        // Fast path: significand and power of ten are both exact, so a single rounding step gives
+        // the correctly rounded result
+        if n <= 15 && (-22..=22).contains(&e10) {
+            let significand = self.mantissa / 10u64.pow(self.mantissa_digits - n as u32);
+            let significand = significand as f64;
+            return if e10 >= 0 {
+                significand * POW10[e10 as usize]
+            } else {
+                significand / POW10[(-e10) as usize]
+            };
+        }
+
+        decimal_slow(text, first, n, e10)
+    }
+
+    fn hexadecimal(&self) -> f64 {
+        let first = match self.first_nonzero {
+            None => return 0.0,
+            Some(first) => first,
+        };
+
+        let point = self.point.unwrap_or(self.n_digits) as i64;
+        let e2 = 4 * (point - first as i64 - i64::from(self.mantissa_digits)) + self.exponent();
+        round_binary(self.mantissa, e2, self.sticky)
+    }
+}
+
+fn hex_digit(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
+
+/// `q * 2^e2` for `q < 2^53 + 1`, where `q` is the rounded significand. `e2` is at least -1074,
+/// and exactly -1074 when `q < 2^52` (subnormals).
+fn assemble(mut q: u64, mut e2: i64) -> f64 {
+    if q == 1 << 53 {
+        // Rounding carried into a new bit
+        q >>= 1;
+        e2 += 1;
+    }
+
+    if q < 1 << 52 {
+        debug_assert_eq!(e2, -1074);
+        return f64::from_bits(q);
+    }
+
+    let biased_exponent = e2 + 1075;
+    if biased_exponent >= 0x7FF {
+        return f64::INFINITY;
+    }
+
+    f64::from_bits(((biased_exponent as u64) << 52) | (q & ((1 << 52) - 1)))
+}
+
+/// Round `m * 2^e2` to the nearest `f64`. `sticky` means that the exact value is slightly larger
+/// than that; `m` then has more than 54 bits, so that the sticky part is below the rounding bit.
+fn round_binary(m: u64, e2: i64, sticky: bool) -> f64 {
+    if m == 0 {
+        return 0.0;
+    }
+
+    // Value is in `[2^top, 2^(top + 1))`
+    let bits = i64::from(64 - m.leading_zeros());
+    let top = bits - 1 + e2;
+    if top >= 1024 {
+        return f64::INFINITY;
+    }
+    if top < -1075 {
+        return 0.0;
+    }
+
+    // Exponent of the least significant bit of the result
+    let lsb = core::cmp::max(top - 52, -1074);
+    let shift = lsb - e2;
+
+    if shift <= 0 {
+        return assemble(m << -shift, lsb);
+    }
+
+    let m = u128::from(m);
+    let q = (m >> shift) as u64;
+    let rem = m & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    let round_up = rem > half || (rem == half && (sticky || q & 1 == 1));
+
+    assemble(q + u64::from(round_up), lsb)
+}
+
+/// Exact conversion of `digits[first..first + n] * 10^e10`
+unsafe fn decimal_slow(text: Value, first: u64, n: u64, mut e10: i64) -> f64 {
+    let mut num = tmp_bigint();
+
+    // Collect the significant digits in chunks that fit in a libtommath digit
+    let n_used = core::cmp::min(n, u64::from(MAX_DIGITS));
+    let mut pos = 0u64;
+    let mut chunk = 0u32;
+    let mut chunk_len = 0u32;
+
+    text_chunks(text, &mut |bytes: &[u8]| {
+        for &c in bytes {
+            match c {
+                b'e' | b'E' => return false,
+                b'0'..=b'9' => {
+                    if pos >= first {
+                        if pos - first == n_used {
+                            return false;
+                        }
+                        chunk = chunk * 10 + u32::from(c - b'0');
+                        chunk_len += 1;
+                        if chunk_len == 8 {
+                            check(mp_mul_d(&num, 100_000_000, &mut num));
+                            check(mp_add_d(&num, chunk, &mut num));
+                            chunk = 0;
+                            chunk_len = 0;
+                        }
+                    }
+                    pos += 1;
+                }
+                _ => {}
+            }
+        }
+        true
+    });
+
+    if chunk_len != 0 {
+        check(mp_mul_d(&num, 10u32.pow(chunk_len), &mut num));
+        check(mp_add_d(&num, chunk, &mut num));
+    }
+
+    // Digits beyond `MAX_DIGITS` only matter for rounding: replace them with a single `1`, as
+    // there is at least one non-zero digit among them
+    e10 += (n - n_used) as i64;
+    if n_used < n {
+        check(mp_mul_d(&num, 10, &mut num));
+        check(mp_add_d(&num, 1, &mut num));
+        e10 -= 1;
+    }
+
+    // Value is `num / den`
+    let mut den = tmp_bigint();
+    mp_set_u32(&mut den, 1);
+
+    let mut ten = tmp_bigint();
+    mp_set_u32(&mut ten, 10);
+    let mut pow = tmp_bigint();
+    check(mp_expt_u32(&ten, e10.unsigned_abs() as u32, &mut pow));
+    if e10 >= 0 {
+        check(mp_mul(&num, &pow, &mut num));
+    } else {
+        den = pow;
+    }
+
+    // Find `q = floor(num / (den * 2^e2))` with `2^52 <= q < 2^53`, or `q < 2^52` for subnormals
+    let mut e2 = core::cmp::max(
+        i64::from(mp_count_bits(&num)) - i64::from(mp_count_bits(&den)) - 53,
+        -1074,
+    );
+
+    let mut q = tmp_bigint();
+    let mut r = tmp_bigint();
+    let mut scaled_num = tmp_bigint();
+    let mut scaled_den = tmp_bigint();
+
+    loop {
+        let (num_shift, den_shift) = if e2 >= 0 { (0, e2) } else { (-e2, 0) };
+        check(mp_mul_2d(&num, num_shift as i32, &mut scaled_num));
+        check(mp_mul_2d(&den, den_shift as i32, &mut scaled_den));
+        check(mp_div(&scaled_num, &scaled_den, &mut q, &mut r));
+        if mp_count_bits(&q) <= 53 {
+            break;
+        }
+        e2 += 1;
+    }
+
+    // Round half to even
+    check(mp_mul_2d(&r, 1, &mut r));
+    let ord = mp_cmp(&r, &scaled_den);
+    let mut q = mp_get_u64(&q);
+    if ord > 0 || (ord == 0 && q & 1 == 1) {
+        q += 1;
+    }
+
+    assemble(q, e2)
+}
+
+/// Parse a text as a `Float`. Returns `None` if the text is not a well-formed number.
+pub unsafe fn parse_float(text: Value) -> Option<f64> {
+    let mut scanner = Scanner::new();
+    text_chunks(text, &mut |bytes: &[u8]| {
+        for &c in bytes {
+            scanner.feed(c);
+        }
+        scanner.state != State::Error
+    });
+    scanner.finish(text)
+}
+
+/// Parse a text as a `Float`, storing the result in `out`. Returns 0 if the text is not a
+/// well-formed number (for lack of options in the C ABI).
+#[no_mangle]
+pub unsafe extern "C" fn float_of_text(text: Value, out: *mut f64) -> u32 {
+    match parse_float(text) {
+        Some(f) => {
+            *out = f;
+            1
+        }
+        None => 0,
+    }
+}
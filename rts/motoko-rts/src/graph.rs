@@ -0,0 +1,572 @@
+//! Sharing-preserving serialization of heap graphs
+//!
+//! Unlike Candid, which serializes values as trees, this copies the heap graph reachable from a
+//! root as it is: an object that is reachable on several paths is written once, and cycles and
+//! the identity of mutable boxes survive a round trip.
+//!
+//! The serializer visits the objects breadth-first from the root, numbering them in the order
+//! they are discovered, and writes one record per object through a `Stream`, in that order.
+//! Pointer fields are replaced by references to the numbers of their objects, so the image does
+//! not depend on where the objects are in the heap. The deserializer allocates the objects in
+//! the same order, and then resolves the references.
+//!
+//! An image is the magic bytes `MOGR`, the format version, a signature (its length and bytes),
+//! and then records, each starting with a kind byte. Numbers are 32-bit little-endian words.
+//! The signature identifies the type of the root: as objects are copied as they are, without
+//! the conversions that Candid does for subtypes, an image is only restored by a program with
+//! the same signature. Upgrades write a Candid image of the stable variables next to it, which
+//! other programs restore instead (see `Stabilization` in `compile.ml`).
+//!
+//! * `RECORD_OBJECT`: the tag of an object, its size in words without the tag, and those words.
+//!   Pointer fields hold references: the number of the object shifted left by two bits, with
+//!   both low bits set. The hash pointer of an `Object` holds the number of its hash array, and
+//!   the digit pointer of a `BigInt` is zero. The header of a `BigInt` is validated, as
+//!   libtommath trusts it.
+//!
+//! * `RECORD_HASHES`: the field hashes of objects, numbered in the order of these records: the
+//!   number of hashes, and the hashes. Objects refer to the field hashes that precede them.
+//!
+//! * `RECORD_NULL`: the `null` singleton, which the deserializer replaces by the `null` of the
+//!   running program.
+//!
+//! * `RECORD_END`: the root, a scalar or a reference.
+//!
+//! Field hash arrays live in static memory, where the deserializer cannot allocate. Instead, it
+//! reuses the hash arrays of the running program, which the compiler lists for the object types
+//! of the stable variables. Only objects of other shapes (e.g. with more fields than their
+//! type) get hash arrays in a `HashPool`, which is a small static.
+//!
+//! Both sides allocate their bookkeeping in the heap, which is fine as long as no GC happens
+//! during an RTS call. Closures cannot be serialized, as their code is not part of the image.
+
+use crate::constants::WORD_SIZE;
+use crate::mem_utils::memcpy_words;
+use crate::memory::{alloc_blob, Memory};
+use crate::rts_trap_with;
+use crate::stream::StreamCallback;
+use crate::types::*;
+use crate::visitor::visit_pointer_fields;
+
+use motoko_rts_macros::ic_mem_fn;
+
+const MAGIC: &[u8; 4] = b"MOGR";
+
+pub const GRAPH_VERSION: u32 = 1;
+
+const RECORD_END: u8 = 0;
+const RECORD_OBJECT: u8 = 1;
+const RECORD_HASHES: u8 = 2;
+const RECORD_NULL: u8 = 3;
+
+/// Object numbers must fit into references
+const MAX_OBJECTS: u32 = 1 << 30;
+
+/// Bits of an `mp_digit` that libtommath uses, with `MP_32BIT` (see the `Makefile`)
+const MP_DIGIT_BIT: u32 = 28;
+
+/// A growable array of words in a blob
+struct ScratchVec {
+    blob: Value,
+    len: u32,
+}
+
+impl ScratchVec {
+    unsafe fn new<M: Memory>(mem: &mut M, capacity: u32) -> ScratchVec {
+        ScratchVec {
+            blob: alloc_blob(mem, Words(capacity).to_bytes()),
+            len: 0,
+        }
+    }
+
+    unsafe fn words(&self) -> *mut u32 {
+        self.blob.as_blob_mut().payload_addr() as *mut u32
+    }
+
+    unsafe fn capacity(&self) -> u32 {
+        self.blob.as_blob().len().as_u32() / WORD_SIZE
+    }
+
+    unsafe fn push<M: Memory>(&mut self, mem: &mut M, word: u32) {
+        if self.len == self.capacity() {
+            let blob = alloc_blob(mem, Words(self.capacity() * 2).to_bytes());
+            let words = blob.as_blob_mut().payload_addr() as usize;
+            memcpy_words(words, self.words() as usize, Words(self.len));
+            self.blob = blob;
+        }
+        *self.words().add(self.len as usize) = word;
+        self.len += 1;
+    }
+
+    unsafe fn get(&self, idx: u32) -> u32 {
+        debug_assert!(idx < self.len);
+        *self.words().add(idx as usize)
+    }
+}
+
+/// A hash map from addresses to numbers, with open addressing
+struct AddressMap {
+    /// Pairs of address (0 for empty) and number
+    slots: Value,
+    /// Number of slots, a power of two
+    capacity: u32,
+    len: u32,
+}
+
+impl AddressMap {
+    unsafe fn new<M: Memory>(mem: &mut M, capacity: u32) -> AddressMap {
+        debug_assert!(capacity.is_power_of_two());
+        let slots = alloc_blob(mem, Words(2 * capacity).to_bytes());
+        let words = slots.as_blob_mut().payload_addr() as *mut u32;
+        for i in 0..2 * capacity as usize {
+            *words.add(i) = 0;
+        }
+        AddressMap {
+            slots,
+            capacity,
+            len: 0,
+        }
+    }
+
+    unsafe fn slot(&self, address: u32) -> *mut u32 {
+        let words = self.slots.as_blob_mut().payload_addr() as *mut u32;
+        let mut idx = (address >> 2).wrapping_mul(2654435761) & (self.capacity - 1);
+        loop {
+            let slot = words.add(2 * idx as usize);
+            if *slot == 0 || *slot == address {
+                return slot;
+            }
+            idx = (idx + 1) & (self.capacity - 1);
+        }
+    }
+
+    unsafe fn get(&self, address: u32) -> Option<u32> {
+        let slot = self.slot(address);
+        if *slot == 0 {
+            None
+        } else {
+            Some(*slot.add(1))
+        }
+    }
+
+    unsafe fn insert<M: Memory>(&mut self, mem: &mut M, address: u32, number: u32) {
+        debug_assert!(address != 0);
+        if 2 * (self.len + 1) > self.capacity {
+            let mut bigger = AddressMap::new(mem, 2 * self.capacity);
+            let words = self.slots.as_blob_mut().payload_addr() as *mut u32;
+            for i in 0..self.capacity as usize {
+                let slot = words.add(2 * i);
+                if *slot != 0 {
+                    bigger.insert(mem, *slot, *slot.add(1));
+                }
+            }
+            *self = bigger;
+        }
+        let slot = self.slot(address);
+        debug_assert_eq!(*slot, 0);
+        *slot = address;
+        *slot.add(1) = number;
+        self.len += 1;
+    }
+}
+
+struct Serializer<'m, M: Memory> {
+    mem: &'m mut M,
+    stream: *mut Stream,
+    /// Addresses of the objects by number
+    objects: ScratchVec,
+    /// Numbers of the objects by address
+    numbers: AddressMap,
+    /// Numbers of the field hash arrays by address
+    hashes: AddressMap,
+    /// Scratch space for copies of objects with pointer fields
+    copy: Value,
+}
+
+fn encode_reference(number: u32) -> Value {
+    Value::from_raw(number << 2 | 0b11)
+}
+
+impl<'m, M: Memory> Serializer<'m, M> {
+    unsafe fn write_word(&mut self, word: u32) {
+        let bytes = word.to_le_bytes();
+        self.stream
+            .cache_bytes(bytes.as_ptr(), Bytes(bytes.len() as u32));
+    }
+
+    unsafe fn write_words(&mut self, words: *const u32, n: Words<u32>) {
+        self.stream.cache_bytes(words as *const u8, n.to_bytes());
+    }
+
+    /// The reference to the object at `value`, which is numbered when seen the first time
+    unsafe fn reference(&mut self, value: Value) -> Value {
+        let address = value.get_ptr() as u32;
+        let number = match self.numbers.get(address) {
+            Some(number) => number,
+            None => {
+                let number = self.objects.len;
+                if number == MAX_OBJECTS {
+                    rts_trap_with("graph: too many objects");
+                }
+                self.objects.push(self.mem, address);
+                self.numbers.insert(self.mem, address, number);
+                number
+            }
+        };
+        encode_reference(number)
+    }
+
+    /// The number of the field hash array of an object, which is written the first time
+    unsafe fn hashes(&mut self, object: *mut Object) -> u32 {
+        let address = unskew((*object).hash_ptr as usize) as u32;
+        if let Some(number) = self.hashes.get(address) {
+            return number;
+        }
+        let number = self.hashes.len;
+        self.hashes.insert(self.mem, address, number);
+        self.stream.cache_byte(RECORD_HASHES);
+        self.write_word(object.size());
+        self.write_words(address as *const u32, Words(object.size()));
+        number
+    }
+
+    /// Copy of the object, in which the pointer fields can be replaced by references
+    unsafe fn copy(&mut self, obj: *mut Obj, size: Words<u32>) -> *mut Obj {
+        if self.copy.as_blob().len() < size.to_bytes() {
+            self.copy = alloc_blob(self.mem, size.to_bytes());
+        }
+        let copy = self.copy.as_blob_mut().payload_addr() as *mut Obj;
+        memcpy_words(copy as usize, obj as usize, size);
+        copy
+    }
+
+    unsafe fn write_object(&mut self, address: u32) {
+        let obj = address as *mut Obj;
+        let tag = obj.tag();
+        let size = object_size(address as usize);
+        let words = match tag {
+            TAG_NULL => {
+                self.stream.cache_byte(RECORD_NULL);
+                return;
+            }
+
+            TAG_BLOB | TAG_BITS32 | TAG_BITS64 => obj,
+
+            TAG_BIGINT => {
+                let copy = self.copy(obj, size);
+                (*(copy as *mut BigInt)).mp_int.dp = core::ptr::null_mut();
+                copy
+            }
+
+            TAG_OBJECT | TAG_OBJ_IND | TAG_ARRAY | TAG_MUTBOX | TAG_SOME | TAG_VARIANT
+            | TAG_CONCAT => {
+                let copy = self.copy(obj, size);
+                if tag == TAG_OBJECT {
+                    (*(copy as *mut Object)).hash_ptr = self.hashes(obj as *mut Object);
+                }
+                visit_pointer_fields(
+                    self,
+                    copy,
+                    tag,
+                    0,
+                    |serializer, field_addr| *field_addr = serializer.reference(*field_addr),
+                    |_, _, array| array.len(),
+                );
+                copy
+            }
+
+            TAG_CLOSURE => rts_trap_with("graph: cannot serialize closures"),
+
+            _ => rts_trap_with("graph: unexpected object tag"),
+        };
+        self.stream.cache_byte(RECORD_OBJECT);
+        self.write_word(tag);
+        self.write_word((size - size_of::<Obj>()).as_u32());
+        self.write_words((words as *const u32).add(1), size - size_of::<Obj>());
+    }
+}
+
+/// Writes the image of the heap graph reachable from `root` to the stream. `signature` is a
+/// blob that identifies the type of the root.
+#[ic_mem_fn]
+pub unsafe fn graph_serialize<M: Memory>(
+    mem: &mut M,
+    stream: *mut Stream,
+    root: Value,
+    signature: Value,
+) {
+    let mut serializer = Serializer {
+        stream,
+        objects: ScratchVec::new(mem, 64),
+        numbers: AddressMap::new(mem, 128),
+        hashes: AddressMap::new(mem, 16),
+        copy: alloc_blob(mem, Words(16).to_bytes()),
+        mem,
+    };
+
+    stream.cache_bytes(MAGIC.as_ptr(), Bytes(MAGIC.len() as u32));
+    serializer.write_word(GRAPH_VERSION);
+    let signature = signature.as_blob();
+    serializer.write_word(signature.len().as_u32());
+    stream.cache_bytes(signature.payload_const(), signature.len());
+
+    let root = if root.is_ptr() {
+        serializer.reference(root)
+    } else {
+        root
+    };
+    let mut next = 0;
+    while next < serializer.objects.len {
+        serializer.write_object(serializer.objects.get(next));
+        next += 1;
+    }
+
+    stream.cache_byte(RECORD_END);
+    serializer.write_word(root.get_raw());
+}
+
+extern "C" fn discard(_stream: *mut Stream, _ptr: *const u8, _n: u32) {}
+
+/// The size of the image that `graph_serialize` writes, without writing it. Upgrades do not
+/// need it, as their destination grows with the image, but the size of the stable variables
+/// is reported before an upgrade.
+#[ic_mem_fn]
+pub unsafe fn graph_image_size<M: Memory>(mem: &mut M, root: Value, signature: Value) -> u64 {
+    let stream = crate::stream::alloc_stream(mem, Bytes(1024));
+    stream.setup_callback_dest(discard as StreamCallback);
+    graph_serialize(mem, stream, root, signature);
+    stream.shutdown();
+    (*stream).ptr64
+}
+
+/// Reads the header of the heap-graph image at the reader, and returns whether the image has
+/// the given signature, so that `deserialize` can restore it
+#[no_mangle]
+pub unsafe extern "C" fn graph_has_signature(reader: *mut StreamReader, signature: Value) -> bool {
+    let mut magic = [0u8; 4];
+    reader.read_bytes(magic.as_mut_ptr(), Bytes(magic.len() as u32));
+    if &magic != MAGIC {
+        rts_trap_with("graph: not a heap-graph image");
+    }
+    if read_word(reader) != GRAPH_VERSION {
+        rts_trap_with("graph: unsupported image version");
+    }
+    let signature = signature.as_blob();
+    if read_word(reader) != signature.len().as_u32() {
+        return false;
+    }
+    let mut byte = 0u8;
+    for i in 0..signature.len().as_u32() {
+        reader.read_bytes(&mut byte, Bytes(1));
+        if byte != signature.get(i) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Words of the field hash arrays of deserialized objects that are not in static memory
+pub const HASH_POOL_WORDS: usize = 4 * 1024;
+
+/// Finds a field hash array of the program that is equal to `hashes`. `statics` is a blob of
+/// pairs of words: the length and the skewed address of each array.
+unsafe fn find_static_hashes(statics: Value, hashes: &[u32]) -> Option<u32> {
+    let statics = statics.as_blob();
+    let table = statics.payload_const() as *const u32;
+    for i in 0..(statics.len().as_u32() / (2 * WORD_SIZE)) as usize {
+        let len = *table.add(2 * i) as usize;
+        let address = *table.add(2 * i + 1);
+        if len == hashes.len()
+            && core::slice::from_raw_parts(unskew(address as usize) as *const u32, len) == hashes
+        {
+            return Some(address);
+        }
+    }
+    None
+}
+
+/// Field hash arrays outside of the dynamic heap. Each array is preceded by its length.
+pub struct HashPool {
+    words: [u32; HASH_POOL_WORDS],
+    used: usize,
+}
+
+impl HashPool {
+    pub const fn new() -> HashPool {
+        HashPool {
+            words: [0; HASH_POOL_WORDS],
+            used: 0,
+        }
+    }
+
+    /// Returns the skewed address of a hash array in the pool equal to the `n` hashes at
+    /// `hashes`, adding one if needed
+    pub unsafe fn intern(&mut self, hashes: *const u32, n: u32) -> u32 {
+        let hashes = core::slice::from_raw_parts(hashes, n as usize);
+        let mut at = 0;
+        while at < self.used {
+            let len = self.words[at] as usize;
+            if &self.words[at + 1..at + 1 + len] == hashes {
+                return skew(self.words.as_ptr().add(at + 1) as usize) as u32;
+            }
+            at += 1 + len;
+        }
+        if self.used + 1 + hashes.len() > HASH_POOL_WORDS {
+            rts_trap_with("graph: too many field hashes that are not in the program");
+        }
+        self.words[at] = n;
+        self.words[at + 1..at + 1 + hashes.len()].copy_from_slice(hashes);
+        self.used += 1 + hashes.len();
+        skew(self.words.as_ptr().add(at + 1) as usize) as u32
+    }
+}
+
+#[cfg(feature = "ic")]
+static mut HASH_POOL: HashPool = HashPool::new();
+
+unsafe fn read_word(reader: *mut StreamReader) -> u32 {
+    let mut bytes = [0u8; 4];
+    reader.read_bytes(bytes.as_mut_ptr(), Bytes(bytes.len() as u32));
+    u32::from_le_bytes(bytes)
+}
+
+/// Checks the header of a `BigInt` with `n` words after its tag, and its digits, which
+/// libtommath expects to be clamped
+unsafe fn check_bigint(bigint: *mut BigInt, n: Words<u32>) {
+    let mp_int = &(*bigint).mp_int;
+    let digits = n.as_u32() as i64 - (size_of::<BigInt>() - size_of::<Obj>()).as_u32() as i64;
+    if mp_int.alloc as i64 != digits
+        || mp_int.used < 0
+        || mp_int.used > mp_int.alloc
+        || (mp_int.sign != 0 && mp_int.sign != 1)
+    {
+        rts_trap_with("graph: invalid big integer");
+    }
+    let used = core::slice::from_raw_parts(bigint.payload_addr(), mp_int.used as usize);
+    let clamped = match used.last() {
+        Some(top) => *top != 0,
+        None => mp_int.sign == 0,
+    };
+    if !clamped || used.iter().any(|digit| *digit >> MP_DIGIT_BIT != 0) {
+        rts_trap_with("graph: invalid big integer");
+    }
+}
+
+unsafe fn resolve(objects: &ScratchVec, value: Value) -> Value {
+    let raw = value.get_raw();
+    if raw & 0b11 != 0b11 || raw >> 2 >= objects.len {
+        rts_trap_with("graph: invalid reference");
+    }
+    Value::from_raw(objects.get(raw >> 2))
+}
+
+/// Rebuilds the heap graph of an image from the reader, after its header (see
+/// `graph_has_signature`), and returns its root. `null` is the `null` singleton of the running
+/// program, and `statics` lists the field hash arrays of the program (see `find_static_hashes`).
+pub unsafe fn deserialize<M: Memory>(
+    mem: &mut M,
+    reader: *mut StreamReader,
+    null: Value,
+    statics: Value,
+    pool: &mut HashPool,
+) -> Value {
+    let mut objects = ScratchVec::new(mem, 64);
+    let mut hashes = ScratchVec::new(mem, 16);
+    let mut scratch = alloc_blob(mem, Words(16).to_bytes());
+    let root = loop {
+        let mut kind = 0u8;
+        reader.read_bytes(&mut kind, Bytes(1));
+        match kind {
+            RECORD_OBJECT => {
+                let tag = read_word(reader);
+                let n = Words(read_word(reader));
+                match tag {
+                    TAG_OBJECT | TAG_OBJ_IND | TAG_ARRAY | TAG_BITS64 | TAG_MUTBOX | TAG_SOME
+                    | TAG_VARIANT | TAG_BLOB | TAG_BITS32 | TAG_BIGINT | TAG_CONCAT => {}
+                    _ => rts_trap_with("graph: invalid object tag"),
+                }
+                if objects.len == MAX_OBJECTS {
+                    rts_trap_with("graph: too many objects");
+                }
+                let value = mem.alloc_words(size_of::<Obj>() + n);
+                let obj = value.as_obj();
+                (*obj).tag = tag;
+                reader.read_bytes(obj.add(1) as *mut u8, n.to_bytes());
+                if tag == TAG_OBJECT {
+                    let object = obj as *mut Object;
+                    let number = (*object).hash_ptr;
+                    if number >= hashes.len {
+                        rts_trap_with("graph: invalid field hashes");
+                    }
+                    let hash_ptr = hashes.get(number);
+                    if *(unskew(hash_ptr as usize) as *const u32).sub(1) != object.size() {
+                        rts_trap_with("graph: invalid field hashes");
+                    }
+                    (*object).hash_ptr = hash_ptr;
+                }
+                if tag == TAG_BIGINT {
+                    if n < size_of::<BigInt>() - size_of::<Obj>() {
+                        rts_trap_with("graph: invalid object size");
+                    }
+                    check_bigint(obj as *mut BigInt, n);
+                }
+                if object_size(obj as usize) != size_of::<Obj>() + n {
+                    rts_trap_with("graph: invalid object size");
+                }
+                objects.push(mem, value.get_raw());
+            }
+
+            RECORD_HASHES => {
+                let n = read_word(reader);
+                if scratch.as_blob().len() < Words(n).to_bytes() {
+                    scratch = alloc_blob(mem, Words(n).to_bytes());
+                }
+                let words = scratch.as_blob_mut().payload_addr();
+                reader.read_bytes(words, Words(n).to_bytes());
+                let words = core::slice::from_raw_parts(words as *const u32, n as usize);
+                let hash_ptr = match find_static_hashes(statics, words) {
+                    Some(hash_ptr) => hash_ptr,
+                    None => pool.intern(words.as_ptr(), n),
+                };
+                hashes.push(mem, hash_ptr);
+            }
+
+            RECORD_NULL => objects.push(mem, null.get_raw()),
+
+            RECORD_END => break Value::from_raw(read_word(reader)),
+
+            _ => rts_trap_with("graph: invalid record"),
+        }
+    };
+
+    for number in 0..objects.len {
+        let value = Value::from_raw(objects.get(number));
+        if value.get_raw() == null.get_raw() {
+            continue;
+        }
+        let obj = value.as_obj();
+        visit_pointer_fields(
+            &mut objects,
+            obj,
+            obj.tag(),
+            0,
+            |objects, field_addr| *field_addr = resolve(objects, *field_addr),
+            |_, _, array| array.len(),
+        );
+    }
+
+    if root.is_ptr() {
+        resolve(&objects, root)
+    } else {
+        root
+    }
+}
+
+#[ic_mem_fn(ic_only)]
+unsafe fn graph_deserialize<M: Memory>(
+    mem: &mut M,
+    reader: *mut StreamReader,
+    null: Value,
+    statics: Value,
+) -> Value {
+    deserialize(mem, reader, null, statics, &mut HASH_POOL)
+}
@@ -3,14 +3,25 @@
 use crate::buf::{read_byte, read_word, skip_leb128, Buf};
 use crate::idl_trap_with;
 use crate::leb128::{leb128_decode, sleb128_decode};
+use crate::mem_utils::{memcpy_bytes, memzero};
 use crate::memory::{alloc_blob, Memory};
-use crate::types::Words;
+use crate::types::{Bytes, Value, Words};
 use crate::utf8::utf8_validate;
 
 use core::cmp::min;
 
 use motoko_rts_macros::ic_mem_fn;
 
+pub mod canonical;
+pub mod error;
+pub mod print;
+pub mod quota;
+pub mod subtype;
+pub mod value;
+
+use self::error::{idl_end_message, path_len, set_segment, set_type, truncate_path, Segment};
+use self::quota::{idl_charge_decoding, reset_decoding_quota};
+
 //
 // IDL constants
 //
@@ -44,6 +55,10 @@ const IDL_REF_principal: i32 = -24;
 
 const IDL_CON_alias: i32 = 1;
 
+const IDL_FUNC_ANN_query: u8 = 1;
+const IDL_FUNC_ANN_oneway: u8 = 2;
+const IDL_FUNC_ANN_composite_query: u8 = 3;
+
 const IDL_PRIM_lowest: i32 = -17;
 
 unsafe fn is_primitive_type(ty: i32) -> bool {
@@ -93,7 +108,7 @@ unsafe fn alloc<M: Memory>(mem: &mut M, size: Words<u32>) -> *mut u8 {
 /// * returns a pointer to the beginning of the list of main types
 ///   (again via pointer argument, for lack of multi-value returns in C ABI)
 #[ic_mem_fn]
-unsafe fn parse_idl_header<M: Memory>(
+pub unsafe fn parse_idl_header<M: Memory>(
     mem: &mut M,
     extended: bool,
     buf: *mut Buf,
@@ -107,6 +122,9 @@ unsafe fn parse_idl_header<M: Memory>(
         );
     }
 
+    error::start_message(buf);
+    reset_decoding_quota(((*buf).end as usize - (*buf).ptr as usize) as u32, extended);
+
     // Magic bytes (DIDL)
     if read_word(buf) != 0x4C444944 {
         idl_trap_with("missing magic bytes");
@@ -128,6 +146,7 @@ unsafe fn parse_idl_header<M: Memory>(
 
     // Go through the table
     for i in 0..n_types {
+        set_type(i as i32);
         *typtbl.add(i as usize) = (*buf).ptr;
 
         let ty = sleb128_decode(buf);
@@ -163,11 +182,12 @@ unsafe fn parse_idl_header<M: Memory>(
                 let t = sleb128_decode(buf);
                 check_typearg(t, n_types);
             }
-            // Annotations
+            // Annotations. Unlike type codes, they have no room for future extensions, as a
+            // decoder could not tell how to treat a reference with an unknown annotation.
             for _ in 0..leb128_decode(buf) {
-                let a = read_byte(buf);
-                if !(1 <= a && a <= 2) {
-                    idl_trap_with("func annotation not within 1..2");
+                match read_byte(buf) {
+                    IDL_FUNC_ANN_query | IDL_FUNC_ANN_oneway | IDL_FUNC_ANN_composite_query => {}
+                    _ => idl_trap_with("unknown func annotation"),
                 }
             }
         } else if ty == IDL_CON_service {
@@ -210,6 +230,7 @@ unsafe fn parse_idl_header<M: Memory>(
     // (We could not do that in the first run because of possible forward
     // references
     for i in 0..n_types {
+        set_type(i as i32);
         // do not modify the main buf
         let mut tmp_buf = Buf {
             end: (*buf).end,
@@ -239,12 +260,20 @@ unsafe fn parse_idl_header<M: Memory>(
         }
     }
 
+    if let Some(t) = find_non_productive_type(mem, typtbl, n_types, (*buf).end) {
+        set_type(t as i32);
+        idl_trap_with("non-productive recursive type");
+    }
+    set_type(-1);
+
     // Now read the main types
     *main_types_out = (*buf).ptr;
-    for _ in 0..leb128_decode(buf) {
+    for i in 0..leb128_decode(buf) {
+        set_segment(0, Segment::Arg(i));
         let t = sleb128_decode(buf);
         check_typearg(t, n_types);
     }
+    truncate_path(0);
 
     *typtbl_out = typtbl;
 }
@@ -258,6 +287,36 @@ unsafe fn read_byte_tag(buf: *mut Buf) -> u8 {
     b
 }
 
+/// Reads a (non-opaque) reference tag
+unsafe fn read_reference_tag(buf: *mut Buf) {
+    if read_byte_tag(buf) == 0 {
+        idl_trap_with("opaque reference");
+    }
+}
+
+/// Reads a little-endian number of `n` bytes
+unsafe fn read_fixed(buf: *mut Buf, n: u32) -> u64 {
+    let p = (*buf).ptr;
+    buf.advance(n);
+    (0..n as usize)
+        .rev()
+        .fold(0, |acc, i| acc << 8 | u64::from(*p.add(i)))
+}
+
+/// Copies a blob (e.g. a principal) to the heap
+unsafe fn read_blob<M: Memory>(mem: &mut M, buf: *mut Buf) -> Value {
+    let len = leb128_decode(buf);
+    let p = (*buf).ptr;
+    buf.advance(len);
+    let blob = alloc_blob(mem, Bytes(len));
+    memcpy_bytes(
+        blob.as_blob_mut().payload_addr() as usize,
+        p as usize,
+        Bytes(len),
+    );
+    blob
+}
+
 unsafe fn skip_blob(buf: *mut Buf) {
     let len = leb128_decode(buf);
     buf.advance(len);
@@ -270,158 +329,430 @@ unsafe fn skip_text(buf: *mut Buf) {
     utf8_validate(p as *const _, len);
 }
 
-unsafe fn skip_any_vec(buf: *mut Buf, typtbl: *mut *mut u8, t: i32, count: u32) {
-    if count == 0 {
-        return;
+/// Values nested deeper than this are not skipped, unless configured otherwise
+pub const DEFAULT_MAX_SKIP_DEPTH: u32 = 10_000;
+
+/// Skipping more values than this in one go traps, unless configured otherwise. Every nested
+/// value counts, except the elements of vectors of fixed-size primitive types, which are skipped
+/// at once.
+pub const DEFAULT_MAX_SKIP_WORK: u32 = 1 << 26;
+
+struct SkipLimits {
+    max_depth: u32,
+    max_work: u32,
+}
+
+static mut SKIP_LIMITS: SkipLimits = SkipLimits {
+    max_depth: DEFAULT_MAX_SKIP_DEPTH,
+    max_work: DEFAULT_MAX_SKIP_WORK,
+};
+
+/// Sets the limits of `skip_any`: the nesting depth of a skipped value, and the number of values
+/// skipped by one call (see `DEFAULT_MAX_SKIP_WORK`)
+#[no_mangle]
+pub unsafe extern "C" fn idl_set_skip_limits(max_depth: u32, max_work: u32) {
+    SKIP_LIMITS = SkipLimits {
+        max_depth,
+        max_work,
+    };
+}
+
+/// Size of the values of a primitive type that are skipped without looking at them
+fn fixed_size(t: i32) -> Option<u32> {
+    match t {
+        IDL_PRIM_null | IDL_PRIM_reserved => Some(0),
+        IDL_PRIM_nat8 | IDL_PRIM_int8 => Some(1),
+        IDL_PRIM_nat16 | IDL_PRIM_int16 => Some(2),
+        IDL_PRIM_nat32 | IDL_PRIM_int32 | IDL_PRIM_float32 => Some(4),
+        IDL_PRIM_nat64 | IDL_PRIM_int64 | IDL_PRIM_float64 => Some(8),
+        _ => None,
     }
-    let ptr_before = (*buf).ptr;
-    skip_any(buf, typtbl, t, 0);
-    let ptr_after = (*buf).ptr;
-    if ptr_after == ptr_before {
-        // this looks like a vec null bomb, or equivalent, where skip_any
-        // makes no progress. No point in calling it over and over again.
-        // (This is easier to detect this way than by analyzing the type table,
-        // where we’d have to chase single-field-records.)
+}
+
+unsafe fn skip_primitive(buf: *mut Buf, t: i32) {
+    if let Some(size) = fixed_size(t) {
+        buf.advance(size);
         return;
     }
-    for _ in 1..count {
-        skip_any(buf, typtbl, t, 0);
+    match t {
+        IDL_PRIM_bool => {
+            read_byte_tag(buf);
+        }
+        IDL_PRIM_nat | IDL_PRIM_int => {
+            skip_leb128(buf);
+        }
+        IDL_PRIM_text => skip_text(buf),
+        IDL_PRIM_empty => {
+            idl_trap_with("skip_any: encountered empty");
+        }
+        IDL_REF_principal => {
+            if read_byte_tag(buf) != 0 {
+                skip_blob(buf);
+            }
+        }
+        _ => {
+            idl_trap_with("skip_any: unknown prim");
+        }
+    }
+}
+
+/// The elements of a vector or the fields of a record that are still to be skipped
+#[derive(Clone, Copy)]
+struct SkipFrame {
+    /// Nesting depth of the elements
+    depth: u32,
+    remaining: u32,
+    /// Index of the next vector element
+    next: u32,
+    /// Type of the vector elements
+    elem: i32,
+    /// Type table of a record, at the next field; null for vectors
+    fields: *mut u8,
+    /// Data of the first vector element, until it is skipped; null otherwise
+    first: *mut u8,
+}
+
+/// Frames live on the native stack until they do not fit anymore, then in a blob
+const INLINE_SKIP_FRAMES: usize = 32;
+
+struct SkipStack {
+    frames: *mut SkipFrame,
+    capacity: u32,
+    len: u32,
+}
+
+impl SkipStack {
+    unsafe fn push<M: Memory>(&mut self, mem: &mut M, frame: SkipFrame) {
+        if self.len == self.capacity {
+            let capacity = self.capacity * 2;
+            let frame_size = core::mem::size_of::<SkipFrame>() as u32;
+            let frames = alloc(mem, Bytes(capacity * frame_size).to_words()) as *mut SkipFrame;
+            memcpy_bytes(
+                frames as usize,
+                self.frames as usize,
+                Bytes(self.len * frame_size),
+            );
+            self.frames = frames;
+            self.capacity = capacity;
+        }
+        *self.frames.add(self.len as usize) = frame;
+        self.len += 1;
     }
 }
 
 // Assumes buf is the encoding of type t, and fast-forwards past that
-// Assumes all type references in the typtbl are already checked
+// Assumes all type references in the typtbl are already checked, and that the type table has no
+// non-productive recursive types (see `find_non_productive_type`)
 //
-// This is currently implemented recursively, but we could
-// do this in a loop (by maintaing a stack of the t arguments)
-#[no_mangle]
-unsafe extern "C" fn skip_any(buf: *mut Buf, typtbl: *mut *mut u8, t: i32, depth: i32) {
-    if depth > 100 {
-        idl_trap_with("skip_any: too deeply nested record");
-    }
-
-    if t < 0 {
-        // Primitive type
-        match t {
-            IDL_PRIM_null | IDL_PRIM_reserved => {}
-            IDL_PRIM_bool => {
-                read_byte_tag(buf);
-            }
-            IDL_PRIM_nat | IDL_PRIM_int => {
-                skip_leb128(buf);
-            }
-            IDL_PRIM_nat8 | IDL_PRIM_int8 => {
-                buf.advance(1);
-            }
-            IDL_PRIM_nat16 | IDL_PRIM_int16 => {
-                buf.advance(2);
-            }
-            IDL_PRIM_nat32 | IDL_PRIM_int32 | IDL_PRIM_float32 => {
-                buf.advance(4);
-            }
-            IDL_PRIM_nat64 | IDL_PRIM_int64 | IDL_PRIM_float64 => {
-                buf.advance(8);
-            }
-            IDL_PRIM_text => skip_text(buf),
-            IDL_PRIM_empty => {
-                idl_trap_with("skip_any: encountered empty");
-            }
-            IDL_REF_principal => {
-                if read_byte_tag(buf) != 0 {
-                    skip_blob(buf);
-                }
-            }
-            _ => {
-                idl_trap_with("skip_any: unknown prim");
-            }
+// This does not recurse: nested values are found in a stack of the vectors and records that are
+// still being skipped. `depth` is the nesting depth of the value itself.
+#[ic_mem_fn]
+pub unsafe fn skip_any<M: Memory>(
+    mem: &mut M,
+    buf: *mut Buf,
+    typtbl: *mut *mut u8,
+    mut t: i32,
+    depth: i32,
+) {
+    let max_depth = SKIP_LIMITS.max_depth;
+    let max_work = SKIP_LIMITS.max_work;
+
+    let mut inline_frames = [SkipFrame {
+        depth: 0,
+        remaining: 0,
+        next: 0,
+        elem: 0,
+        fields: core::ptr::null_mut(),
+        first: core::ptr::null_mut(),
+    }; INLINE_SKIP_FRAMES];
+    let mut stack = SkipStack {
+        frames: inline_frames.as_mut_ptr(),
+        capacity: INLINE_SKIP_FRAMES as u32,
+        len: 0,
+    };
+
+    let mut depth = depth as u32;
+    let mut work: u32 = 0;
+
+    // The path to the skipped value is extended by the path within it (see `idl::error`)
+    let path_base = path_len();
+    let start_depth = depth;
+    let segment =
+        |depth: u32, segment: Segment| set_segment(path_base + (depth - start_depth) - 1, segment);
+
+    loop {
+        // Skip a value of type `t`, or push a frame for its elements
+        if depth > max_depth {
+            idl_trap_with("skip_any: too deeply nested");
         }
-    } else {
-        // t >= 0
-        let mut tb = Buf {
-            ptr: *typtbl.add(t as usize),
-            end: (*buf).end,
-        };
-        let tc = sleb128_decode(&mut tb);
-        match tc {
-            IDL_CON_opt => {
-                let it = sleb128_decode(&mut tb);
-                if read_byte_tag(buf) != 0 {
-                    skip_any(buf, typtbl, it, 0);
+        work += 1;
+        if work > max_work {
+            idl_trap_with("skip_any: too much work");
+        }
+        idl_charge_decoding(1);
+        set_type(t);
+
+        if t < 0 {
+            skip_primitive(buf, t);
+        } else {
+            // t >= 0
+            let mut tb = Buf {
+                ptr: *typtbl.add(t as usize),
+                end: (*buf).end,
+            };
+            let tc = sleb128_decode(&mut tb);
+            match tc {
+                IDL_CON_opt => {
+                    let it = sleb128_decode(&mut tb);
+                    if read_byte_tag(buf) != 0 {
+                        t = it;
+                        depth += 1;
+                        segment(depth, Segment::Opt);
+                        continue;
+                    }
                 }
-            }
-            IDL_CON_vec => {
-                let it = sleb128_decode(&mut tb);
-                let count = leb128_decode(buf);
-                skip_any_vec(buf, typtbl, it, count);
-            }
-            IDL_CON_record => {
-                for _ in 0..leb128_decode(&mut tb) {
-                    skip_leb128(&mut tb);
+                IDL_CON_vec => {
                     let it = sleb128_decode(&mut tb);
-                    // This is just a quick check; we should be keeping
-                    // track of all enclosing records to detect larger loops
-                    if it == t {
-                        idl_trap_with("skip_any: recursive record");
+                    let count = leb128_decode(buf);
+                    if let Some(size) = fixed_size(it) {
+                        let size = u64::from(size) * u64::from(count);
+                        if size > ((*buf).end as usize - (*buf).ptr as usize) as u64 {
+                            idl_trap_with("skip_any: vec out of buffer");
+                        }
+                        buf.advance(size as u32);
+                    } else if count > 0 {
+                        // Skip the first element right away, and remember where it started
+                        stack.push(
+                            mem,
+                            SkipFrame {
+                                depth: depth + 1,
+                                remaining: count - 1,
+                                next: 1,
+                                elem: it,
+                                fields: core::ptr::null_mut(),
+                                first: (*buf).ptr,
+                            },
+                        );
+                        t = it;
+                        depth += 1;
+                        segment(depth, Segment::Elem(0));
+                        continue;
                     }
-                    skip_any(buf, typtbl, it, depth + 1);
                 }
-            }
-            IDL_CON_variant => {
-                let n = leb128_decode(&mut tb);
-                let i = leb128_decode(buf);
-                if i >= n {
-                    idl_trap_with("skip_any: variant tag too large");
+                IDL_CON_record => {
+                    let n = leb128_decode(&mut tb);
+                    if n > 0 {
+                        stack.push(
+                            mem,
+                            SkipFrame {
+                                depth: depth + 1,
+                                remaining: n,
+                                next: 0,
+                                elem: 0,
+                                fields: tb.ptr,
+                                first: core::ptr::null_mut(),
+                            },
+                        );
+                    }
                 }
-                for _ in 0..i {
-                    skip_leb128(&mut tb);
-                    skip_leb128(&mut tb);
+                IDL_CON_variant => {
+                    let n = leb128_decode(&mut tb);
+                    let i = leb128_decode(buf);
+                    if i >= n {
+                        idl_trap_with("skip_any: variant tag too large");
+                    }
+                    for _ in 0..i {
+                        skip_leb128(&mut tb);
+                        skip_leb128(&mut tb);
+                    }
+                    let id = leb128_decode(&mut tb);
+                    t = sleb128_decode(&mut tb);
+                    depth += 1;
+                    segment(depth, Segment::Variant(id));
+                    continue;
                 }
-                skip_leb128(&mut tb);
-                let it = sleb128_decode(&mut tb);
-                skip_any(buf, typtbl, it, 0);
-            }
-            IDL_CON_func => {
-                if read_byte_tag(buf) == 0 {
-                    idl_trap_with("skip_any: skipping references");
-                } else {
+                IDL_CON_func => {
+                    if read_byte_tag(buf) == 0 {
+                        idl_trap_with("skip_any: skipping references");
+                    } else {
+                        if read_byte_tag(buf) == 0 {
+                            idl_trap_with("skip_any: skipping references");
+                        } else {
+                            skip_blob(buf)
+                        }
+                        skip_text(buf)
+                    }
+                }
+                IDL_CON_service => {
                     if read_byte_tag(buf) == 0 {
                         idl_trap_with("skip_any: skipping references");
                     } else {
                         skip_blob(buf)
                     }
-                    skip_text(buf)
                 }
-            }
-            IDL_CON_service => {
-                if read_byte_tag(buf) == 0 {
-                    idl_trap_with("skip_any: skipping references");
-                } else {
-                    skip_blob(buf)
+                IDL_CON_alias => {
+                    // See Note [mutable stable values] in codegen/compile.ml
+                    let it = sleb128_decode(&mut tb);
+                    let tag = read_byte_tag(buf);
+                    if tag == 0 {
+                        buf.advance(8);
+                        // this is the contents (not a reference)
+                        t = it;
+                        depth += 1;
+                        segment(depth, Segment::Var);
+                        continue;
+                    } else {
+                        buf.advance(4);
+                    }
+                }
+                _ => {
+                    // Future type
+                    let n_data = leb128_decode(buf);
+                    let n_ref = leb128_decode(buf);
+                    buf.advance(n_data);
+                    if n_ref > 0 {
+                        idl_trap_with("skip_any: skipping references");
+                    }
                 }
             }
-            IDL_CON_alias => {
-                // See Note [mutable stable values] in codegen/compile.ml
-                let it = sleb128_decode(&mut tb);
-                let tag = read_byte_tag(buf);
-                if tag == 0 {
-                    buf.advance(8);
-                    // this is the contents (not a reference)
-                    skip_any(buf, typtbl, it, 0);
-                } else {
-                    buf.advance(4);
+        }
+
+        // Find the next value to skip
+        loop {
+            if stack.len == 0 {
+                truncate_path(path_base);
+                return;
+            }
+            let frame = stack.frames.add(stack.len as usize - 1);
+            if !(*frame).first.is_null() {
+                if (*frame).first == (*buf).ptr {
+                    // this looks like a vec null bomb, or equivalent, where the first element
+                    // took no input. No point in skipping the others.
+                    (*frame).remaining = 0;
                 }
+                (*frame).first = core::ptr::null_mut();
+            }
+            if (*frame).remaining == 0 {
+                stack.len -= 1;
+                continue;
+            }
+            (*frame).remaining -= 1;
+            depth = (*frame).depth;
+            if (*frame).fields.is_null() {
+                t = (*frame).elem;
+                segment(depth, Segment::Elem((*frame).next));
+                (*frame).next += 1;
+            } else {
+                let mut tb = Buf {
+                    ptr: (*frame).fields,
+                    end: (*buf).end,
+                };
+                let id = leb128_decode(&mut tb);
+                t = sleb128_decode(&mut tb);
+                (*frame).fields = tb.ptr;
+                segment(depth, Segment::Field(id));
             }
-            _ => {
-                // Future type
-                let n_data = leb128_decode(buf);
-                let n_ref = leb128_decode(buf);
-                buf.advance(n_data);
-                if n_ref > 0 {
-                    idl_trap_with("skip_any: skipping references");
+            break;
+        }
+    }
+}
+
+/// Finds a non-productive recursive type in the type table, like `type t = record { t }`, which
+/// contains itself through record fields only. Its values would nest forever without taking any
+/// input, as all other type constructors take at least one byte of a value.
+pub unsafe fn find_non_productive_type<M: Memory>(
+    mem: &mut M,
+    typtbl: *mut *mut u8,
+    n_types: u32,
+    end: *mut u8,
+) -> Option<u32> {
+    const UNVISITED: u8 = 0;
+    const VISITING: u8 = 1;
+    const VISITED: u8 = 2;
+
+    /// A record type, whose fields are being visited
+    struct Visit {
+        t: u32,
+        remaining: u32,
+        fields: *mut u8,
+    }
+
+    let fields_of_record = |t: u32| {
+        let mut tb = Buf {
+            ptr: *typtbl.add(t as usize),
+            end,
+        };
+        if sleb128_decode(&mut tb) == IDL_CON_record {
+            let n = leb128_decode(&mut tb);
+            Some((n, tb.ptr))
+        } else {
+            None
+        }
+    };
+
+    let states = alloc(mem, Bytes(n_types).to_words());
+    memzero(states as usize, Bytes(n_types).to_words());
+    let stack = alloc(
+        mem,
+        Bytes(n_types * core::mem::size_of::<Visit>() as u32).to_words(),
+    ) as *mut Visit;
+
+    // Depth-first search, each type is pushed at most once
+    for root in 0..n_types {
+        if *states.add(root as usize) != UNVISITED {
+            continue;
+        }
+        let (remaining, fields) = match fields_of_record(root) {
+            Some(fields) => fields,
+            None => continue,
+        };
+        *states.add(root as usize) = VISITING;
+        *stack = Visit {
+            t: root,
+            remaining,
+            fields,
+        };
+        let mut len = 1;
+
+        while len > 0 {
+            let top = stack.add(len - 1);
+            if (*top).remaining == 0 {
+                *states.add((*top).t as usize) = VISITED;
+                len -= 1;
+                continue;
+            }
+            (*top).remaining -= 1;
+            let mut tb = Buf {
+                ptr: (*top).fields,
+                end,
+            };
+            skip_leb128(&mut tb);
+            let it = sleb128_decode(&mut tb);
+            (*top).fields = tb.ptr;
+
+            if it < 0 {
+                continue;
+            }
+            let it = it as u32;
+            match *states.add(it as usize) {
+                VISITING => return Some(it),
+                VISITED => {}
+                _ => {
+                    if let Some((remaining, fields)) = fields_of_record(it) {
+                        *states.add(it as usize) = VISITING;
+                        *stack.add(len) = Visit {
+                            t: it,
+                            remaining,
+                            fields,
+                        };
+                        len += 1;
+                    }
                 }
             }
         }
     }
+
+    None
 }
 
 /*
@@ -451,8 +782,9 @@ If the tag does not exist:
          or at the value past the record
   n:     the number of fields left, including the field pointed to by tb
 */
-#[no_mangle]
-unsafe extern "C" fn find_field(
+#[ic_mem_fn]
+pub unsafe fn find_field<M: Memory>(
+    mem: &mut M,
     tb: *mut Buf,
     buf: *mut Buf,
     typtbl: *mut *mut u8,
@@ -464,7 +796,7 @@ unsafe extern "C" fn find_field(
         let this_tag = leb128_decode(tb);
         if this_tag < tag {
             let it = sleb128_decode(tb);
-            skip_any(buf, typtbl, it, 0);
+            skip_any(mem, buf, typtbl, it, 0);
             *n -= 1;
         } else if tag == this_tag {
             *n -= 1;
@@ -479,12 +811,18 @@ unsafe extern "C" fn find_field(
     0
 }
 
-#[no_mangle]
-unsafe extern "C" fn skip_fields(tb: *mut Buf, buf: *mut Buf, typtbl: *mut *mut u8, n: *mut u8) {
+#[ic_mem_fn]
+pub unsafe fn skip_fields<M: Memory>(
+    mem: &mut M,
+    tb: *mut Buf,
+    buf: *mut Buf,
+    typtbl: *mut *mut u8,
+    n: *mut u8,
+) {
     while *n > 0 {
         skip_leb128(tb);
         let it = sleb128_decode(tb);
-        skip_any(buf, typtbl, it, 0);
+        skip_any(mem, buf, typtbl, it, 0);
         *n -= 1;
     }
 }
@@ -0,0 +1,497 @@
+//! Canonical form and structural hash of Candid type tables
+//!
+//! The same types can be described by many type tables: entries can come in any order, a type can
+//! be described by several equivalent entries, recursive types can be unrolled any number of
+//! times, and the table can have entries that no main type refers to. To compare the interface a
+//! canister was built with against the types of a message or of a stable-variable snapshot, the
+//! RTS computes a canonical form of the header of the message:
+//!
+//! * Equivalent entries are merged, by partition refinement: entries start out in one class, and
+//!   classes are split by the contents of their entries, with the types they refer to replaced by
+//!   their classes, until no class splits. Entries of the same final class describe the same
+//!   types (they are bisimilar). The classes are numbered by rank of their contents, so the
+//!   numbering does not depend on the order of the table.
+//!
+//! * The canonical table has one entry per class that is reachable from the main types, numbered
+//!   in breadth-first order from the main types, in order.
+//!
+//! The canonical form is itself a valid Candid header, which parses back to the same canonical
+//! form. The `extended` alias entries of stable variables are kept as constructors of their own,
+//! so a mutable and an immutable stable variable do not compare equal. The structural hash is a
+//! 64-bit FNV-1a hash of the canonical form.
+//!
+//! Function annotations and the contents of future types are compared byte by byte, as they are
+//! on the wire.
+
+use super::*;
+
+use core::cmp::Ordering;
+
+/// Visits the contents of a type table entry, in the order of its encoding
+trait EntryVisitor {
+    /// Type code of the entry
+    unsafe fn code(&mut self, tc: i32);
+    /// Counts and field ids
+    unsafe fn num(&mut self, n: u32);
+    /// Method names, function annotations, and the contents of future types
+    unsafe fn bytes(&mut self, bytes: &[u8]);
+    /// Type arguments
+    unsafe fn typ(&mut self, t: i32);
+}
+
+unsafe fn visit_entry<V: EntryVisitor>(mut tb: Buf, visitor: &mut V) {
+    let tc = sleb128_decode(&mut tb);
+    visitor.code(tc);
+    match tc {
+        IDL_CON_opt | IDL_CON_vec | IDL_CON_alias => visitor.typ(sleb128_decode(&mut tb)),
+        IDL_CON_record | IDL_CON_variant => {
+            let n = leb128_decode(&mut tb);
+            visitor.num(n);
+            for _ in 0..n {
+                visitor.num(leb128_decode(&mut tb));
+                visitor.typ(sleb128_decode(&mut tb));
+            }
+        }
+        IDL_CON_func => {
+            // Arguments and results
+            for _ in 0..2 {
+                let n = leb128_decode(&mut tb);
+                visitor.num(n);
+                for _ in 0..n {
+                    visitor.typ(sleb128_decode(&mut tb));
+                }
+            }
+            visit_bytes(&mut tb, visitor);
+        }
+        IDL_CON_service => {
+            let n = leb128_decode(&mut tb);
+            visitor.num(n);
+            for _ in 0..n {
+                visit_bytes(&mut tb, visitor);
+                visitor.typ(sleb128_decode(&mut tb));
+            }
+        }
+        _ => visit_bytes(&mut tb, visitor),
+    }
+}
+
+/// Visits a length and as many bytes
+unsafe fn visit_bytes<V: EntryVisitor>(tb: *mut Buf, visitor: &mut V) {
+    let len = leb128_decode(tb);
+    visitor.num(len);
+    visitor.bytes(core::slice::from_raw_parts((*tb).ptr, len as usize));
+    tb.advance(len);
+}
+
+/// Flattens an entry into its type arguments and everything else (its "shape"), which together
+/// determine the entry. Only counts when the arrays are null.
+struct Flattener {
+    shape: *mut u32,
+    shape_len: u32,
+    args: *mut i32,
+    args_len: u32,
+}
+
+impl Flattener {
+    unsafe fn push_shape(&mut self, word: u32) {
+        if !self.shape.is_null() {
+            *self.shape.add(self.shape_len as usize) = word;
+        }
+        self.shape_len += 1;
+    }
+}
+
+impl EntryVisitor for Flattener {
+    unsafe fn code(&mut self, tc: i32) {
+        self.push_shape(tc as u32);
+    }
+
+    unsafe fn num(&mut self, n: u32) {
+        self.push_shape(n);
+    }
+
+    unsafe fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.push_shape(u32::from(*byte));
+        }
+    }
+
+    unsafe fn typ(&mut self, t: i32) {
+        if !self.args.is_null() {
+            *self.args.add(self.args_len as usize) = t;
+        }
+        self.args_len += 1;
+    }
+}
+
+/// Entries of a type table, flattened, and their classes
+struct Partition {
+    n_types: u32,
+    /// Entry `i` has the shape `shape[shape_start[i]..shape_start[i + 1]]`
+    shape_start: *mut u32,
+    shape: *mut u32,
+    /// Entry `i` has the type arguments `args[args_start[i]..args_start[i + 1]]`
+    args_start: *mut u32,
+    args: *mut i32,
+    class: *mut u32,
+}
+
+impl Partition {
+    unsafe fn new<M: Memory>(
+        mem: &mut M,
+        typtbl: *mut *mut u8,
+        n_types: u32,
+        end: *mut u8,
+    ) -> Partition {
+        let entry = |i: u32| Buf {
+            ptr: *typtbl.add(i as usize),
+            end,
+        };
+
+        // Count, then flatten
+        let mut flattener = Flattener {
+            shape: core::ptr::null_mut(),
+            shape_len: 0,
+            args: core::ptr::null_mut(),
+            args_len: 0,
+        };
+        for i in 0..n_types {
+            visit_entry(entry(i), &mut flattener);
+        }
+
+        let partition = Partition {
+            n_types,
+            shape_start: alloc(mem, Words(n_types + 1)) as *mut u32,
+            shape: alloc(mem, Words(flattener.shape_len)) as *mut u32,
+            args_start: alloc(mem, Words(n_types + 1)) as *mut u32,
+            args: alloc(mem, Words(flattener.args_len)) as *mut i32,
+            class: alloc(mem, Words(n_types)) as *mut u32,
+        };
+
+        let mut flattener = Flattener {
+            shape: partition.shape,
+            shape_len: 0,
+            args: partition.args,
+            args_len: 0,
+        };
+        for i in 0..n_types {
+            *partition.shape_start.add(i as usize) = flattener.shape_len;
+            *partition.args_start.add(i as usize) = flattener.args_len;
+            visit_entry(entry(i), &mut flattener);
+            *partition.class.add(i as usize) = 0;
+        }
+        *partition.shape_start.add(n_types as usize) = flattener.shape_len;
+        *partition.args_start.add(n_types as usize) = flattener.args_len;
+
+        partition
+    }
+
+    unsafe fn shape(&self, i: u32) -> &[u32] {
+        let start = *self.shape_start.add(i as usize);
+        let end = *self.shape_start.add(i as usize + 1);
+        core::slice::from_raw_parts(self.shape.add(start as usize), (end - start) as usize)
+    }
+
+    unsafe fn args(&self, i: u32) -> &[i32] {
+        let start = *self.args_start.add(i as usize);
+        let end = *self.args_start.add(i as usize + 1);
+        core::slice::from_raw_parts(self.args.add(start as usize), (end - start) as usize)
+    }
+
+    unsafe fn class(&self, t: i32) -> u32 {
+        *self.class.add(t as usize)
+    }
+
+    /// A type argument with a type index replaced by its class. Primitive types are negative and
+    /// stay apart from the classes.
+    unsafe fn key(&self, t: i32) -> i64 {
+        if t >= 0 {
+            i64::from(self.class(t))
+        } else {
+            i64::from(t)
+        }
+    }
+
+    /// Orders entries by their class, then their shape, then their type arguments
+    unsafe fn compare(&self, i: u32, j: u32) -> Ordering {
+        self.class(i as i32)
+            .cmp(&self.class(j as i32))
+            .then_with(|| self.shape(i).cmp(self.shape(j)))
+            .then_with(|| {
+                let args_i = self.args(i).iter().map(|t| self.key(*t));
+                let args_j = self.args(j).iter().map(|t| self.key(*t));
+                args_i.cmp(args_j)
+            })
+    }
+
+    /// Splits the classes until they are stable, and returns their number
+    unsafe fn refine<M: Memory>(&mut self, mem: &mut M) -> u32 {
+        let n_types = self.n_types;
+        if n_types == 0 {
+            return 0;
+        }
+
+        let order = alloc(mem, Words(n_types)) as *mut u32;
+        let order = core::slice::from_raw_parts_mut(order, n_types as usize);
+        let mut new_class = alloc(mem, Words(n_types)) as *mut u32;
+
+        let mut n_classes = 1;
+        loop {
+            for (i, entry) in order.iter_mut().enumerate() {
+                *entry = i as u32;
+            }
+            order.sort_unstable_by(|i, j| self.compare(*i, *j));
+
+            // Classes are numbered by rank, which only depends on the structure of the entries
+            let mut class = 0;
+            for k in 0..order.len() {
+                if k > 0 && self.compare(order[k - 1], order[k]) != Ordering::Equal {
+                    class += 1;
+                }
+                *new_class.add(order[k] as usize) = class;
+            }
+
+            core::mem::swap(&mut self.class, &mut new_class);
+
+            // Classes only split, so they are stable when their number is
+            if class + 1 == n_classes {
+                return n_classes;
+            }
+            n_classes = class + 1;
+        }
+    }
+}
+
+/// Writes a Candid header. Only counts when `out` is null.
+struct Writer {
+    out: *mut u8,
+    len: u32,
+    /// Class of each entry of the original table
+    class: *const u32,
+    /// Index of each class in the canonical table
+    canonical: *const u32,
+}
+
+impl Writer {
+    unsafe fn byte(&mut self, byte: u8) {
+        if !self.out.is_null() {
+            *self.out.add(self.len as usize) = byte;
+        }
+        self.len += 1;
+    }
+
+    unsafe fn leb128(&mut self, mut n: u32) {
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                self.byte(byte);
+                return;
+            }
+            self.byte(byte | 0x80);
+        }
+    }
+
+    unsafe fn sleb128(&mut self, mut n: i32) {
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if (n == 0 && byte & 0x40 == 0) || (n == -1 && byte & 0x40 != 0) {
+                self.byte(byte);
+                return;
+            }
+            self.byte(byte | 0x80);
+        }
+    }
+}
+
+impl EntryVisitor for Writer {
+    unsafe fn code(&mut self, tc: i32) {
+        self.sleb128(tc);
+    }
+
+    unsafe fn num(&mut self, n: u32) {
+        self.leb128(n);
+    }
+
+    unsafe fn bytes(&mut self, bytes: &[u8]) {
+        for byte in bytes {
+            self.byte(*byte);
+        }
+    }
+
+    unsafe fn typ(&mut self, t: i32) {
+        if t >= 0 {
+            let class = *self.class.add(t as usize);
+            self.sleb128(*self.canonical.add(class as usize) as i32);
+        } else {
+            self.sleb128(t);
+        }
+    }
+}
+
+/// Canonical form of a Candid header, see the module documentation: a blob with the header
+/// `DIDL`, the canonical type table and the main types. `typtbl` and `n_types` are the type table
+/// returned by `parse_idl_header` for a buffer ending at `end`, and `main_types` are its main
+/// types.
+pub unsafe fn canonical_header<M: Memory>(
+    mem: &mut M,
+    typtbl: *mut *mut u8,
+    n_types: u32,
+    end: *mut u8,
+    main_types: *mut u8,
+) -> Value {
+    let mut partition = Partition::new(mem, typtbl, n_types, end);
+    let n_classes = partition.refine(mem);
+
+    // An entry of each class, the canonical index of each class, and the classes in canonical
+    // order
+    let entry_of_class = alloc(mem, Words(n_classes)) as *mut u32;
+    for i in 0..n_types {
+        *entry_of_class.add(partition.class(i as i32) as usize) = i;
+    }
+    let canonical = alloc(mem, Words(n_classes)) as *mut u32;
+    for class in 0..n_classes {
+        *canonical.add(class as usize) = u32::MAX;
+    }
+    let queue = alloc(mem, Words(n_classes)) as *mut u32;
+    let mut n_canonical = 0;
+
+    let mut main_buf = Buf {
+        ptr: main_types,
+        end,
+    };
+    for _ in 0..leb128_decode(&mut main_buf) {
+        let t = sleb128_decode(&mut main_buf);
+        reach(&partition, canonical, queue, &mut n_canonical, t);
+    }
+    let mut next = 0;
+    while next < n_canonical {
+        let i = *entry_of_class.add(*queue.add(next as usize) as usize);
+        for t in partition.args(i) {
+            reach(&partition, canonical, queue, &mut n_canonical, *t);
+        }
+        next += 1;
+    }
+
+    // Measure, then write
+    let mut writer = Writer {
+        out: core::ptr::null_mut(),
+        len: 0,
+        class: partition.class,
+        canonical,
+    };
+    write_header(
+        &mut writer,
+        typtbl,
+        end,
+        main_types,
+        entry_of_class,
+        queue,
+        n_canonical,
+    );
+    let header = alloc_blob(mem, Bytes(writer.len));
+    writer.out = header.as_blob_mut().payload_addr();
+    writer.len = 0;
+    write_header(
+        &mut writer,
+        typtbl,
+        end,
+        main_types,
+        entry_of_class,
+        queue,
+        n_canonical,
+    );
+    header
+}
+
+/// Numbers the class of type `t` and queues it, unless it is a primitive type or already queued
+unsafe fn reach(
+    partition: &Partition,
+    canonical: *mut u32,
+    queue: *mut u32,
+    n_canonical: &mut u32,
+    t: i32,
+) {
+    if t >= 0 {
+        let class = partition.class(t);
+        if *canonical.add(class as usize) == u32::MAX {
+            *canonical.add(class as usize) = *n_canonical;
+            *queue.add(*n_canonical as usize) = class;
+            *n_canonical += 1;
+        }
+    }
+}
+
+unsafe fn write_header(
+    writer: &mut Writer,
+    typtbl: *mut *mut u8,
+    end: *mut u8,
+    main_types: *mut u8,
+    entry_of_class: *const u32,
+    queue: *const u32,
+    n_canonical: u32,
+) {
+    writer.bytes(b"DIDL");
+    writer.leb128(n_canonical);
+    for k in 0..n_canonical {
+        let i = *entry_of_class.add(*queue.add(k as usize) as usize);
+        let entry = Buf {
+            ptr: *typtbl.add(i as usize),
+            end,
+        };
+        visit_entry(entry, writer);
+    }
+
+    let mut main_buf = Buf {
+        ptr: main_types,
+        end,
+    };
+    let n_args = leb128_decode(&mut main_buf);
+    writer.leb128(n_args);
+    for _ in 0..n_args {
+        writer.typ(sleb128_decode(&mut main_buf));
+    }
+}
+
+/// 64-bit FNV-1a hash of a canonical header
+pub fn structural_hash(header: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in header {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+/// Parses the Candid header read by `buf` like `parse_idl_header`, which leaves `buf` at the
+/// values, and returns its canonical form. Unlike decoding, this ends the message, so that IDL
+/// errors do not report the context of `buf` afterwards.
+#[ic_mem_fn]
+pub unsafe fn idl_canonical_header<M: Memory>(mem: &mut M, extended: bool, buf: *mut Buf) -> Value {
+    let mut typtbl = core::ptr::null_mut();
+    let mut n_types = 0;
+    let mut main_types = core::ptr::null_mut();
+    parse_idl_header(
+        mem,
+        extended,
+        buf,
+        &mut typtbl,
+        &mut n_types,
+        &mut main_types,
+    );
+    let header = canonical_header(mem, typtbl, n_types, (*buf).end, main_types);
+    error::idl_end_message();
+    header
+}
+
+/// Parses the Candid header read by `buf` like `parse_idl_header`, which leaves `buf` at the
+/// values, and returns the structural hash of its canonical form
+#[ic_mem_fn]
+pub unsafe fn idl_structural_hash<M: Memory>(mem: &mut M, extended: bool, buf: *mut Buf) -> u64 {
+    let header = idl_canonical_header(mem, extended, buf).as_blob();
+    structural_hash(core::slice::from_raw_parts(
+        header.payload_const(),
+        header.len().as_usize(),
+    ))
+}
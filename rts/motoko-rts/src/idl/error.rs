@@ -0,0 +1,205 @@
+//! Context of IDL errors
+//!
+//! An IDL error reports where in the message it happened: the byte offset in the message, the
+//! entry of the type table that was being parsed or the type of the value that was being decoded,
+//! and the path to that value, like `arg 0 → field 3 → vec[12]`. The parser and the decoders
+//! record this context as they go, and `idl_trap_with` renders it into the trap message.
+//!
+//! Generated code marks the arguments it decodes with `idl_error_arg`, and the values within them
+//! with the `idl_error_push_*` functions and `idl_error_pop`. Its errors go through `idl_trap`.
+
+use crate::buf::Buf;
+use crate::idl_trap_with;
+
+use core::fmt::{self, Write};
+
+/// A step in the path from the arguments of a message to a value
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Segment {
+    /// Argument of the message
+    Arg(u32),
+    /// Record field, by its id
+    Field(u32),
+    /// Vector element, by its index
+    Elem(u32),
+    /// Contents of an option
+    Opt,
+    /// Contents of a variant, by the id of its field
+    Variant(u32),
+    /// Contents of a mutable stable value
+    Var,
+}
+
+impl fmt::Display for Segment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Segment::Arg(i) => write!(f, "arg {}", i),
+            Segment::Field(id) => write!(f, "field {}", id),
+            Segment::Elem(i) => write!(f, "vec[{}]", i),
+            Segment::Opt => f.write_str("opt"),
+            Segment::Variant(id) => write!(f, "variant {}", id),
+            Segment::Var => f.write_str("var"),
+        }
+    }
+}
+
+/// Only the outermost segments of deeper paths are kept
+const MAX_PATH: usize = 32;
+
+struct Context {
+    /// Start and end of the message, null when no message is being decoded
+    start: *const u8,
+    end: *const u8,
+    /// The buffer reading the message
+    buf: *const Buf,
+    /// Entry of the type table, if any
+    typ: Option<u32>,
+    path: [Segment; MAX_PATH],
+    path_len: u32,
+}
+
+static mut CONTEXT: Context = Context {
+    start: core::ptr::null(),
+    end: core::ptr::null(),
+    buf: core::ptr::null(),
+    typ: None,
+    path: [Segment::Opt; MAX_PATH],
+    path_len: 0,
+};
+
+/// Starts recording the context of the message read by `buf`
+pub unsafe fn start_message(buf: *const Buf) {
+    CONTEXT.start = (*buf).ptr;
+    CONTEXT.end = (*buf).end;
+    CONTEXT.buf = buf;
+    CONTEXT.typ = None;
+    CONTEXT.path_len = 0;
+}
+
+/// Stops recording the context, after the message is decoded
+#[no_mangle]
+pub unsafe extern "C" fn idl_end_message() {
+    CONTEXT.start = core::ptr::null();
+    CONTEXT.end = core::ptr::null();
+    CONTEXT.buf = core::ptr::null();
+    CONTEXT.typ = None;
+    CONTEXT.path_len = 0;
+}
+
+/// Records the type being parsed or decoded: an index into the type table, or a primitive type
+pub unsafe fn set_type(t: i32) {
+    CONTEXT.typ = if t >= 0 { Some(t as u32) } else { None };
+}
+
+/// Length of the path
+pub unsafe fn path_len() -> u32 {
+    CONTEXT.path_len
+}
+
+/// Sets the segment of the path at `depth`, and drops the deeper ones
+pub unsafe fn set_segment(depth: u32, segment: Segment) {
+    if (depth as usize) < MAX_PATH {
+        CONTEXT.path[depth as usize] = segment;
+    }
+    CONTEXT.path_len = depth + 1;
+}
+
+/// Drops the segments of the path from `depth` on
+pub unsafe fn truncate_path(depth: u32) {
+    CONTEXT.path_len = depth;
+}
+
+/// Marks the decoding of argument `i` of a message, by generated code
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_arg(i: u32) {
+    set_segment(0, Segment::Arg(i));
+}
+
+unsafe fn push(segment: Segment) {
+    set_segment(CONTEXT.path_len, segment);
+}
+
+/// Marks the decoding of a record field by generated code, until `idl_error_pop`
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_push_field(id: u32) {
+    push(Segment::Field(id));
+}
+
+/// Marks the decoding of the elements of a vector by generated code, until `idl_error_pop`. The
+/// index of the element is set by `idl_error_set_elem`.
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_push_elem() {
+    push(Segment::Elem(0));
+}
+
+/// Sets the index of the vector element being decoded, see `idl_error_push_elem`
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_set_elem(i: u32) {
+    set_segment(CONTEXT.path_len - 1, Segment::Elem(i));
+}
+
+/// Marks the decoding of the contents of an option by generated code, until `idl_error_pop`
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_push_opt() {
+    push(Segment::Opt);
+}
+
+/// Marks the decoding of the contents of a variant by generated code, until `idl_error_pop`
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_push_variant(id: u32) {
+    push(Segment::Variant(id));
+}
+
+/// Marks the decoding of the contents of a mutable stable value by generated code, until
+/// `idl_error_pop`
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_push_var() {
+    push(Segment::Var);
+}
+
+/// Drops the innermost segment of the path, after generated code decoded the value
+#[no_mangle]
+pub unsafe extern "C" fn idl_error_pop() {
+    truncate_path(CONTEXT.path_len.saturating_sub(1));
+}
+
+/// Traps with an IDL error of generated code, adding the context like `idl_trap_with`. The message
+/// is UTF-8, without the `IDL error: ` prefix.
+#[no_mangle]
+pub unsafe extern "C" fn idl_trap(msg: *const u8, len: u32) -> ! {
+    let msg = core::slice::from_raw_parts(msg, len as usize);
+    idl_trap_with(core::str::from_utf8_unchecked(msg))
+}
+
+/// Renders the context, as ` (at byte 42, type 3, in arg 0 → field 3 → vec[12])`
+pub unsafe fn write_context<W: Write>(w: &mut W) -> fmt::Result {
+    if CONTEXT.start.is_null() {
+        return Ok(());
+    }
+
+    w.write_str(" (")?;
+    // The buffer may be out of date if generated code failed to end the message
+    let ptr = (*CONTEXT.buf).ptr as *const u8;
+    if CONTEXT.start <= ptr && ptr <= CONTEXT.end {
+        write!(w, "at byte {}", ptr as usize - CONTEXT.start as usize)?;
+    } else {
+        w.write_str("at unknown byte")?;
+    }
+    if let Some(t) = CONTEXT.typ {
+        write!(w, ", type {}", t)?;
+    }
+    if CONTEXT.path_len > 0 {
+        w.write_str(", in ")?;
+        let len = core::cmp::min(CONTEXT.path_len as usize, MAX_PATH);
+        for (i, segment) in CONTEXT.path[..len].iter().enumerate() {
+            if i > 0 {
+                w.write_str(" → ")?;
+            }
+            write!(w, "{}", segment)?;
+        }
+        if CONTEXT.path_len as usize > MAX_PATH {
+            write!(w, " → ... ({} more)", CONTEXT.path_len as usize - MAX_PATH)?;
+        }
+    }
+    w.write_str(")")
+}
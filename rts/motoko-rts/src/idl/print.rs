@@ -0,0 +1,387 @@
+//! Rendering Candid messages as textual Candid, for debug output and error messages
+//!
+//! The output follows the textual format of the Candid spec, e.g.
+//! `(record { 1 = "x"; 2 = 5 : nat8 }, opt vec { +3; -4 })`. Field and variant tags are printed
+//! as numbers, as the message does not contain the labels. Records with fields `0`, `1`, ... are
+//! printed as tuples, and vectors of `nat8` as `blob "..."`.
+//!
+//! Values of type `reserved` and of future types (type codes we do not know yet) are printed as
+//! `reserved`, as they are decoded as `#reserved` by `value`.
+//!
+//! Both the output length and the nesting depth are bounded by the caller, the depth at most by
+//! `MAX_DEPTH`, as values are printed by recursion: deeper compound values are printed as `...`
+//! and skipped, and output that does not fit ends with `...`.
+
+use super::*;
+
+use crate::bigint::{bigint_leb128_decode, bigint_sleb128_decode, bigint_to_text};
+use crate::float::format::{format_float, FMT_SHORTEST, MAX_FORMATTED_LEN};
+use crate::mem_utils::memcpy_bytes;
+use crate::principal_id::principal_of_blob;
+use crate::text::text_of_ptr_size;
+use crate::types::{Bytes, Value};
+
+use core::cmp::min;
+use core::fmt::{self, Write};
+
+const TRUNCATION_MARK: &str = "...";
+
+/// Nesting depth of printed values, whatever the caller asks for
+pub const MAX_DEPTH: u32 = 64;
+
+struct Printer {
+    out: *mut u8,
+    len: u32,
+    max_len: u32,
+    truncated: bool,
+}
+
+impl Write for Printer {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.truncated {
+            return Ok(());
+        }
+
+        let space_left = self.max_len - self.len;
+        let bytes = s.as_bytes();
+        if bytes.len() as u32 <= space_left {
+            unsafe {
+                memcpy_bytes(
+                    self.out.add(self.len as usize) as usize,
+                    bytes.as_ptr() as usize,
+                    Bytes(bytes.len() as u32),
+                );
+            }
+            self.len += bytes.len() as u32;
+            return Ok(());
+        }
+
+        // Fill up the buffer, then make room for the mark at a character boundary
+        let _ = self.write_str(unsafe { prefix(s, space_left as usize) });
+        let written = self.len;
+        self.len = min(written, self.max_len - TRUNCATION_MARK.len() as u32);
+        while self.len < written
+            && unsafe { is_continuation_byte(*self.out.add(self.len as usize)) }
+        {
+            self.len -= 1;
+        }
+        let _ = self.write_str(TRUNCATION_MARK);
+        self.truncated = true;
+        Ok(())
+    }
+}
+
+fn is_continuation_byte(byte: u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+/// Longest prefix of `s` of at most `n` bytes ending at a character boundary
+unsafe fn prefix(s: &str, mut n: usize) -> &str {
+    while !s.is_char_boundary(n) {
+        n -= 1;
+    }
+    s.get_unchecked(..n)
+}
+
+/// Writes the contents of a text (a blob, as returned by the RTS)
+unsafe fn write_blob_text(printer: &mut Printer, text: Value) {
+    let blob = text.as_blob();
+    let bytes = core::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize());
+    let _ = printer.write_str(core::str::from_utf8_unchecked(bytes));
+}
+
+unsafe fn print_text(printer: &mut Printer, buf: *mut Buf) {
+    let len = leb128_decode(buf);
+    let p = (*buf).ptr;
+    buf.advance(len); // advance first; does the bounds check
+    utf8_validate(p as *const _, len);
+    let s = core::str::from_utf8_unchecked(core::slice::from_raw_parts(p, len as usize));
+
+    let _ = printer.write_char('"');
+    for c in s.chars() {
+        if printer.truncated {
+            return;
+        }
+        let _ = match c {
+            '"' => printer.write_str("\\\""),
+            '\\' => printer.write_str("\\\\"),
+            '\n' => printer.write_str("\\n"),
+            '\r' => printer.write_str("\\r"),
+            '\t' => printer.write_str("\\t"),
+            c if c.is_control() => write!(printer, "\\u{{{:x}}}", u32::from(c)),
+            c => printer.write_char(c),
+        };
+    }
+    let _ = printer.write_char('"');
+}
+
+unsafe fn print_principal<M: Memory>(mem: &mut M, printer: &mut Printer, buf: *mut Buf) {
+    let blob = read_blob(mem, buf);
+    let _ = printer.write_char('"');
+    write_blob_text(printer, principal_of_blob(mem, blob));
+    let _ = printer.write_char('"');
+}
+
+unsafe fn print_float(printer: &mut Printer, f: f64, ty: &str) {
+    let mut digits = [0u8; MAX_FORMATTED_LEN];
+    let len = format_float(&mut digits, f, 0, FMT_SHORTEST);
+    let _ = printer.write_str(core::str::from_utf8_unchecked(&digits[..len]));
+    let _ = write!(printer, " : {}", ty);
+}
+
+unsafe fn print_primitive<M: Memory>(mem: &mut M, printer: &mut Printer, buf: *mut Buf, t: i32) {
+    let _ = match t {
+        IDL_PRIM_null => printer.write_str("null"),
+        IDL_PRIM_reserved => printer.write_str("reserved"),
+        IDL_PRIM_bool => printer.write_str(if read_byte_tag(buf) == 1 {
+            "true"
+        } else {
+            "false"
+        }),
+        IDL_PRIM_nat => {
+            let n = bigint_leb128_decode(buf);
+            write_blob_text(printer, bigint_to_text(mem, n, 10));
+            Ok(())
+        }
+        IDL_PRIM_int => {
+            let n = bigint_sleb128_decode(buf);
+            let text = bigint_to_text(mem, n, 10);
+            if *text.as_blob().payload_const() != b'-' {
+                let _ = printer.write_char('+');
+            }
+            write_blob_text(printer, text);
+            Ok(())
+        }
+        IDL_PRIM_nat8 => write!(printer, "{} : nat8", read_fixed(buf, 1)),
+        IDL_PRIM_nat16 => write!(printer, "{} : nat16", read_fixed(buf, 2)),
+        IDL_PRIM_nat32 => write!(printer, "{} : nat32", read_fixed(buf, 4)),
+        IDL_PRIM_nat64 => write!(printer, "{} : nat64", read_fixed(buf, 8)),
+        IDL_PRIM_int8 => write!(printer, "{} : int8", read_fixed(buf, 1) as i8),
+        IDL_PRIM_int16 => write!(printer, "{} : int16", read_fixed(buf, 2) as i16),
+        IDL_PRIM_int32 => write!(printer, "{} : int32", read_fixed(buf, 4) as i32),
+        IDL_PRIM_int64 => write!(printer, "{} : int64", read_fixed(buf, 8) as i64),
+        IDL_PRIM_float32 => {
+            let f = f32::from_bits(read_fixed(buf, 4) as u32);
+            print_float(printer, f64::from(f), "float32");
+            Ok(())
+        }
+        IDL_PRIM_float64 => {
+            print_float(printer, f64::from_bits(read_fixed(buf, 8)), "float64");
+            Ok(())
+        }
+        IDL_PRIM_text => {
+            print_text(printer, buf);
+            Ok(())
+        }
+        IDL_PRIM_empty => idl_trap_with("print: encountered empty"),
+        IDL_REF_principal => {
+            read_reference_tag(buf);
+            let _ = printer.write_str("principal ");
+            print_principal(mem, printer, buf);
+            Ok(())
+        }
+        _ => idl_trap_with("print: unknown prim"),
+    };
+}
+
+/// Whether the fields of the record type at `tb` are numbered `0`, `1`, ...
+unsafe fn is_tuple(mut tb: Buf) -> bool {
+    for i in 0..leb128_decode(&mut tb) {
+        if leb128_decode(&mut tb) != i {
+            return false;
+        }
+        skip_leb128(&mut tb);
+    }
+    true
+}
+
+/// Prints a value of type `t`, with at most `depth` levels of compound values. Deeper ones are
+/// skipped.
+unsafe fn print_value<M: Memory>(
+    mem: &mut M,
+    printer: &mut Printer,
+    buf: *mut Buf,
+    typtbl: *mut *mut u8,
+    t: i32,
+    depth: u32,
+) {
+    if printer.truncated {
+        return;
+    }
+    idl_charge_decoding(1);
+
+    if t < 0 {
+        print_primitive(mem, printer, buf, t);
+        return;
+    }
+
+    let mut tb = Buf {
+        ptr: *typtbl.add(t as usize),
+        end: (*buf).end,
+    };
+    let tc = sleb128_decode(&mut tb);
+
+    if depth == 0 && (tc == IDL_CON_opt || tc == IDL_CON_vec || tc == IDL_CON_record) {
+        skip_any(mem, buf, typtbl, t, 0);
+        let _ = printer.write_str("...");
+        return;
+    }
+
+    match tc {
+        IDL_CON_opt => {
+            let it = sleb128_decode(&mut tb);
+            if read_byte_tag(buf) == 0 {
+                let _ = printer.write_str("null");
+            } else {
+                let _ = printer.write_str("opt ");
+                print_value(mem, printer, buf, typtbl, it, depth - 1);
+            }
+        }
+        IDL_CON_vec => {
+            let it = sleb128_decode(&mut tb);
+            let count = leb128_decode(buf);
+            if it == IDL_PRIM_nat8 {
+                let p = (*buf).ptr;
+                buf.advance(count);
+                let _ = printer.write_str("blob \"");
+                for i in 0..count as usize {
+                    let byte = *p.add(i);
+                    let _ = if byte.is_ascii_graphic() && byte != b'"' && byte != b'\\' {
+                        printer.write_char(char::from(byte))
+                    } else {
+                        write!(printer, "\\{:02x}", byte)
+                    };
+                }
+                let _ = printer.write_char('"');
+            } else if count == 0 {
+                let _ = printer.write_str("vec {}");
+            } else {
+                let _ = printer.write_str("vec { ");
+                for i in 0..count {
+                    if printer.truncated {
+                        // Skipping the remaining elements of a vector of a zero-sized type would
+                        // take a long time, and there is no more output
+                        return;
+                    }
+                    if i > 0 {
+                        let _ = printer.write_str("; ");
+                    }
+                    print_value(mem, printer, buf, typtbl, it, depth - 1);
+                }
+                let _ = printer.write_str(" }");
+            }
+        }
+        IDL_CON_record => {
+            let tuple = is_tuple(Buf {
+                ptr: tb.ptr,
+                end: tb.end,
+            });
+            let n = leb128_decode(&mut tb);
+            if n == 0 {
+                let _ = printer.write_str("record {}");
+                return;
+            }
+            let _ = printer.write_str("record { ");
+            for i in 0..n {
+                if i > 0 {
+                    let _ = printer.write_str("; ");
+                }
+                let id = leb128_decode(&mut tb);
+                let it = sleb128_decode(&mut tb);
+                if !tuple {
+                    let _ = write!(printer, "{} = ", id);
+                }
+                print_value(mem, printer, buf, typtbl, it, depth - 1);
+            }
+            let _ = printer.write_str(" }");
+        }
+        IDL_CON_variant => {
+            let n = leb128_decode(&mut tb);
+            let i = leb128_decode(buf);
+            if i >= n {
+                idl_trap_with("print: variant tag too large");
+            }
+            for _ in 0..i {
+                skip_leb128(&mut tb);
+                skip_leb128(&mut tb);
+            }
+            let id = leb128_decode(&mut tb);
+            let it = sleb128_decode(&mut tb);
+            if it == IDL_PRIM_null {
+                let _ = write!(printer, "variant {{ {} }}", id);
+            } else if depth == 0 {
+                skip_any(mem, buf, typtbl, it, 0);
+                let _ = write!(printer, "variant {{ {} = ... }}", id);
+            } else {
+                let _ = write!(printer, "variant {{ {} = ", id);
+                print_value(mem, printer, buf, typtbl, it, depth - 1);
+                let _ = printer.write_str(" }");
+            }
+        }
+        IDL_CON_func => {
+            read_reference_tag(buf);
+            read_reference_tag(buf);
+            let _ = printer.write_str("func ");
+            print_principal(mem, printer, buf);
+            let _ = printer.write_char('.');
+            print_text(printer, buf);
+        }
+        IDL_CON_service => {
+            read_reference_tag(buf);
+            let _ = printer.write_str("service ");
+            print_principal(mem, printer, buf);
+        }
+        _ => {
+            // Future type, or a mutable stable value, which are not in Candid messages
+            skip_any(mem, buf, typtbl, t, 0);
+            let _ = printer.write_str("reserved");
+        }
+    }
+}
+
+/// Renders a Candid message as textual Candid, in at most `max_len` bytes (but at least enough
+/// for `...`). Only `max_depth` (at most `MAX_DEPTH`) levels of compound values are printed, deeper
+/// ones are printed as `...`. Traps if the message is malformed.
+#[ic_mem_fn]
+pub unsafe fn idl_print_args<M: Memory>(
+    mem: &mut M,
+    buf: *mut Buf,
+    max_len: u32,
+    max_depth: u32,
+) -> Value {
+    let mut typtbl = core::ptr::null_mut();
+    let mut n_types = 0;
+    let mut main_types = core::ptr::null_mut();
+    parse_idl_header(mem, false, buf, &mut typtbl, &mut n_types, &mut main_types);
+
+    let max_len = core::cmp::max(max_len, TRUNCATION_MARK.len() as u32);
+    let max_depth = min(max_depth, MAX_DEPTH);
+    let out = alloc_blob(mem, Bytes(max_len));
+    let mut printer = Printer {
+        out: out.as_blob_mut().payload_addr(),
+        len: 0,
+        max_len,
+        truncated: false,
+    };
+
+    let mut tb = Buf {
+        ptr: main_types,
+        end: (*buf).end,
+    };
+    let _ = printer.write_char('(');
+    for i in 0..leb128_decode(&mut tb) {
+        if printer.truncated {
+            break;
+        }
+        if i > 0 {
+            let _ = printer.write_str(", ");
+        }
+        set_segment(0, Segment::Arg(i));
+        let t = sleb128_decode(&mut tb);
+        print_value(mem, &mut printer, buf, typtbl, t, max_depth);
+    }
+    let _ = printer.write_char(')');
+    idl_end_message();
+
+    text_of_ptr_size(mem, printer.out, Bytes(printer.len))
+}
@@ -0,0 +1,71 @@
+//! Budget for the work of decoding a Candid message
+//!
+//! Decoding or skipping a value can take much more work than the size of its encoding suggests:
+//! values of types like `record { null; null }` take no input at all, and a vector of records
+//! of many such fields takes work quadratic in the size of the message. So every decoded or
+//! skipped value is charged against a budget, which `parse_idl_header` sets in proportion to the
+//! size of the message. Exceeding the budget traps.
+//!
+//! The stable variables decoded after an upgrade (`extended` Candid) are not limited: they are
+//! the canister's own state, which is valid however much work it takes to decode, e.g. a
+//! `stable var xs : [()]` of millions of elements.
+//!
+//! Vectors are charged for their elements up front when they are allocated, as the elements take
+//! memory even when they take no input.
+
+use crate::idl_trap_with;
+
+/// Budget per byte of a message, unless configured otherwise
+pub const DEFAULT_QUOTA_PER_BYTE: u32 = 32;
+
+/// Budget of every message on top of the budget per byte, unless configured otherwise
+pub const DEFAULT_BASE_QUOTA: u32 = 1 << 16;
+
+static mut QUOTA_PER_BYTE: u32 = DEFAULT_QUOTA_PER_BYTE;
+static mut BASE_QUOTA: u32 = DEFAULT_BASE_QUOTA;
+
+/// Budget of the current message. Unlimited until the first message is parsed.
+static mut QUOTA: u64 = u64::MAX;
+
+/// Work charged for the current message so far
+static mut WORK: u64 = 0;
+
+/// Sets the budget of the messages decoded from now on to `quota_per_byte` units of work per
+/// byte of the message, plus `base_quota`. One unit is charged per value.
+#[no_mangle]
+pub unsafe extern "C" fn idl_set_decoding_quota(quota_per_byte: u32, base_quota: u32) {
+    QUOTA_PER_BYTE = quota_per_byte;
+    BASE_QUOTA = base_quota;
+}
+
+/// Starts the budget of a message of `len` bytes, which is unlimited for `extended` Candid
+pub(crate) unsafe fn reset_decoding_quota(len: u32, extended: bool) {
+    QUOTA = if extended {
+        u64::MAX
+    } else {
+        u64::from(QUOTA_PER_BYTE) * u64::from(len) + u64::from(BASE_QUOTA)
+    };
+    WORK = 0;
+}
+
+/// Charges `units` of work to the budget of the current message, and traps if it is exceeded.
+/// Also called by generated code, once per decoded value.
+#[no_mangle]
+pub unsafe extern "C" fn idl_charge_decoding(units: u32) {
+    WORK += u64::from(units);
+    if WORK > QUOTA {
+        idl_trap_with(
+            "decoding quota exceeded: the message takes too much work to decode for its size",
+        );
+    }
+}
+
+/// Work charged for the current message so far
+pub unsafe fn decoding_work() -> u64 {
+    WORK
+}
+
+/// Budget of the current message, `u64::MAX` if unlimited
+pub unsafe fn decoding_quota() -> u64 {
+    QUOTA
+}
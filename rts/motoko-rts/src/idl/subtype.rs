@@ -0,0 +1,392 @@
+//! Candid subtype checking between two type tables
+//!
+//! `idl_sub` checks whether a type of one type table (e.g. the wire types, parsed from a message
+//! by `parse_idl_header`) is a subtype of a type of another one (e.g. the expected types), by the
+//! rules of the Candid specification:
+//!
+//! * `nat <: int`, every type is a subtype of `reserved`, `empty` is a subtype of every type, and
+//!   other primitive types are only subtypes of themselves
+//!
+//! * Every type is a subtype of `opt t` (the "special opt rule"): a value that does not decode at
+//!   `t` decodes as `null`, so the decoder checks the payload type again when it decodes an
+//!   `opt` value
+//!
+//! * `vec` is covariant
+//!
+//! * A record is a subtype of a record with fewer fields (widening), and fields missing in the
+//!   subtype are allowed when they are of type `null`, `opt _` or `reserved`
+//!
+//! * A variant is a subtype of a variant with more fields (narrowing)
+//!
+//! * `func` is contravariant in the argument and covariant in the result types, which are
+//!   compared like records with fields `0, 1, ...`. The annotations must match.
+//!
+//! * A service is a subtype of a service with fewer methods
+//!
+//! Types refer to each other recursively, so the checks of constructed types are memoized in a
+//! cache, which is allocated once per pair of type tables by `idl_sub_cache`. A check that is
+//! already in progress is assumed to succeed, which makes the relation the largest one that is
+//! consistent with the rules (as usual for equi-recursive types). A check that fails makes all the
+//! checks that depend on it fail too, so after a failed check the assumptions made during it are
+//! dropped from the cache, and only the failures are kept.
+//!
+//! The generated deserialisation code checks references with `idl_sub`: a function or service
+//! reference decodes only if its wire type is a subtype of the expected type. The expected types
+//! form one type table for the whole program, which the compiler puts into static memory, and the
+//! cache is allocated on the first check of each message (see `deserialize_go` in `compile.ml`).
+//! Other values are still checked structurally while they are decoded.
+
+use super::*;
+
+use crate::mem_utils::memzero;
+
+use core::cmp::Ordering;
+
+/// Subtype checks nested deeper than this trap
+pub const MAX_SUB_DEPTH: u32 = 1_000;
+
+// The cache stores two bits for each pair of types and each direction
+const VISITED: u8 = 0b01;
+const FAILED: u8 = 0b10;
+const ALL_FAILED: u8 = 0b1010_1010;
+
+/// Header of the cache blob, followed by the bits
+#[repr(C)]
+struct CacheHeader {
+    n_types1: u32,
+    n_types2: u32,
+}
+
+/// A parsed type table, with the end of the buffer it points into
+#[derive(Clone, Copy)]
+struct TypeTable {
+    typtbl: *mut *mut u8,
+    end: *mut u8,
+}
+
+impl TypeTable {
+    /// Type code of constructed type `t`, and a buffer at its arguments
+    unsafe fn entry(self, t: i32) -> (i32, Buf) {
+        let mut tb = Buf {
+            ptr: *self.typtbl.add(t as usize),
+            end: self.end,
+        };
+        let tc = sleb128_decode(&mut tb);
+        (tc, tb)
+    }
+
+    /// Whether a record field of type `t` may be missing in a subtype
+    unsafe fn is_optional(self, t: i32) -> bool {
+        t == IDL_PRIM_null || t == IDL_PRIM_reserved || (t >= 0 && self.entry(t).0 == IDL_CON_opt)
+    }
+}
+
+/// Fields of a record or variant type, in increasing order of their ids
+struct Fields {
+    tb: Buf,
+    remaining: u32,
+}
+
+impl Fields {
+    unsafe fn new(mut tb: Buf) -> Fields {
+        let remaining = leb128_decode(&mut tb);
+        Fields { tb, remaining }
+    }
+}
+
+impl Iterator for Fields {
+    type Item = (u32, i32);
+
+    fn next(&mut self) -> Option<(u32, i32)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        unsafe {
+            let id = leb128_decode(&mut self.tb);
+            let t = sleb128_decode(&mut self.tb);
+            Some((id, t))
+        }
+    }
+}
+
+/// Methods of a service type, in increasing order of their names
+struct Methods {
+    tb: Buf,
+    remaining: u32,
+}
+
+impl Methods {
+    unsafe fn new(mut tb: Buf) -> Methods {
+        let remaining = leb128_decode(&mut tb);
+        Methods { tb, remaining }
+    }
+}
+
+impl Iterator for Methods {
+    type Item = (&'static [u8], i32);
+
+    fn next(&mut self) -> Option<(&'static [u8], i32)> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        unsafe {
+            let len = leb128_decode(&mut self.tb);
+            let name = core::slice::from_raw_parts(self.tb.ptr, len as usize);
+            Buf::advance(&mut self.tb, len);
+            let t = sleb128_decode(&mut self.tb);
+            Some((name, t))
+        }
+    }
+}
+
+/// A sequence of argument or result types of a function type
+#[derive(Clone, Copy)]
+struct Tuple {
+    ptr: *mut u8,
+    end: *mut u8,
+    len: u32,
+}
+
+impl Tuple {
+    /// Reads the length of the sequence, and skips past it
+    unsafe fn read(tb: *mut Buf) -> Tuple {
+        let len = leb128_decode(tb);
+        let tuple = Tuple {
+            ptr: (*tb).ptr,
+            end: (*tb).end,
+            len,
+        };
+        for _ in 0..len {
+            sleb128_decode(tb);
+        }
+        tuple
+    }
+
+    unsafe fn get(self, i: u32) -> i32 {
+        let mut tb = Buf {
+            ptr: self.ptr,
+            end: self.end,
+        };
+        for _ in 0..i {
+            sleb128_decode(&mut tb);
+        }
+        sleb128_decode(&mut tb)
+    }
+}
+
+struct FuncType {
+    args: Tuple,
+    results: Tuple,
+    /// Bit `a` is set for annotation `a`
+    annotations: u32,
+}
+
+impl FuncType {
+    unsafe fn read(mut tb: Buf) -> FuncType {
+        let args = Tuple::read(&mut tb);
+        let results = Tuple::read(&mut tb);
+        let mut annotations = 0;
+        for _ in 0..leb128_decode(&mut tb) {
+            annotations |= 1 << read_byte(&mut tb);
+        }
+        FuncType {
+            args,
+            results,
+            annotations,
+        }
+    }
+}
+
+struct Checker {
+    /// Payload of the cache blob, after the header
+    bits: *mut u8,
+    n_types1: u32,
+    n_types2: u32,
+    table1: TypeTable,
+    table2: TypeTable,
+}
+
+impl Checker {
+    /// The tables of the sub- and supertype. When `reversed`, the subtype is a type of the
+    /// second table, as for function arguments.
+    fn tables(&self, reversed: bool) -> (TypeTable, TypeTable) {
+        if reversed {
+            (self.table2, self.table1)
+        } else {
+            (self.table1, self.table2)
+        }
+    }
+
+    /// Byte and shift of the cache bits of the check `t1 <: t2`
+    unsafe fn cache_entry(&self, reversed: bool, t1: i32, t2: i32) -> (*mut u8, u32) {
+        let (i1, i2) = if reversed { (t2, t1) } else { (t1, t2) };
+        debug_assert!((i1 as u32) < self.n_types1 && (i2 as u32) < self.n_types2);
+        let index = ((reversed as usize * self.n_types1 as usize + i1 as usize)
+            * self.n_types2 as usize)
+            + i2 as usize;
+        (self.bits.add(index / 4), (index % 4) as u32 * 2)
+    }
+
+    /// Whether `t1` is a subtype of `t2`
+    unsafe fn sub(&self, reversed: bool, t1: i32, t2: i32, depth: u32) -> bool {
+        if depth > MAX_SUB_DEPTH {
+            idl_trap_with("subtype check: types too deeply nested");
+        }
+
+        let (table1, table2) = self.tables(reversed);
+
+        if t2 == IDL_PRIM_reserved || t1 == IDL_PRIM_empty {
+            return true;
+        }
+        if t2 >= 0 && table2.entry(t2).0 == IDL_CON_opt {
+            return true;
+        }
+        if t1 < 0 || t2 < 0 {
+            return t1 == t2 || (t1 == IDL_PRIM_nat && t2 == IDL_PRIM_int);
+        }
+
+        let (entry, shift) = self.cache_entry(reversed, t1, t2);
+        let bits = *entry >> shift;
+        if bits & VISITED != 0 {
+            return bits & FAILED == 0;
+        }
+        *entry |= VISITED << shift;
+
+        let (tc1, mut tb1) = table1.entry(t1);
+        let (tc2, mut tb2) = table2.entry(t2);
+        let result = match (tc1, tc2) {
+            (IDL_CON_vec, IDL_CON_vec) => {
+                let t1 = sleb128_decode(&mut tb1);
+                let t2 = sleb128_decode(&mut tb2);
+                self.sub(reversed, t1, t2, depth + 1)
+            }
+            (IDL_CON_record, IDL_CON_record) => {
+                let mut fields1 = Fields::new(tb1).peekable();
+                Fields::new(tb2).all(|(id2, t2)| {
+                    while fields1.next_if(|(id1, _)| *id1 < id2).is_some() {}
+                    match fields1.next_if(|(id1, _)| *id1 == id2) {
+                        Some((_, t1)) => self.sub(reversed, t1, t2, depth + 1),
+                        None => table2.is_optional(t2),
+                    }
+                })
+            }
+            (IDL_CON_variant, IDL_CON_variant) => {
+                let mut fields2 = Fields::new(tb2).peekable();
+                Fields::new(tb1).all(|(id1, t1)| {
+                    while fields2.next_if(|(id2, _)| *id2 < id1).is_some() {}
+                    match fields2.next_if(|(id2, _)| *id2 == id1) {
+                        Some((_, t2)) => self.sub(reversed, t1, t2, depth + 1),
+                        None => false,
+                    }
+                })
+            }
+            (IDL_CON_func, IDL_CON_func) => {
+                let func1 = FuncType::read(tb1);
+                let func2 = FuncType::read(tb2);
+                func1.annotations == func2.annotations
+                    && self.sub_tuple(!reversed, func2.args, func1.args, depth)
+                    && self.sub_tuple(reversed, func1.results, func2.results, depth)
+            }
+            (IDL_CON_service, IDL_CON_service) => {
+                let mut methods1 = Methods::new(tb1).peekable();
+                Methods::new(tb2).all(|(name2, t2)| {
+                    while methods1
+                        .next_if(|(name1, _)| name1.cmp(&name2) == Ordering::Less)
+                        .is_some()
+                    {}
+                    match methods1.next_if(|(name1, _)| *name1 == name2) {
+                        Some((_, t1)) => self.sub(reversed, t1, t2, depth + 1),
+                        None => false,
+                    }
+                })
+            }
+            // Different constructors, and future types
+            _ => false,
+        };
+
+        if !result {
+            *entry |= FAILED << shift;
+        }
+        result
+    }
+
+    /// Whether the tuple `ts1` is a subtype of `ts2`, like records with fields `0, 1, ...`
+    unsafe fn sub_tuple(&self, reversed: bool, ts1: Tuple, ts2: Tuple, depth: u32) -> bool {
+        let table2 = self.tables(reversed).1;
+        (0..ts2.len).all(|i| {
+            let t2 = ts2.get(i);
+            if i < ts1.len {
+                self.sub(reversed, ts1.get(i), t2, depth + 1)
+            } else {
+                table2.is_optional(t2)
+            }
+        })
+    }
+
+    /// Drops the assumptions made during a failed check, keeping the failures
+    unsafe fn forget_assumptions(&self, cache: Value) {
+        let len = cache.as_blob().len().as_u32() - core::mem::size_of::<CacheHeader>() as u32;
+        for i in 0..len as usize {
+            let failed = *self.bits.add(i) & ALL_FAILED;
+            *self.bits.add(i) = failed | failed >> 1;
+        }
+    }
+}
+
+/// Allocates the cache for the subtype checks between two type tables of the given sizes, to be
+/// passed to all calls of `idl_sub` for these tables.
+///
+/// NB. This function assumes the cache does not need to survive GC
+#[ic_mem_fn]
+pub unsafe fn idl_sub_cache<M: Memory>(mem: &mut M, n_types1: u32, n_types2: u32) -> Value {
+    // Two bits for each pair of types, in both directions
+    let n_entries = 2 * u64::from(n_types1) * u64::from(n_types2);
+    if n_entries > u64::from(u32::MAX) {
+        idl_trap_with("subtype check: type tables too large");
+    }
+
+    let size = Bytes(((n_entries + 3) / 4) as u32 + core::mem::size_of::<CacheHeader>() as u32);
+    let cache = alloc_blob(mem, size);
+    let header = cache.as_blob_mut().payload_addr();
+    memzero(header as usize, size.to_words());
+    *(header as *mut CacheHeader) = CacheHeader { n_types1, n_types2 };
+    cache
+}
+
+/// Checks whether type `t1` of the first type table is a subtype of type `t2` of the second, see
+/// the module documentation. The type tables must be the ones returned by `parse_idl_header`, each
+/// with the end of the buffer it was parsed from, and `t1` and `t2` must be valid type arguments
+/// for them. `cache` must be allocated by `idl_sub_cache` for the sizes of these type tables.
+#[no_mangle]
+pub unsafe extern "C" fn idl_sub(
+    cache: Value,
+    typtbl1: *mut *mut u8,
+    typtbl_end1: *mut u8,
+    typtbl2: *mut *mut u8,
+    typtbl_end2: *mut u8,
+    t1: i32,
+    t2: i32,
+) -> bool {
+    let header = cache.as_blob_mut().payload_addr() as *mut CacheHeader;
+    let checker = Checker {
+        bits: header.add(1) as *mut u8,
+        n_types1: (*header).n_types1,
+        n_types2: (*header).n_types2,
+        table1: TypeTable {
+            typtbl: typtbl1,
+            end: typtbl_end1,
+        },
+        table2: TypeTable {
+            typtbl: typtbl2,
+            end: typtbl_end2,
+        },
+    };
+
+    let result = checker.sub(false, t1, t2, 0);
+    if !result {
+        checker.forget_assumptions(cache);
+    }
+    result
+}
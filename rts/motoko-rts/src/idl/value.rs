@@ -0,0 +1,413 @@
+//! Decoding Candid values of arbitrary type into a generic value tree
+//!
+//! Every node of the tree is a variant, with the shape of the Motoko type
+//!
+//! ```text
+//! type Value = {
+//!   #null; #reserved;
+//!   #bool : Bool;
+//!   #nat : Nat; #nat8 : Nat; #nat16 : Nat; #nat32 : Nat; #nat64 : Nat;
+//!   #int : Int; #int8 : Int; #int16 : Int; #int32 : Int; #int64 : Int;
+//!   #float32 : Float; #float64 : Float;
+//!   #text : Text;
+//!   #principal : Blob;
+//!   #opt : [Value];               // empty for `null`
+//!   #vec : [Value];
+//!   #record : [(Nat, Value)];     // field ids in increasing order
+//!   #variant : (Nat, Value);
+//!   #func : (Blob, Text);         // principal of the service and method name
+//!   #service : Blob;
+//! }
+//! ```
+//!
+//! Values of future types (type codes we do not know yet) are skipped and decoded as `#reserved`.
+//!
+//! The decoder does not recurse. It allocates each compound value when it reaches it, and keeps
+//! a stack of the compound values whose elements are not decoded yet. The stack lives in a blob
+//! that is reallocated when it fills up.
+
+use super::*;
+
+use crate::bigint::{
+    bigint_leb128_decode, bigint_of_int64, bigint_of_word32, bigint_of_word64,
+    bigint_sleb128_decode,
+};
+use crate::mem_utils::memcpy_bytes;
+use crate::memory::alloc_array;
+use crate::text::text_of_ptr_size;
+use crate::types::{size_of, Array, Bits64, Bytes, Value, Variant, TAG_BITS64, TAG_VARIANT};
+
+/// Values nested deeper than this are rejected
+pub const MAX_DEPTH: u32 = 10_000;
+
+/// Candid field hash of a label, which is also how Motoko hashes variant labels
+const fn label_hash(label: &str) -> u32 {
+    let bytes = label.as_bytes();
+    let mut hash: u32 = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash = hash.wrapping_mul(223).wrapping_add(bytes[i] as u32);
+        i += 1;
+    }
+    hash & 0x7FFF_FFFF
+}
+
+pub const VALUE_null: u32 = label_hash("null");
+pub const VALUE_reserved: u32 = label_hash("reserved");
+pub const VALUE_bool: u32 = label_hash("bool");
+pub const VALUE_nat: u32 = label_hash("nat");
+pub const VALUE_nat8: u32 = label_hash("nat8");
+pub const VALUE_nat16: u32 = label_hash("nat16");
+pub const VALUE_nat32: u32 = label_hash("nat32");
+pub const VALUE_nat64: u32 = label_hash("nat64");
+pub const VALUE_int: u32 = label_hash("int");
+pub const VALUE_int8: u32 = label_hash("int8");
+pub const VALUE_int16: u32 = label_hash("int16");
+pub const VALUE_int32: u32 = label_hash("int32");
+pub const VALUE_int64: u32 = label_hash("int64");
+pub const VALUE_float32: u32 = label_hash("float32");
+pub const VALUE_float64: u32 = label_hash("float64");
+pub const VALUE_text: u32 = label_hash("text");
+pub const VALUE_principal: u32 = label_hash("principal");
+pub const VALUE_opt: u32 = label_hash("opt");
+pub const VALUE_vec: u32 = label_hash("vec");
+pub const VALUE_record: u32 = label_hash("record");
+pub const VALUE_variant: u32 = label_hash("variant");
+pub const VALUE_func: u32 = label_hash("func");
+pub const VALUE_service: u32 = label_hash("service");
+
+/// A compound value whose elements are being decoded: elements `index..len` of `array` are
+/// still missing. The elements of a record are `(id, value)` tuples, the field ids and types are
+/// read from the type table at `fields`. All other compound values have elements of one type,
+/// `elem`, and `fields` is null. `segment` is the path segment of the elements, with the index
+/// or id filled in for each element.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Frame {
+    array: Value,
+    index: u32,
+    len: u32,
+    elem: i32,
+    fields: *mut u8,
+    segment: Segment,
+}
+
+struct Stack {
+    /// Payload of a blob, with room for `capacity` frames
+    frames: *mut Frame,
+    capacity: u32,
+    depth: u32,
+}
+
+impl Stack {
+    unsafe fn new<M: Memory>(mem: &mut M) -> Stack {
+        const INITIAL_CAPACITY: u32 = 16;
+        Stack {
+            frames: alloc_frames(mem, INITIAL_CAPACITY),
+            capacity: INITIAL_CAPACITY,
+            depth: 0,
+        }
+    }
+
+    unsafe fn push<M: Memory>(&mut self, mem: &mut M, frame: Frame) {
+        if self.depth == MAX_DEPTH {
+            idl_trap_with("decode: value too deeply nested");
+        }
+        if self.depth == self.capacity {
+            let frames = alloc_frames(mem, self.capacity * 2);
+            memcpy_bytes(
+                frames as usize,
+                self.frames as usize,
+                Bytes(self.capacity * core::mem::size_of::<Frame>() as u32),
+            );
+            self.frames = frames;
+            self.capacity *= 2;
+        }
+        *self.frames.add(self.depth as usize) = frame;
+        self.depth += 1;
+    }
+
+    unsafe fn top(&mut self) -> *mut Frame {
+        self.frames.add(self.depth as usize - 1)
+    }
+}
+
+unsafe fn alloc_frames<M: Memory>(mem: &mut M, n: u32) -> *mut Frame {
+    alloc_blob(mem, Bytes(n * core::mem::size_of::<Frame>() as u32))
+        .as_blob_mut()
+        .payload_addr() as *mut Frame
+}
+
+unsafe fn alloc_variant<M: Memory>(mem: &mut M, tag: u32, field: Value) -> Value {
+    let value = mem.alloc_words(size_of::<Variant>());
+    let variant = value.get_ptr() as *mut Variant;
+    (*variant).header.tag = TAG_VARIANT;
+    (*variant).tag = tag;
+    (*variant).field = field;
+    value
+}
+
+unsafe fn alloc_float<M: Memory>(mem: &mut M, f: f64) -> Value {
+    let value = mem.alloc_words(size_of::<Bits64>());
+    let bits64 = value.get_ptr() as *mut Bits64;
+    (*bits64).header.tag = TAG_BITS64;
+    (*bits64).set_bits(f.to_bits());
+    value
+}
+
+unsafe fn set_element<M: Memory>(mem: &mut M, array: *mut Array, index: u32, value: Value) {
+    if value.is_ptr() {
+        array.set_pointer(index, value, mem);
+    } else {
+        array.set_scalar(index, value);
+    }
+}
+
+unsafe fn decode_text<M: Memory>(mem: &mut M, buf: *mut Buf) -> Value {
+    let len = leb128_decode(buf);
+    let p = (*buf).ptr;
+    buf.advance(len); // advance first; does the bounds check
+    utf8_validate(p as *const _, len);
+    text_of_ptr_size(mem, p, Bytes(len))
+}
+
+/// Decodes a value of primitive type `t`
+unsafe fn decode_primitive<M: Memory>(mem: &mut M, buf: *mut Buf, t: i32) -> Value {
+    let unit = Value::from_scalar(0);
+    let (tag, payload) = match t {
+        IDL_PRIM_null => (VALUE_null, unit),
+        IDL_PRIM_reserved => (VALUE_reserved, unit),
+        IDL_PRIM_bool => (VALUE_bool, Value::from_raw(u32::from(read_byte_tag(buf)))),
+        IDL_PRIM_nat => (VALUE_nat, bigint_leb128_decode(buf)),
+        IDL_PRIM_int => (VALUE_int, bigint_sleb128_decode(buf)),
+        IDL_PRIM_nat8 => (VALUE_nat8, bigint_of_word64(read_fixed(buf, 1))),
+        IDL_PRIM_nat16 => (VALUE_nat16, bigint_of_word64(read_fixed(buf, 2))),
+        IDL_PRIM_nat32 => (VALUE_nat32, bigint_of_word64(read_fixed(buf, 4))),
+        IDL_PRIM_nat64 => (VALUE_nat64, bigint_of_word64(read_fixed(buf, 8))),
+        IDL_PRIM_int8 => (VALUE_int8, bigint_of_int64(read_fixed(buf, 1) as i8 as i64)),
+        IDL_PRIM_int16 => (
+            VALUE_int16,
+            bigint_of_int64(read_fixed(buf, 2) as i16 as i64),
+        ),
+        IDL_PRIM_int32 => (
+            VALUE_int32,
+            bigint_of_int64(read_fixed(buf, 4) as i32 as i64),
+        ),
+        IDL_PRIM_int64 => (VALUE_int64, bigint_of_int64(read_fixed(buf, 8) as i64)),
+        IDL_PRIM_float32 => {
+            let f = f32::from_bits(read_fixed(buf, 4) as u32);
+            (VALUE_float32, alloc_float(mem, f64::from(f)))
+        }
+        IDL_PRIM_float64 => {
+            let f = f64::from_bits(read_fixed(buf, 8));
+            (VALUE_float64, alloc_float(mem, f))
+        }
+        IDL_PRIM_text => (VALUE_text, decode_text(mem, buf)),
+        IDL_PRIM_empty => idl_trap_with("decode: encountered empty"),
+        IDL_REF_principal => {
+            read_reference_tag(buf);
+            (VALUE_principal, read_blob(mem, buf))
+        }
+        _ => idl_trap_with("decode: unknown prim"),
+    };
+    alloc_variant(mem, tag, payload)
+}
+
+/// Decodes the value of type `t` at `buf` into element `index` of `array`. Compound values are
+/// allocated and stored, but their elements are left to the caller: a frame for them is pushed
+/// to the stack.
+unsafe fn decode_step<M: Memory>(
+    mem: &mut M,
+    buf: *mut Buf,
+    typtbl: *mut *mut u8,
+    stack: &mut Stack,
+    t: i32,
+    array: *mut Array,
+    index: u32,
+) {
+    idl_charge_decoding(1);
+    set_type(t);
+
+    if t < 0 {
+        let value = decode_primitive(mem, buf, t);
+        set_element(mem, array, index, value);
+        return;
+    }
+
+    let mut tb = Buf {
+        ptr: *typtbl.add(t as usize),
+        end: (*buf).end,
+    };
+    let mut frame = Frame {
+        array: Value::from_scalar(0),
+        index: 0,
+        len: 0,
+        elem: 0,
+        fields: core::ptr::null_mut(),
+        segment: Segment::Opt,
+    };
+    let (tag, payload) = match sleb128_decode(&mut tb) {
+        IDL_CON_opt => {
+            frame.elem = sleb128_decode(&mut tb);
+            frame.len = u32::from(read_byte_tag(buf));
+            frame.array = alloc_array(mem, frame.len);
+            (VALUE_opt, frame.array)
+        }
+        IDL_CON_vec => {
+            frame.elem = sleb128_decode(&mut tb);
+            frame.len = leb128_decode(buf);
+            frame.segment = Segment::Elem(0);
+            idl_charge_decoding(frame.len);
+            frame.array = alloc_array(mem, frame.len);
+            (VALUE_vec, frame.array)
+        }
+        IDL_CON_record => {
+            frame.len = leb128_decode(&mut tb);
+            frame.fields = tb.ptr;
+            frame.segment = Segment::Field(0);
+            frame.array = alloc_array(mem, frame.len);
+            (VALUE_record, frame.array)
+        }
+        IDL_CON_variant => {
+            let n = leb128_decode(&mut tb);
+            let i = leb128_decode(buf);
+            if i >= n {
+                idl_trap_with("decode: variant tag too large");
+            }
+            for _ in 0..i {
+                skip_leb128(&mut tb);
+                skip_leb128(&mut tb);
+            }
+            let id = leb128_decode(&mut tb);
+            frame.elem = sleb128_decode(&mut tb);
+            frame.segment = Segment::Variant(id);
+            frame.array = alloc_array(mem, 2);
+            frame
+                .array
+                .as_array()
+                .set_pointer(0, bigint_of_word32(id), mem);
+            frame.index = 1;
+            frame.len = 2;
+            (VALUE_variant, frame.array)
+        }
+        IDL_CON_func => {
+            read_reference_tag(buf);
+            read_reference_tag(buf);
+            let principal = read_blob(mem, buf);
+            let method = decode_text(mem, buf);
+            let pair = alloc_array(mem, 2);
+            pair.as_array().set_pointer(0, principal, mem);
+            pair.as_array().set_pointer(1, method, mem);
+            (VALUE_func, pair)
+        }
+        IDL_CON_service => {
+            read_reference_tag(buf);
+            (VALUE_service, read_blob(mem, buf))
+        }
+        IDL_CON_alias => idl_trap_with("decode: mutable stable value"),
+        _ => {
+            // Future type
+            let n_data = leb128_decode(buf);
+            let n_ref = leb128_decode(buf);
+            buf.advance(n_data);
+            if n_ref > 0 {
+                idl_trap_with("decode: opaque reference");
+            }
+            (VALUE_reserved, Value::from_scalar(0))
+        }
+    };
+
+    let value = alloc_variant(mem, tag, payload);
+    set_element(mem, array, index, value);
+
+    if frame.index < frame.len {
+        stack.push(mem, frame);
+    }
+}
+
+/// Decodes a value of type `t`. The type table needs to be checked by `parse_idl_header`.
+pub unsafe fn decode_value<M: Memory>(
+    mem: &mut M,
+    buf: *mut Buf,
+    typtbl: *mut *mut u8,
+    t: i32,
+) -> Value {
+    // The value is decoded into a one-element array, so that the root is not a special case
+    let root = alloc_array(mem, 1);
+    let mut stack = Stack::new(mem);
+    decode_step(mem, buf, typtbl, &mut stack, t, root.as_array(), 0);
+
+    // The path to the value is extended by the path within it (see `idl::error`)
+    let path_base = path_len();
+
+    while stack.depth > 0 {
+        let frame = stack.top();
+        if (*frame).index == (*frame).len {
+            stack.depth -= 1;
+            continue;
+        }
+
+        let array = (*frame).array.as_array();
+        let index = (*frame).index;
+        (*frame).index += 1;
+        let depth = path_base + stack.depth - 1;
+
+        if (*frame).fields.is_null() {
+            let t = (*frame).elem;
+            let segment = match (*frame).segment {
+                Segment::Elem(_) => Segment::Elem(index),
+                segment => segment,
+            };
+            set_segment(depth, segment);
+            decode_step(mem, buf, typtbl, &mut stack, t, array, index);
+        } else {
+            let mut tb = Buf {
+                ptr: (*frame).fields,
+                end: (*buf).end,
+            };
+            let id = leb128_decode(&mut tb);
+            let t = sleb128_decode(&mut tb);
+            (*frame).fields = tb.ptr;
+            set_segment(depth, Segment::Field(id));
+
+            let field = alloc_array(mem, 2);
+            field.as_array().set_pointer(0, bigint_of_word32(id), mem);
+            array.set_pointer(index, field, mem);
+            // NB. `frame` is invalid after this, as the stack may be reallocated
+            decode_step(mem, buf, typtbl, &mut stack, t, field.as_array(), 1);
+        }
+    }
+
+    truncate_path(path_base);
+    root.as_array().get(0)
+}
+
+/// Decodes a Candid message into an array of the argument values
+#[ic_mem_fn]
+pub unsafe fn idl_decode_args<M: Memory>(mem: &mut M, buf: *mut Buf) -> Value {
+    let mut typtbl = core::ptr::null_mut();
+    let mut n_types = 0;
+    let mut main_types = core::ptr::null_mut();
+    parse_idl_header(mem, false, buf, &mut typtbl, &mut n_types, &mut main_types);
+
+    let mut tb = Buf {
+        ptr: main_types,
+        end: (*buf).end,
+    };
+    let n_args = leb128_decode(&mut tb);
+    let args = alloc_array(mem, n_args);
+    for i in 0..n_args {
+        set_segment(0, Segment::Arg(i));
+        let t = sleb128_decode(&mut tb);
+        let value = decode_value(mem, buf, typtbl, t);
+        set_element(mem, args.as_array(), i, value);
+    }
+    truncate_path(0);
+
+    if (*buf).ptr != (*buf).end {
+        idl_trap_with("decode: left-over bytes");
+    }
+    idl_end_message();
+
+    args
+}
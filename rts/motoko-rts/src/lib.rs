@@ -13,18 +13,21 @@ pub mod bigint;
 #[cfg(feature = "ic")]
 mod blob_iter;
 pub mod buf;
-mod char;
+pub mod char;
+pub mod codec;
 pub mod constants;
 pub mod continuation_table;
-#[cfg(feature = "ic")]
-mod float;
+pub mod float;
 pub mod gc;
-#[cfg(feature = "ic")]
-mod idl;
+pub mod graph;
+pub mod idl;
 pub mod leb128;
+pub mod lz;
 mod mem_utils;
 pub mod memory;
 pub mod principal_id;
+pub mod region;
+pub mod sha2;
 mod static_checks;
 pub mod stream;
 pub mod text;
@@ -83,8 +86,20 @@ pub(crate) unsafe fn trap_with_prefix(prefix: &str, msg: &str) -> ! {
     rts_trap(c_str.as_ptr(), Bytes(b_idx as u32));
 }
 
+/// Traps with an IDL error, and where in the message it happened (see `idl::error`)
 pub(crate) unsafe fn idl_trap_with(msg: &str) -> ! {
-    trap_with_prefix("IDL error: ", msg);
+    use core::fmt::Write;
+
+    // Messages longer than this are cut off, as in `trap_with_prefix`
+    const BUF_LEN: usize = 512;
+
+    let mut buf = [0u8; BUF_LEN];
+    let mut fmt = print::WriteBuf::new(&mut buf);
+    let _ = fmt.write_str("IDL error: ");
+    let _ = fmt.write_str(msg);
+    let _ = idl::error::write_context(&mut fmt);
+    let bytes = fmt.as_bytes();
+    rts_trap(bytes.as_ptr(), Bytes(bytes.len() as u32));
 }
 
 pub(crate) unsafe fn rts_trap_with(msg: &str) -> ! {
@@ -0,0 +1,431 @@
+//! LZ compression of stable-variable images
+//!
+//! A simple LZ77 format in the style of LZ4, which needs no allocation: the compressor keeps a
+//! fixed-size hash table of recent positions, and the decompressor a fixed-size window of the
+//! recent output. Both are meant to be `static`, and work incrementally: the compressor on the
+//! chunks that a stream flushes, the decompressor on the requests of a stream reader.
+//!
+//! The compressed data is a sequence of blocks, one per compressed chunk. A block is the LEB128
+//! length of the chunk, followed by sequences. A sequence is
+//!
+//! * a token byte, with the number of literals in the high nibble, and the length of the match
+//!   minus `MIN_MATCH` in the low nibble,
+//!
+//! * if the number of literals is 15 or more, the rest of it in bytes that are added up until a
+//!   byte is less than 255,
+//!
+//! * the literals,
+//!
+//! * unless the literals complete the block, the offset of the match (1 to `WINDOW_SIZE - 1`) as
+//!   a 16-bit little-endian number, followed by the rest of the length of the match like the
+//!   number of literals.
+//!
+//! Matches may refer back beyond the start of their block, and may overlap with their output.
+//!
+//! Compressing with `write` and `finish` cuts the data into blocks of `BLOCK_SIZE` bytes,
+//! whatever the chunks, so that the size of the output is bounded by `max_compressed_size`.
+
+use crate::rts_trap_with;
+
+/// Matches are at least this long
+pub const MIN_MATCH: u32 = 4;
+
+/// Matches refer back less than this many bytes
+pub const WINDOW_SIZE: u32 = 1 << 12;
+
+const HASH_BITS: u32 = 12;
+
+/// Size of the buffers of compressed data
+const BUFFER_SIZE: usize = 256;
+
+/// Size of the blocks of `Compressor::write`
+pub const BLOCK_SIZE: u32 = 1 << 12;
+
+/// Most bytes that a block takes beyond its data: its length, and, when nothing matches, the
+/// token and the bytes that add up the number of literals (matches save more than they cost)
+const BLOCK_OVERHEAD: u64 = 5 + 1 + (BLOCK_SIZE as u64 / 255 + 1);
+
+/// Upper bound of the size of `n` bytes compressed with `write` and `finish`
+pub const fn max_compressed_size(n: u64) -> u64 {
+    n + (n + BLOCK_SIZE as u64 - 1) / BLOCK_SIZE as u64 * BLOCK_OVERHEAD
+}
+
+/// Where compressed data goes
+pub trait Sink {
+    unsafe fn write(&mut self, bytes: &[u8]);
+}
+
+/// Where compressed data comes from
+pub trait Source {
+    /// Reads up to `n` bytes to `dest`, and returns how many. Returns 0 only at the end.
+    unsafe fn read(&mut self, dest: *mut u8, n: u32) -> u32;
+}
+
+pub struct Compressor {
+    /// Position after the last occurrence of each hash of `MIN_MATCH` bytes, 0 for none.
+    /// Positions count all the bytes compressed so far.
+    table: [u32; 1 << HASH_BITS],
+    /// Number of bytes compressed so far
+    position: u32,
+    /// The last bytes compressed, the window that matches can refer to
+    window: [u8; WINDOW_SIZE as usize],
+    output: [u8; BUFFER_SIZE],
+    output_len: usize,
+    /// The bytes of `write` that do not fill a block yet
+    block: [u8; BLOCK_SIZE as usize],
+    block_len: u32,
+}
+
+impl Compressor {
+    pub const fn new() -> Compressor {
+        Compressor {
+            table: [0; 1 << HASH_BITS],
+            position: 0,
+            window: [0; WINDOW_SIZE as usize],
+            output: [0; BUFFER_SIZE],
+            output_len: 0,
+            block: [0; BLOCK_SIZE as usize],
+            block_len: 0,
+        }
+    }
+
+    /// Compresses `n` bytes at `data` in blocks of `BLOCK_SIZE` bytes. The bytes that do not
+    /// fill a block are kept until more are written, or `finish` is called.
+    pub unsafe fn write<S: Sink>(&mut self, sink: &mut S, mut data: *const u8, mut n: u32) {
+        while n > 0 {
+            if self.block_len == 0 && n >= BLOCK_SIZE {
+                self.compress(sink, data, BLOCK_SIZE);
+                data = data.add(BLOCK_SIZE as usize);
+                n -= BLOCK_SIZE;
+                continue;
+            }
+            let chunk = core::cmp::min(n, BLOCK_SIZE - self.block_len);
+            let dest = self.block.as_mut_ptr().add(self.block_len as usize);
+            core::ptr::copy_nonoverlapping(data, dest, chunk as usize);
+            self.block_len += chunk;
+            data = data.add(chunk as usize);
+            n -= chunk;
+            if self.block_len == BLOCK_SIZE {
+                self.finish(sink);
+            }
+        }
+    }
+
+    /// Compresses the bytes kept by `write`
+    pub unsafe fn finish<S: Sink>(&mut self, sink: &mut S) {
+        let block = self.block.as_ptr();
+        let n = self.block_len;
+        self.block_len = 0;
+        self.compress(sink, block, n);
+    }
+
+    /// Forgets the data compressed so far, to start a new image
+    pub fn reset(&mut self) {
+        *self = Compressor::new();
+    }
+
+    /// Byte at `position`, which must be in the window or the current chunk
+    unsafe fn byte_at(&self, data: *const u8, start: u32, position: u32) -> u8 {
+        if position >= start {
+            *data.add((position - start) as usize)
+        } else {
+            self.window[(position % WINDOW_SIZE) as usize]
+        }
+    }
+
+    /// Compresses `n` bytes at `data` into a block
+    pub unsafe fn compress<S: Sink>(&mut self, sink: &mut S, data: *const u8, n: u32) {
+        if n == 0 {
+            return;
+        }
+
+        // Positions count up to 2^32, then the table starts over
+        if self.position.checked_add(n).is_none() {
+            self.table = [0; 1 << HASH_BITS];
+            self.position = 0;
+        }
+
+        self.leb128(sink, n);
+
+        let start = self.position;
+        let end = start + n;
+        let mut anchor = start;
+        let mut position = start;
+        while end - position >= MIN_MATCH {
+            let offset = (position - start) as usize;
+            let word = core::ptr::read_unaligned(data.add(offset) as *const u32);
+            let hash = (word.wrapping_mul(2654435761) >> (32 - HASH_BITS)) as usize;
+            let candidate = self.table[hash];
+            self.table[hash] = position + 1;
+
+            let distance = position.wrapping_sub(candidate.wrapping_sub(1));
+            if candidate == 0 || distance >= WINDOW_SIZE || distance == 0 {
+                position += 1;
+                continue;
+            }
+            let mut length = 0;
+            while position + length < end
+                && self.byte_at(data, start, position - distance + length)
+                    == *data.add(offset + length as usize)
+            {
+                length += 1;
+            }
+            if length < MIN_MATCH {
+                position += 1;
+                continue;
+            }
+
+            self.sequence(
+                sink,
+                data,
+                start,
+                anchor,
+                position,
+                Some((distance, length)),
+            );
+            position += length;
+            anchor = position;
+        }
+        if anchor < end {
+            self.sequence(sink, data, start, anchor, end, None);
+        }
+
+        // Keep the end of the chunk as the window
+        for position in end.saturating_sub(WINDOW_SIZE).max(start)..end {
+            self.window[(position % WINDOW_SIZE) as usize] = *data.add((position - start) as usize);
+        }
+        self.position = end;
+
+        self.flush(sink);
+    }
+
+    /// Emits the literals from `anchor` to `position`, and the match, if any
+    unsafe fn sequence<S: Sink>(
+        &mut self,
+        sink: &mut S,
+        data: *const u8,
+        start: u32,
+        anchor: u32,
+        position: u32,
+        match_: Option<(u32, u32)>,
+    ) {
+        let literals = position - anchor;
+        let match_length = match_.map_or(0, |(_, length)| length - MIN_MATCH);
+        let token = (literals.min(15) << 4) | match_length.min(15);
+        self.byte(sink, token as u8);
+        if literals >= 15 {
+            self.length(sink, literals - 15);
+        }
+        for i in anchor..position {
+            self.byte(sink, *data.add((i - start) as usize));
+        }
+        if let Some((distance, _)) = match_ {
+            self.byte(sink, distance as u8);
+            self.byte(sink, (distance >> 8) as u8);
+            if match_length >= 15 {
+                self.length(sink, match_length - 15);
+            }
+        }
+    }
+
+    unsafe fn length<S: Sink>(&mut self, sink: &mut S, mut n: u32) {
+        while n >= 255 {
+            self.byte(sink, 255);
+            n -= 255;
+        }
+        self.byte(sink, n as u8);
+    }
+
+    unsafe fn leb128<S: Sink>(&mut self, sink: &mut S, mut n: u32) {
+        loop {
+            let byte = (n & 0x7F) as u8;
+            n >>= 7;
+            if n == 0 {
+                return self.byte(sink, byte);
+            }
+            self.byte(sink, byte | 0x80);
+        }
+    }
+
+    unsafe fn byte<S: Sink>(&mut self, sink: &mut S, byte: u8) {
+        if self.output_len == BUFFER_SIZE {
+            self.flush(sink);
+        }
+        self.output[self.output_len] = byte;
+        self.output_len += 1;
+    }
+
+    unsafe fn flush<S: Sink>(&mut self, sink: &mut S) {
+        sink.write(&self.output[..self.output_len]);
+        self.output_len = 0;
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    /// Before a token
+    Token,
+    /// Copying literals, then the match of the token
+    Literals { remaining: u32, match_length: u32 },
+    /// Copying a match
+    Match { remaining: u32, distance: u32 },
+}
+
+pub struct Decompressor {
+    state: State,
+    /// Bytes left in the current block
+    block_remaining: u32,
+    /// Number of bytes decompressed so far
+    position: u64,
+    /// The last bytes decompressed, the window that matches refer to
+    window: [u8; WINDOW_SIZE as usize],
+    input: [u8; BUFFER_SIZE],
+    input_pos: u32,
+    input_len: u32,
+}
+
+impl Decompressor {
+    pub const fn new() -> Decompressor {
+        Decompressor {
+            state: State::Token,
+            block_remaining: 0,
+            position: 0,
+            window: [0; WINDOW_SIZE as usize],
+            input: [0; BUFFER_SIZE],
+            input_pos: 0,
+            input_len: 0,
+        }
+    }
+
+    /// Forgets the data decompressed so far, to start a new image
+    pub fn reset(&mut self) {
+        *self = Decompressor::new();
+    }
+
+    /// Decompresses the next `n` bytes to `dest`. Traps if the compressed data ends before.
+    pub unsafe fn decompress<S: Source>(&mut self, source: &mut S, mut dest: *mut u8, mut n: u32) {
+        while n > 0 {
+            if self.block_remaining == 0 {
+                match self.state {
+                    State::Token | State::Match { remaining: 0, .. } => {}
+                    _ => rts_trap_with("lz: block overrun"),
+                }
+                self.block_remaining = self.leb128(source);
+                self.state = State::Token;
+                continue;
+            }
+
+            match self.state {
+                State::Token => {
+                    let token = u32::from(self.next_byte(source));
+                    let mut literals = token >> 4;
+                    if literals == 15 {
+                        literals += self.length(source);
+                    }
+                    self.state = State::Literals {
+                        remaining: literals,
+                        match_length: token & 0xF,
+                    };
+                }
+                State::Literals {
+                    remaining: 0,
+                    match_length,
+                } => {
+                    let distance =
+                        u32::from(self.next_byte(source)) | u32::from(self.next_byte(source)) << 8;
+                    let mut length = match_length;
+                    if length == 15 {
+                        length += self.length(source);
+                    }
+                    if distance == 0
+                        || distance >= WINDOW_SIZE
+                        || u64::from(distance) > self.position
+                    {
+                        rts_trap_with("lz: invalid match");
+                    }
+                    self.state = State::Match {
+                        remaining: length + MIN_MATCH,
+                        distance,
+                    };
+                }
+                State::Literals {
+                    remaining,
+                    match_length,
+                } => {
+                    let byte = self.next_byte(source);
+                    self.emit(&mut dest, &mut n, byte);
+                    self.state = if remaining == 1 && self.block_remaining == 0 {
+                        State::Token
+                    } else {
+                        State::Literals {
+                            remaining: remaining - 1,
+                            match_length,
+                        }
+                    };
+                }
+                State::Match { remaining: 0, .. } => self.state = State::Token,
+                State::Match {
+                    remaining,
+                    distance,
+                } => {
+                    let source_position = self.position - u64::from(distance);
+                    let byte = self.window[(source_position % u64::from(WINDOW_SIZE)) as usize];
+                    self.emit(&mut dest, &mut n, byte);
+                    self.state = State::Match {
+                        remaining: remaining - 1,
+                        distance,
+                    };
+                }
+            }
+        }
+    }
+
+    unsafe fn emit(&mut self, dest: &mut *mut u8, n: &mut u32, byte: u8) {
+        **dest = byte;
+        *dest = dest.add(1);
+        *n -= 1;
+        self.window[(self.position % u64::from(WINDOW_SIZE)) as usize] = byte;
+        self.position += 1;
+        self.block_remaining -= 1;
+    }
+
+    unsafe fn length<S: Source>(&mut self, source: &mut S) -> u32 {
+        let mut n = 0u32;
+        loop {
+            let byte = self.next_byte(source);
+            n = n.saturating_add(u32::from(byte));
+            if byte < 255 {
+                return n;
+            }
+        }
+    }
+
+    unsafe fn leb128<S: Source>(&mut self, source: &mut S) -> u32 {
+        let mut n = 0u32;
+        let mut shift = 0;
+        loop {
+            let byte = self.next_byte(source);
+            if shift > 28 {
+                rts_trap_with("lz: invalid block length");
+            }
+            n |= u32::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                return n;
+            }
+            shift += 7;
+        }
+    }
+
+    unsafe fn next_byte<S: Source>(&mut self, source: &mut S) -> u8 {
+        if self.input_pos == self.input_len {
+            self.input_len = source.read(self.input.as_mut_ptr(), BUFFER_SIZE as u32);
+            self.input_pos = 0;
+            if self.input_len == 0 {
+                rts_trap_with("lz: truncated data");
+            }
+        }
+        let byte = self.input[self.input_pos as usize];
+        self.input_pos += 1;
+        byte
+    }
+}
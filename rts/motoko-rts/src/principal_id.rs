@@ -1,8 +1,10 @@
 //! Principal ID encoding and decoding, with integrity checking
 
+use crate::codec::{blob_bytes, decode_bytes, encode_chunks, CODEC_BASE32, CODEC_HEX};
 use crate::mem_utils::memcpy_bytes;
 use crate::memory::{alloc_blob, Memory};
 use crate::rts_trap_with;
+use crate::sha2::{Sha256, SHA224_SIZE};
 use crate::text::{blob_compare, blob_of_text};
 use crate::types::{Bytes, Value, TAG_BLOB};
 
@@ -17,12 +19,16 @@ pub unsafe extern "C" fn compute_crc32(blob: Value) -> u32 {
     }
 
     let blob = blob.as_blob();
-    let len = blob.len();
+    let bytes = core::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize());
+    update_crc32(0, bytes)
+}
 
-    let mut crc: u32 = !0;
+/// Running CRC32: the checksum of the bytes checksummed to `crc`, followed by `bytes`.
+/// The checksum of no bytes is 0.
+pub fn update_crc32(crc: u32, bytes: &[u8]) -> u32 {
+    let mut crc = !crc;
 
-    for i in 0..len.as_u32() {
-        let octet = blob.get(i);
+    for octet in bytes {
         crc = (crc >> 8) ^ CRC_TABLE[usize::from((crc & 0xFF) as u8 ^ octet)];
     }
 
@@ -64,147 +70,18 @@ static CRC_TABLE: [u32; 256] = [
     0xb3667a2e, 0xc4614ab8, 0x5d681b02, 0x2a6f2b94, 0xb40bbe37, 0xc30c8ea1, 0x5a05df1b, 0x2d02ef8d,
 ];
 
-struct Pump {
-    inp_gran: u32,
-    out_gran: u32,
-    dest: *mut u8,
-    pending_data: u32,
-    pending_bits: u32,
-}
-
-static BASE32_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
-
-unsafe fn stash_enc_base32(d: u8, dest: *mut u8) {
-    *dest = BASE32_CHARS[(d & 0b0001_1111) as usize];
-}
-
-unsafe fn enc_stash(pump: &mut Pump, data: u8) {
-    pump.pending_data <<= pump.inp_gran;
-    pump.pending_data |= data as u32;
-    pump.pending_bits += pump.inp_gran;
-
-    while pump.pending_bits >= pump.out_gran {
-        pump.pending_bits -= pump.out_gran;
-        stash_enc_base32((pump.pending_data >> pump.pending_bits) as u8, pump.dest);
-        pump.dest = pump.dest.add(1);
-        pump.pending_data &= (1 << pump.pending_bits) - 1;
-    }
-}
-
 /// Encode a blob into an checksum-prepended base32 representation
 pub unsafe fn base32_of_checksummed_blob<M: Memory>(mem: &mut M, b: Value) -> Value {
-    let checksum = compute_crc32(b);
-    let n = b.as_blob().len();
-    let mut data = b.as_blob().payload_const();
-
-    let r = alloc_blob(mem, Bytes((n.as_u32() + 4 + 4) / 5 * 8)); // contains padding
-    let blob = r.as_blob_mut();
-    let dest = blob.payload_addr();
-
-    let mut pump = Pump {
-        inp_gran: 8,
-        out_gran: 5,
-        dest,
-        pending_data: 0,
-        pending_bits: 0,
-    };
-    enc_stash(&mut pump, (checksum >> 24) as u8); // checksum is serialized as big-endian
-    enc_stash(&mut pump, (checksum >> 16) as u8);
-    enc_stash(&mut pump, (checksum >> 8) as u8);
-    enc_stash(&mut pump, checksum as u8);
-
-    for _ in 0..n.as_u32() {
-        enc_stash(&mut pump, *data);
-        data = data.add(1);
-    }
-
-    if pump.pending_bits != 0 {
-        // Flush odd bits
-        pump.pending_data <<= pump.out_gran - pump.pending_bits;
-        stash_enc_base32(pump.pending_data as u8, pump.dest);
-        pump.dest = pump.dest.add(1);
-        // Discount padding
-        let new_len = Bytes(pump.dest.offset_from(dest) as u32);
-        blob.shrink(new_len);
-    }
-
-    r
-}
-
-// tolerant conversion
-// accepts lower case and fillers/padding '-', '='
-// values are one more than base32 value
-// 0 elements signify invalid alphabet letter
-// fillers/padding have upper bit set
-// Returns in range [1,32]
-fn conv(b: u8) -> u8 {
-    if b == b'-' {
-        0xF0
-    } else if b == b'=' {
-        0xF1
-    } else if b >= b'A' && b <= b'Z' {
-        b - b'A' + 1
-    } else if b >= b'a' && b <= b'z' {
-        b - b'a' + 1
-    } else if b >= b'2' && b <= b'7' {
-        b - b'2' + 27
-    } else {
-        0
-    }
-}
-
-unsafe fn accum_base32(pump: &mut Pump, c: u8) {
-    if c > b'z' {
-        rts_trap_with("accum_base32: Base32 symbol out of range");
-    }
-
-    let v = conv(c & 0b0111_1111) - 1; // 0..31
-
-    if v < 32 {
-        // excludes '-' and '='
-        pump.pending_bits += pump.inp_gran;
-        pump.pending_data <<= pump.inp_gran;
-        pump.pending_data |= v as u32;
-    }
-}
-
-unsafe fn dec_stash(pump: &mut Pump, data: u8) {
-    accum_base32(pump, data);
-
-    while pump.pending_bits >= pump.out_gran {
-        pump.pending_bits -= pump.out_gran;
-        *pump.dest = (pump.pending_data >> pump.pending_bits) as u8;
-        pump.dest = pump.dest.add(1);
-        pump.pending_data &= (1 << pump.pending_bits) - 1;
-    }
+    let checksum = compute_crc32(b).to_be_bytes(); // checksum is serialized as big-endian
+    encode_chunks(mem, CODEC_BASE32, &[&checksum, blob_bytes(b)], false)
 }
 
+/// Decode a base32 blob tolerantly (see `codec`)
 pub unsafe fn base32_to_blob<M: Memory>(mem: &mut M, b: Value) -> Value {
-    let n = b.as_blob().len();
-    let mut data = b.as_blob().payload_const();
-
-    // Every group of 8 characters will yield 5 bytes
-    let r = alloc_blob(mem, Bytes(((n.as_u32() + 7) / 8) * 5)); // we deal with padding later
-    let blob = r.as_blob_mut();
-    let dest = blob.payload_addr();
-
-    let mut pump = Pump {
-        inp_gran: 5,
-        out_gran: 8,
-        dest,
-        pending_bits: 0,
-        pending_data: 0,
-    };
-
-    for _ in 0..n.as_u32() {
-        dec_stash(&mut pump, *data);
-        data = data.add(1);
+    match decode_bytes(mem, CODEC_BASE32, blob_bytes(b), true) {
+        Some(r) => r,
+        None => rts_trap_with("base32_to_blob: invalid base32"),
     }
-
-    // Adjust resulting blob len
-    let new_len = Bytes(pump.dest.offset_from(dest) as u32);
-    blob.shrink(new_len);
-    r
 }
 
 /// Encode a blob into its textual representation
@@ -259,7 +136,10 @@ unsafe fn base32_to_principal<M: Memory>(mem: &mut M, b: Value) -> Value {
 #[ic_mem_fn]
 pub unsafe fn blob_of_principal<M: Memory>(mem: &mut M, t: Value) -> Value {
     let b0 = blob_of_text(mem, t);
-    let bytes = base32_to_blob(mem, b0);
+    let bytes = match decode_bytes(mem, CODEC_BASE32, blob_bytes(b0), true) {
+        Some(bytes) => bytes,
+        None => rts_trap_with("blob_of_principal: invalid principal"),
+    };
 
     // Strip first four bytes
     let bytes_len = bytes.as_blob().len();
@@ -282,3 +162,159 @@ pub unsafe fn blob_of_principal<M: Memory>(mem: &mut M, t: Value) -> Value {
 
     stripped
 }
+
+// Construction and classification of principals, see "Principals" in the Internet Computer
+// interface specification. The last byte of a principal tells its class.
+
+const CLASS_OPAQUE: u8 = 0x01;
+const CLASS_SELF_AUTHENTICATING: u8 = 0x02;
+const CLASS_DERIVED: u8 = 0x03;
+const CLASS_ANONYMOUS: u8 = 0x04;
+const CLASS_RESERVED: u8 = 0x7F;
+
+/// Principals are at most this long
+pub const MAX_PRINCIPAL_SIZE: u32 = 29;
+
+// Results of `principal_class`
+pub const PRINCIPAL_OPAQUE: u32 = 0;
+pub const PRINCIPAL_SELF_AUTHENTICATING: u32 = 1;
+pub const PRINCIPAL_DERIVED: u32 = 2;
+pub const PRINCIPAL_ANONYMOUS: u32 = 3;
+pub const PRINCIPAL_RESERVED: u32 = 4;
+
+/// A principal of the given class, with the hash of the bytes hashed by `hasher`
+unsafe fn principal_of_hash<M: Memory>(mem: &mut M, hasher: Sha256, class: u8) -> Value {
+    let r = alloc_blob(mem, Bytes(SHA224_SIZE as u32 + 1));
+    let bytes = core::slice::from_raw_parts_mut(r.as_blob_mut().payload_addr(), SHA224_SIZE + 1);
+    hasher.finish(&mut bytes[..SHA224_SIZE]);
+    bytes[SHA224_SIZE] = class;
+    r
+}
+
+/// The self-authenticating principal of a DER-encoded public key
+#[ic_mem_fn]
+pub unsafe fn principal_self_authenticating<M: Memory>(mem: &mut M, public_key: Value) -> Value {
+    let mut hasher = Sha256::sha224();
+    hasher.update(blob_bytes(public_key));
+    principal_of_hash(mem, hasher, CLASS_SELF_AUTHENTICATING)
+}
+
+/// The principal derived from a registering principal (such as a canister id) and a nonce
+#[ic_mem_fn]
+pub unsafe fn principal_derived<M: Memory>(mem: &mut M, registrar: Value, nonce: Value) -> Value {
+    let registrar = blob_bytes(registrar);
+    if registrar.len() > MAX_PRINCIPAL_SIZE as usize {
+        rts_trap_with("principal_derived: registering principal too long");
+    }
+    let mut hasher = Sha256::sha224();
+    hasher.update(&[registrar.len() as u8]);
+    hasher.update(registrar);
+    hasher.update(blob_bytes(nonce));
+    principal_of_hash(mem, hasher, CLASS_DERIVED)
+}
+
+/// The class of a principal, one of the `PRINCIPAL_*` constants. Principals that are not of
+/// another class are opaque, including the management canister, which is empty.
+#[no_mangle]
+pub unsafe extern "C" fn principal_class(principal: Value) -> u32 {
+    let bytes = blob_bytes(principal);
+    let n = bytes.len() as u32;
+    match bytes.last() {
+        Some(&CLASS_SELF_AUTHENTICATING) if n == SHA224_SIZE as u32 + 1 => {
+            PRINCIPAL_SELF_AUTHENTICATING
+        }
+        Some(&CLASS_DERIVED) if n == SHA224_SIZE as u32 + 1 => PRINCIPAL_DERIVED,
+        Some(&CLASS_ANONYMOUS) if n == 1 => PRINCIPAL_ANONYMOUS,
+        Some(&CLASS_RESERVED) if n <= MAX_PRINCIPAL_SIZE => PRINCIPAL_RESERVED,
+        Some(&CLASS_OPAQUE) => PRINCIPAL_OPAQUE,
+        _ => PRINCIPAL_OPAQUE,
+    }
+}
+
+// Ledger account identifiers. An account is a principal with a 32-byte subaccount, and its
+// identifier is the SHA-224 hash of a domain separator, the principal and the subaccount,
+// prefixed with the big-endian CRC32 of that hash. Textually, it is in lowercase hex.
+
+const ACCOUNT_ID_DOMAIN: &[u8] = b"\x0Aaccount-id";
+
+/// Subaccounts are exactly this long
+pub const SUBACCOUNT_SIZE: u32 = 32;
+
+/// Account identifiers are exactly this long
+pub const ACCOUNT_ID_SIZE: u32 = 4 + SHA224_SIZE as u32;
+
+/// The account identifier of a principal and a subaccount
+#[ic_mem_fn]
+pub unsafe fn account_id_of_principal<M: Memory>(
+    mem: &mut M,
+    principal: Value,
+    subaccount: Value,
+) -> Value {
+    let subaccount = blob_bytes(subaccount);
+    if subaccount.len() != SUBACCOUNT_SIZE as usize {
+        rts_trap_with("account_id_of_principal: subaccount must be 32 bytes");
+    }
+    let mut hasher = Sha256::sha224();
+    hasher.update(ACCOUNT_ID_DOMAIN);
+    hasher.update(blob_bytes(principal));
+    hasher.update(subaccount);
+
+    let r = alloc_blob(mem, Bytes(ACCOUNT_ID_SIZE));
+    let bytes =
+        core::slice::from_raw_parts_mut(r.as_blob_mut().payload_addr(), ACCOUNT_ID_SIZE as usize);
+    hasher.finish(&mut bytes[4..]);
+    let checksum = update_crc32(0, &bytes[4..]);
+    bytes[..4].copy_from_slice(&checksum.to_be_bytes());
+    r
+}
+
+/// Whether a blob is an account identifier, i.e. has the right size and checksum
+#[no_mangle]
+pub unsafe extern "C" fn account_id_valid(account_id: Value) -> bool {
+    let bytes = blob_bytes(account_id);
+    bytes.len() == ACCOUNT_ID_SIZE as usize
+        && bytes[..4] == update_crc32(0, &bytes[4..]).to_be_bytes()
+}
+
+/// Encode an account identifier in hex
+#[ic_mem_fn]
+pub unsafe fn account_id_to_text<M: Memory>(mem: &mut M, account_id: Value) -> Value {
+    if !account_id_valid(account_id) {
+        rts_trap_with("account_id_to_text: invalid account identifier");
+    }
+    encode_chunks(mem, CODEC_HEX, &[blob_bytes(account_id)], false)
+}
+
+/// Checks that `digits` are the text of an account identifier: exactly 64 hex digits, in
+/// either case, and nothing else, with the right checksum
+pub fn check_account_id_text(digits: &[u8]) -> Result<(), &'static str> {
+    if digits.len() != 2 * ACCOUNT_ID_SIZE as usize {
+        return Err("account_id_of_text: account identifier must be 64 hex digits");
+    }
+    let mut bytes = [0u8; ACCOUNT_ID_SIZE as usize];
+    for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+        match (
+            (pair[0] as char).to_digit(16),
+            (pair[1] as char).to_digit(16),
+        ) {
+            (Some(high), Some(low)) => *byte = (high << 4 | low) as u8,
+            _ => return Err("account_id_of_text: invalid hex digit"),
+        }
+    }
+    if bytes[..4] != update_crc32(0, &bytes[4..]).to_be_bytes() {
+        return Err("account_id_of_text: invalid checksum");
+    }
+    Ok(())
+}
+
+/// Decode a hex account identifier, see `check_account_id_text`
+#[ic_mem_fn]
+pub unsafe fn account_id_of_text<M: Memory>(mem: &mut M, t: Value) -> Value {
+    let b = blob_of_text(mem, t);
+    let digits = blob_bytes(b);
+    if let Err(msg) = check_account_id_text(digits) {
+        rts_trap_with(msg);
+    }
+    // Only the case of the digits is left to tolerate
+    decode_bytes(mem, CODEC_HEX, digits, true).unwrap()
+}
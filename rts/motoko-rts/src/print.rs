@@ -75,6 +75,10 @@ impl<'a> WriteBuf<'a> {
         self.offset = 0;
     }
 
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buf[..self.offset]
+    }
+
     pub(crate) unsafe fn print(&self) {
         print_ptr(self.buf.as_ptr() as usize, self.offset as u32)
     }
@@ -0,0 +1,512 @@
+//! Regions of stable memory
+//!
+//! The region space is a part of stable memory, starting at a page chosen by the caller, that
+//! is divided into blocks of `BLOCK_PAGES` pages. A region is a numbered, growable sequence of
+//! pages, made of the blocks it owns, in the order it acquired them. Blocks are handed out in
+//! address order and never given back, so regions of different owners can grow independently
+//! without coordinating offsets.
+//!
+//! The region space starts with metadata pages, which make it survive upgrades:
+//!
+//! * a header: the magic bytes `MORG`, the format version, the number of blocks and the number
+//!   of regions, as 32-bit little-endian numbers,
+//!
+//! * the size of each region in pages, as 64-bit little-endian numbers, `MAX_REGIONS` of them,
+//!
+//! * the block-ownership table: for each block, the owning region and the rank of the block in
+//!   that region, as 16-bit little-endian numbers, `MAX_BLOCKS` of them.
+//!
+//! `Regions` keeps an index of the blocks of each region, which it rebuilds from the ownership
+//! table on `init`. All accesses to stable memory go through the `StableMemory` trait, so that
+//! the allocator can be tested against an in-memory stand-in.
+//!
+//! The region space shares stable memory with the `ExperimentalStableMemory` library, which
+//! stays below the base page, and with the image of the stable variables, which is written
+//! after the end of the region space on upgrade (see `Stabilization` in `compile.ml`). Page 0
+//! is never in the region space, as the stable variables leave a marker there.
+
+use crate::constants::WASM_PAGE_SIZE;
+use crate::rts_trap_with;
+
+#[cfg(feature = "ic")]
+use crate::{memory::Memory, types::Value};
+
+#[cfg(feature = "ic")]
+use motoko_rts_macros::ic_mem_fn;
+
+/// Pages of a block
+pub const BLOCK_PAGES: u64 = 128;
+
+/// Maximum number of blocks in a region space
+pub const MAX_BLOCKS: usize = 16384;
+
+/// Maximum number of regions in a region space
+pub const MAX_REGIONS: usize = 1024;
+
+const MAGIC: &[u8; 4] = b"MORG";
+
+const VERSION: u32 = 1;
+
+const PAGE_SIZE: u64 = WASM_PAGE_SIZE.0 as u64;
+
+const BLOCK_SIZE: u64 = BLOCK_PAGES * PAGE_SIZE;
+
+const HEADER_SIZE: u64 = 16;
+
+const SIZES_OFFSET: u64 = HEADER_SIZE;
+
+const TABLE_OFFSET: u64 = SIZES_OFFSET + 8 * MAX_REGIONS as u64;
+
+/// Pages of the metadata, before the first block
+pub const METADATA_PAGES: u64 = (TABLE_OFFSET + 4 * MAX_BLOCKS as u64 + PAGE_SIZE - 1) / PAGE_SIZE;
+
+/// Access to stable memory, in the style of the `ic0.stable64_*` system calls
+pub trait StableMemory {
+    /// Current size in pages
+    unsafe fn size(&mut self) -> u64;
+
+    /// Grows by `pages` pages. Returns the old size, or `u64::MAX` when out of memory.
+    unsafe fn grow(&mut self, pages: u64) -> u64;
+
+    unsafe fn read(&mut self, offset: u64, dest: *mut u8, n: u64);
+
+    unsafe fn write(&mut self, offset: u64, src: *const u8, n: u64);
+}
+
+pub struct Regions {
+    /// Byte offset of the region space in stable memory
+    base: u64,
+    /// Number of blocks handed out
+    blocks: u32,
+    /// Number of regions created
+    regions: u32,
+    /// Size of each region in pages
+    sizes: [u64; MAX_REGIONS],
+    /// The blocks of region `r` are `index[first[r]..first[r + 1]]`, in rank order
+    index: [u16; MAX_BLOCKS],
+    first: [u16; MAX_REGIONS + 1],
+    initialized: bool,
+}
+
+impl Regions {
+    pub const fn new() -> Regions {
+        Regions {
+            base: 0,
+            blocks: 0,
+            regions: 0,
+            sizes: [0; MAX_REGIONS],
+            index: [0; MAX_BLOCKS],
+            first: [0; MAX_REGIONS + 1],
+            initialized: false,
+        }
+    }
+
+    /// Sets up the region space at `base_page`. When stable memory ends before it, or has
+    /// zeroes there (as left by the image of the stable variables after an upgrade), the region
+    /// space is created empty, otherwise the regions of the previous version are loaded.
+    pub unsafe fn init<S: StableMemory>(&mut self, stable: &mut S, base_page: u64) {
+        if base_page == 0 {
+            rts_trap_with("region: page 0 is reserved");
+        }
+        *self = Regions::new();
+        self.base = base_page * PAGE_SIZE;
+
+        let size = stable.size();
+        let mut header = [0u8; HEADER_SIZE as usize];
+        if size > base_page {
+            stable.read(self.base, header.as_mut_ptr(), HEADER_SIZE);
+        }
+        if header == [0u8; HEADER_SIZE as usize] {
+            let end_page = base_page + METADATA_PAGES;
+            if end_page > size && stable.grow(end_page - size) == u64::MAX {
+                rts_trap_with("region: out of stable memory");
+            }
+            if size > base_page {
+                // Fresh pages are zeroed, pages in use before may not be
+                self.clear_metadata(stable);
+            }
+            stable.write(self.base, MAGIC.as_ptr(), MAGIC.len() as u64);
+            self.write_u32(stable, 4, VERSION);
+            self.write_u32(stable, 8, 0);
+            self.write_u32(stable, 12, 0);
+        } else {
+            if &header[..4] != MAGIC {
+                rts_trap_with("region: no region space at base page");
+            }
+            if self.read_u32(stable, 4) != VERSION {
+                rts_trap_with("region: unsupported version");
+            }
+            self.blocks = self.read_u32(stable, 8);
+            self.regions = self.read_u32(stable, 12);
+            if self.blocks as usize > MAX_BLOCKS || self.regions as usize > MAX_REGIONS {
+                rts_trap_with("region: corrupt header");
+            }
+            self.load(stable);
+        }
+
+        self.initialized = true;
+    }
+
+    /// Zeroes the sizes and the block table
+    unsafe fn clear_metadata<S: StableMemory>(&self, stable: &mut S) {
+        let zeroes = [0u8; 1024];
+        let mut offset = SIZES_OFFSET;
+        let end = TABLE_OFFSET + 4 * MAX_BLOCKS as u64;
+        while offset < end {
+            let chunk = (end - offset).min(zeroes.len() as u64);
+            stable.write(self.base + offset, zeroes.as_ptr(), chunk);
+            offset += chunk;
+        }
+    }
+
+    /// Rebuilds the sizes and the index from the metadata
+    unsafe fn load<S: StableMemory>(&mut self, stable: &mut S) {
+        let mut counts = [0u16; MAX_REGIONS];
+        for block in 0..self.blocks {
+            let (region, _) = self.read_entry(stable, block);
+            if region >= self.regions {
+                rts_trap_with("region: corrupt block table");
+            }
+            counts[region as usize] += 1;
+        }
+
+        for region in 0..self.regions as usize {
+            self.first[region + 1] = self.first[region] + counts[region];
+            self.sizes[region] = self.read_u64(stable, SIZES_OFFSET + 8 * region as u64);
+            if self.sizes[region] > counts[region] as u64 * BLOCK_PAGES {
+                rts_trap_with("region: corrupt region size");
+            }
+        }
+
+        // Every block fills a distinct slot of its region, as ranks are distinct
+        for slot in self.index[..self.blocks as usize].iter_mut() {
+            *slot = u16::MAX;
+        }
+        for block in 0..self.blocks {
+            let (region, rank) = self.read_entry(stable, block);
+            let region = region as usize;
+            if rank >= counts[region] {
+                rts_trap_with("region: corrupt block table");
+            }
+            let slot = (self.first[region] + rank) as usize;
+            if self.index[slot] != u16::MAX {
+                rts_trap_with("region: corrupt block table");
+            }
+            self.index[slot] = block as u16;
+        }
+        for region in self.regions as usize..MAX_REGIONS {
+            self.first[region + 1] = self.first[region];
+        }
+    }
+
+    fn check_initialized(&self) {
+        if !self.initialized {
+            unsafe { rts_trap_with("region: not initialized") }
+        }
+    }
+
+    fn check_region(&self, region: u32) {
+        self.check_initialized();
+        if region >= self.regions {
+            unsafe { rts_trap_with("region: invalid region") }
+        }
+    }
+
+    /// Number of regions created so far
+    pub fn count(&self) -> u32 {
+        self.check_initialized();
+        self.regions
+    }
+
+    /// Number of blocks handed out so far
+    pub fn blocks(&self) -> u32 {
+        self.check_initialized();
+        self.blocks
+    }
+
+    /// First page of the region space, if it is initialized
+    pub fn base_page(&self) -> Option<u64> {
+        if self.initialized {
+            Some(self.base / PAGE_SIZE)
+        } else {
+            None
+        }
+    }
+
+    /// Page after the last block handed out, or 0 if the region space is not initialized
+    pub fn end_page(&self) -> u64 {
+        match self.base_page() {
+            Some(base_page) => base_page + METADATA_PAGES + self.blocks as u64 * BLOCK_PAGES,
+            None => 0,
+        }
+    }
+
+    /// Creates an empty region, and returns its number
+    pub unsafe fn new_region<S: StableMemory>(&mut self, stable: &mut S) -> u32 {
+        self.check_initialized();
+        if self.regions as usize == MAX_REGIONS {
+            rts_trap_with("region: too many regions");
+        }
+        let region = self.regions;
+        self.regions += 1;
+        self.write_u32(stable, 12, self.regions);
+        region
+    }
+
+    /// Size of the region in pages
+    pub fn size(&self, region: u32) -> u64 {
+        self.check_region(region);
+        self.sizes[region as usize]
+    }
+
+    /// Grows the region by `pages` pages. Returns the old size, or `u64::MAX` when there are
+    /// not enough blocks or stable memory left, in which case nothing changes.
+    pub unsafe fn grow<S: StableMemory>(&mut self, stable: &mut S, region: u32, pages: u64) -> u64 {
+        self.check_region(region);
+        let r = region as usize;
+        let old_size = self.sizes[r];
+        let new_size = match old_size.checked_add(pages) {
+            Some(new_size) => new_size,
+            None => return u64::MAX,
+        };
+
+        let have = (self.first[r + 1] - self.first[r]) as u64;
+        let need = (new_size + BLOCK_PAGES - 1) / BLOCK_PAGES;
+        if need > have {
+            let extra = need - have;
+            if self.blocks as u64 + extra > MAX_BLOCKS as u64 {
+                return u64::MAX;
+            }
+            let end_page =
+                self.base / PAGE_SIZE + METADATA_PAGES + (self.blocks as u64 + extra) * BLOCK_PAGES;
+            let size = stable.size();
+            if end_page > size && stable.grow(end_page - size) == u64::MAX {
+                return u64::MAX;
+            }
+
+            for rank in have..need {
+                let block = self.blocks;
+                self.write_entry(stable, block, region, rank as u16);
+                self.blocks += 1;
+
+                // Insert into the index after the other blocks of the region
+                let at = self.first[r + 1] as usize;
+                self.index.copy_within(at..block as usize, at + 1);
+                self.index[at] = block as u16;
+                for first in self.first[r + 1..].iter_mut() {
+                    *first += 1;
+                }
+            }
+            self.write_u32(stable, 8, self.blocks);
+        }
+
+        self.sizes[r] = new_size;
+        self.write_u64(stable, SIZES_OFFSET + 8 * region as u64, new_size);
+        old_size
+    }
+
+    /// Reads `n` bytes at `offset` of the region to `dest`
+    pub unsafe fn read<S: StableMemory>(
+        &self,
+        stable: &mut S,
+        region: u32,
+        offset: u64,
+        dest: *mut u8,
+        n: u64,
+    ) {
+        let mut done = 0;
+        while done < n {
+            let (address, chunk) = self.translate(region, offset, n, done);
+            stable.read(address, dest.add(done as usize), chunk);
+            done += chunk;
+        }
+    }
+
+    /// Writes `n` bytes from `src` at `offset` of the region
+    pub unsafe fn write<S: StableMemory>(
+        &self,
+        stable: &mut S,
+        region: u32,
+        offset: u64,
+        src: *const u8,
+        n: u64,
+    ) {
+        let mut done = 0;
+        while done < n {
+            let (address, chunk) = self.translate(region, offset, n, done);
+            stable.write(address, src.add(done as usize), chunk);
+            done += chunk;
+        }
+    }
+
+    /// Address in stable memory of byte `done` of an access of `n` bytes at `offset`, and the
+    /// number of bytes of the access in the same block
+    fn translate(&self, region: u32, offset: u64, n: u64, done: u64) -> (u64, u64) {
+        self.check_region(region);
+        let r = region as usize;
+        match offset.checked_add(n) {
+            Some(end) if end <= self.sizes[r] * PAGE_SIZE => {}
+            _ => unsafe { rts_trap_with("region: access out of bounds") },
+        }
+        let position = offset + done;
+        let rank = position / BLOCK_SIZE;
+        let block = self.index[self.first[r] as usize + rank as usize] as u64;
+        let in_block = position % BLOCK_SIZE;
+        let address = self.base + METADATA_PAGES * PAGE_SIZE + block * BLOCK_SIZE + in_block;
+        (address, (n - done).min(BLOCK_SIZE - in_block))
+    }
+
+    unsafe fn read_entry<S: StableMemory>(&self, stable: &mut S, block: u32) -> (u32, u16) {
+        let mut entry = [0u8; 4];
+        let offset = self.base + TABLE_OFFSET + 4 * block as u64;
+        stable.read(offset, entry.as_mut_ptr(), 4);
+        let region = u16::from_le_bytes([entry[0], entry[1]]);
+        let rank = u16::from_le_bytes([entry[2], entry[3]]);
+        (region as u32, rank)
+    }
+
+    unsafe fn write_entry<S: StableMemory>(
+        &self,
+        stable: &mut S,
+        block: u32,
+        region: u32,
+        rank: u16,
+    ) {
+        let region = (region as u16).to_le_bytes();
+        let rank = rank.to_le_bytes();
+        let entry = [region[0], region[1], rank[0], rank[1]];
+        let offset = self.base + TABLE_OFFSET + 4 * block as u64;
+        stable.write(offset, entry.as_ptr(), 4);
+    }
+
+    unsafe fn read_u32<S: StableMemory>(&self, stable: &mut S, offset: u64) -> u32 {
+        let mut bytes = [0u8; 4];
+        stable.read(self.base + offset, bytes.as_mut_ptr(), 4);
+        u32::from_le_bytes(bytes)
+    }
+
+    unsafe fn write_u32<S: StableMemory>(&self, stable: &mut S, offset: u64, value: u32) {
+        stable.write(self.base + offset, value.to_le_bytes().as_ptr(), 4);
+    }
+
+    unsafe fn read_u64<S: StableMemory>(&self, stable: &mut S, offset: u64) -> u64 {
+        let mut bytes = [0u8; 8];
+        stable.read(self.base + offset, bytes.as_mut_ptr(), 8);
+        u64::from_le_bytes(bytes)
+    }
+
+    unsafe fn write_u64<S: StableMemory>(&self, stable: &mut S, offset: u64, value: u64) {
+        stable.write(self.base + offset, value.to_le_bytes().as_ptr(), 8);
+    }
+}
+
+#[cfg(feature = "ic")]
+extern "C" {
+    // generated by `moc`
+    fn stable64_size_moc() -> u64;
+    fn stable64_grow_moc(pages: u64) -> u64;
+    fn stable64_write_moc(to: u64, ptr: u64, n: u64);
+    fn stable64_read_moc(ptr: u64, from: u64, n: u64);
+}
+
+/// The stable memory of the canister
+#[cfg(feature = "ic")]
+pub struct IcStableMemory;
+
+#[cfg(feature = "ic")]
+impl StableMemory for IcStableMemory {
+    unsafe fn size(&mut self) -> u64 {
+        stable64_size_moc()
+    }
+
+    unsafe fn grow(&mut self, pages: u64) -> u64 {
+        stable64_grow_moc(pages)
+    }
+
+    unsafe fn read(&mut self, offset: u64, dest: *mut u8, n: u64) {
+        stable64_read_moc(dest as usize as u64, offset, n)
+    }
+
+    unsafe fn write(&mut self, offset: u64, src: *const u8, n: u64) {
+        stable64_write_moc(offset, src as usize as u64, n)
+    }
+}
+
+#[cfg(feature = "ic")]
+static mut REGIONS: Regions = Regions::new();
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_init(base_page: u64) {
+    REGIONS.init(&mut IcStableMemory, base_page)
+}
+
+/// First page of the region space, which the `ExperimentalStableMemory` library must not
+/// reach, or `u64::MAX` if the region space is not initialized
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_base_page() -> u64 {
+    REGIONS.base_page().unwrap_or(u64::MAX)
+}
+
+/// Page after the region space, where the image of the stable variables may start
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_end_page() -> u64 {
+    REGIONS.end_page()
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_new() -> u32 {
+    REGIONS.new_region(&mut IcStableMemory)
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_count() -> u32 {
+    REGIONS.count()
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_size(region: u32) -> u64 {
+    REGIONS.size(region)
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_grow(region: u32, pages: u64) -> u64 {
+    REGIONS.grow(&mut IcStableMemory, region, pages)
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_read(region: u32, offset: u64, dest: *mut u8, n: u32) {
+    REGIONS.read(&mut IcStableMemory, region, offset, dest, n as u64)
+}
+
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_write(region: u32, offset: u64, src: *const u8, n: u32) {
+    REGIONS.write(&mut IcStableMemory, region, offset, src, n as u64)
+}
+
+/// Reads `n` bytes at `offset` of the region into a new blob
+#[cfg(feature = "ic")]
+#[ic_mem_fn(ic_only)]
+unsafe fn region_load_blob<M: Memory>(mem: &mut M, region: u32, offset: u64, n: u32) -> Value {
+    let blob = crate::memory::alloc_blob(mem, crate::types::Bytes(n));
+    let dest = blob.as_blob_mut().payload_addr();
+    REGIONS.read(&mut IcStableMemory, region, offset, dest, n as u64);
+    blob
+}
+
+/// Writes the bytes of the blob at `offset` of the region
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn region_store_blob(region: u32, offset: u64, blob: Value) {
+    let blob = blob.as_blob();
+    let n = blob.len().as_u32() as u64;
+    REGIONS.write(&mut IcStableMemory, region, offset, blob.payload_const(), n)
+}
@@ -0,0 +1,513 @@
+//! SHA-2 hash functions (FIPS 180-4)
+//!
+//! Besides the hashers for Rust code, this exports hasher objects to Motoko: blobs that hold the
+//! state of a hasher, which can be fed blobs and texts, and finished any number of times. Texts
+//! are hashed one chunk of the rope at a time, without flattening them.
+
+use crate::memory::{alloc_blob, Memory};
+use crate::rts_trap_with;
+use crate::text::text_chunks;
+use crate::types::{Bytes, Value, TAG_BLOB};
+
+use core::convert::TryInto;
+
+use motoko_rts_macros::ic_mem_fn;
+
+const K256: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const SHA224_INIT: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+const SHA256_INIT: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+#[rustfmt::skip]
+const K512: [u64; 80] = [
+    0x428a2f98d728ae22, 0x7137449123ef65cd, 0xb5c0fbcfec4d3b2f, 0xe9b5dba58189dbbc,
+    0x3956c25bf348b538, 0x59f111f1b605d019, 0x923f82a4af194f9b, 0xab1c5ed5da6d8118,
+    0xd807aa98a3030242, 0x12835b0145706fbe, 0x243185be4ee4b28c, 0x550c7dc3d5ffb4e2,
+    0x72be5d74f27b896f, 0x80deb1fe3b1696b1, 0x9bdc06a725c71235, 0xc19bf174cf692694,
+    0xe49b69c19ef14ad2, 0xefbe4786384f25e3, 0x0fc19dc68b8cd5b5, 0x240ca1cc77ac9c65,
+    0x2de92c6f592b0275, 0x4a7484aa6ea6e483, 0x5cb0a9dcbd41fbd4, 0x76f988da831153b5,
+    0x983e5152ee66dfab, 0xa831c66d2db43210, 0xb00327c898fb213f, 0xbf597fc7beef0ee4,
+    0xc6e00bf33da88fc2, 0xd5a79147930aa725, 0x06ca6351e003826f, 0x142929670a0e6e70,
+    0x27b70a8546d22ffc, 0x2e1b21385c26c926, 0x4d2c6dfc5ac42aed, 0x53380d139d95b3df,
+    0x650a73548baf63de, 0x766a0abb3c77b2a8, 0x81c2c92e47edaee6, 0x92722c851482353b,
+    0xa2bfe8a14cf10364, 0xa81a664bbc423001, 0xc24b8b70d0f89791, 0xc76c51a30654be30,
+    0xd192e819d6ef5218, 0xd69906245565a910, 0xf40e35855771202a, 0x106aa07032bbd1b8,
+    0x19a4c116b8d2d0c8, 0x1e376c085141ab53, 0x2748774cdf8eeb99, 0x34b0bcb5e19b48a8,
+    0x391c0cb3c5c95a63, 0x4ed8aa4ae3418acb, 0x5b9cca4f7763e373, 0x682e6ff3d6b2b8a3,
+    0x748f82ee5defb2fc, 0x78a5636f43172f60, 0x84c87814a1f0ab72, 0x8cc702081a6439ec,
+    0x90befffa23631e28, 0xa4506cebde82bde9, 0xbef9a3f7b2c67915, 0xc67178f2e372532b,
+    0xca273eceea26619c, 0xd186b8c721c0c207, 0xeada7dd6cde0eb1e, 0xf57d4f7fee6ed178,
+    0x06f067aa72176fba, 0x0a637dc5a2c898a6, 0x113f9804bef90dae, 0x1b710b35131c471b,
+    0x28db77f523047d84, 0x32caab7b40c72493, 0x3c9ebe0a15c9bebc, 0x431d67c49c100d4c,
+    0x4cc5d4becb3e42b6, 0x597f299cfc657e2a, 0x5fcb6fab3ad6faec, 0x6c44198c4a475817,
+];
+
+#[rustfmt::skip]
+const SHA512_INIT: [u64; 8] = [
+    0x6a09e667f3bcc908, 0xbb67ae8584caa73b, 0x3c6ef372fe94f82b, 0xa54ff53a5f1d36f1,
+    0x510e527fade682d1, 0x9b05688c2b3e6c1f, 0x1f83d9abfb41bd6b, 0x5be0cd19137e2179,
+];
+
+pub const SHA224_SIZE: usize = 28;
+
+pub const SHA256_SIZE: usize = 32;
+
+pub const SHA512_SIZE: usize = 64;
+
+/// Sizes of the states in hasher objects: the state words, the block, its length, and the length
+/// of the input
+const SHA256_STATE_SIZE: usize = 32 + 64 + 4 + 8;
+const SHA512_STATE_SIZE: usize = 64 + 128 + 4 + 16;
+
+/// Incremental SHA-256, and SHA-224, which differs only in the initial state and the length of
+/// the digest
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    /// Number of bytes hashed so far
+    length: u64,
+    digest_size: usize,
+}
+
+impl Sha256 {
+    pub const fn sha224() -> Sha256 {
+        Sha256 {
+            state: SHA224_INIT,
+            block: [0; 64],
+            block_len: 0,
+            length: 0,
+            digest_size: SHA224_SIZE,
+        }
+    }
+
+    pub const fn sha256() -> Sha256 {
+        Sha256 {
+            state: SHA256_INIT,
+            block: [0; 64],
+            block_len: 0,
+            length: 0,
+            digest_size: SHA256_SIZE,
+        }
+    }
+
+    pub fn digest_size(&self) -> usize {
+        self.digest_size
+    }
+
+    /// Writes the state to `SHA256_STATE_SIZE` bytes of `out`, see `Hasher::store`
+    fn store(&self, out: &mut [u8]) {
+        let (state, rest) = out.split_at_mut(32);
+        for (chunk, word) in state.chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        let (block, rest) = rest.split_at_mut(64);
+        block.copy_from_slice(&self.block);
+        rest[..4].copy_from_slice(&(self.block_len as u32).to_le_bytes());
+        rest[4..12].copy_from_slice(&self.length.to_le_bytes());
+    }
+
+    /// Reads a state written by `store`, if it is one
+    fn load(bytes: &[u8], digest_size: usize) -> Option<Sha256> {
+        let mut hasher = Sha256 {
+            state: [0; 8],
+            block: [0; 64],
+            block_len: 0,
+            length: 0,
+            digest_size,
+        };
+        for (word, chunk) in hasher.state.iter_mut().zip(bytes[..32].chunks(4)) {
+            *word = u32::from_le_bytes(chunk.try_into().unwrap());
+        }
+        hasher.block.copy_from_slice(&bytes[32..96]);
+        hasher.block_len = u32::from_le_bytes(bytes[96..100].try_into().unwrap()) as usize;
+        hasher.length = u64::from_le_bytes(bytes[100..108].try_into().unwrap());
+        if hasher.block_len >= 64 || hasher.length % 64 != hasher.block_len as u64 {
+            return None;
+        }
+        Some(hasher)
+    }
+
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u64);
+        while !bytes.is_empty() {
+            let n = (64 - self.block_len).min(bytes.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&bytes[..n]);
+            self.block_len += n;
+            bytes = &bytes[n..];
+            if self.block_len == 64 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    /// Writes the digest to the first `digest_size` bytes of `out`
+    pub fn finish(mut self, out: &mut [u8]) {
+        let bit_length = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bit_length.to_be_bytes());
+        debug_assert_eq!(self.block_len, 0);
+
+        for (chunk, word) in out[..self.digest_size].chunks_mut(4).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes()[..chunk.len()]);
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 64];
+        for (i, chunk) in self.block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..64 {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K256[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *state = state.wrapping_add(*v);
+        }
+    }
+}
+
+/// Incremental SHA-512
+pub struct Sha512 {
+    state: [u64; 8],
+    block: [u8; 128],
+    block_len: usize,
+    /// Number of bytes hashed so far
+    length: u128,
+}
+
+impl Sha512 {
+    pub const fn new() -> Sha512 {
+        Sha512 {
+            state: SHA512_INIT,
+            block: [0; 128],
+            block_len: 0,
+            length: 0,
+        }
+    }
+
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.length = self.length.wrapping_add(bytes.len() as u128);
+        while !bytes.is_empty() {
+            let n = (128 - self.block_len).min(bytes.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&bytes[..n]);
+            self.block_len += n;
+            bytes = &bytes[n..];
+            if self.block_len == 128 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    /// Writes the state to `SHA512_STATE_SIZE` bytes of `out`, see `Hasher::store`
+    fn store(&self, out: &mut [u8]) {
+        let (state, rest) = out.split_at_mut(64);
+        for (chunk, word) in state.chunks_mut(8).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        let (block, rest) = rest.split_at_mut(128);
+        block.copy_from_slice(&self.block);
+        rest[..4].copy_from_slice(&(self.block_len as u32).to_le_bytes());
+        rest[4..20].copy_from_slice(&self.length.to_le_bytes());
+    }
+
+    /// Reads a state written by `store`, if it is one
+    fn load(bytes: &[u8]) -> Option<Sha512> {
+        let mut hasher = Sha512::new();
+        for (word, chunk) in hasher.state.iter_mut().zip(bytes[..64].chunks(8)) {
+            *word = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+        hasher.block.copy_from_slice(&bytes[64..192]);
+        hasher.block_len = u32::from_le_bytes(bytes[192..196].try_into().unwrap()) as usize;
+        hasher.length = u128::from_le_bytes(bytes[196..212].try_into().unwrap());
+        if hasher.block_len >= 128 || hasher.length % 128 != hasher.block_len as u128 {
+            return None;
+        }
+        Some(hasher)
+    }
+
+    /// Writes the digest to the first `SHA512_SIZE` bytes of `out`
+    pub fn finish(mut self, out: &mut [u8]) {
+        let bit_length = self.length.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.block_len != 112 {
+            self.update(&[0]);
+        }
+        self.update(&bit_length.to_be_bytes());
+        debug_assert_eq!(self.block_len, 0);
+
+        for (chunk, word) in out[..SHA512_SIZE].chunks_mut(8).zip(self.state.iter()) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u64; 80];
+        for (i, chunk) in self.block.chunks(8).enumerate() {
+            let mut word = [0u8; 8];
+            word.copy_from_slice(chunk);
+            w[i] = u64::from_be_bytes(word);
+        }
+        for i in 16..80 {
+            let s0 = w[i - 15].rotate_right(1) ^ w[i - 15].rotate_right(8) ^ (w[i - 15] >> 7);
+            let s1 = w[i - 2].rotate_right(19) ^ w[i - 2].rotate_right(61) ^ (w[i - 2] >> 6);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = self.state;
+        for i in 0..80 {
+            let s1 = e.rotate_right(14) ^ e.rotate_right(18) ^ e.rotate_right(41);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = h
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(K512[i])
+                .wrapping_add(w[i]);
+            let s0 = a.rotate_right(28) ^ a.rotate_right(34) ^ a.rotate_right(39);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            h = g;
+            g = f;
+            f = e;
+            e = d.wrapping_add(t1);
+            d = c;
+            c = b;
+            b = a;
+            a = t1.wrapping_add(t2);
+        }
+
+        for (state, v) in self.state.iter_mut().zip([a, b, c, d, e, f, g, h].iter()) {
+            *state = state.wrapping_add(*v);
+        }
+    }
+}
+
+pub fn sha224(bytes: &[u8]) -> [u8; SHA224_SIZE] {
+    let mut hasher = Sha256::sha224();
+    hasher.update(bytes);
+    let mut digest = [0; SHA224_SIZE];
+    hasher.finish(&mut digest);
+    digest
+}
+
+pub fn sha256(bytes: &[u8]) -> [u8; SHA256_SIZE] {
+    let mut hasher = Sha256::sha256();
+    hasher.update(bytes);
+    let mut digest = [0; SHA256_SIZE];
+    hasher.finish(&mut digest);
+    digest
+}
+
+pub fn sha512(bytes: &[u8]) -> [u8; SHA512_SIZE] {
+    let mut hasher = Sha512::new();
+    hasher.update(bytes);
+    let mut digest = [0; SHA512_SIZE];
+    hasher.finish(&mut digest);
+    digest
+}
+
+// Algorithms of the exported hashers
+pub const SHA2_224: u32 = 0;
+pub const SHA2_256: u32 = 1;
+pub const SHA2_512: u32 = 2;
+
+/// A hasher of any of the algorithms
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha512(Sha512),
+}
+
+impl Hasher {
+    pub fn new(algorithm: u32) -> Hasher {
+        match algorithm {
+            SHA2_224 => Hasher::Sha256(Sha256::sha224()),
+            SHA2_256 => Hasher::Sha256(Sha256::sha256()),
+            SHA2_512 => Hasher::Sha512(Sha512::new()),
+            _ => unsafe { rts_trap_with("sha2: unknown algorithm") },
+        }
+    }
+
+    pub fn algorithm(&self) -> u32 {
+        match self {
+            Hasher::Sha256(hasher) if hasher.digest_size() == SHA224_SIZE => SHA2_224,
+            Hasher::Sha256(_) => SHA2_256,
+            Hasher::Sha512(_) => SHA2_512,
+        }
+    }
+
+    pub fn digest_size(&self) -> usize {
+        match self {
+            Hasher::Sha256(hasher) => hasher.digest_size(),
+            Hasher::Sha512(_) => SHA512_SIZE,
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.update(bytes),
+            Hasher::Sha512(hasher) => hasher.update(bytes),
+        }
+    }
+
+    pub fn finish(self, out: &mut [u8]) {
+        match self {
+            Hasher::Sha256(hasher) => hasher.finish(out),
+            Hasher::Sha512(hasher) => hasher.finish(out),
+        }
+    }
+
+    /// Hashes the bytes of a blob or a text
+    unsafe fn update_value(&mut self, value: Value) {
+        text_chunks(value, &mut |bytes| {
+            self.update(bytes);
+            true
+        });
+    }
+
+    /// The digest, as a new blob
+    unsafe fn digest<M: Memory>(self, mem: &mut M) -> Value {
+        let size = self.digest_size();
+        let r = alloc_blob(mem, Bytes(size as u32));
+        self.finish(core::slice::from_raw_parts_mut(
+            r.as_blob_mut().payload_addr(),
+            size,
+        ));
+        r
+    }
+}
+
+// Hasher objects are blobs that hold the algorithm, as a little-endian word, followed by the state
+// of the hasher, in little-endian words (see `store` of `Sha256` and `Sha512`). Loading checks
+// both, as hasher objects are ordinary blobs to Motoko code.
+
+/// Size of hasher objects of an algorithm
+fn object_size(algorithm: u32) -> usize {
+    4 + match algorithm {
+        SHA2_512 => SHA512_STATE_SIZE,
+        _ => SHA256_STATE_SIZE,
+    }
+}
+
+impl Hasher {
+    /// Writes the hasher to `object_size(self.algorithm())` bytes of `out`
+    fn store(&self, out: &mut [u8]) {
+        out[..4].copy_from_slice(&self.algorithm().to_le_bytes());
+        match self {
+            Hasher::Sha256(hasher) => hasher.store(&mut out[4..]),
+            Hasher::Sha512(hasher) => hasher.store(&mut out[4..]),
+        }
+    }
+
+    /// Reads the bytes of a hasher object, if they are one
+    pub fn of_object(bytes: &[u8]) -> Option<Hasher> {
+        let algorithm = if bytes.len() >= 4 {
+            u32::from_le_bytes(bytes[..4].try_into().unwrap())
+        } else {
+            u32::MAX
+        };
+        match algorithm {
+            _ if bytes.len() != object_size(algorithm) => None,
+            SHA2_224 => Sha256::load(&bytes[4..], SHA224_SIZE).map(Hasher::Sha256),
+            SHA2_256 => Sha256::load(&bytes[4..], SHA256_SIZE).map(Hasher::Sha256),
+            SHA2_512 => Sha512::load(&bytes[4..]).map(Hasher::Sha512),
+            _ => None,
+        }
+    }
+
+    /// Reads a hasher object, trapping if it is not one
+    unsafe fn load(object: Value) -> Hasher {
+        match Hasher::of_object(hasher_bytes(object)) {
+            Some(hasher) => hasher,
+            None => rts_trap_with("sha2: hasher expected"),
+        }
+    }
+}
+
+unsafe fn hasher_bytes<'a>(object: Value) -> &'a mut [u8] {
+    if object.tag() != TAG_BLOB {
+        rts_trap_with("sha2: hasher expected");
+    }
+    let blob = object.as_blob_mut();
+    core::slice::from_raw_parts_mut(blob.payload_addr(), blob.len().as_usize())
+}
+
+/// A new hasher object of the algorithm, one of the `SHA2_*` constants
+#[ic_mem_fn]
+pub unsafe fn sha2_new<M: Memory>(mem: &mut M, algorithm: u32) -> Value {
+    let hasher = Hasher::new(algorithm);
+    let r = alloc_blob(mem, Bytes(object_size(algorithm) as u32));
+    hasher.store(hasher_bytes(r));
+    r
+}
+
+/// Feeds the bytes of a blob or a text to a hasher object
+#[no_mangle]
+pub unsafe extern "C" fn sha2_update(hasher: Value, value: Value) {
+    let mut state = Hasher::load(hasher);
+    state.update_value(value);
+    state.store(hasher_bytes(hasher));
+}
+
+/// The digest of the bytes fed to a hasher object so far. The hasher object can be fed more.
+#[ic_mem_fn]
+pub unsafe fn sha2_finish<M: Memory>(mem: &mut M, hasher: Value) -> Value {
+    Hasher::load(hasher).digest(mem)
+}
+
+/// The digest of a blob or a text
+#[ic_mem_fn]
+pub unsafe fn sha2_digest<M: Memory>(mem: &mut M, algorithm: u32, value: Value) -> Value {
+    let mut hasher = Hasher::new(algorithm);
+    hasher.update_value(value);
+    hasher.digest(mem)
+}
@@ -10,13 +10,33 @@
 //! for bytes in transit, while bigger chunks will flush this staging area before being written
 //! directly to destination.
 //!
+//! The read side works the same way in reverse: a `StreamReader` pulls chunks from stable
+//! memory into a small staging area on demand, and exposes it through a `Buf` cursor. Only
+//! items of bounded size, like LEB128 numbers (see `fill_leb128` and the bigint decoders that
+//! use it), are decoded from the cursor, as it never holds more than the staging area. Bigger
+//! items are copied out with `read_bytes`, or into a new blob with `stream_reader_read_blob`.
+//!
+//! Only heap graph images (see `graph.rs`) are decoded from the reader on upgrade. Candid images
+//! are not: they are read into one blob with `stream_reader_read_blob`, and decoded from there.
+//! The Candid decoders (`parse_idl_header`, `skip_any` and the generated code) read their `Buf`
+//! directly, and the aliases of stable variables are offsets back into the image, so they
+//! need all of it in the heap. For Candid, the reader only checks the trailer and decompresses.
+//!
+//! Images written to stable memory end with a trailer, which carries their length, a CRC32
+//! checksum of the bytes before it and a format version, so that a corrupted or truncated
+//! image is rejected before decoding starts.
+//!
+//! Both sides can optionally compress the data in stable memory, see `lz.rs`. A compressed
+//! image is a frame: a header that tells it apart from a raw image (which starts with the
+//! magic bytes of its format), the compressed data, and the length of the uncompressed data.
+//! The trailer follows the frame, and covers all of it.
 //!
 
 // Layout of a stream node:
 //
-//      ┌────────────┬─────┬───────┬─────────┬─────────┬───────────┬────────┬──────────┐
-//      │ tag (blob) │ len │ ptr64 │ start64 │ limit64 │ outputter │ filled │ cache... │
-//      └────────────┴─────┴───┴───┴────┴────┴────┴────┴───────────┴────────┴──────────┘
+//      ┌────────────┬─────┬───────┬─────────┬─────────┬───────────┬────────┬──────┬───────┬──────────┬──────────┐
+//      │ tag (blob) │ len │ ptr64 │ start64 │ limit64 │ outputter │ filled │ dest │ chain │ callback │ cache... │
+//      └────────────┴─────┴───┴───┴────┴────┴────┴────┴───────────┴────────┴──────┴───────┴──────────┴──────────┘
 //
 // We reuse the opaque nature of blobs (to Motoko) and stick Rust-related information
 // into the leading bytes:
@@ -25,23 +45,74 @@
 // - `filled` and `cache` are the number of bytes consumed from the blob, and the
 //   staging area of the stream, respectively
 // - `outputter` is the function to be called when `len - filled` approaches zero.
+// - `dest` is the kind of destination (one of the `DEST_*` constants). Streams with a
+//   destination let bigger chunks bypass the cache.
+// - Destinations other than raw stable memory use `ptr64` to count the bytes outputted
+//   after `start64`, see `setup_compressed_stable_dest`.
+//   `chain` is the last chunk written by a heap destination, see `append_to_heap`,
+//   and `callback` is the function a callback destination forwards chunks to.
 // - INVARIANT: keep `BlobStream.{ptr64_field, start64_field, filled_field}`,
 //              (from `compile.ml`) in sync with the layout!
 // - Note: `len` and `filled` are relative to the encompassing blob.
 
 use crate::bigint::{check, mp_get_u32, mp_isneg, mp_iszero};
+use crate::buf::Buf;
+#[cfg(feature = "ic")]
+use crate::lz::{max_compressed_size, Compressor, Decompressor, Sink, Source};
 use crate::mem_utils::memcpy_bytes;
 use crate::memory::{alloc_blob, Memory};
+use crate::principal_id::update_crc32;
 use crate::rts_trap_with;
 use crate::tommath_bindings::{mp_div_2d, mp_int};
-use crate::types::{size_of, Blob, Bytes, Stream, Value, TAG_BLOB};
+use crate::types::{size_of, Blob, Bytes, Stream, StreamReader, Value, Words, TAG_BLOB};
 
 use motoko_rts_macros::ic_mem_fn;
 
 const MAX_STREAM_SIZE: Bytes<u32> = Bytes((1 << 30) - 1);
-const INITIAL_STREAM_FILLED: Bytes<u32> = Bytes(32);
+const INITIAL_STREAM_FILLED: Bytes<u32> = Bytes(48);
 const STREAM_CHUNK_SIZE: Bytes<u32> = Bytes(128);
 
+/// The destinations of a stream
+pub const DEST_NONE: u32 = 0;
+pub const DEST_STABLE: u32 = 1;
+pub const DEST_HEAP: u32 = 2;
+pub const DEST_CALLBACK: u32 = 3;
+pub const DEST_COMPRESSED_STABLE: u32 = 4;
+
+/// A callback that receives the chunks of a stream
+pub type StreamCallback = extern "C" fn(stream: *mut Stream, ptr: *const u8, n: u32);
+
+/// Compressed frames start with these magic bytes and the format version, and end with the
+/// length of the uncompressed image (64 bits, little endian)
+pub const COMPRESSED_MAGIC: [u8; 4] = *b"MOLZ";
+pub const COMPRESSED_VERSION: u8 = 1;
+pub const COMPRESSED_HEADER_SIZE: u32 = 5;
+pub const COMPRESSED_FOOTER_SIZE: u32 = 8;
+
+/// Images in stable memory end with a trailer: the length of the image before the trailer
+/// (64 bits), its CRC32 checksum and the format version (32 bits each), all little endian
+pub const IMAGE_VERSION: u32 = 1;
+pub const IMAGE_TRAILER_SIZE: u32 = 16;
+
+// Only one image is written or read at a time
+#[cfg(feature = "ic")]
+static mut IMAGE_CHECKSUM: u32 = 0;
+#[cfg(feature = "ic")]
+static mut COMPRESSOR: Compressor = Compressor::new();
+#[cfg(feature = "ic")]
+static mut DECOMPRESSOR: Decompressor = Decompressor::new();
+#[cfg(feature = "ic")]
+static mut IMAGE_SINK: StableSink = StableSink {
+    ptr64: 0,
+    limit64: 0,
+    grown64: 0,
+};
+#[cfg(feature = "ic")]
+static mut COMPRESSED_SOURCE: StableSource = StableSource {
+    ptr64: 0,
+    limit64: 0,
+};
+
 #[ic_mem_fn]
 pub unsafe fn alloc_stream<M: Memory>(mem: &mut M, size: Bytes<u32>) -> *mut Stream {
     debug_assert_eq!(
@@ -57,13 +128,19 @@ pub unsafe fn alloc_stream<M: Memory>(mem: &mut M, size: Bytes<u32>) -> *mut Str
     (*stream).limit64 = 0;
     (*stream).outputter = Stream::no_backing_store;
     (*stream).filled = INITIAL_STREAM_FILLED;
+    (*stream).dest = DEST_NONE;
+    (*stream).chain = Value::from_scalar(0);
+    (*stream).callback = None;
     stream
 }
 
 #[allow(dead_code)]
 extern "C" {
     // generated by `moc`
+    fn stable64_size_moc() -> u64;
+    fn stable64_grow_moc(pages: u64) -> u64;
     fn stable64_write_moc(to: u64, ptr: u64, n: u64);
+    fn stable64_read_moc(ptr: u64, from: u64, n: u64);
 }
 
 impl Stream {
@@ -98,15 +175,17 @@ impl Stream {
     #[cfg(feature = "ic")]
     fn send_to_stable(self: *mut Self, ptr: *const u8, n: Bytes<u32>) {
         unsafe {
-            let next_ptr64 = (*self).ptr64 + n.as_u32() as u64;
-            stable64_write_moc((*self).ptr64, ptr as u64, n.as_u32() as u64);
-            (*self).ptr64 = next_ptr64
+            IMAGE_SINK.write(core::slice::from_raw_parts(ptr, n.as_usize()));
+            (*self).ptr64 += n.as_u32() as u64
         }
     }
 
     #[cfg(feature = "ic")]
-    /// Sets up the bottleneck routine to output towards a range of stable memory
-    /// Note: assumes that the entire byte range is writable
+    /// Sets up the bottleneck routine to output an image towards a range of stable memory.
+    /// The image ends with a trailer on shutdown, and traps if it does not fit.
+    /// Stable memory grows as the image is written, so `limit` can be `u64::MAX` for an
+    /// image whose size is not known in advance. The trailer takes `IMAGE_TRAILER_SIZE`
+    /// bytes beyond the data
     #[export_name = "stream_stable_dest"]
     pub fn setup_stable_dest(self: *mut Self, start: u64, limit: u64) {
         unsafe {
@@ -114,6 +193,132 @@ impl Stream {
             (*self).start64 = start;
             (*self).limit64 = limit;
             (*self).outputter = Self::send_to_stable;
+            (*self).dest = DEST_STABLE;
+            IMAGE_SINK = StableSink {
+                ptr64: start,
+                limit64: limit,
+                grown64: 0,
+            };
+            IMAGE_CHECKSUM = 0;
+        }
+    }
+
+    #[cfg(feature = "ic")]
+    fn compress_to_stable(self: *mut Self, ptr: *const u8, n: Bytes<u32>) {
+        unsafe {
+            COMPRESSOR.write(&mut IMAGE_SINK, ptr, n.as_u32());
+            (*self).ptr64 += n.as_u32() as u64
+        }
+    }
+
+    #[cfg(feature = "ic")]
+    /// Like `setup_stable_dest`, but compresses the bytes into a frame. `ptr64` counts
+    /// the uncompressed bytes, so that offsets within the image are the same as without
+    /// compression, until shutdown sets it to the end of the frame.
+    /// Note: `compressed_image_size` bytes from `start` must fit below `limit`, for an
+    /// image that is not bigger than the given size
+    #[export_name = "stream_stable_dest_compressed"]
+    pub fn setup_compressed_stable_dest(self: *mut Self, start: u64, limit: u64) {
+        unsafe {
+            self.setup_stable_dest(start, limit);
+            let mut header = [0u8; COMPRESSED_HEADER_SIZE as usize];
+            header[..4].copy_from_slice(&COMPRESSED_MAGIC);
+            header[4] = COMPRESSED_VERSION;
+            IMAGE_SINK.write(&header);
+            COMPRESSOR.reset();
+            (*self).outputter = Self::compress_to_stable;
+            (*self).dest = DEST_COMPRESSED_STABLE;
+        }
+    }
+
+    /// Append a chunk to the chain of heap blobs of the stream. Each blob of the
+    /// chain starts with the previous one, the last one is in `chain`.
+    /// Note: like the stream itself, the chain is only used within a message, so
+    /// no GC happens while it is reachable from the stream only
+    pub unsafe fn append_to_heap<M: Memory>(
+        self: *mut Self,
+        mem: &mut M,
+        ptr: *const u8,
+        n: Bytes<u32>,
+    ) {
+        let link = Words(1).to_bytes();
+        let chunk = alloc_blob(mem, link + n);
+        let payload = chunk.as_blob_mut().payload_addr();
+        *(payload as *mut Value) = (*self).chain;
+        memcpy_bytes(payload as usize + link.as_usize(), ptr as usize, n);
+        (*self).chain = chunk;
+        (*self).ptr64 += n.as_u32() as u64
+    }
+
+    /// Concatenate the chunks of a stream that outputs to the heap, including the
+    /// cached ones, into a single blob. The stream starts over empty.
+    pub unsafe fn concat_heap<M: Memory>(self: *mut Self, mem: &mut M) -> Value {
+        self.flush();
+        if (*self).ptr64 > MAX_STREAM_SIZE.as_u32() as u64 {
+            rts_trap_with("stream_split: Output too large");
+        }
+        let link = Words(1).to_bytes();
+        let blob = alloc_blob(mem, Bytes((*self).ptr64 as u32));
+        let mut end = blob.as_blob_mut().payload_addr() as usize + (*self).ptr64 as usize;
+        // Walk back from the last chunk
+        let mut chunk = (*self).chain;
+        while chunk.is_ptr() {
+            let chunk_blob = chunk.as_blob_mut();
+            let payload = chunk_blob.payload_addr();
+            let n = chunk_blob.len() - link;
+            end -= n.as_usize();
+            memcpy_bytes(end, payload as usize + link.as_usize(), n);
+            chunk = *(payload as *const Value);
+        }
+        debug_assert_eq!(end, blob.as_blob_mut().payload_addr() as usize);
+        (*self).ptr64 = 0;
+        (*self).chain = Value::from_scalar(0);
+        blob
+    }
+
+    #[cfg(feature = "ic")]
+    fn send_to_heap(self: *mut Self, ptr: *const u8, n: Bytes<u32>) {
+        unsafe { self.append_to_heap(&mut crate::memory::ic::IcMemory, ptr, n) }
+    }
+
+    #[cfg(feature = "ic")]
+    /// Sets up the bottleneck routine to output towards a chain of heap blobs,
+    /// which `split` concatenates, so that the final size need not be known
+    #[export_name = "stream_heap_dest"]
+    pub fn setup_heap_dest(self: *mut Self) {
+        self.setup_heap_outputter(Self::send_to_heap)
+    }
+
+    /// Like `setup_heap_dest`, with an outputter that calls `append_to_heap`
+    pub fn setup_heap_outputter(self: *mut Self, outputter: fn(*mut Self, *const u8, Bytes<u32>)) {
+        unsafe {
+            (*self).ptr64 = 0;
+            (*self).start64 = 0;
+            (*self).limit64 = 0;
+            (*self).outputter = outputter;
+            (*self).dest = DEST_HEAP;
+            (*self).chain = Value::from_scalar(0);
+        }
+    }
+
+    fn send_to_callback(self: *mut Self, ptr: *const u8, n: Bytes<u32>) {
+        unsafe {
+            let callback = (*self).callback.unwrap();
+            callback(self, ptr, n.as_u32());
+            (*self).ptr64 += n.as_u32() as u64
+        }
+    }
+
+    /// Sets up the bottleneck routine to forward each chunk to `callback`
+    #[export_name = "stream_callback_dest"]
+    pub fn setup_callback_dest(self: *mut Self, callback: StreamCallback) {
+        unsafe {
+            (*self).ptr64 = 0;
+            (*self).start64 = 0;
+            (*self).limit64 = 0;
+            (*self).outputter = Self::send_to_callback;
+            (*self).dest = DEST_CALLBACK;
+            (*self).callback = Some(callback);
         }
     }
 
@@ -121,7 +326,7 @@ impl Stream {
     #[export_name = "stream_write"]
     pub fn cache_bytes(self: *mut Self, ptr: *const u8, n: Bytes<u32>) {
         unsafe {
-            if (*self).limit64 != 0 && n > STREAM_CHUNK_SIZE
+            if (*self).dest != DEST_NONE && n > STREAM_CHUNK_SIZE
                 || (*self).filled + n > (*self).header.len
             {
                 self.flush();
@@ -186,8 +391,14 @@ impl Stream {
     /// Split the stream object into two `Blob`s, a front-runner (small) one
     /// and a latter one that comprises the current amount of the cached bytes.
     /// Lengths are adjusted correspondingly.
+    /// For streams that output to the heap, return the concatenation of all
+    /// the bytes instead.
     #[export_name = "stream_split"]
     pub unsafe fn split(self: *mut Self) -> Value {
+        #[cfg(feature = "ic")]
+        if (*self).dest == DEST_HEAP {
+            return self.concat_heap(&mut crate::memory::ic::IcMemory);
+        }
         if (*self).header.len > (*self).filled {
             self.as_blob_mut().shrink((*self).filled);
         }
@@ -201,8 +412,455 @@ impl Stream {
 
     /// Shut down the stream by outputting all data. Lengths are
     /// adjusted correspondingly, and the stream remains intact.
+    /// Images in stable memory are completed: a compressed frame gets its
+    /// footer, and every image its trailer.
     #[export_name = "stream_shutdown"]
     pub unsafe fn shutdown(self: *mut Self) {
-        self.flush()
+        self.flush();
+        #[cfg(feature = "ic")]
+        if (*self).dest == DEST_COMPRESSED_STABLE {
+            COMPRESSOR.finish(&mut IMAGE_SINK);
+            let length = (*self).ptr64 - (*self).start64;
+            IMAGE_SINK.write(&length.to_le_bytes());
+        }
+        #[cfg(feature = "ic")]
+        if (*self).dest == DEST_STABLE || (*self).dest == DEST_COMPRESSED_STABLE {
+            let length = IMAGE_SINK.ptr64 - (*self).start64;
+            IMAGE_SINK.put(&image_trailer(length, IMAGE_CHECKSUM));
+            (*self).ptr64 = IMAGE_SINK.ptr64;
+        }
+    }
+}
+
+// Layout of a stream reader node:
+//
+//      ┌────────────┬─────┬───────┬─────────┬──────────┬──────────┬────────────┬──────────┐
+//      │ tag (blob) │ len │ ptr64 │ limit64 │ inputter │ seekable │ cursor     │ cache... │
+//      └────────────┴─────┴───┴───┴────┴────┴──────────┴──────────┴─────┴──────┴──────────┘
+//
+// - `ptr64` and `limit64` are the next and past-end pointers into stable memory,
+//   the bytes before `ptr64` are either consumed or in the cache
+// - `cursor` is a `Buf` over the unconsumed bytes of the `cache`
+// - `inputter` is the function to be called to pull bytes into the cache. It
+//   advances `ptr64`.
+// - `seekable` tells whether bytes can be skipped by just advancing `ptr64`,
+//   which is not the case for compressed images
+// - Note: `cursor` points into the blob itself, so the reader must not move
+//   while it is in use (no GC happens during deserialisation).
+
+const MAX_READER_SIZE: Bytes<u32> = MAX_STREAM_SIZE;
+
+#[ic_mem_fn]
+pub unsafe fn alloc_stream_reader<M: Memory>(mem: &mut M, size: Bytes<u32>) -> *mut StreamReader {
+    if size > MAX_READER_SIZE {
+        rts_trap_with("alloc_stream_reader: Cache too large");
+    }
+    if size == Bytes(0) {
+        rts_trap_with("alloc_stream_reader: Cache too small");
+    }
+    let header_size = (size_of::<StreamReader>() - size_of::<Blob>()).to_bytes();
+    let reader = alloc_blob(mem, size + header_size).as_stream_reader();
+    (*reader).ptr64 = 0;
+    (*reader).limit64 = 0;
+    (*reader).inputter = StreamReader::no_backing_store;
+    (*reader).seekable = true;
+    let cache = reader.cache_addr();
+    (*reader).cursor = Buf {
+        ptr: cache,
+        end: cache,
+    };
+    reader
+}
+
+impl StreamReader {
+    #[inline]
+    pub unsafe fn cache_addr(self: *mut Self) -> *mut u8 {
+        self.add(1) as *mut u8 // skip closure header
+    }
+
+    /// Size of the staging area
+    pub unsafe fn capacity(self: *mut Self) -> Bytes<u32> {
+        let header_size = (size_of::<StreamReader>() - size_of::<Blob>()).to_bytes();
+        (*self).header.len - header_size
+    }
+
+    /// Number of bytes in the cache that are not consumed yet
+    pub unsafe fn available(self: *mut Self) -> Bytes<u32> {
+        Bytes(((*self).cursor.end as usize - (*self).cursor.ptr as usize) as u32)
+    }
+
+    fn no_backing_store(self: *mut Self, _ptr: *mut u8, _n: Bytes<u32>) {
+        assert!(false)
+    }
+
+    #[cfg(feature = "ic")]
+    fn receive_from_stable(self: *mut Self, ptr: *mut u8, n: Bytes<u32>) {
+        unsafe {
+            stable64_read_moc(ptr as u64, (*self).ptr64, n.as_u32() as u64);
+            (*self).ptr64 += n.as_u32() as u64
+        }
+    }
+
+    #[cfg(feature = "ic")]
+    fn decompress_from_stable(self: *mut Self, ptr: *mut u8, n: Bytes<u32>) {
+        unsafe {
+            DECOMPRESSOR.decompress(&mut COMPRESSED_SOURCE, ptr, n.as_u32());
+            (*self).ptr64 += n.as_u32() as u64
+        }
+    }
+
+    /// Sets up the reader to pull the bytes from `start` to `limit` with `inputter`,
+    /// which is called with the position in the cache and the number of bytes, and
+    /// must advance `ptr64` past the bytes it provides
+    pub unsafe fn setup_source(
+        self: *mut Self,
+        start: u64,
+        limit: u64,
+        inputter: fn(*mut Self, *mut u8, Bytes<u32>),
+    ) {
+        (*self).ptr64 = start;
+        (*self).limit64 = limit;
+        (*self).inputter = inputter;
+        (*self).seekable = true;
+        let cache = self.cache_addr();
+        (*self).cursor = Buf {
+            ptr: cache,
+            end: cache,
+        };
+    }
+
+    #[cfg(feature = "ic")]
+    /// Sets up the reader to pull from a range of stable memory
+    /// Note: assumes that the entire byte range is readable
+    #[export_name = "stream_reader_stable_source"]
+    pub unsafe fn setup_stable_source(self: *mut Self, start: u64, limit: u64) {
+        self.setup_source(start, limit, Self::receive_from_stable)
+    }
+
+    #[cfg(feature = "ic")]
+    /// Sets up the reader to pull the image that a stream wrote to a range of stable memory,
+    /// trailer included. The image is verified first, and decompressed if it is a frame.
+    #[export_name = "stream_reader_stable_image"]
+    pub unsafe fn setup_stable_image(self: *mut Self, start: u64, limit: u64) {
+        let end = start + verify_stable_image(start, limit);
+        let mut header = [0u8; COMPRESSED_HEADER_SIZE as usize];
+        let frame = (COMPRESSED_HEADER_SIZE + COMPRESSED_FOOTER_SIZE) as u64;
+        if end - start >= frame {
+            stable64_read_moc(header.as_mut_ptr() as u64, start, header.len() as u64);
+        }
+        if header[..4] != COMPRESSED_MAGIC {
+            return self.setup_stable_source(start, end);
+        }
+        if header[4] != COMPRESSED_VERSION {
+            rts_trap_with("stream_reader_stable_image: Unsupported compression version");
+        }
+        let footer = end - COMPRESSED_FOOTER_SIZE as u64;
+        let mut length = [0u8; COMPRESSED_FOOTER_SIZE as usize];
+        stable64_read_moc(length.as_mut_ptr() as u64, footer, length.len() as u64);
+        DECOMPRESSOR.reset();
+        COMPRESSED_SOURCE = StableSource {
+            ptr64: start + COMPRESSED_HEADER_SIZE as u64,
+            limit64: footer,
+        };
+        self.setup_source(0, u64::from_le_bytes(length), Self::decompress_from_stable);
+        (*self).seekable = false;
+    }
+
+    /// Number of bytes not consumed yet, cached or not
+    #[export_name = "stream_reader_remaining"]
+    pub unsafe fn remaining(self: *mut Self) -> u64 {
+        self.available().as_u32() as u64 + ((*self).limit64 - (*self).ptr64)
+    }
+
+    /// Make at least `n` bytes available in the cursor, or as many as remain, and
+    /// return the cursor. Reading past the cursor then traps like reading past the
+    /// end of any other buffer. `n` is at most the capacity of the reader, values
+    /// that can be longer are read with `read_bytes` or `stream_reader_read_blob`.
+    #[export_name = "stream_reader_fill"]
+    pub unsafe fn fill(self: *mut Self, n: Bytes<u32>) -> *mut Buf {
+        let available = self.available();
+        if available < n && (*self).ptr64 < (*self).limit64 {
+            if n > self.capacity() {
+                rts_trap_with("stream_reader_fill: Request too large");
+            }
+            // Move the unconsumed bytes to the front, and pull as many as fit behind them
+            let cache = self.cache_addr();
+            core::ptr::copy((*self).cursor.ptr, cache, available.as_usize());
+            let pull = core::cmp::min(
+                (self.capacity() - available).as_u32() as u64,
+                (*self).limit64 - (*self).ptr64,
+            ) as u32;
+            ((*self).inputter)(self, cache.add(available.as_usize()), Bytes(pull));
+            (*self).cursor = Buf {
+                ptr: cache,
+                end: cache.add((available.as_u32() + pull) as usize),
+            };
+        }
+        &mut (*self).cursor
+    }
+
+    /// Make a complete (S)LEB128 number available in the cursor, and return the
+    /// cursor. Numbers may be as long as the cache.
+    #[export_name = "stream_reader_fill_leb128"]
+    pub unsafe fn fill_leb128(self: *mut Self) -> *mut Buf {
+        let mut scanned = 0;
+        loop {
+            let available = self.available().as_u32();
+            let ptr = (*self).cursor.ptr;
+            while scanned < available {
+                if *ptr.add(scanned as usize) & 0b1000_0000 == 0 {
+                    return &mut (*self).cursor;
+                }
+                scanned += 1;
+            }
+            if (*self).ptr64 == (*self).limit64 {
+                // Truncated, the consumer will trap
+                return &mut (*self).cursor;
+            }
+            if available == self.capacity().as_u32() {
+                rts_trap_with("stream_reader_fill_leb128: Number too large");
+            }
+            self.fill(Bytes(available + 1));
+        }
+    }
+
+    /// Copy the next `n` bytes to `dest`. Bigger chunks are read directly from
+    /// the source, bypassing the cache.
+    #[export_name = "stream_reader_read"]
+    pub unsafe fn read_bytes(self: *mut Self, dest: *mut u8, n: Bytes<u32>) {
+        if (n.as_u32() as u64) > self.remaining() {
+            rts_trap_with("stream_reader_read: Out of data");
+        }
+        let cached = core::cmp::min(self.available(), n);
+        memcpy_bytes(dest as usize, (*self).cursor.ptr as usize, cached);
+        (*self).cursor.ptr = (*self).cursor.ptr.add(cached.as_usize());
+        let rest = n - cached;
+        if rest > STREAM_CHUNK_SIZE || rest > self.capacity() {
+            ((*self).inputter)(self, dest.add(cached.as_usize()), rest);
+        } else if rest > Bytes(0) {
+            let cursor = self.fill(rest);
+            memcpy_bytes(
+                dest as usize + cached.as_usize(),
+                (*cursor).ptr as usize,
+                rest,
+            );
+            (*cursor).ptr = (*cursor).ptr.add(rest.as_usize());
+        }
+    }
+
+    /// Skip the next `n` bytes, without reading the ones that are not cached
+    /// when the source is seekable
+    #[export_name = "stream_reader_skip"]
+    pub unsafe fn skip_bytes(self: *mut Self, n: u64) {
+        if n > self.remaining() {
+            rts_trap_with("stream_reader_skip: Out of data");
+        }
+        let cached = core::cmp::min(self.available().as_u32() as u64, n);
+        (*self).cursor.ptr = (*self).cursor.ptr.add(cached as usize);
+        let mut rest = n - cached;
+        if (*self).seekable {
+            (*self).ptr64 += rest;
+        } else {
+            // Pull the bytes through the cache, which is empty by now
+            let cache = self.cache_addr();
+            while rest > 0 {
+                let chunk = core::cmp::min(rest, self.capacity().as_u32() as u64) as u32;
+                ((*self).inputter)(self, cache, Bytes(chunk));
+                rest -= chunk as u64;
+            }
+            (*self).cursor = Buf {
+                ptr: cache,
+                end: cache,
+            };
+        }
+    }
+}
+
+/// Copy the next `n` bytes of a reader to a new blob, refilling the cache as needed
+#[ic_mem_fn]
+pub unsafe fn stream_reader_read_blob<M: Memory>(
+    mem: &mut M,
+    reader: *mut StreamReader,
+    n: Bytes<u32>,
+) -> Value {
+    if (n.as_u32() as u64) > reader.remaining() {
+        rts_trap_with("stream_reader_read_blob: Out of data");
+    }
+    let blob = alloc_blob(mem, n);
+    reader.read_bytes(blob.as_blob_mut().payload_addr(), n);
+    blob
+}
+
+/// Trailer of an image of `length` bytes with checksum `checksum`
+pub fn image_trailer(length: u64, checksum: u32) -> [u8; IMAGE_TRAILER_SIZE as usize] {
+    let mut trailer = [0u8; IMAGE_TRAILER_SIZE as usize];
+    trailer[..8].copy_from_slice(&length.to_le_bytes());
+    trailer[8..12].copy_from_slice(&checksum.to_le_bytes());
+    trailer[12..].copy_from_slice(&IMAGE_VERSION.to_le_bytes());
+    trailer
+}
+
+/// Length and checksum recorded in the trailer of an image of `size` bytes, trailer included,
+/// or why the image is corrupt
+fn read_image_trailer(
+    trailer: &[u8; IMAGE_TRAILER_SIZE as usize],
+    size: u64,
+) -> Result<(u64, u32), &'static str> {
+    let mut length = [0u8; 8];
+    length.copy_from_slice(&trailer[..8]);
+    let mut checksum = [0u8; 4];
+    checksum.copy_from_slice(&trailer[8..12]);
+    let mut version = [0u8; 4];
+    version.copy_from_slice(&trailer[12..]);
+
+    if u32::from_le_bytes(version) != IMAGE_VERSION {
+        return Err("unsupported image version");
+    }
+    let length = u64::from_le_bytes(length);
+    if length != size - IMAGE_TRAILER_SIZE as u64 {
+        return Err("image length mismatch");
+    }
+    Ok((length, u32::from_le_bytes(checksum)))
+}
+
+/// Length of the image `image` without its trailer, or why it is corrupt
+pub fn check_image(image: &[u8]) -> Result<u64, &'static str> {
+    if image.len() < IMAGE_TRAILER_SIZE as usize {
+        return Err("image too short");
+    }
+    let (data, trailer_bytes) = image.split_at(image.len() - IMAGE_TRAILER_SIZE as usize);
+    let mut trailer = [0u8; IMAGE_TRAILER_SIZE as usize];
+    trailer.copy_from_slice(trailer_bytes);
+    let (length, checksum) = read_image_trailer(&trailer, image.len() as u64)?;
+    if update_crc32(0, data) != checksum {
+        return Err("image checksum mismatch");
+    }
+    Ok(length)
+}
+
+/// Verifies an image that was copied to a blob, and drops its trailer
+#[no_mangle]
+pub unsafe extern "C" fn verify_image_blob(blob: Value) {
+    let blob = blob.as_blob_mut();
+    let image = core::slice::from_raw_parts(blob.payload_const(), blob.len().as_usize());
+    match check_image(image) {
+        Ok(length) => blob.shrink(Bytes(length as u32)),
+        Err(msg) => rts_trap_with(msg),
+    }
+}
+
+/// Verifies an image in stable memory from `start` to `limit`, and returns its length without
+/// the trailer
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn verify_stable_image(start: u64, limit: u64) -> u64 {
+    let size = limit - start;
+    if size < IMAGE_TRAILER_SIZE as u64 {
+        rts_trap_with("image too short");
+    }
+    let mut trailer = [0u8; IMAGE_TRAILER_SIZE as usize];
+    stable64_read_moc(
+        trailer.as_mut_ptr() as u64,
+        limit - IMAGE_TRAILER_SIZE as u64,
+        IMAGE_TRAILER_SIZE as u64,
+    );
+    let (length, checksum) = match read_image_trailer(&trailer, size) {
+        Ok(trailer) => trailer,
+        Err(msg) => rts_trap_with(msg),
+    };
+
+    let mut chunk = [0u8; 1024];
+    let mut crc = 0;
+    let mut ptr64 = start;
+    while ptr64 < start + length {
+        let n = core::cmp::min(chunk.len() as u64, start + length - ptr64) as usize;
+        stable64_read_moc(chunk.as_mut_ptr() as u64, ptr64, n as u64);
+        crc = update_crc32(crc, &chunk[..n]);
+        ptr64 += n as u64;
+    }
+    if crc != checksum {
+        rts_trap_with("image checksum mismatch");
+    }
+    length
+}
+
+/// Zeroes stable memory from `start` to `limit`, e.g. an image that was read, so that the
+/// pages are fresh again
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub unsafe extern "C" fn stable_clear(start: u64, limit: u64) {
+    let zeros = [0u8; 1024];
+    let mut ptr64 = start;
+    while ptr64 < limit {
+        let n = core::cmp::min(zeros.len() as u64, limit - ptr64);
+        stable64_write_moc(ptr64, zeros.as_ptr() as u64, n);
+        ptr64 += n;
+    }
+}
+
+/// Size of the range of stable memory for a compressed image of up to `n` bytes, trailer
+/// included
+#[cfg(feature = "ic")]
+#[no_mangle]
+pub extern "C" fn compressed_image_size(n: u64) -> u64 {
+    let frame = (COMPRESSED_HEADER_SIZE + COMPRESSED_FOOTER_SIZE) as u64;
+    max_compressed_size(n) + frame + IMAGE_TRAILER_SIZE as u64
+}
+
+/// The bytes of an image go to a range of stable memory, raw or compressed
+#[cfg(feature = "ic")]
+struct StableSink {
+    ptr64: u64,
+    limit64: u64,
+    /// Size of stable memory in bytes, as far as known
+    grown64: u64,
+}
+
+#[cfg(feature = "ic")]
+impl StableSink {
+    /// Writes bytes at `ptr64`, trapping if they go beyond `limit64`, and grows stable
+    /// memory if they go beyond its end
+    unsafe fn put(&mut self, bytes: &[u8]) {
+        if bytes.len() as u64 > self.limit64 - self.ptr64 {
+            rts_trap_with("stream: image does not fit its stable memory range");
+        }
+        let end = self.ptr64 + bytes.len() as u64;
+        if end > self.grown64 {
+            let page_size = crate::constants::WASM_PAGE_SIZE.as_u32() as u64;
+            let size = stable64_size_moc();
+            let pages = (end + page_size - 1) / page_size;
+            if pages > size && stable64_grow_moc(pages - size) == u64::MAX {
+                rts_trap_with("stream: out of stable memory");
+            }
+            self.grown64 = core::cmp::max(pages, size) * page_size;
+        }
+        stable64_write_moc(self.ptr64, bytes.as_ptr() as u64, bytes.len() as u64);
+        self.ptr64 += bytes.len() as u64
+    }
+}
+
+#[cfg(feature = "ic")]
+impl Sink for StableSink {
+    /// Writes bytes of the image, and adds them to its checksum
+    unsafe fn write(&mut self, bytes: &[u8]) {
+        IMAGE_CHECKSUM = update_crc32(IMAGE_CHECKSUM, bytes);
+        self.put(bytes)
+    }
+}
+
+/// Compressed bytes come from a range of stable memory
+#[cfg(feature = "ic")]
+struct StableSource {
+    ptr64: u64,
+    limit64: u64,
+}
+
+#[cfg(feature = "ic")]
+impl Source for StableSource {
+    unsafe fn read(&mut self, dest: *mut u8, n: u32) -> u32 {
+        let n = core::cmp::min(n as u64, self.limit64 - self.ptr64);
+        stable64_read_moc(dest as u64, self.ptr64, n);
+        self.ptr64 += n;
+        n as u32
     }
 }
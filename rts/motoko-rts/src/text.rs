@@ -124,7 +124,7 @@ struct Crumb {
 }
 
 #[no_mangle]
-unsafe extern "C" fn text_to_buf(mut s: Value, mut buf: *mut u8) {
+pub(crate) unsafe extern "C" fn text_to_buf(mut s: Value, mut buf: *mut u8) {
     let mut next_crumb: *const Crumb = core::ptr::null();
 
     loop {
@@ -180,6 +180,27 @@ unsafe extern "C" fn stream_write_text(stream: *mut Stream, mut s: Value) {
     }
 }
 
+/// Visit the bytes of a text in order, one blob at a time, without flattening it. Stops early
+/// and returns `false` when the visitor returns `false`.
+pub(crate) unsafe fn text_chunks<F: FnMut(&[u8]) -> bool>(mut s: Value, f: &mut F) -> bool {
+    loop {
+        let s_ptr = s.as_obj();
+        if s_ptr.tag() == TAG_BLOB {
+            let blob = s_ptr.as_blob();
+            return f(slice::from_raw_parts(
+                blob.payload_const(),
+                blob.len().as_usize(),
+            ));
+        } else {
+            let concat = s_ptr.as_concat();
+            if !text_chunks(concat.text1(), f) {
+                return false;
+            }
+            s = concat.text2()
+        }
+    }
+}
+
 // Straighten into contiguous memory, if needed (e.g. for system calls)
 #[ic_mem_fn]
 pub unsafe fn blob_of_text<M: Memory>(mem: &mut M, s: Value) -> Value {
@@ -24,8 +24,10 @@ use crate::memory::Memory;
 use crate::tommath_bindings::{mp_digit, mp_int};
 use core::ops::{Add, AddAssign, Div, Mul, Sub, SubAssign};
 
+use crate::buf::Buf;
 use crate::constants::WORD_SIZE;
 use crate::rts_trap_with;
+use crate::stream::StreamCallback;
 
 pub fn size_of<T>() -> Words<u32> {
     Bytes(::core::mem::size_of::<T>() as u32).to_words()
@@ -308,6 +310,14 @@ impl Value {
         self.get_ptr() as *mut Stream
     }
 
+    /// Get the pointer as `StreamReader`, which is a glorified `Blob` too.
+    /// In debug mode panics if the value is not a pointer or the
+    /// pointed object is not a `Blob`.
+    pub unsafe fn as_stream_reader(self) -> *mut StreamReader {
+        debug_assert_eq!(self.tag(), TAG_BLOB);
+        self.get_ptr() as *mut StreamReader
+    }
+
     /// Get the pointer as `BigInt`. In debug mode panics if the value is not a pointer or the
     /// pointed object is not a `BigInt`.
     pub unsafe fn as_bigint(self) -> *mut BigInt {
@@ -557,7 +567,20 @@ pub struct Stream {
     pub start64: u64,
     pub limit64: u64,
     pub outputter: fn(*mut Self, *const u8, Bytes<u32>) -> (),
-    pub filled: Bytes<u32>, // cache data follows ..
+    pub filled: Bytes<u32>,
+    pub dest: u32,
+    pub chain: Value,
+    pub callback: Option<StreamCallback>, // cache data follows ..
+}
+
+#[repr(C)] // See the note at the beginning of this module
+pub struct StreamReader {
+    pub header: Blob,
+    pub ptr64: u64,
+    pub limit64: u64,
+    pub inputter: fn(*mut Self, *mut u8, Bytes<u32>) -> (),
+    pub seekable: bool,
+    pub cursor: Buf, // cache data follows ..
 }
 
 /// A forwarding pointer placed by the GC in place of an evacuated object.
@@ -661,6 +684,11 @@ impl Bits64 {
     pub fn bits(&self) -> u64 {
         (u64::from(self.bits_hi) << 32) | u64::from(self.bits_lo)
     }
+
+    pub fn set_bits(&mut self, bits: u64) {
+        self.bits_lo = bits as u32;
+        self.bits_hi = (bits >> 32) as u32;
+    }
 }
 
 #[repr(C)] // See the note at the beginning of this module
@@ -283,6 +283,8 @@ module E = struct
       (* Sanity check: Nothing should bump end_of_static_memory once it has been read *)
     static_roots : int32 list ref;
       (* GC roots in static memory. (Everything that may be mutable.) *)
+    typtbl_typs : Type.typ list ref;
+      (* Expected types of subtype checks, see Serialization.static_typtbl *)
 
     (* Metadata *)
     args : (bool * string) option ref;
@@ -324,6 +326,7 @@ module E = struct
     static_memory = ref [];
     static_memory_frozen = ref false;
     static_roots = ref [];
+    typtbl_typs = ref [];
     (* Metadata *)
     args = ref None;
     service = ref None;
@@ -561,6 +564,18 @@ module E = struct
   let get_static_memory env =
     !(env.static_memory)
 
+  (* Returns the position of the type among the main types of the expected
+     type table *)
+  let add_typtbl_typ (env : t) ty : int32 =
+    let rec go i = function
+      | [] -> reg env.typtbl_typs ty
+      | ty' :: tys when Type.Ord.compare ty ty' = 0 -> Int32.of_int i
+      | _ :: tys -> go (i + 1) tys
+    in go 0 !(env.typtbl_typs)
+
+  let get_typtbl_typs (env : t) =
+    !(env.typtbl_typs)
+
   let mem_size env =
     Int32.(add (div (get_end_of_static_memory env) page_size) 1l)
 
@@ -610,6 +625,16 @@ let compile_eq_const = function
   | 0l -> G.i (Test (Wasm.Values.I32 I32Op.Eqz))
   | i -> compile_rel_const I32Op.Eq i
 
+(* Traps with an IDL error, and where in the message it happened,
+   see rts/motoko-rts/src/idl/error.rs. The RTS adds the "IDL error: " prefix. *)
+let idl_trap_with env msg =
+  compile_unboxed_const (Int32.add ptr_unskew (E.add_static env StaticBytes.[Bytes msg])) ^^
+  compile_unboxed_const (Int32.of_int (String.length msg)) ^^
+  E.call_import env "rts" "idl_trap" ^^
+  G.i Unreachable
+let idl_then_trap_with env msg = G.if0 (idl_trap_with env msg) G.nop
+let idl_else_trap_with env msg = G.if0 G.nop (idl_trap_with env msg)
+
 let compile_op64_const op i =
     compile_const_64 i ^^
     G.i (Binary (Wasm.Values.I64 op))
@@ -825,6 +850,8 @@ module RTS = struct
     E.add_func_import env "rts" "memcmp" [I32Type; I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "version" [] [I32Type];
     E.add_func_import env "rts" "parse_idl_header" [I32Type; I32Type; I32Type; I32Type; I32Type] [];
+    E.add_func_import env "rts" "idl_sub_cache" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "idl_sub" [I32Type; I32Type; I32Type; I32Type; I32Type; I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "leb128_decode" [I32Type] [I32Type];
     E.add_func_import env "rts" "sleb128_decode" [I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_of_word32" [I32Type] [I32Type];
@@ -855,7 +882,17 @@ module RTS = struct
     E.add_func_import env "rts" "bigint_neg" [I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_lsh" [I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_rsh" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_and" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_or" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_xor" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_not" [I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_test_bit" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_set_bit" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_clear_bit" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_popcount" [I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_bit_length" [I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_abs" [I32Type] [I32Type];
+    E.add_func_import env "rts" "bigint_of_text" [I32Type; I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_leb128_size" [I32Type] [I32Type];
     E.add_func_import env "rts" "bigint_leb128_encode" [I32Type; I32Type] [];
     E.add_func_import env "rts" "bigint_leb128_stream_encode" [I32Type; I32Type] [];
@@ -874,6 +911,19 @@ module RTS = struct
     E.add_func_import env "rts" "skip_any" [I32Type; I32Type; I32Type; I32Type] [];
     E.add_func_import env "rts" "find_field" [I32Type; I32Type; I32Type; I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "skip_fields" [I32Type; I32Type; I32Type; I32Type] [];
+    E.add_func_import env "rts" "idl_charge_decoding" [I32Type] [];
+    E.add_func_import env "rts" "idl_set_decoding_quota" [I32Type; I32Type] [];
+    E.add_func_import env "rts" "idl_set_skip_limits" [I32Type; I32Type] [];
+    E.add_func_import env "rts" "idl_error_arg" [I32Type] [];
+    E.add_func_import env "rts" "idl_error_push_field" [I32Type] [];
+    E.add_func_import env "rts" "idl_error_push_elem" [] [];
+    E.add_func_import env "rts" "idl_error_set_elem" [I32Type] [];
+    E.add_func_import env "rts" "idl_error_push_opt" [] [];
+    E.add_func_import env "rts" "idl_error_push_variant" [I32Type] [];
+    E.add_func_import env "rts" "idl_error_push_var" [] [];
+    E.add_func_import env "rts" "idl_error_pop" [] [];
+    E.add_func_import env "rts" "idl_trap" [I32Type; I32Type] [];
+    E.add_func_import env "rts" "idl_end_message" [] [];
     E.add_func_import env "rts" "remember_continuation" [I32Type] [I32Type];
     E.add_func_import env "rts" "recall_continuation" [I32Type] [I32Type];
     E.add_func_import env "rts" "peek_future_continuation" [I32Type] [I32Type];
@@ -908,6 +958,7 @@ module RTS = struct
     E.add_func_import env "rts" "log" [F64Type] [F64Type]; (* musl *)
     E.add_func_import env "rts" "fmod" [F64Type; F64Type] [F64Type]; (* remainder, musl *)
     E.add_func_import env "rts" "float_fmt" [F64Type; I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "float_of_text" [I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "char_to_upper" [I32Type] [I32Type];
     E.add_func_import env "rts" "char_to_lower" [I32Type] [I32Type];
     E.add_func_import env "rts" "char_is_whitespace" [I32Type] [I32Type];
@@ -936,6 +987,26 @@ module RTS = struct
     E.add_func_import env "rts" "stream_shutdown" [I32Type] [];
     E.add_func_import env "rts" "stream_reserve" [I32Type; I32Type] [I32Type];
     E.add_func_import env "rts" "stream_stable_dest" [I32Type; I64Type; I64Type] [];
+    E.add_func_import env "rts" "stream_stable_dest_compressed" [I32Type; I64Type; I64Type] [];
+    E.add_func_import env "rts" "compressed_image_size" [I64Type] [I64Type];
+    E.add_func_import env "rts" "alloc_stream_reader" [I32Type] [I32Type];
+    E.add_func_import env "rts" "stream_reader_stable_image" [I32Type; I64Type; I64Type] [];
+    E.add_func_import env "rts" "stream_reader_remaining" [I32Type] [I64Type];
+    E.add_func_import env "rts" "stream_reader_read_blob" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "graph_serialize" [I32Type; I32Type; I32Type] [];
+    E.add_func_import env "rts" "graph_image_size" [I32Type; I32Type] [I64Type];
+    E.add_func_import env "rts" "graph_has_signature" [I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "graph_deserialize" [I32Type; I32Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "stable_clear" [I64Type; I64Type] [];
+    E.add_func_import env "rts" "region_init" [I64Type] [];
+    E.add_func_import env "rts" "region_base_page" [] [I64Type];
+    E.add_func_import env "rts" "region_end_page" [] [I64Type];
+    E.add_func_import env "rts" "region_new" [] [I32Type];
+    E.add_func_import env "rts" "region_count" [] [I32Type];
+    E.add_func_import env "rts" "region_size" [I32Type] [I64Type];
+    E.add_func_import env "rts" "region_grow" [I32Type; I64Type] [I64Type];
+    E.add_func_import env "rts" "region_load_blob" [I32Type; I64Type; I32Type] [I32Type];
+    E.add_func_import env "rts" "region_store_blob" [I32Type; I64Type; I32Type] [];
     E.add_func_import env "rts" "init_write_barrier" [] [];
     E.add_func_import env "rts" "write_barrier" [I32Type] [];
     ()
@@ -1996,6 +2067,22 @@ module Float = struct
 
   let unbox env = Heap.load_field_float64 payload_field
 
+  (* Parses a text into a `?Float`, see `float_of_text` in `float/parse.rs` *)
+  let of_text env =
+    Func.share_code1 env "text_to_float" ("text", I32Type) [I32Type] (fun env get_text ->
+      Stack.with_words env "dst" 2l (fun get_dst ->
+        get_text ^^
+        get_dst ^^
+        E.call_import env "rts" "float_of_text" ^^
+        G.if1 I32Type
+          (Opt.inject_noop env
+            (get_dst ^^
+             G.i (Load {ty = F64Type; align = 2; offset = 0l; sz = None}) ^^
+             box env))
+          (Opt.null_lit env)
+      )
+    )
+
 end (* Float *)
 
 
@@ -2046,7 +2133,7 @@ module ReadBuf = struct
     get_delta ^^
     get_end get_buf ^^ get_ptr get_buf ^^ G.i (Binary (Wasm.Values.I32 I32Op.Sub)) ^^
     G.i (Compare (Wasm.Values.I32 I64Op.LeU)) ^^
-    E.else_trap_with env "IDL error: out of bounds read"
+    idl_else_trap_with env "out of bounds read"
 
   let check_page_end env get_buf incr_delta =
     get_ptr get_buf ^^ compile_bitand_const 0xFFFFl ^^
@@ -2160,6 +2247,10 @@ sig
   (* literals *)
   val vanilla_lit : E.t -> Big_int.big_int -> int32
 
+  (* text conversion, given a Text and a radix (i32) on the stack, push a
+     ?Int, which is null for malformed text *)
+  val compile_of_text : E.t -> G.t
+
   (* arithmetic *)
   val compile_abs : E.t -> G.t
   val compile_neg : E.t -> G.t
@@ -2175,6 +2266,20 @@ sig
   val compile_lsh : E.t -> G.t
   val compile_rsh : E.t -> G.t
 
+  (* bitwise operations, treating negative numbers as infinite
+     two's complement bit strings *)
+  val compile_and : E.t -> G.t
+  val compile_or : E.t -> G.t
+  val compile_xor : E.t -> G.t
+  val compile_not : E.t -> G.t
+  (* given a number (vanilla) and a bit index (i32) on the stack *)
+  val compile_test_bit : E.t -> G.t (* pushes a Bool *)
+  val compile_set_bit : E.t -> G.t
+  val compile_clear_bit : E.t -> G.t
+  (* bits of the magnitude, pushes an unsigned i32 *)
+  val compile_popcount : E.t -> G.t
+  val compile_bit_length : E.t -> G.t
+
   (* comparisons *)
   val compile_eq : E.t -> G.t
   val compile_is_negative : E.t -> G.t
@@ -2298,10 +2403,11 @@ module MakeCompact (Num : BigNumType) : BigNumType = struct
   (* check if both arguments are tagged scalars,
      if so, promote to right-0-padded, signed i64 and perform the fast path.
      Otherwise make sure that both arguments are in heap representation,
+     unless the slow path takes tagged scalars as they are (`box_scalars`),
      and run the slow path on them.
      In both cases bring the results into normal form.
    *)
-  let try_unbox2 name fast slow env =
+  let try_unbox2' box_scalars name fast slow env =
     Func.share_code2 env name (("a", I32Type), ("b", I32Type)) [I32Type]
       (fun env get_a get_b ->
         let set_res, get_res = new_local env "res" in
@@ -2318,12 +2424,15 @@ module MakeCompact (Num : BigNumType) : BigNumType = struct
               (get_res64 ^^ box64 env)
           end
           begin
-            get_a ^^ BitTagged.if_tagged_scalar env [I32Type]
-              (get_a ^^ extend_and_box64 env)
-              get_a ^^
-            get_b ^^ BitTagged.if_tagged_scalar env [I32Type]
-              (get_b ^^ extend_and_box64 env)
-              get_b ^^
+            (if box_scalars then
+              get_a ^^ BitTagged.if_tagged_scalar env [I32Type]
+                (get_a ^^ extend_and_box64 env)
+                get_a ^^
+              get_b ^^ BitTagged.if_tagged_scalar env [I32Type]
+                (get_b ^^ extend_and_box64 env)
+                get_b
+            else
+              get_a ^^ get_b) ^^
             slow env ^^ set_res ^^ get_res ^^
             fits_in_vanilla env ^^
             G.if1 I32Type
@@ -2331,18 +2440,25 @@ module MakeCompact (Num : BigNumType) : BigNumType = struct
               get_res
           end)
 
-  let compile_add = try_unbox2 "B_add" Word64.compile_add Num.compile_add
+  let try_unbox2 = try_unbox2' true
+
+  (* Addition, subtraction and multiplication in the RTS take tagged scalars
+     (see `compact` in `bigint.rs`), so that e.g. incrementing a boxed number
+     only allocates the result *)
+  let try_unbox2_scalars = try_unbox2' false
+
+  let compile_add = try_unbox2_scalars "B_add" Word64.compile_add Num.compile_add
 
   let adjust_arg2 code env = compile_shrS64_const 1L ^^ code env
   let adjust_result code env = code env ^^ compile_shl64_const 1L
 
-  let compile_mul = try_unbox2 "B_mul" (adjust_arg2 Word64.compile_mul) Num.compile_mul
-  let compile_signed_sub = try_unbox2 "B+sub" Word64.compile_signed_sub Num.compile_signed_sub
+  let compile_mul = try_unbox2_scalars "B_mul" (adjust_arg2 Word64.compile_mul) Num.compile_mul
+  let compile_signed_sub = try_unbox2_scalars "B+sub" Word64.compile_signed_sub Num.compile_signed_sub
   let compile_signed_div = try_unbox2 "B+div" (adjust_result Word64.compile_signed_div) Num.compile_signed_div
   let compile_signed_mod = try_unbox2 "B_mod" Word64.compile_signed_mod Num.compile_signed_mod
   let compile_unsigned_div = try_unbox2 "B_div" (adjust_result Word64.compile_unsigned_div) Num.compile_unsigned_div
   let compile_unsigned_rem = try_unbox2 "B_rem" Word64.compile_unsigned_rem Num.compile_unsigned_rem
-  let compile_unsigned_sub = try_unbox2 "B_sub" Word64.compile_unsigned_sub Num.compile_unsigned_sub
+  let compile_unsigned_sub = try_unbox2_scalars "B_sub" Word64.compile_unsigned_sub Num.compile_unsigned_sub
 
   let compile_unsigned_pow env =
     Func.share_code2 env "B_pow" (("a", I32Type), ("b", I32Type)) [I32Type]
@@ -2473,6 +2589,20 @@ module MakeCompact (Num : BigNumType) : BigNumType = struct
       BitTagged.tag_const (Big_int.int64_of_big_int n)
     | n -> Num.vanilla_lit env n
 
+  let compile_of_text env =
+    Func.share_code2 env "B_of_text" (("text", I32Type), ("radix", I32Type)) [I32Type]
+      (fun env get_text get_radix ->
+        let set_res, get_res = new_local env "res" in
+        get_text ^^ get_radix ^^ Num.compile_of_text env ^^ set_res ^^
+        get_res ^^ Opt.is_some env ^^
+        G.if1 I32Type
+          (get_res ^^ fits_in_vanilla env ^^
+           G.if1 I32Type
+             (Opt.inject_noop env
+               (get_res ^^ Num.truncate_to_word32 env ^^ BitTagged.tag_i32))
+             get_res)
+          get_res)
+
   let compile_neg env =
     Func.share_code1 env "B_neg" ("n", I32Type) [I32Type] (fun env get_n ->
       get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
@@ -2489,6 +2619,105 @@ module MakeCompact (Num : BigNumType) : BigNumType = struct
         (get_n ^^ Num.compile_neg env)
     )
 
+  (* Bitwise operations commute with the right-0-padding, so the fast path
+     works on the padded representation directly, and its result always fits *)
+  let compile_and = try_unbox2 "B_and" (fun _ -> G.i (Binary (Wasm.Values.I64 I64Op.And))) Num.compile_and
+  let compile_or = try_unbox2 "B_or" (fun _ -> G.i (Binary (Wasm.Values.I64 I64Op.Or))) Num.compile_or
+  let compile_xor = try_unbox2 "B_xor" (fun _ -> G.i (Binary (Wasm.Values.I64 I64Op.Xor))) Num.compile_xor
+
+  let compile_not env =
+    Func.share_code1 env "B_not" ("n", I32Type) [I32Type] (fun env get_n ->
+      get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
+        (* flip all bits but the tag bit *)
+        (get_n ^^ compile_unboxed_const 0xFFFFFFFEl ^^
+         G.i (Binary (Wasm.Values.I32 I32Op.Xor)))
+        begin
+          get_n ^^ Num.compile_not env ^^
+          let set_res, get_res = new_local env "res" in
+          set_res ^^ get_res ^^
+          fits_in_vanilla env ^^
+          G.if1 I32Type
+            (get_res ^^ Num.truncate_to_word32 env ^^ BitTagged.tag_i32)
+            get_res
+        end)
+
+  let compile_test_bit env =
+    Func.share_code2 env "B_test_bit" (("n", I32Type), ("bit", I32Type)) [I32Type]
+      (fun env get_n get_bit ->
+        get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
+          begin
+            (* above bit 30, all bits are the sign bit *)
+            get_n ^^ compile_shrS_const 1l ^^
+            get_bit ^^ compile_rel_const I32Op.LtU 31l ^^
+            G.if1 I32Type get_bit (compile_unboxed_const 31l) ^^
+            G.i (Binary (Wasm.Values.I32 I32Op.ShrS)) ^^
+            compile_bitand_const 1l
+          end
+          (get_n ^^ get_bit ^^ Num.compile_test_bit env))
+
+  (* Setting or clearing bits below bit 30 keeps a tagged scalar in range *)
+  let try_modify_bit name fast slow env =
+    Func.share_code2 env name (("n", I32Type), ("bit", I32Type)) [I32Type]
+      (fun env get_n get_bit ->
+        let set_res, get_res = new_local env "res" in
+        get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
+          (get_bit ^^ compile_rel_const I32Op.LtU 30l)
+          (Bool.lit false) ^^
+        G.if1 I32Type
+          begin
+            get_n ^^
+            compile_unboxed_const 2l ^^ get_bit ^^
+            G.i (Binary (Wasm.Values.I32 I32Op.Shl)) ^^ (* the padded mask *)
+            fast
+          end
+          begin
+            get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
+              (get_n ^^ extend_and_box64 env)
+              get_n ^^
+            get_bit ^^ slow env ^^ set_res ^^
+            get_res ^^ fits_in_vanilla env ^^
+            G.if1 I32Type
+              (get_res ^^ Num.truncate_to_word32 env ^^ BitTagged.tag_i32)
+              get_res
+          end)
+
+  let compile_set_bit =
+    try_modify_bit "B_set_bit"
+      (G.i (Binary (Wasm.Values.I32 I32Op.Or)))
+      Num.compile_set_bit
+  let compile_clear_bit =
+    try_modify_bit "B_clear_bit"
+      (compile_unboxed_const (-1l) ^^ G.i (Binary (Wasm.Values.I32 I32Op.Xor)) ^^
+       G.i (Binary (Wasm.Values.I32 I32Op.And)))
+      Num.compile_clear_bit
+
+  (* the fast path gets the magnitude of the tagged scalar *)
+  let try_magnitude name fast slow env =
+    Func.share_code1 env name ("n", I32Type) [I32Type] (fun env get_n ->
+      get_n ^^ BitTagged.if_tagged_scalar env [I32Type]
+        begin
+          let set_a, get_a = new_local env "a" in
+          get_n ^^ compile_shrS_const 1l ^^ set_a ^^
+          fast
+            (get_a ^^ compile_rel_const I32Op.LtS 0l ^^
+             G.if1 I32Type
+               (compile_unboxed_const 0l ^^ get_a ^^ G.i (Binary (Wasm.Values.I32 I32Op.Sub)))
+               get_a)
+        end
+        (get_n ^^ slow env))
+
+  let compile_popcount =
+    try_magnitude "B_popcount"
+      (fun magnitude -> magnitude ^^ G.i (Unary (Wasm.Values.I32 I32Op.Popcnt)))
+      Num.compile_popcount
+  let compile_bit_length =
+    try_magnitude "B_bit_length"
+      (fun magnitude ->
+        compile_unboxed_const 32l ^^
+        magnitude ^^ G.i (Unary (Wasm.Values.I32 I32Op.Clz)) ^^
+        G.i (Binary (Wasm.Values.I32 I32Op.Sub)))
+      Num.compile_bit_length
+
   let try_comp_unbox2 name fast slow env =
     Func.share_code2 env name (("a", I32Type), ("b", I32Type)) [I32Type]
       (fun env get_a get_b ->
@@ -2854,6 +3083,20 @@ module BigNumLibtommath : BigNumType = struct
     ] in
     ptr
 
+  let compile_of_text env =
+    Func.share_code2 env "text_to_bigint" (("text", I32Type), ("radix", I32Type)) [I32Type]
+      (fun env get_text get_radix ->
+        Stack.with_words env "dst" 1l (fun get_dst ->
+          get_text ^^
+          get_radix ^^
+          get_dst ^^
+          E.call_import env "rts" "bigint_of_text" ^^
+          G.if1 I32Type
+            (Opt.inject_noop env (get_dst ^^ load_unskewed_ptr))
+            (Opt.null_lit env)
+        )
+      )
+
   let assert_nonneg env =
     Func.share_code1 env "assert_nonneg" ("n", I32Type) [I32Type] (fun env get_n ->
       get_n ^^
@@ -2876,6 +3119,16 @@ module BigNumLibtommath : BigNumType = struct
   let compile_lsh env = E.call_import env "rts" "bigint_lsh"
   let compile_rsh env = E.call_import env "rts" "bigint_rsh"
 
+  let compile_and env = E.call_import env "rts" "bigint_and"
+  let compile_or env = E.call_import env "rts" "bigint_or"
+  let compile_xor env = E.call_import env "rts" "bigint_xor"
+  let compile_not env = E.call_import env "rts" "bigint_not"
+  let compile_test_bit env = E.call_import env "rts" "bigint_test_bit"
+  let compile_set_bit env = E.call_import env "rts" "bigint_set_bit"
+  let compile_clear_bit env = E.call_import env "rts" "bigint_clear_bit"
+  let compile_popcount env = E.call_import env "rts" "bigint_popcount"
+  let compile_bit_length env = E.call_import env "rts" "bigint_bit_length"
+
   let compile_eq env = E.call_import env "rts" "bigint_eq"
   let compile_is_negative env = E.call_import env "rts" "bigint_isneg"
   let compile_relop env = function
@@ -4278,8 +4531,11 @@ end (* Cycles *)
 
 module StableMem = struct
 
-  (* start from 1 to avoid accidental reads of 0 *)
-  let version = Int32.of_int 1
+  (* start from 1 to avoid accidental reads of 0;
+     version 2: the image ends with a trailer, and its page is recorded;
+     version 3: the image is followed by the length of a heap-graph image
+     (0 for none), and that image *)
+  let version = Int32.of_int 3
 
   let register_globals env =
     (* size (in pages) *)
@@ -4479,6 +4735,13 @@ module StableMem = struct
           G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
           compile_const_64 (Int64.of_int (!Flags.max_stable_pages)) ^^
           G.i (Compare (Wasm.Values.I64 I64Op.GtU)) ^^
+          (* and below the region space, see `region.rs` *)
+          get_size ^^
+          get_pages ^^
+          G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+          E.call_import env "rts" "region_base_page" ^^
+          G.i (Compare (Wasm.Values.I64 I64Op.GtU)) ^^
+          G.i (Binary (Wasm.Values.I32 I32Op.Or)) ^^
           G.if1 I64Type
             begin
              compile_const_64 (-1L) ^^
@@ -4617,6 +4880,48 @@ module RTS_Exports = struct
     E.add_export env (nr {
       name = Wasm.Utf8.decode "stable64_write_moc";
       edesc = nr (FuncExport (nr stable64_write_moc_fi))
+    });
+
+    let stable64_read_moc_fi =
+      if E.mode env = Flags.WASIMode then
+        E.add_fun env "stable64_read_moc" (
+            Func.of_body env ["to", I64Type; "from", I64Type; "len", I64Type] []
+              (fun env ->
+                E.trap_with env "stable64_read_moc is not supposed to be called in WASI"
+              )
+          )
+      else E.reuse_import env "ic0" "stable64_read" in
+    E.add_export env (nr {
+      name = Wasm.Utf8.decode "stable64_read_moc";
+      edesc = nr (FuncExport (nr stable64_read_moc_fi))
+    });
+
+    let stable64_size_moc_fi =
+      if E.mode env = Flags.WASIMode then
+        E.add_fun env "stable64_size_moc" (
+            Func.of_body env [] [I64Type]
+              (fun env ->
+                E.trap_with env "stable64_size_moc is not supposed to be called in WASI"
+              )
+          )
+      else E.reuse_import env "ic0" "stable64_size" in
+    E.add_export env (nr {
+      name = Wasm.Utf8.decode "stable64_size_moc";
+      edesc = nr (FuncExport (nr stable64_size_moc_fi))
+    });
+
+    let stable64_grow_moc_fi =
+      if E.mode env = Flags.WASIMode then
+        E.add_fun env "stable64_grow_moc" (
+            Func.of_body env ["pages", I64Type] [I64Type]
+              (fun env ->
+                E.trap_with env "stable64_grow_moc is not supposed to be called in WASI"
+              )
+          )
+      else E.reuse_import env "ic0" "stable64_grow" in
+    E.add_export env (nr {
+      name = Wasm.Utf8.decode "stable64_grow_moc";
+      edesc = nr (FuncExport (nr stable64_grow_moc_fi))
     })
 
 end (* RTS_Exports *)
@@ -4835,7 +5140,9 @@ module MakeSerialization (Strm : Stream) = struct
   let idl_alias     = 1l (* see Note [mutable stable values] *)
 
 
-  let type_desc env ts : string =
+  (* Returns the type description, the offsets of the entries of its type
+     table, and the main types *)
+  let type_desc_table env ts : string * int list * int32 list =
     let open Type in
 
     (* Type traversal *)
@@ -4902,11 +5209,13 @@ module MakeSerialization (Strm : Stream) = struct
 
     (* Actual binary data *)
 
-    let add_idx t =
+    let idl_idx t =
       let t = Type.normalize t in
       match to_idl_prim t with
-      | Some i -> add_sleb128 (Int32.neg i)
-      | None -> add_sleb128 (TM.find (normalize t) idx) in
+      | Some i -> Int32.neg i
+      | None -> TM.find (normalize t) idx in
+
+    let add_idx t = add_sleb128 (idl_idx t) in
 
     let rec add_typ t =
       match t with
@@ -4971,10 +5280,54 @@ module MakeSerialization (Strm : Stream) = struct
 
     Buffer.add_string buf "DIDL";
     add_leb128 (List.length typs);
-    List.iter add_typ typs;
+    let offsets = List.map (fun t ->
+      let offset = Buffer.length buf in
+      add_typ t;
+      offset
+    ) typs in
     add_leb128 (List.length ts);
     List.iter add_idx ts;
-    Buffer.contents buf
+    (Buffer.contents buf, offsets, List.map idl_idx ts)
+
+  let type_desc env ts : string =
+    let (desc, _, _) = type_desc_table env ts in
+    desc
+
+  (* The expected types of subtype checks, see `idl_sub` in deserialize_go,
+     form one type table for the whole module. Once the module is complete,
+     it goes to static memory, already parsed, as parse_idl_header would
+     leave it: an array of pointers to the entries. The positions of the
+     types in the table are in another array. *)
+  let register_globals env =
+    E.add_global32 env "__expected_typtbl" Mutable 0l;
+    E.add_global32 env "__expected_typtbl_end" Mutable 0l;
+    E.add_global32 env "__expected_typtbl_size" Mutable 0l;
+    E.add_global32 env "__expected_idltyps" Mutable 0l;
+    (* The cache of idl_sub, allocated on first use in each message *)
+    E.add_global32 env "__idl_sub_cache" Mutable 0l
+
+  let get_global env name = G.i (GlobalGet (nr (E.get_global env name)))
+  let set_global env name = G.i (GlobalSet (nr (E.get_global env name)))
+
+  (* Puts the expected type table into static memory, and returns the code
+     that points the globals to it *)
+  let static_typtbl env =
+    match E.get_typtbl_typs env with
+    | [] -> G.nop
+    | ts ->
+      let (desc, offsets, idltyps) = type_desc_table env ts in
+      let desc_ptr = Int32.add (E.add_static env StaticBytes.[ Bytes desc ]) ptr_unskew in
+      let entries = List.map (fun offset -> Int32.(add desc_ptr (of_int offset))) offsets in
+      let typtbl_ptr = Int32.add (E.add_static env StaticBytes.[ i32s entries ]) ptr_unskew in
+      let idltyps_ptr = Int32.add (E.add_static env StaticBytes.[ i32s idltyps ]) ptr_unskew in
+      compile_unboxed_const typtbl_ptr ^^
+      set_global env "__expected_typtbl" ^^
+      compile_unboxed_const Int32.(add desc_ptr (of_int (String.length desc))) ^^
+      set_global env "__expected_typtbl_end" ^^
+      compile_unboxed_const (Lib.List32.length offsets) ^^
+      set_global env "__expected_typtbl_size" ^^
+      compile_unboxed_const idltyps_ptr ^^
+      set_global env "__expected_idltyps"
 
   (* Returns data (in bytes) and reference buffer size (in entries) needed *)
   let rec buffer_size env t =
@@ -5317,7 +5670,11 @@ module MakeSerialization (Strm : Stream) = struct
       get_depth ^^
       get_typtbl_size ^^ compile_add_const 1l ^^ compile_mul_const 2l ^^
       G.i (Compare (Wasm.Values.I32 I32Op.LeU)) ^^
-      E.else_trap_with env ("IDL error: circular record read") ^^
+      idl_else_trap_with env ("circular record read") ^^
+
+      (* Charge the decoding quota of the message, see rts/motoko-rts/src/idl/quota.rs *)
+      compile_unboxed_const 1l ^^
+      E.call_import env "rts" "idl_charge_decoding" ^^
 
       (* Remember data buffer position, to detect progress *)
       let (set_old_pos, get_old_pos) = new_local env "old_pos" in
@@ -5352,6 +5709,12 @@ module MakeSerialization (Strm : Stream) = struct
         E.call_import env "rts" "skip_any"
       in
 
+      (* Records the segment pushed by `push` in the path to the decoded value,
+         for error messages, while `code` decodes it *)
+      let in_segment push code =
+        push ^^ code ^^ E.call_import env "rts" "idl_error_pop"
+      in
+
       (* This flag is set to return a coercion error at the very end
          We cannot use (G.i Return) for early exit, or we’d leak stack space,
          as Stack.with_words is used to allocate scratch space.
@@ -5373,7 +5736,7 @@ module MakeSerialization (Strm : Stream) = struct
         (* If we know that there is no backtracking `opt t` around, then just trap.
            This gives a better error message
         *)
-        get_can_recover ^^ E.else_trap_with env msg ^^
+        get_can_recover ^^ idl_else_trap_with env msg ^^
         set_failure ^^ compile_unboxed_const (coercion_error_value env) in
 
       (* returns true if we are looking at primitive type with this id *)
@@ -5386,7 +5749,7 @@ module MakeSerialization (Strm : Stream) = struct
         check_prim_typ t ^^
         G.if1 I32Type f
           ( skip get_idltyp ^^
-            coercion_failed ("IDL error: unexpected IDL type when parsing " ^ string_of_typ t)
+            coercion_failed ("unexpected IDL type when parsing " ^ string_of_typ t)
           )
       in
 
@@ -5401,7 +5764,7 @@ module MakeSerialization (Strm : Stream) = struct
           begin code0
           end begin
             get_b ^^ compile_eq_const 1l ^^
-            E.else_trap_with env "IDL error: byte tag not 0 or 1" ^^
+            idl_else_trap_with env "byte tag not 0 or 1" ^^
             code1
           end
         | _ -> assert false; (* can be generalized later as needed *)
@@ -5427,7 +5790,7 @@ module MakeSerialization (Strm : Stream) = struct
            https://sdk.dfinity.org/docs/interface-spec/index.html#principal
         *)
         get_len ^^ compile_unboxed_const 29l ^^ G.i (Compare (Wasm.Values.I32 I32Op.LeU)) ^^
-        E.else_trap_with env "IDL error: principal too long" ^^
+        idl_else_trap_with env "principal too long" ^^
 
         get_len ^^ Blob.alloc env ^^ set_x ^^
         get_x ^^ Blob.payload_ptr_unskewed ^^
@@ -5449,7 +5812,7 @@ module MakeSerialization (Strm : Stream) = struct
 
       let read_actor_data () =
         read_byte_tagged
-          [ E.trap_with env "IDL error: unexpected actor reference"
+          [ idl_trap_with env "unexpected actor reference"
           ; read_principal ()
           ]
       in
@@ -5508,13 +5871,13 @@ module MakeSerialization (Strm : Stream) = struct
             end
             begin
               skip get_arg_typ ^^
-              coercion_failed ("IDL error: unexpected IDL type when parsing " ^ string_of_typ t)
+              coercion_failed ("unexpected IDL type when parsing " ^ string_of_typ t)
             end
           )
         end
         begin
           skip get_arg_typ ^^
-          coercion_failed ("IDL error: unexpected IDL type when parsing " ^ string_of_typ t)
+          coercion_failed ("unexpected IDL type when parsing " ^ string_of_typ t)
         end
       in
 
@@ -5522,6 +5885,28 @@ module MakeSerialization (Strm : Stream) = struct
         with_composite_arg_typ get_idltyp idl_tycon_id f
       in
 
+      (* returns true if the IDL type is a subtype of t, by the rules of the
+         Candid specification, see rts/motoko-rts/src/idl/subtype.rs.
+         The cache is shared by all checks of the message. *)
+      let idl_sub t =
+        let idx = E.add_typtbl_typ env t in
+        get_global env "__idl_sub_cache" ^^ compile_eq_const 0l ^^
+        G.if0
+          begin
+            get_typtbl_size ^^ get_global env "__expected_typtbl_size" ^^
+            E.call_import env "rts" "idl_sub_cache" ^^
+            set_global env "__idl_sub_cache"
+          end
+          G.nop ^^
+        get_global env "__idl_sub_cache" ^^
+        get_typtbl ^^ ReadBuf.get_end get_data_buf ^^
+        get_global env "__expected_typtbl" ^^ get_global env "__expected_typtbl_end" ^^
+        get_idltyp ^^
+        get_global env "__expected_idltyps" ^^
+        compile_add_const (Int32.mul idx Heap.word_size) ^^ load_unskewed_ptr ^^
+        E.call_import env "rts" "idl_sub"
+      in
+
       let with_record_typ f = with_composite_typ idl_record (fun get_typ_buf ->
         Stack.with_words env "get_n_ptr" 1l (fun get_n_ptr ->
           get_n_ptr ^^
@@ -5539,7 +5924,7 @@ module MakeSerialization (Strm : Stream) = struct
             f
             begin
               skip get_idltyp ^^
-              coercion_failed "IDL error: blob not a vector of nat8"
+              coercion_failed "blob not a vector of nat8"
             end
         )
       in
@@ -5683,7 +6068,7 @@ module MakeSerialization (Strm : Stream) = struct
         with_prim_typ t
         begin
           read_byte_tagged
-            [ E.trap_with env "IDL error: unexpected principal reference"
+            [ idl_trap_with env "unexpected principal reference"
             ; read_principal ()
             ]
         end
@@ -5703,14 +6088,18 @@ module MakeSerialization (Strm : Stream) = struct
             G.if1 I32Type
               begin
                 ReadBuf.read_sleb128 env get_typ_buf ^^
-                go env t ^^ set_val ^^
+                in_segment
+                  (compile_unboxed_const (Int32.of_int i) ^^
+                   E.call_import env "rts" "idl_error_push_field")
+                  (go env t) ^^
+                set_val ^^
                 remember_failure get_val ^^
                 get_val
               end
               begin
                 match normalize t with
                 | Opt _ | Any -> Opt.null_lit env
-                | _ -> coercion_failed "IDL error: did not find tuple field in record"
+                | _ -> coercion_failed "did not find tuple field in record"
               end
           ) ts ^^
 
@@ -5732,14 +6121,18 @@ module MakeSerialization (Strm : Stream) = struct
               G.if1 I32Type
                 begin
                   ReadBuf.read_sleb128 env get_typ_buf ^^
-                  go env f.typ ^^ set_val ^^
+                  in_segment
+                    (compile_unboxed_const (Lib.Uint32.to_int32 h) ^^
+                     E.call_import env "rts" "idl_error_push_field")
+                    (go env f.typ) ^^
+                  set_val ^^
                   remember_failure get_val ^^
                   get_val
                   end
                 begin
                   match normalize f.typ with
                   | Opt _ | Any -> Opt.null_lit env
-                  | _ -> coercion_failed (Printf.sprintf "IDL error: did not find field %s in record" f.lab)
+                  | _ -> coercion_failed (Printf.sprintf "did not find field %s in record" f.lab)
                 end
           ) (sort_by_hash fs)) ^^
 
@@ -5757,11 +6150,14 @@ module MakeSerialization (Strm : Stream) = struct
           ReadBuf.read_leb128 env get_data_buf ^^ set_len ^^
           get_len ^^ Arr.alloc env ^^ set_x ^^
           on_alloc get_x ^^
-          get_len ^^ from_0_to_n env (fun get_i ->
-            get_x ^^ get_i ^^ Arr.idx env ^^
-            get_arg_typ ^^ go env t ^^ set_val ^^
-            remember_failure get_val ^^
-            get_val ^^ store_ptr
+          in_segment (E.call_import env "rts" "idl_error_push_elem") (
+            get_len ^^ from_0_to_n env (fun get_i ->
+              get_i ^^ E.call_import env "rts" "idl_error_set_elem" ^^
+              get_x ^^ get_i ^^ Arr.idx env ^^
+              get_arg_typ ^^ go env t ^^ set_val ^^
+              remember_failure get_val ^^
+              get_val ^^ store_ptr
+            )
           )
         )
       | Array t ->
@@ -5772,11 +6168,14 @@ module MakeSerialization (Strm : Stream) = struct
         with_composite_typ idl_vec (ReadBuf.read_sleb128 env) ^^ set_arg_typ ^^
         ReadBuf.read_leb128 env get_data_buf ^^ set_len ^^
         get_len ^^ Arr.alloc env ^^ set_x ^^
-        get_len ^^ from_0_to_n env (fun get_i ->
-          get_x ^^ get_i ^^ Arr.idx env ^^
-          get_arg_typ ^^ go env t ^^ set_val ^^
-          remember_failure get_val ^^
-          get_val ^^ store_ptr
+        in_segment (E.call_import env "rts" "idl_error_push_elem") (
+          get_len ^^ from_0_to_n env (fun get_i ->
+            get_i ^^ E.call_import env "rts" "idl_error_set_elem" ^^
+            get_x ^^ get_i ^^ Arr.idx env ^^
+            get_arg_typ ^^ go env t ^^ set_val ^^
+            remember_failure get_val ^^
+            get_val ^^ store_ptr
+          )
         ) ^^
         get_x
       | Opt t ->
@@ -5794,7 +6193,9 @@ module MakeSerialization (Strm : Stream) = struct
               read_byte_tagged
                 [ Opt.null_lit env
                 ; let (set_val, get_val) = new_local env "val" in
-                  get_arg_typ ^^ go_can_recover env t ^^ set_val ^^
+                  in_segment (E.call_import env "rts" "idl_error_push_opt")
+                    (get_arg_typ ^^ go_can_recover env t) ^^
+                  set_val ^^
                   get_val ^^ compile_eq_const (coercion_error_value env) ^^
                   G.if1 I32Type
                     (* decoding failed, but this is opt, so: return null *)
@@ -5835,7 +6236,7 @@ module MakeSerialization (Strm : Stream) = struct
 
           get_tagidx ^^ get_n ^^
           G.i (Compare (Wasm.Values.I32 I32Op.LtU)) ^^
-          E.else_trap_with env "IDL error: variant index out of bounds" ^^
+          idl_else_trap_with env "variant index out of bounds" ^^
 
           (* Zoom past the previous entries *)
           get_tagidx ^^ from_0_to_n env (fun _ ->
@@ -5853,37 +6254,58 @@ module MakeSerialization (Strm : Stream) = struct
               get_tag ^^ compile_eq_const (Lib.Uint32.to_int32 h) ^^
               G.if1 I32Type
                 ( Variant.inject env l (
-                  get_arg_typ ^^ go env t ^^ set_val ^^
+                  in_segment
+                    (compile_unboxed_const (Lib.Uint32.to_int32 h) ^^
+                     E.call_import env "rts" "idl_error_push_variant")
+                    (get_arg_typ ^^ go env t) ^^
+                  set_val ^^
                   remember_failure get_val ^^
                   get_val
                 ))
                 continue
             )
             ( sort_by_hash vs )
-            ( coercion_failed "IDL error: unexpected variant tag" )
+            ( coercion_failed "unexpected variant tag" )
         )
       | Func _ ->
         with_composite_typ idl_func (fun _get_typ_buf ->
-          read_byte_tagged
-            [ E.trap_with env "IDL error: unexpected function reference"
-            ; read_actor_data () ^^
-              read_text () ^^
-              Tuple.from_stack env 2
-            ]
+          idl_sub t ^^
+          G.if1 I32Type
+            begin
+              read_byte_tagged
+                [ idl_trap_with env "unexpected function reference"
+                ; read_actor_data () ^^
+                  read_text () ^^
+                  Tuple.from_stack env 2
+                ]
+            end
+            begin
+              skip get_idltyp ^^
+              coercion_failed "incompatible function type"
+            end
         );
       | Obj (Actor, _) ->
-        with_composite_typ idl_service (fun _get_typ_buf -> read_actor_data ())
+        with_composite_typ idl_service (fun _get_typ_buf ->
+          idl_sub t ^^
+          G.if1 I32Type
+            (read_actor_data ())
+            begin
+              skip get_idltyp ^^
+              coercion_failed "incompatible actor type"
+            end
+        )
       | Mut t ->
         read_alias env (Mut t) (fun get_arg_typ on_alloc ->
           let (set_result, get_result) = new_local env "result" in
           Tagged.obj env Tagged.ObjInd [ compile_unboxed_const 0l ] ^^ set_result ^^
           on_alloc get_result ^^
           get_result ^^
-            get_arg_typ ^^ go env t ^^
+            in_segment (E.call_import env "rts" "idl_error_push_var")
+              (get_arg_typ ^^ go env t) ^^
           Heap.store_field MutBox.field
         )
       | Non ->
-        E.trap_with env "IDL error: deserializing value of type None"
+        idl_trap_with env "deserializing value of type None"
       | _ -> todo_trap env "deserialize" (Arrange_ir.typ t)
       end ^^
       (* Parsed value on the stack, return that, unless the failure flag is set *)
@@ -5977,6 +6399,9 @@ module MakeSerialization (Strm : Stream) = struct
       Bool.lit extended ^^ get_data_buf ^^ get_typtbl_ptr ^^ get_typtbl_size_ptr ^^ get_maintyps_ptr ^^
       E.call_import env "rts" "parse_idl_header" ^^
 
+      (* Subtype checks get a fresh cache for the new type table *)
+      compile_unboxed_const 0l ^^ set_global env "__idl_sub_cache" ^^
+
       (* set up a dedicated read buffer for the list of main types *)
       ReadBuf.alloc env (fun get_main_typs_buf ->
         ReadBuf.set_ptr get_main_typs_buf (get_maintyps_ptr ^^ load_unskewed_ptr) ^^
@@ -5985,9 +6410,12 @@ module MakeSerialization (Strm : Stream) = struct
 
         get_arg_count ^^
         compile_rel_const I32Op.GeU (Int32.of_int (List.length ts)) ^^
-        E.else_trap_with env ("IDL error: too few arguments " ^ ts_name) ^^
+        idl_else_trap_with env ("too few arguments " ^ ts_name) ^^
 
-        G.concat_map (fun t ->
+        G.concat_mapi (fun i t ->
+          (* Mark the argument, for error messages *)
+          compile_unboxed_const (Int32.of_int i) ^^
+          E.call_import env "rts" "idl_error_arg" ^^
           get_data_buf ^^ get_ref_buf ^^
           get_typtbl_ptr ^^ load_unskewed_ptr ^^
           ReadBuf.read_sleb128 env get_main_typs_buf ^^
@@ -5999,10 +6427,13 @@ module MakeSerialization (Strm : Stream) = struct
           G.if0
             (G.nop)
             (get_val ^^ compile_eq_const (coercion_error_value env) ^^
-             E.then_trap_with env ("IDL error: coercion failure encountered")) ^^
+             idl_then_trap_with env ("coercion failure encountered")) ^^
           get_val
         ) ts ^^
 
+        (* The rest of the message is in no argument *)
+        E.call_import env "rts" "idl_error_pop" ^^
+
         (* Skip any extra arguments *)
         get_arg_count ^^ compile_sub_const (Int32.of_int (List.length ts)) ^^
         from_0_to_n env (fun _ ->
@@ -6014,9 +6445,10 @@ module MakeSerialization (Strm : Stream) = struct
         ) ^^
 
         ReadBuf.is_empty env get_data_buf ^^
-        E.else_trap_with env ("IDL error: left-over bytes " ^ ts_name) ^^
+        idl_else_trap_with env ("left-over bytes " ^ ts_name) ^^
         ReadBuf.is_empty env get_ref_buf ^^
-        E.else_trap_with env ("IDL error: left-over references " ^ ts_name)
+        idl_else_trap_with env ("left-over references " ^ ts_name) ^^
+        E.call_import env "rts" "idl_end_message"
       ))))))
     )
 
@@ -6212,6 +6644,70 @@ module Stabilization = struct
 
   let extend64 code = code ^^ G.i (Convert (Wasm.Values.I64 I64Op.ExtendUI32))
 
+  (* size of the trailer of an image, see `IMAGE_TRAILER_SIZE` in `stream.rs` *)
+  let image_trailer_size = 16L
+
+  (* The page where the image of the stable variables goes (`N` of the
+     design document, in pages): after the StableMemory in use and the
+     region space, and never page 0, whose first word is the marker *)
+  let image_page env =
+    let (set_page, get_page) = new_local64 env "page" in
+    let raise_to code =
+      code ^^
+      get_page ^^
+      G.i (Compare (Wasm.Values.I64 I64Op.GtU)) ^^
+      G.if0 (code ^^ set_page) G.nop in
+    compile_const_64 1L ^^
+    set_page ^^
+    raise_to (StableMem.get_mem_size env) ^^
+    raise_to (E.call_import env "rts" "region_end_page") ^^
+    get_page
+
+  let ptr64_field = Int32.add Blob.len_field 1l (* see invariant in `stream.rs` *)
+
+  (* Points the stream to the stable memory after `N` of the design
+     document, for an image of up to `size` bytes (an I64). The image ends
+     with a trailer, and may expand when compressed, see `stream.rs` *)
+  let setup_image_dest env get_token get_size =
+    let (set_reserved, get_reserved) = new_local64 env "reserved" in
+    get_size ^^
+    (if !Flags.stable_compression
+     then E.call_import env "rts" "compressed_image_size"
+     else compile_add64_const image_trailer_size) ^^
+    set_reserved ^^
+
+    let (set_dst, get_dst) = new_local64 env "dst" in
+    image_page env ^^
+    compile_shl64_const (Int64.of_int page_size_bits) ^^
+    compile_add64_const 4L ^^ (* `N` is now on the stack *)
+    set_dst ^^
+
+    get_dst ^^
+    get_reserved ^^
+    StableMem.ensure env ^^
+
+    get_token ^^
+    get_dst ^^
+    get_dst ^^ get_reserved ^^
+    G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+    E.call_import env "rts"
+      (if !Flags.stable_compression
+       then "stream_stable_dest_compressed"
+       else "stream_stable_dest")
+
+  (* Shuts the stream down, and returns the length of the image that it
+     wrote after `N` *)
+  let image_length env get_token =
+    get_token ^^
+    E.call_import env "rts" "stream_shutdown" ^^
+    get_token ^^
+    Heap.load_field64_unskewed ptr64_field ^^
+    image_page env ^^
+    compile_shl64_const (Int64.of_int page_size_bits) ^^
+    G.i (Binary (Wasm.Values.I64 I64Op.Sub)) ^^
+    compile_sub64_const 4L ^^  (* `N` is now subtracted *)
+    G.i (Convert (Wasm.Values.I32 I32Op.WrapI64))
+
   (* The below stream implementation is geared towards the
      tail section of stable memory, where the serialised
      stable variables go. As such a few intimate details of
@@ -6231,35 +6727,11 @@ module Stabilization = struct
       compile_add_const (Int32.of_int (String.length header)) ^^
       set_len ^^
 
-      let (set_dst, get_dst) = new_local64 env "dst" in
-      StableMem.get_mem_size env ^^
-      compile_shl64_const (Int64.of_int page_size_bits) ^^
-      compile_add64_const 4L ^^ (* `N` is now on the stack *)
-      set_dst ^^
-
-      get_dst ^^
-      extend64 get_len ^^
-      StableMem.ensure env ^^
-
-      get_token ^^
-      get_dst ^^
-      get_dst ^^ extend64 get_len ^^
-      G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
-      E.call_import env "rts" "stream_stable_dest"
-
-    let ptr64_field = Int32.add Blob.len_field 1l (* see invariant in `stream.rs` *)
+      setup_image_dest env get_token (extend64 get_len)
 
     let terminate env get_token get_data_size header_size =
-      get_token ^^
-      E.call_import env "rts" "stream_shutdown" ^^
       compile_unboxed_zero ^^ (* no need to write *)
-      get_token ^^
-      Heap.load_field64_unskewed ptr64_field ^^
-      StableMem.get_mem_size env ^^
-      compile_shl64_const (Int64.of_int page_size_bits) ^^
-      G.i (Binary (Wasm.Values.I64 I64Op.Sub)) ^^
-      compile_sub64_const 4L ^^  (* `N` is now subtracted *)
-      G.i (Convert (Wasm.Values.I32 I32Op.WrapI64))
+      image_length env get_token
 
     let finalize_buffer _ = G.nop (* everything is outputted already *)
 
@@ -6280,98 +6752,180 @@ module Stabilization = struct
 
   module Externalization = MakeSerialization(StableMemoryStream)
 
+  (* The signature of heap-graph images of the stable variables, which
+     must be the same on both sides of an upgrade, see `graph.rs` *)
+  let graph_signature env ty = Blob.lit env (Typ_hash.typ_hash ty)
+
+  module TS = Set.Make (Type.Ord)
+
+  (* The field hash arrays of the objects in a stable type, for
+     `graph_deserialize` to reuse instead of allocating its own: a blob of
+     the length and address of each array. `E.add_static` shares them with
+     the object literals (see `Object.lit_raw`). *)
+  let static_hashes env ty =
+    let open Type in
+    let seen = ref TS.empty in
+    let arrays = ref [] in
+    let rec go t =
+      let t = normalize t in
+      if TS.mem t !seen then () else begin
+        seen := TS.add t !seen;
+        match t with
+        | Obj ((Object | Memory), fs) ->
+          let fs = List.filter (fun f -> not (is_typ f.typ)) fs in
+          let hashes = fs |>
+            List.map (fun f -> E.hash env f.lab) |>
+            List.sort compare in
+          let hash_ptr = E.add_static env StaticBytes.[ i32s hashes ] in
+          arrays := !arrays @ [Int32.of_int (List.length hashes); hash_ptr];
+          List.iter (fun f -> go f.typ) fs
+        | Tup ts -> List.iter go ts
+        | Array t | Opt t | Mut t -> go t
+        | Variant fs -> List.iter (fun f -> go f.typ) fs
+        | _ -> ()
+      end
+    in
+    go ty;
+    Blob.lit env StaticBytes.(as_bytes [ i32s !arrays ])
+
+  (* Copies the heap graph of the stable variables (on the stack) to stable
+     memory from `get_start` (an I64) on, and returns the length of the
+     image. Its size is not known in advance, stable memory grows as it
+     is written (see `stream_stable_dest`). *)
+  let graph_serialize env ty get_start =
+    let (set_val, get_val) = new_local env "val" in
+    let (set_token, get_token) = new_local env "token" in
+    set_val ^^
+    compile_unboxed_const 0x8000l ^^
+    E.call_import env "rts" "alloc_stream" ^^
+    set_token ^^
+
+    get_token ^^
+    get_start ^^
+    compile_const_64 (-1L) ^^ (* no limit *)
+    E.call_import env "rts"
+      (if !Flags.stable_compression
+       then "stream_stable_dest_compressed"
+       else "stream_stable_dest") ^^
+
+    get_token ^^
+    get_val ^^
+    graph_signature env ty ^^
+    E.call_import env "rts" "graph_serialize" ^^
+
+    get_token ^^
+    E.call_import env "rts" "stream_shutdown" ^^
+    get_token ^^
+    Heap.load_field64_unskewed ptr64_field ^^
+    get_start ^^
+    G.i (Binary (Wasm.Values.I64 I64Op.Sub)) ^^
+    G.i (Convert (Wasm.Values.I32 I32Op.WrapI64))
+
+  (* The Candid image is always written, as it can be read at other types.
+     With `--stable-graph`, the heap graph, which preserves sharing, follows
+     it, and `destabilize` prefers it if the types stay the same. *)
   let stabilize env t =
+    let (set_val, get_val) = new_local env "val" in
     let (set_dst, get_dst) = new_local env "dst" in
     let (set_len, get_len) = new_local env "len" in
+    set_val ^^
+    get_val ^^
     Externalization.serialize env [t] ^^
     set_len ^^
     set_dst ^^
 
-    StableMem.get_mem_size env ^^
-    G.i (Test (Wasm.Values.I64 I64Op.Eqz)) ^^
-    G.if0
-      begin (* ensure [0,..,3,...len+4) *)
-        compile_const_64 0L ^^
-        extend64 get_len ^^
-        compile_add64_const 4L ^^  (* reserve one word for size *)
-        StableMem.ensure env ^^
+    let (set_N, get_N) = new_local64 env "N" in
 
-        (* write len to initial word of stable memory*)
-        compile_const_64 0L ^^
-        get_len ^^
-        StableMem.write_word32 env ^^
+    (* let N = image_page * page_size *)
+    image_page env ^^
+    compile_shl64_const (Int64.of_int page_size_bits) ^^
+    set_N ^^
 
-        (* copy data to following stable memory *)
-        Externalization.Strm.finalize_buffer
-          begin
-            compile_const_64 4L ^^
-            extend64 get_dst ^^
-            extend64 get_len ^^
-            IC.system_call env "stable64_write"
-          end
-      end
-      begin
-        let (set_N, get_N) = new_local64 env "N" in
+    (* grow mem to page including address
+       N + 4 + len + 4 + 4 + 4 + 4 = N + len + 20
+    *)
+    get_N ^^
+    extend64 get_len ^^
+    compile_add64_const 20L ^^
+    StableMem.ensure env  ^^
 
-        (* let N = !size * page_size *)
-        StableMem.get_mem_size env ^^
-        compile_shl64_const (Int64.of_int page_size_bits) ^^
-        set_N ^^
+    get_N ^^
+    get_len ^^
+    StableMem.write_word32 env ^^
 
-        (* grow mem to page including address
-           N + 4 + len + 4 + 4 + 4 = N + len + 16
-        *)
+    (* copy data to following stable memory *)
+    Externalization.Strm.finalize_buffer
+      begin
         get_N ^^
+        compile_add64_const 4L ^^
+        extend64 get_dst ^^
         extend64 get_len ^^
-        compile_add64_const 16L ^^
-        StableMem.ensure env  ^^
+        IC.system_call env "stable64_write"
+      end ^^
 
-        get_N ^^
-        get_len ^^
-        StableMem.write_word32 env ^^
+    (* let G = N + 4 + len, where the length of the heap-graph image goes *)
+    let (set_G, get_G) = new_local64 env "G" in
+    get_N ^^
+    compile_add64_const 4L ^^
+    extend64 get_len ^^
+    G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+    set_G ^^
 
-        (* copy data to following stable memory *)
-        Externalization.Strm.finalize_buffer
-          begin
-            get_N ^^
-            compile_add64_const 4L ^^
-            extend64 get_dst ^^
-            extend64 get_len ^^
-            IC.system_call env "stable64_write"
-          end ^^
+    get_G ^^
+    (if !Flags.stable_graph
+     then
+       let (set_graph_len, get_graph_len) = new_local env "graph_len" in
+       get_val ^^
+       graph_serialize env t (get_G ^^ compile_add64_const 4L) ^^
+       set_graph_len ^^
+       (* grow mem to page including address G + 4 + graph_len + 16 *)
+       get_G ^^
+       extend64 get_graph_len ^^
+       compile_add64_const 20L ^^
+       StableMem.ensure env ^^
+       get_graph_len
+     else
+       compile_unboxed_const 0l) ^^
+    StableMem.write_word32 env ^^
+
+    (* let M = pagesize * ic0.stable64_size64() - 1 *)
+    (* M is beginning of last page *)
+    let (set_M, get_M) = new_local64 env "M" in
+    IC.system_call env "stable64_size" ^^
+    compile_sub64_const 1L ^^
+    compile_shl64_const (Int64.of_int page_size_bits) ^^
+    set_M ^^
+
+    (* store the image page at M + (pagesize - 16) *)
+    get_M ^^
+    compile_add64_const (Int64.sub page_size64 16L) ^^
+    get_N ^^
+    compile_const_64 (Int64.of_int page_size_bits) ^^
+    G.i (Binary (Wasm.Values.I64 I64Op.ShrU)) ^^
+    G.i (Convert (Wasm.Values.I32 I32Op.WrapI64)) ^^
+    StableMem.write_word32 env ^^
 
-        (* let M = pagesize * ic0.stable64_size64() - 1 *)
-        (* M is beginning of last page *)
-        let (set_M, get_M) = new_local64 env "M" in
-        IC.system_call env "stable64_size" ^^
-        compile_sub64_const 1L ^^
-        compile_shl64_const (Int64.of_int page_size_bits) ^^
-        set_M ^^
-
-        (* store mem_size at M + (pagesize - 12) *)
-        get_M ^^
-        compile_add64_const (Int64.sub page_size64 12L) ^^
-        StableMem.get_mem_size env ^^
-        G.i (Convert (Wasm.Values.I32 I32Op.WrapI64)) ^^
-        (* TODO: write word64 *)
-        StableMem.write_word32 env ^^
-
-        (* save first word at M + (pagesize - 8);
-           mark first word as 0 *)
-        get_M ^^
-        compile_add64_const (Int64.sub page_size64 8L) ^^
-        compile_const_64 0L ^^
-        StableMem.read_and_clear_word32 env ^^
-        StableMem.write_word32 env ^^
+    (* store mem_size at M + (pagesize - 12) *)
+    get_M ^^
+    compile_add64_const (Int64.sub page_size64 12L) ^^
+    StableMem.get_mem_size env ^^
+    G.i (Convert (Wasm.Values.I32 I32Op.WrapI64)) ^^
+    (* TODO: write word64 *)
+    StableMem.write_word32 env ^^
 
-        (* save version at M + (pagesize - 4) *)
-        get_M ^^
-          compile_add64_const (Int64.sub page_size64 4L) ^^
-        (* TODO bump version? *)
-        compile_unboxed_const StableMem.version ^^
-        StableMem.write_word32 env
+    (* save first word at M + (pagesize - 8);
+       mark first word as 0 *)
+    get_M ^^
+    compile_add64_const (Int64.sub page_size64 8L) ^^
+    compile_const_64 0L ^^
+    StableMem.read_and_clear_word32 env ^^
+    StableMem.write_word32 env ^^
 
-      end
+    (* save version at M + (pagesize - 4) *)
+    get_M ^^
+    compile_add64_const (Int64.sub page_size64 4L) ^^
+    compile_unboxed_const StableMem.version ^^
+    StableMem.write_word32 env
 
   let destabilize env ty =
     match E.mode env with
@@ -6398,6 +6952,14 @@ module Stabilization = struct
           let (set_marker, get_marker) = new_local env "marker" in
           let (set_len, get_len) = new_local env "len" in
           let (set_offset, get_offset) = new_local64 env "offset" in
+          (* whether the image ends with a trailer (since version 2) *)
+          let (set_checked, get_checked) = new_local env "checked" in
+          (* length of the heap-graph image after it (since version 3) *)
+          let (set_graph_len, get_graph_len) = new_local env "graph_len" in
+          let get_graph_offset =
+            get_offset ^^ extend64 get_len ^^
+            G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+            compile_add64_const 4L in
           compile_const_64 0L ^^
           StableMem.read_and_clear_word32 env ^^
           set_marker ^^
@@ -6442,7 +7004,19 @@ module Stabilization = struct
               extend64 (StableMem.read_and_clear_word32 env) ^^ (*TODO: use 64 bits *)
               StableMem.set_mem_size env ^^
 
-              StableMem.get_mem_size env ^^
+              (* the image page is recorded since version 2,
+                 before it was the logical size *)
+              get_version ^^
+              compile_unboxed_const 2l ^^
+              G.i (Compare (Wasm.Values.I32 I32Op.GeU)) ^^
+              set_checked ^^
+
+              get_checked ^^
+              G.if1 I64Type
+                (get_M ^^
+                 compile_add64_const (Int64.sub page_size64 16L) ^^
+                 extend64 (StableMem.read_and_clear_word32 env))
+                (StableMem.get_mem_size env) ^^
               compile_shl64_const (Int64.of_int page_size_bits) ^^
               set_N ^^
 
@@ -6454,7 +7028,18 @@ module Stabilization = struct
               (* set offset *)
               get_N ^^
               compile_add64_const 4L ^^
-              set_offset
+              set_offset ^^
+
+              (* set graph_len *)
+              get_version ^^
+              compile_unboxed_const 3l ^^
+              G.i (Compare (Wasm.Values.I32 I32Op.GeU)) ^^
+              G.if1 I32Type
+                (get_graph_offset ^^
+                 compile_sub64_const 4L ^^
+                 StableMem.read_and_clear_word32 env)
+                (compile_unboxed_const 0l) ^^
+              set_graph_len
             end
             begin
               (* assert mem_size == 0 *)
@@ -6466,35 +7051,90 @@ module Stabilization = struct
               get_marker ^^
               set_len ^^
 
+              (* written before version 2 *)
+              Bool.lit false ^^
+              set_checked ^^
+              compile_unboxed_const 0l ^^
+              set_graph_len ^^
+
               (* set offset *)
               compile_const_64 4L ^^
               set_offset
             end ^^ (* if_ *)
 
           let (set_blob, get_blob) = new_local env "blob" in
-          (* read blob from stable memory *)
-          get_len ^^ Blob.alloc env ^^ set_blob ^^
-          extend64 (get_blob ^^ Blob.payload_ptr_unskewed) ^^
-          get_offset ^^
-          extend64 get_len ^^
-          IC.system_call env "stable64_read" ^^
-
           let (set_val, get_val) = new_local env "val" in
           (* deserialize blob to val *)
-          get_blob ^^
-          Bool.lit false ^^ (* can't recover *)
-          Serialization.deserialize_from_blob true env [ty] ^^
+          let deserialize_blob =
+            Bool.lit false ^^ (* can't recover *)
+            Serialization.deserialize_from_blob true env [ty] in
+          get_checked ^^
+          G.if1 I32Type
+            begin
+              (* read the images through a reader, which verifies the trailer
+                 before anything is decoded, and decompresses it if needed *)
+              let (set_reader, get_reader) = new_local env "reader" in
+              let read_image get_start get_size =
+                compile_unboxed_const 0x8000l ^^
+                E.call_import env "rts" "alloc_stream_reader" ^^
+                set_reader ^^
+                get_reader ^^
+                get_start ^^
+                get_start ^^ extend64 get_size ^^
+                G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+                E.call_import env "rts" "stream_reader_stable_image" in
+
+              (* the heap graph is only used at the same type *)
+              get_graph_len ^^
+              G.if1 I32Type
+                begin
+                  read_image get_graph_offset get_graph_len ^^
+                  get_reader ^^
+                  graph_signature env ty ^^
+                  E.call_import env "rts" "graph_has_signature"
+                end
+                (Bool.lit false) ^^
+              G.if1 I32Type
+                begin
+                  (* a heap graph is rebuilt while it is read *)
+                  get_reader ^^
+                  Opt.null_lit env ^^
+                  static_hashes env ty ^^
+                  E.call_import env "rts" "graph_deserialize"
+                end
+                begin
+                  (* Candid is decoded from one blob, as alias offsets
+                     point back into it *)
+                  read_image get_offset get_len ^^
+                  get_reader ^^
+                  get_reader ^^
+                  E.call_import env "rts" "stream_reader_remaining" ^^
+                  G.i (Convert (Wasm.Values.I32 I32Op.WrapI64)) ^^
+                  E.call_import env "rts" "stream_reader_read_blob" ^^
+                  deserialize_blob
+                end
+            end
+            begin
+              (* read blob from stable memory *)
+              get_len ^^ Blob.alloc env ^^ set_blob ^^
+              extend64 (get_blob ^^ Blob.payload_ptr_unskewed) ^^
+              get_offset ^^
+              extend64 get_len ^^
+              IC.system_call env "stable64_read" ^^
+              get_blob ^^
+              deserialize_blob
+            end ^^
           set_val ^^
 
-          (* clear blob contents *)
-          get_blob ^^
-          Blob.clear env ^^
-
-          (* copy zeros from blob to stable memory *)
+          (* clear the images, trailers included *)
           get_offset ^^
-          extend64 (get_blob ^^ Blob.payload_ptr_unskewed) ^^
-          extend64 (get_blob ^^ Blob.len env) ^^
-          IC.system_call env "stable64_write" ^^
+          get_offset ^^ extend64 get_len ^^
+          G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+          E.call_import env "rts" "stable_clear" ^^
+          get_graph_offset ^^
+          get_graph_offset ^^ extend64 get_graph_len ^^
+          G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+          E.call_import env "rts" "stable_clear" ^^
 
           (* return val *)
           get_val
@@ -8649,11 +9289,23 @@ and compile_prim_invocation (env : E.t) ae p es at =
     SR.UnboxedWord64,
     let tydesc = Serialization.type_desc env [t] in
     let tydesc_len = Int32.of_int (String.length tydesc) in
+    let (set_val, get_val) = new_local env "val" in
     compile_exp_vanilla env ae e ^^
+    set_val ^^
+    get_val ^^
     Serialization.buffer_size env t ^^
     G.i Drop ^^
     compile_add_const tydesc_len  ^^
-    G.i (Convert (Wasm.Values.I64 I64Op.ExtendUI32))
+    G.i (Convert (Wasm.Values.I64 I64Op.ExtendUI32)) ^^
+    (if !Flags.stable_graph
+     then
+       (* the heap graph follows, after its length, see `Stabilization` *)
+       get_val ^^
+       Stabilization.graph_signature env t ^^
+       E.call_import env "rts" "graph_image_size" ^^
+       G.i (Binary (Wasm.Values.I64 I64Op.Add)) ^^
+       compile_add64_const 4L
+     else G.nop)
 
   (* Other prims, unary *)
 
@@ -8699,6 +9351,44 @@ and compile_prim_invocation (env : E.t) ae p es at =
     compile_exp_as env ae SR.UnboxedWord32 e2 ^^
     BigNum.compile_rsh env
 
+  | OtherPrim ("andInt" | "orInt" | "xorInt" as p), [e1; e2] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e1 ^^
+    compile_exp_vanilla env ae e2 ^^
+    (match p with
+     | "andInt" -> BigNum.compile_and env
+     | "orInt" -> BigNum.compile_or env
+     | _ -> BigNum.compile_xor env)
+
+  | OtherPrim "notInt", [e] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e ^^
+    BigNum.compile_not env
+
+  | OtherPrim "btstInt", [e1; e2] ->
+    SR.bool,
+    compile_exp_vanilla env ae e1 ^^
+    compile_exp_as env ae SR.UnboxedWord32 e2 ^^
+    BigNum.compile_test_bit env
+
+  | OtherPrim ("bsetInt" | "bclrInt" as p), [e1; e2] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e1 ^^
+    compile_exp_as env ae SR.UnboxedWord32 e2 ^^
+    (if p = "bsetInt" then BigNum.compile_set_bit env else BigNum.compile_clear_bit env)
+
+  | OtherPrim "popcntInt", [e] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e ^^
+    BigNum.compile_popcount env ^^
+    BigNum.from_word32 env
+
+  | OtherPrim "bitLengthInt", [e] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e ^^
+    BigNum.compile_bit_length env ^^
+    BigNum.from_word32 env
+
   | OtherPrim "abs", [e] ->
     SR.Vanilla,
     compile_exp_vanilla env ae e ^^
@@ -8759,6 +9449,17 @@ and compile_prim_invocation (env : E.t) ae p es at =
     compile_unboxed_const (TaggedSmallWord.vanilla_lit Type.Nat8 0) ^^
     E.call_import env "rts" "float_fmt"
 
+  | OtherPrim "Text->Int", [e1; e2] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e1 ^^
+    compile_exp_as env ae SR.UnboxedWord32 e2 ^^
+    BigNum.compile_of_text env
+
+  | OtherPrim "Text->Float", [e] ->
+    SR.Vanilla,
+    compile_exp_vanilla env ae e ^^
+    Float.of_text env
+
   | OtherPrim "fmtFloat->Text", [f; prec; mode] ->
     SR.Vanilla,
     compile_exp_as env ae SR.UnboxedFloat64 f ^^
@@ -8970,6 +9671,12 @@ and compile_prim_invocation (env : E.t) ae p es at =
 
   (* Other prims, binary *)
 
+  | OtherPrim ("idl_set_decoding_quota" | "idl_set_skip_limits" as name), [e1; e2] ->
+    SR.unit,
+    compile_exp_as env ae SR.UnboxedWord32 e1 ^^
+    compile_exp_as env ae SR.UnboxedWord32 e2 ^^
+    E.call_import env "rts" name
+
   | OtherPrim ("stableMemoryStoreNat8"), [e1; e2] ->
     SR.unit,
     compile_exp_as env ae SR.UnboxedWord64 e1 ^^
@@ -9050,6 +9757,54 @@ and compile_prim_invocation (env : E.t) ae p es at =
     compile_exp_as env ae SR.UnboxedWord64 e ^^
     StableMem.logical_grow env
 
+  | OtherPrim ("regionInit"), [e] ->
+    let (set_base, get_base) = new_local64 env "base" in
+    SR.unit,
+    compile_exp_as env ae SR.UnboxedWord64 e ^^
+    set_base ^^
+    (* the StableMemory in use stays below the region space *)
+    get_base ^^
+    StableMem.get_mem_size env ^^
+    G.i (Compare (Wasm.Values.I64 I64Op.LtU)) ^^
+    E.then_trap_with env "region: base page is below the StableMemory in use" ^^
+    get_base ^^
+    E.call_import env "rts" "region_init"
+
+  | OtherPrim ("regionNew"), [] ->
+    SR.UnboxedWord32,
+    E.call_import env "rts" "region_new"
+
+  | OtherPrim ("regionCount"), [] ->
+    SR.UnboxedWord32,
+    E.call_import env "rts" "region_count"
+
+  | OtherPrim ("regionSize"), [e] ->
+    SR.UnboxedWord64,
+    compile_exp_as env ae SR.UnboxedWord32 e ^^
+    E.call_import env "rts" "region_size"
+
+  | OtherPrim ("regionGrow"), [e1; e2] ->
+    SR.UnboxedWord64,
+    compile_exp_as env ae SR.UnboxedWord32 e1 ^^
+    compile_exp_as env ae SR.UnboxedWord64 e2 ^^
+    E.call_import env "rts" "region_grow"
+
+  | OtherPrim ("regionLoadBlob"), [e1; e2; e3] ->
+    SR.Vanilla,
+    compile_exp_as env ae SR.UnboxedWord32 e1 ^^
+    compile_exp_as env ae SR.UnboxedWord64 e2 ^^
+    compile_exp_as env ae SR.Vanilla e3 ^^
+    Blob.lit env "Blob size out of bounds" ^^
+    BigNum.to_word32_with env ^^
+    E.call_import env "rts" "region_load_blob"
+
+  | OtherPrim ("regionStoreBlob"), [e1; e2; e3] ->
+    SR.unit,
+    compile_exp_as env ae SR.UnboxedWord32 e1 ^^
+    compile_exp_as env ae SR.UnboxedWord64 e2 ^^
+    compile_exp_as env ae SR.Vanilla e3 ^^
+    E.call_import env "rts" "region_store_blob"
+
   | OtherPrim ("stableVarQuery"), [] ->
     SR.UnboxedTuple 2,
     IC.get_self_reference env ^^
@@ -9925,6 +10680,9 @@ and conclude_module env start_fi_o =
 
   FuncDec.export_async_method env;
 
+  (* all expected types of subtype checks are known now *)
+  let set_typtbl = Serialization.static_typtbl env in
+
   let static_roots = GCRoots.store_static_roots env in
   (* declare before building GC *)
 
@@ -9948,6 +10706,7 @@ and conclude_module env start_fi_o =
       E.call_import env "rts" "init_write_barrier"
      else
       G.nop) ^^
+    set_typtbl ^^
     match start_fi_o with
     | Some fi ->
       G.i (Call fi)
@@ -10024,6 +10783,7 @@ let compile mode rts (prog : Ir.prog) : Wasm_exts.CustomModule.extended_module =
   Stack.register_globals env;
   GC.register_globals env;
   StableMem.register_globals env;
+  Serialization.register_globals env;
 
   IC.system_imports env;
   RTS.system_imports env;
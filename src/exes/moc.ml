@@ -160,6 +160,14 @@ let argspec = [
   Arg.Set_int Flags.max_stable_pages,
   "<n>  set maximum number of pages available for library `ExperimentalStableMemory.mo` (default " ^ (Int.to_string Flags.max_stable_pages_default) ^ ")";
 
+  "--stable-compression",
+  Arg.Set Flags.stable_compression,
+  " compress the stable variables written on upgrade";
+
+  "--stable-graph",
+  Arg.Set Flags.stable_graph,
+  " also copy the heap graph of the stable variables on upgrade, which preserves sharing when the stable types stay the same";
+
   "--experimental-field-aliasing",
   Arg.Unit (fun () -> Flags.experimental_field_aliasing := true),
   " enable experimental support for aliasing of var fields"
@@ -26,6 +26,8 @@ let actor_aliases : string M.t ref = ref M.empty
 let actor_idl_path : string option ref = ref None
 let max_stable_pages_default = 65536
 let max_stable_pages : int ref = ref max_stable_pages_default
+let stable_compression = ref false
+let stable_graph = ref false
 let pre_ref : string option ref = ref None
 let post_ref : string option ref = ref None
 let profile = ref false
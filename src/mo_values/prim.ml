@@ -20,6 +20,22 @@ let as_big_int = function
   | Type.Char -> fun v -> Big_int.big_int_of_int (as_char v)
   | t -> raise (Invalid_argument ("Value.as_big_int: " ^ Type.string_of_typ (Type.Prim t)))
 
+(* Bitwise operation on integers as infinite two's complement bit strings,
+   computed on nonnegative residues wide enough to hold both signs *)
+let two_complement op a b =
+  let open Big_int in
+  let n = 1 + max (num_bits_big_int (abs_big_int a)) (num_bits_big_int (abs_big_int b)) in
+  let m = power_int_positive_int 2 n in
+  let r = op (mod_big_int a m) (mod_big_int b m) in
+  if ge_big_int r (power_int_positive_int 2 (n - 1)) then sub_big_int r m else r
+
+let test_bit a bit = Big_int.(sign_big_int (extract_big_int a bit 1) <> 0)
+
+let rec popcount a =
+  let open Big_int in
+  if sign_big_int a = 0 then 0 else
+  (if test_bit a 0 then 1 else 0) + popcount (shift_right_big_int a 1)
+
 (* This function raises `Invalid_argument` exception, catch it in the call site
    to convert to trap *)
 let of_big_int_trap = function
@@ -85,6 +101,32 @@ let num_conv_wrap_prim trap t1 t2 =
   fun v -> try of_big_int_wrap t2 (as_big_int t1 v)
            with Invalid_argument msg -> trap.trap msg
 
+(* Shortest decimal text that parses back to the same float, laid out like
+   JavaScript's Number.prototype.toString, but keeping the sign of -0.
+   Matches `float/format.rs` in the RTS. *)
+let shortest_float_text f =
+  let open Printf in
+  if not (Float.is_finite f) then sprintf "%f" f else
+  let rec shortest p =
+    let s = sprintf "%.*e" p f in
+    if p >= 16 || float_of_string s = f then s else shortest (p + 1) in
+  let s = shortest 0 in
+  let sign, s =
+    if s.[0] = '-' then "-", String.sub s 1 (String.length s - 1) else "", s in
+  let i = String.index s 'e' in
+  let digits = String.concat "" (String.split_on_char '.' (String.sub s 0 i)) in
+  let exp = int_of_string (String.sub s (i + 1) (String.length s - i - 1)) in
+  let k = String.length digits and n = exp + 1 in
+  sign ^
+    if k <= n && n <= 21 then digits ^ String.make (n - k) '0'
+    else if 0 < n && n <= 21 then String.sub digits 0 n ^ "." ^ String.sub digits n (k - n)
+    else if -6 < n && n <= 0 then "0." ^ String.make (-n) '0' ^ digits
+    else
+      let mantissa =
+        if k = 1 then digits
+        else String.sub digits 0 1 ^ "." ^ String.sub digits 1 (k - 1) in
+      sprintf "%se%c%d" mantissa (if exp < 0 then '-' else '+') (abs exp)
+
 let prim trap =
   let via_float f v = Float.(Float (of_float (f (to_float (as_float v))))) in
   let via_float2 f v w = Float.(Float (of_float (f (to_float (as_float v)) (to_float (as_float w))))) in
@@ -96,6 +138,7 @@ let prim trap =
     | 1 -> sprintf "%.*e" prec
     | 2 -> sprintf "%.*g" prec
     | 3 -> sprintf "%.*h" prec
+    | 4 -> shortest_float_text
     | _ -> fun _ -> trap.trap "float_formatter: unrecognised mode" in
   function
   | "abs" -> fun _ v k -> k (Int (Nat.abs (as_int v)))
@@ -118,6 +161,11 @@ let prim trap =
      | [a; b] -> k (Float (Float.copysign (as_float a) (as_float b)))
      | _ -> assert false)
   | "Float->Text" -> fun _ v k -> k (Text (Float.to_string (as_float v)))
+  | "Text->Float" -> fun _ v k ->
+    let t = as_text v in
+    (match float_of_string_opt t with
+     | Some f when not (String.contains t '_') -> k (Opt (Float (Float.of_float f)))
+     | _ -> k Null)
   | "fmtFloat->Text" -> fun _ v k ->
     (match Value.as_tup v with
      | [f; prec; mode] ->
@@ -198,6 +246,34 @@ let prim trap =
      | [x; shift] -> k (Int Numerics.Int.(div (as_int x) (pow (of_int 2) (of_big_int (Nat32.to_big_int (as_nat32 shift))))))
      | _ -> failwith "rsh_Nat")
 
+  | "andInt" | "orInt" | "xorInt" as p -> fun _ v k ->
+    (match as_tup v with
+     | [a; b] ->
+       let op = match p with
+         | "andInt" -> Big_int.and_big_int
+         | "orInt" -> Big_int.or_big_int
+         | _ -> Big_int.xor_big_int in
+       k (Int (Int.of_big_int (two_complement op (Int.to_big_int (as_int a)) (Int.to_big_int (as_int b)))))
+     | _ -> failwith p)
+  | "notInt" -> fun _ v k ->
+    k (Int Int.(sub (neg (as_int v)) (of_int 1)))
+  | "btstInt" | "bsetInt" | "bclrInt" as p -> fun _ v k ->
+    (match as_tup v with
+     | [a; bit] ->
+       let a = Int.to_big_int (as_int a) in
+       let bit = Big_int.int_of_big_int (Nat32.to_big_int (as_nat32 bit)) in
+       let mask = Big_int.power_int_positive_int 2 bit in
+       (match p, test_bit a bit with
+        | "btstInt", b -> k (Bool b)
+        | "bsetInt", false -> k (Int (Int.of_big_int (Big_int.add_big_int a mask)))
+        | "bclrInt", true -> k (Int (Int.of_big_int (Big_int.sub_big_int a mask)))
+        | _ -> k (Int (Int.of_big_int a)))
+     | _ -> failwith p)
+  | "popcntInt" -> fun _ v k ->
+    k (Int (Int.of_int (popcount (Big_int.abs_big_int (Int.to_big_int (as_int v))))))
+  | "bitLengthInt" -> fun _ v k ->
+    k (Int (Int.of_int (Big_int.num_bits_big_int (Big_int.abs_big_int (Int.to_big_int (as_int v))))))
+
   | "conv_Char_Text" -> fun _ v k -> let str = match as_char v with
                                           | c when c <= 0o177 -> String.make 1 (Char.chr c)
                                           | code -> Wasm.Utf8.encode [code]
@@ -215,6 +291,7 @@ let prim trap =
      | "rts_mutator_instructions"
      | "rts_collector_instructions") ->
         fun _ v k -> as_unit v; k (Int (Int.of_int 0))
+  | "idl_set_decoding_quota" | "idl_set_skip_limits" -> fun _ v k -> k unit
   | "time" -> fun _ v k -> as_unit v; k (Value.Nat64 (Numerics.Nat64.of_int 42))
   | "idlHash" -> fun _ v k ->
     let s = as_text v in
@@ -349,4 +426,28 @@ let prim trap =
   | "encodeUtf8" ->
       fun _ v k -> k (Blob (as_text v))
 
+  | "Text->Int" ->
+      fun _ v k ->
+        (match Value.as_tup v with
+         | [t; r] ->
+           let t = as_text t in
+           let radix = Big_int.int_of_big_int (Nat32.to_big_int (as_nat32 r)) in
+           if radix < 2 || radix > 36 then trap.trap "bigint: radix must be between 2 and 36" else
+           let digit c = match c with
+             | '0'..'9' -> Char.code c - Char.code '0'
+             | 'a'..'z' -> Char.code c - Char.code 'a' + 10
+             | 'A'..'Z' -> Char.code c - Char.code 'A' + 10
+             | _ -> radix in
+           let start = if t <> "" && (t.[0] = '+' || t.[0] = '-') then 1 else 0 in
+           let rec go i n =
+             if i = String.length t then Some n
+             else if digit t.[i] >= radix then None
+             else go (i + 1) Big_int.(add_int_big_int (digit t.[i]) (mult_int_big_int radix n)) in
+           (match go start Big_int.zero_big_int with
+            | Some n when String.length t > start ->
+              let n = if start = 1 && t.[0] = '-' then Big_int.minus_big_int n else n in
+              k (Opt (Int (Int.of_big_int n)))
+            | _ -> k Null)
+         | _ -> assert false)
+
   | s -> trap.trap ("Value.prim: " ^ s)